@@ -0,0 +1,231 @@
+//! Statistics of forecasts across range of days, like mean temperature, total precipitation
+//! and windiest day
+use std::fmt::Write as _;
+
+use serde_json::{json, Value};
+
+use crate::date::Date;
+use crate::i18n;
+use crate::provider::WeatherInfo;
+use crate::units::Units;
+
+/// Statistic which summarizes temperature, wind speed and humidity across days
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Statistic {
+    /// Lowest value; days' minimal temperatures are used where reported
+    Min,
+    /// Highest value; days' maximal temperatures are used where reported
+    Max,
+    /// Average value
+    Mean,
+}
+
+impl Statistic {
+    /// Statistic's name, as it's specified on command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Mean => "mean",
+        }
+    }
+}
+/// Forecasts of several days summarized
+pub struct Aggregate {
+    /// Statistic applied to temperature, wind speed and humidity
+    pub statistic: Statistic,
+    /// Number of summarized days
+    pub days: usize,
+    /// Temperature, in Celsius degrees
+    pub temperature: f32,
+    /// Wind speed, in m/s
+    pub wind_speed: f32,
+    /// Humidity, in percents
+    pub humidity: f32,
+    /// Total precipitation, in millimeters, if any day reports it
+    pub precipitation: Option<f32>,
+    /// Day with strongest wind, along with its wind speed
+    pub windiest: (Date, f32),
+}
+/// Summarizes forecasts across days
+///
+/// # Parameters
+/// * `forecasts` - forecasts along with dates they're for
+/// * `statistic` - statistic applied to temperature, wind speed and humidity
+///
+/// # Returns
+/// Statistics, or `None` if there are no forecasts
+pub fn aggregate(forecasts: &[(Date, WeatherInfo)], statistic: Statistic) -> Option<Aggregate> {
+    let (first_date, first) = forecasts.first()?;
+    let apply = |values: &mut dyn Iterator<Item = f32>| -> f32 {
+        match statistic {
+            Statistic::Min => values.fold(f32::MAX, f32::min),
+            Statistic::Max => values.fold(f32::MIN, f32::max),
+            Statistic::Mean => values.sum::<f32>() / forecasts.len() as f32,
+        }
+    };
+    let temperature = apply(&mut forecasts.iter().map(|(_, info)| match statistic {
+        Statistic::Min => info.temperature_min.unwrap_or(info.temperature),
+        Statistic::Max => info.temperature_max.unwrap_or(info.temperature),
+        Statistic::Mean => info.temperature,
+    }));
+    let wind_speed = apply(&mut forecasts.iter().map(|(_, info)| info.wind_speed));
+    let humidity = apply(&mut forecasts.iter().map(|(_, info)| info.humidity));
+    let precipitation = forecasts
+        .iter()
+        .filter_map(|(_, info)| info.precipitation)
+        .reduce(|total, amount| total + amount);
+    let windiest = forecasts.iter().fold(
+        (*first_date, first.wind_speed),
+        |(date, speed), (day, info)| {
+            if info.wind_speed > speed {
+                (*day, info.wind_speed)
+            } else {
+                (date, speed)
+            }
+        },
+    );
+    Some(Aggregate {
+        statistic,
+        days: forecasts.len(),
+        temperature,
+        wind_speed,
+        humidity,
+        precipitation,
+        windiest,
+    })
+}
+
+impl Aggregate {
+    /// Statistics as JSON object, in metric units like forecasts themselves
+    pub fn json(&self) -> Value {
+        let mut value = json!({
+            "statistic": self.statistic.name(),
+            "days": self.days,
+            "temperature": self.temperature,
+            "wind_speed": self.wind_speed,
+            "humidity": self.humidity,
+            "windiest_day": self.windiest.0.to_string(),
+            "windiest_wind_speed": self.windiest.1,
+        });
+        if let Some(precipitation) = self.precipitation {
+            value["precipitation_total"] = json!(precipitation);
+        }
+        value
+    }
+    /// Renders statistics as text block, one line per value
+    ///
+    /// # Parameters
+    /// * `units` - units in which values are shown
+    pub fn render(&self, units: Units) -> String {
+        let statistic = self.statistic.name();
+        let mut text = i18n::tr("label.across_days", "Across {days} days ({statistic})")
+            .replace("{days}", &self.days.to_string())
+            .replace(
+                "{statistic}",
+                i18n::tr(&format!("statistic.{statistic}"), statistic),
+            );
+        text.push_str(":\n");
+        let _ = writeln!(
+            text,
+            "  {}: {:.1}{}",
+            i18n::tr("label.temperature", "Temperature"),
+            units.temperature(self.temperature),
+            units.temperature_unit()
+        );
+        let _ = writeln!(
+            text,
+            "  {}: {:.1} {}",
+            i18n::tr("label.wind_speed", "Wind speed"),
+            units.speed(self.wind_speed),
+            units.speed_unit()
+        );
+        let _ = writeln!(
+            text,
+            "  {}: {:.0}%",
+            i18n::tr("label.humidity", "Humidity"),
+            self.humidity
+        );
+        if let Some(precipitation) = self.precipitation {
+            let _ = writeln!(
+                text,
+                "  {}: {:.1} {}",
+                i18n::tr("label.total_precipitation", "Total precipitation"),
+                units.precipitation(precipitation),
+                units.precipitation_unit()
+            );
+        }
+        let (date, speed) = self.windiest;
+        let _ = writeln!(
+            text,
+            "  {}: {}, {:.1} {}",
+            i18n::tr("label.windiest_day", "Windiest day"),
+            date.formatted(),
+            units.speed(speed),
+            units.speed_unit()
+        );
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{aggregate, Statistic};
+    use crate::date::Date;
+    use crate::provider::{WeatherInfo, WeatherKind};
+    use crate::units::Units;
+
+    fn day(
+        day: u8,
+        temperature: f32,
+        wind_speed: f32,
+        precipitation: Option<f32>,
+    ) -> (Date, WeatherInfo) {
+        let info = WeatherInfo {
+            wind_speed,
+            humidity: 50.0,
+            precipitation,
+            ..WeatherInfo::sample(WeatherKind::Clear, temperature)
+        };
+        (Date::from_str(&format!("2024-07-0{day}")).unwrap(), info)
+    }
+
+    #[test]
+    fn statistics_across_days() {
+        let forecasts = [
+            day(1, 10.0, 2.0, Some(1.5)),
+            day(2, 20.0, 7.0, None),
+            day(3, 15.0, 3.0, Some(2.5)),
+        ];
+        let mean = aggregate(&forecasts, Statistic::Mean).unwrap();
+        assert_eq!(mean.temperature, 15.0);
+        assert_eq!(mean.wind_speed, 4.0);
+        assert_eq!(mean.precipitation, Some(4.0));
+        assert_eq!(mean.windiest.0.to_string(), "2024-07-02");
+        assert_eq!(mean.windiest.1, 7.0);
+
+        let min = aggregate(&forecasts, Statistic::Min).unwrap();
+        assert_eq!((min.temperature, min.wind_speed), (10.0, 2.0));
+        assert!(aggregate(&[], Statistic::Max).is_none());
+    }
+
+    #[test]
+    fn statistics_are_rendered_with_labels() {
+        let forecasts = [day(1, 10.0, 2.0, Some(1.5)), day(2, 20.0, 7.0, None)];
+        let max = aggregate(&forecasts, Statistic::Max).unwrap();
+        let windiest = max.windiest.0.formatted();
+        assert_eq!(
+            max.render(Units::default()),
+            format!(
+                "Across 2 days (max):\n  \
+                 Temperature: 20.0°C\n  \
+                 Wind speed: 7.0 m/s\n  \
+                 Humidity: 50%\n  \
+                 Total precipitation: 1.5 mm\n  \
+                 Windiest day: {windiest}, 7.0 m/s\n"
+            )
+        );
+    }
+}
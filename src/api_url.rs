@@ -0,0 +1,194 @@
+//! Building request URLs for web APIs
+//!
+//! Path segments and query parameters are percent-encoded, so addresses like "New York",
+//! "São Paulo" or "Bed & Breakfast" reach APIs intact. Credentials are added as secret
+//! query parameters or path segments, which are masked whenever URL is shown
+use std::fmt::Display;
+
+use url::Url;
+
+use crate::redact;
+
+/// URL of API request, which knows which of its query parameters are secret
+///
+/// Displayed with secrets masked; use `as_str` to obtain URL actually requested
+#[derive(Clone)]
+pub struct ApiUrl {
+    url: Url,
+    secrets: Vec<String>,
+}
+
+impl ApiUrl {
+    /// Starts URL
+    ///
+    /// # Parameters
+    /// * `base` - absolute URL, possibly with path and query
+    ///
+    /// # Panics
+    /// If `base` isn't valid absolute URL; base URLs are either constants or validated
+    /// when read from config, so it's programmer's error
+    pub fn new(base: &str) -> Self {
+        let url =
+            Url::parse(base).unwrap_or_else(|error| panic!("Invalid base URL '{base}': {error}"));
+        Self {
+            url,
+            secrets: Vec::new(),
+        }
+    }
+    /// Appends path segment; slashes and other special characters in it are encoded
+    ///
+    /// # Parameters
+    /// * `segment` - path segment, like location name
+    pub fn segment(mut self, segment: impl Display) -> Self {
+        if let Ok(mut segments) = self.url.path_segments_mut() {
+            segments.pop_if_empty().push(&segment.to_string());
+        }
+        self
+    }
+    /// Appends query parameter
+    ///
+    /// # Parameters
+    /// * `name` - parameter name
+    /// * `value` - parameter value
+    pub fn query(mut self, name: &str, value: impl Display) -> Self {
+        self.url
+            .query_pairs_mut()
+            .append_pair(name, &value.to_string());
+        self
+    }
+    /// Appends query parameter without value, like `?latest`
+    ///
+    /// # Parameters
+    /// * `name` - parameter name
+    pub fn flag(mut self, name: &str) -> Self {
+        let name: String = url::form_urlencoded::byte_serialize(name.as_bytes()).collect();
+        let query = match self.url.query() {
+            Some(query) if !query.is_empty() => format!("{query}&{name}"),
+            _ => name,
+        };
+        self.url.set_query(Some(&query));
+        self
+    }
+    /// Appends query parameter which holds credentials, like API key
+    ///
+    /// # Parameters
+    /// * `name` - parameter name
+    /// * `value` - secret value, masked whenever URL is shown
+    pub fn secret_query(mut self, name: &str, value: &str) -> Self {
+        self.register_secret(value);
+        self.query(name, value)
+    }
+    /// Appends path segment which holds credentials, for APIs which expect key in path
+    ///
+    /// # Parameters
+    /// * `value` - secret value, masked whenever URL is shown
+    pub fn secret_segment(mut self, value: &str) -> Self {
+        self.register_secret(value);
+        self.segment(value)
+    }
+    /// Remembers secret, both as is and in encoded form it takes in URL, to mask it
+    fn register_secret(&mut self, value: &str) {
+        let encoded: String = url::form_urlencoded::byte_serialize(value.as_bytes()).collect();
+        for secret in [value, encoded.as_str()] {
+            redact::register(secret);
+            if !secret.is_empty() && !self.secrets.iter().any(|known| known == secret) {
+                self.secrets.push(secret.to_string());
+            }
+        }
+    }
+    /// Full URL, including secrets; should only be used to perform request
+    pub fn as_str(&self) -> &str {
+        self.url.as_str()
+    }
+    /// Secret values of this URL
+    pub fn secrets(&self) -> &[String] {
+        &self.secrets
+    }
+    /// Masks this URL's secrets in text, e.g. in error message which mentions URL
+    ///
+    /// # Parameters
+    /// * `text` - text which may contain secrets
+    pub fn redact(&self, text: &str) -> String {
+        redact::mask(text, self.secrets.iter())
+    }
+}
+
+impl Display for ApiUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.redact(self.url.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiUrl;
+
+    #[test]
+    fn query_values_are_encoded() {
+        let url = ApiUrl::new("https://api.example.com/v1/current.json?lang=en")
+            .query("q", "New York")
+            .query("city", "São Paulo")
+            .query("place", "Bed & Breakfast, 1=1");
+        assert_eq!(
+            url.as_str(),
+            "https://api.example.com/v1/current.json?lang=en&q=New+York\
+             &city=S%C3%A3o+Paulo&place=Bed+%26+Breakfast%2C+1%3D1"
+        );
+    }
+
+    #[test]
+    fn path_segments_are_encoded() {
+        let url = ApiUrl::new("https://api.example.com/observations/")
+            .segment("New York")
+            .segment("a/b & c?");
+        assert_eq!(
+            url.as_str(),
+            "https://api.example.com/observations/New%20York/a%2Fb%20&%20c%3F"
+        );
+    }
+
+    #[test]
+    fn flag_is_appended_without_value() {
+        let url = ApiUrl::new("https://api.example.com/").flag("latest");
+        assert_eq!(url.as_str(), "https://api.example.com/?latest");
+        let url = url.query("q", "x").flag("raw");
+        assert_eq!(url.as_str(), "https://api.example.com/?latest&q=x&raw");
+    }
+
+    #[test]
+    fn secret_query_is_masked_when_shown() {
+        let url = ApiUrl::new("https://api.example.com/weather")
+            .query("q", "London")
+            .secret_query("appid", "s3cr&t key");
+        // Request gets actual key, encoded
+        assert_eq!(
+            url.as_str(),
+            "https://api.example.com/weather?q=London&appid=s3cr%26t+key"
+        );
+        // Both raw and encoded forms are masked
+        assert_eq!(
+            url.to_string(),
+            "https://api.example.com/weather?q=London&appid=***"
+        );
+        assert_eq!(
+            url.redact("Key 's3cr&t key' was rejected"),
+            "Key '***' was rejected"
+        );
+        assert_eq!(url.secrets(), ["s3cr&t key", "s3cr%26t+key"]);
+    }
+
+    #[test]
+    fn secret_segment_is_masked_when_shown() {
+        let url = ApiUrl::new("https://api.example.com/forecast/")
+            .secret_segment("abc123")
+            .segment("51.5,-0.1");
+        assert_eq!(
+            url.as_str(),
+            "https://api.example.com/forecast/abc123/51.5,-0.1"
+        );
+        assert_eq!(
+            url.to_string(),
+            "https://api.example.com/forecast/***/51.5,-0.1"
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Application's directories
+//!
+//! Config, cache, data and state are kept apart, in locations conventional for OS:
+//! XDG base directories on Linux, Known Folders on Windows and `~/Library` on macOS.
+//! Cache and data directories may be overridden from command line
+use std::{path::PathBuf, sync::OnceLock};
+
+/// Name of application's subdirectory in each of OS directories
+const APP_DIR: &str = "weather-cli";
+
+/// Directories specified explicitly by user
+#[derive(Default)]
+struct Overrides {
+    cache: Option<PathBuf>,
+    data: Option<PathBuf>,
+}
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+/// Sets directories which replace OS ones; should be called once, before any directory is used
+///
+/// # Parameters
+/// * `cache` - directory for cached data, which may be removed at any moment
+/// * `data` - directory for user's data, like installed language packs, and application's state
+pub fn set_overrides(cache: Option<PathBuf>, data: Option<PathBuf>) {
+    if OVERRIDES.set(Overrides { cache, data }).is_err() {
+        tracing::warn!("Application directories were already set");
+    }
+}
+
+fn overrides() -> &'static Overrides {
+    OVERRIDES.get_or_init(Overrides::default)
+}
+/// Directory with config file, external providers and plugins
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|path| path.join(APP_DIR))
+}
+/// Directory with cached data, like recently obtained weather
+pub fn cache_dir() -> Option<PathBuf> {
+    match &overrides().cache {
+        Some(path) => Some(path.clone()),
+        None => dirs::cache_dir().map(|path| path.join(APP_DIR)),
+    }
+}
+/// Directory with user's data, like installed language packs
+pub fn data_dir() -> Option<PathBuf> {
+    match &overrides().data {
+        Some(path) => Some(path.clone()),
+        None => dirs::data_dir().map(|path| path.join(APP_DIR)),
+    }
+}
+/// Directory with application's state, like history, which is worth keeping yet isn't user's data
+///
+/// Only Linux has separate state directory; elsewhere, and when data directory is overridden,
+/// state is kept in local data directory
+pub fn state_dir() -> Option<PathBuf> {
+    if let Some(path) = &overrides().data {
+        return Some(path.clone());
+    }
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|path| path.join(APP_DIR))
+}
@@ -0,0 +1,120 @@
+//! Credits to providers whose data is shown
+//!
+//! Providers declare their attribution in `ProviderInfo`. It's shown in human-readable output,
+//! either after or before data, as selected by global `attribution` config entry,
+//! and is always included into JSON output. Optional attributions may be hidden with `attribution = none`,
+//! while ones required by provider's terms are still shown after data
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::provider::{Attribution, ProviderInfo};
+
+/// Name of global config entry with attribution placement
+const PLACEMENT_ENTRY: &str = "attribution";
+
+/// Where attribution is shown in human-readable output
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Placement {
+    /// After data
+    #[default]
+    Footer,
+    /// Before data
+    Header,
+    /// Not shown, unless required by provider
+    None,
+}
+
+impl FromStr for Placement {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "footer" => Ok(Placement::Footer),
+            "header" => Ok(Placement::Header),
+            "none" => Ok(Placement::None),
+            _ => {
+                bail!("Unknown attribution placement '{s}', expected 'footer', 'header' or 'none'")
+            }
+        }
+    }
+}
+
+/// Attributions of providers used for single output
+pub struct Credits {
+    placement: Placement,
+    attributions: Vec<&'static Attribution>,
+}
+
+impl Credits {
+    /// Collects attributions, dropping duplicates
+    ///
+    /// # Parameters
+    /// * `config` - configuration, which may specify placement
+    /// * `providers` - infos of used providers
+    pub fn new(
+        config: &Config,
+        providers: impl IntoIterator<Item = &'static ProviderInfo>,
+    ) -> anyhow::Result<Self> {
+        let placement = match config.globals.get(PLACEMENT_ENTRY) {
+            Some(value) => value
+                .parse()
+                .with_context(|| anyhow!("Invalid '{PLACEMENT_ENTRY}' config entry"))?,
+            None => Placement::default(),
+        };
+        let mut unique: Vec<&'static Attribution> = Vec::new();
+        for info in providers {
+            let Some(attribution) = &info.attribution else {
+                continue;
+            };
+            // Provider infos are statics, so same attribution is same object
+            if !unique.iter().any(|known| std::ptr::eq(*known, attribution)) {
+                unique.push(attribution);
+            }
+        }
+        Ok(Self {
+            placement,
+            attributions: unique,
+        })
+    }
+    /// Text shown before data, if any
+    pub fn header(&self) -> Option<String> {
+        match self.placement {
+            Placement::Header => self.lines(|_| true),
+            Placement::Footer | Placement::None => None,
+        }
+    }
+    /// Text shown after data, if any
+    pub fn footer(&self) -> Option<String> {
+        match self.placement {
+            Placement::Footer => self.lines(|_| true),
+            Placement::None => self.lines(|attribution| attribution.required),
+            Placement::Header => None,
+        }
+    }
+    /// All attributions as JSON array, regardless of placement
+    pub fn json(&self) -> Value {
+        self.attributions
+            .iter()
+            .map(|attribution| {
+                json!({
+                    "text": attribution.text,
+                    "url": attribution.url,
+                    "required": attribution.required,
+                })
+            })
+            .collect()
+    }
+
+    fn lines(&self, filter: impl Fn(&Attribution) -> bool) -> Option<String> {
+        let lines: Vec<_> = self
+            .attributions
+            .iter()
+            .filter(|attribution| filter(attribution))
+            .map(ToString::to_string)
+            .collect();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
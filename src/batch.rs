@@ -0,0 +1,252 @@
+//! Batch of weather queries, read from file or stdin and executed concurrently
+//!
+//! Each line of input is "address[;date][;provider]". Results are written in order of queries,
+//! each as soon as it and all preceding ones are done, so output can be consumed while batch runs
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Context};
+use serde_json::json;
+
+use crate::condition_mapping::MappedProvider;
+use crate::date::Date;
+use crate::export::csv_field;
+use crate::geojson;
+use crate::progress::{self, Event};
+use crate::provider::WeatherInfo;
+use crate::rate_limit;
+use crate::redact;
+
+/// CSV header, matching fields written by `row`
+const CSV_HEADER: &str = "line,address,date,provider,weather,temperature,wind_speed,humidity,precipitation,condition_code,error";
+
+/// How batch results are written
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum BatchFormat {
+    /// Comma-separated values with header; values are in metric units
+    #[default]
+    Csv,
+    /// Newline-delimited JSON, one object per query
+    Ndjson,
+    /// GeoJSON feature collection, one point feature per query at its resolved coordinates,
+    /// for mapping tools
+    Geojson,
+}
+/// Arguments of `batch` command
+#[derive(clap::Args)]
+pub struct BatchArgs {
+    /// File with queries, one "address[;date][;provider]" per line, or "-" for stdin;
+    /// date is "YYYY-MM-DD", "YYYY-MM-DD HH:MM", weekday like "friday" or "now", which is default
+    #[arg(short, long)]
+    pub input: PathBuf,
+    /// Results format
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: BatchFormat,
+    /// How many queries are executed at once
+    #[arg(short, long, default_value_t = 4)]
+    pub jobs: usize,
+    /// Provider of queries which don't specify one, instead of default one
+    #[arg(short, long)]
+    pub provider: Option<String>,
+}
+
+/// Single query of batch
+pub struct Query {
+    /// Number of query's line in input, starting from 1
+    pub line: usize,
+    /// Address of location
+    pub address: String,
+    /// Requested date; `None` means current conditions
+    pub date: Option<Date>,
+    /// Provider specified by query, if any
+    pub provider: Option<String>,
+    /// Latitude and longitude of address, if it's resolved before batch runs;
+    /// they're requested from provider instead of address
+    pub coordinates: Option<(f64, f64)>,
+}
+/// Reads queries from file or stdin; empty lines and lines starting with '#' are skipped
+///
+/// # Parameters
+/// * `input` - path of file, or "-" for stdin
+///
+/// # Returns
+/// Queries, or error if input can't be read or has malformed line
+pub fn read_queries(input: &Path) -> anyhow::Result<Vec<Query>> {
+    let text = if input.as_os_str() == "-" {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .with_context(|| anyhow!("When reading queries from stdin"))?;
+        text
+    } else {
+        std::fs::read_to_string(input)
+            .with_context(|| anyhow!("When reading queries from '{}'", input.display()))?
+    };
+    let mut queries = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(';').map(str::trim);
+        let address = fields.next().unwrap_or_default().to_string();
+        ensure!(!address.is_empty(), "Line {line_no}: address is missing");
+        let date = match fields.next() {
+            None | Some("") | Some("now") => None,
+            Some(date) => Some(
+                Date::parse_in(date, crate::timezone::of_location(&address))
+                    .with_context(|| anyhow!("Line {line_no}: invalid date '{date}'"))?,
+            ),
+        };
+        let provider = fields
+            .next()
+            .filter(|provider| !provider.is_empty())
+            .map(str::to_string);
+        ensure!(
+            fields.next().is_none(),
+            "Line {line_no}: expected 'address[;date][;provider]'"
+        );
+        queries.push(Query {
+            line: line_no,
+            address,
+            date,
+            provider,
+            coordinates: None,
+        });
+    }
+    Ok(queries)
+}
+/// Executes queries with bounded concurrency, writing result of each into output
+///
+/// # Parameters
+/// * `queries` - queries, whose addresses are already expanded
+/// * `providers` - providers by name, or errors of their creation; each query's provider should be there
+/// * `default_provider` - name of provider used by queries which don't specify one
+/// * `jobs` - how many queries are executed at once
+/// * `format` - results format
+/// * `out` - where results are written
+///
+/// # Returns
+/// Number of failed queries
+pub async fn run(
+    queries: Vec<Query>,
+    providers: &BTreeMap<String, Result<Arc<MappedProvider>, String>>,
+    default_provider: &str,
+    jobs: usize,
+    format: BatchFormat,
+    out: &mut impl Write,
+) -> anyhow::Result<usize> {
+    ensure!(jobs > 0, "Number of concurrent queries should be positive");
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(jobs));
+    let tasks: Vec<_> = queries
+        .iter()
+        .map(|query| {
+            let provider = providers
+                .get(query.provider.as_deref().unwrap_or(default_provider))
+                .cloned()
+                .unwrap_or_else(|| Err("Provider isn't configured".to_string()));
+            let semaphore = semaphore.clone();
+            let location = match query.coordinates {
+                Some((lat, lon)) => format!("{lat},{lon}"),
+                None => query.address.clone(),
+            };
+            let date = query.date;
+            tokio::spawn(async move {
+                let provider = provider?;
+                let _permit = semaphore
+                    .acquire()
+                    .await
+                    .map_err(|error| error.to_string())?;
+                // Queries rejected by rate limit wait for its end and are repeated
+                rate_limit::retrying(|| provider.get_weather(location.clone().into(), date))
+                    .await
+                    .map_err(|error| redact::redact(&format!("{error:#}")))
+            })
+        })
+        .collect();
+
+    match format {
+        BatchFormat::Csv => writeln!(out, "{CSV_HEADER}")?,
+        BatchFormat::Geojson => writeln!(out, "{}", geojson::COLLECTION_START)?,
+        BatchFormat::Ndjson => {}
+    }
+    let total = queries.len();
+    let mut failed = 0;
+    for (step, (query, task)) in queries.iter().zip(tasks).enumerate() {
+        let result = task.await?;
+        failed += usize::from(result.is_err());
+        let provider = query.provider.as_deref().unwrap_or(default_provider);
+        // Features are separated by commas, so collection stays valid JSON
+        let separator = match format {
+            BatchFormat::Geojson if step > 0 => ",",
+            _ => "",
+        };
+        writeln!(out, "{separator}{}", row(format, query, provider, &result)?)?;
+        out.flush()?;
+        progress::emit(Event::Fetched {
+            location: &query.address,
+            date: query.date.map(|date| date.to_string()),
+            step: step + 1,
+            total,
+        });
+    }
+    if let BatchFormat::Geojson = format {
+        writeln!(out, "{}", geojson::COLLECTION_END)?;
+    }
+    Ok(failed)
+}
+/// Formats result of query as output's line, without line terminator
+fn row(
+    format: BatchFormat,
+    query: &Query,
+    provider: &str,
+    result: &Result<WeatherInfo, String>,
+) -> anyhow::Result<String> {
+    let date = query
+        .date
+        .map_or_else(|| "now".to_string(), |date| date.to_string());
+    Ok(match format {
+        BatchFormat::Csv => {
+            let fields = match result {
+                Ok(info) => format!(
+                    "{},{},{},{},{},{},",
+                    info.weather,
+                    info.temperature,
+                    info.wind_speed,
+                    info.humidity,
+                    info.precipitation
+                        .map(|p| p.to_string())
+                        .unwrap_or_default(),
+                    info.condition_code
+                        .as_deref()
+                        .map(csv_field)
+                        .unwrap_or_default(),
+                ),
+                Err(error) => format!(",,,,,,{}", csv_field(error)),
+            };
+            format!(
+                "{},{},{date},{},{fields}",
+                query.line,
+                csv_field(&query.address),
+                csv_field(provider)
+            )
+        }
+        BatchFormat::Ndjson | BatchFormat::Geojson => {
+            let mut value = match result {
+                Ok(info) => serde_json::to_value(info)?,
+                Err(error) => json!({ "error": error }),
+            };
+            value["line"] = json!(query.line);
+            value["address"] = json!(query.address);
+            value["date"] = json!(date);
+            value["provider"] = json!(provider);
+            match format {
+                BatchFormat::Geojson => geojson::feature(query.coordinates, value).to_string(),
+                _ => value.to_string(),
+            }
+        }
+    })
+}
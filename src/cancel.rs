@@ -0,0 +1,99 @@
+//! Graceful interruption with Ctrl-C
+//!
+//! Ctrl-C cancels token which running commands observe: in-flight HTTP requests are dropped,
+//! and command stops at its next await point, so output written so far stays complete,
+//! and caches and history, which aren't saved across await points, stay consistent.
+//! If nothing observes cancellation, like during interactive prompt, or work doesn't stop
+//! within grace period, or Ctrl-C is pressed again, process exits right away
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+/// Exit status of interrupted process, as shells report it
+pub const EXIT_CODE: u8 = 130;
+/// How long interrupted work may take to stop before process exits anyway
+const GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// Cancelled on Ctrl-C
+static TOKEN: LazyLock<CancellationToken> = LazyLock::new(CancellationToken::new);
+/// Number of running futures which observe cancellation
+static OBSERVERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Error which interrupted work fails with
+#[derive(Debug, thiserror::Error)]
+#[error("Interrupted")]
+pub struct Interrupted;
+
+/// Marks that cancellation is observed while it lives
+pub struct Observer(());
+
+impl Drop for Observer {
+    fn drop(&mut self) {
+        OBSERVERS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+/// Starts listening for Ctrl-C on background thread, so it's noticed
+/// even while main thread is blocked
+pub fn install() {
+    let spawned = std::thread::Builder::new()
+        .name("ctrl-c".to_string())
+        .spawn(|| {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(error) => {
+                    tracing::warn!("Ctrl-C won't stop work gracefully: {error}");
+                    return;
+                }
+            };
+            runtime.block_on(listen());
+        });
+    if let Err(error) = spawned {
+        tracing::warn!("Ctrl-C won't stop work gracefully: {error}");
+    }
+}
+/// Waits for Ctrl-C, then cancels observed work or exits
+async fn listen() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    if OBSERVERS.load(Ordering::Relaxed) > 0 {
+        tracing::info!("Interrupted, stopping");
+        TOKEN.cancel();
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            () = tokio::time::sleep(GRACE_PERIOD) => {}
+        }
+    }
+    std::process::exit(EXIT_CODE.into());
+}
+/// Registers observer of cancellation; until it's dropped, Ctrl-C cancels work
+/// instead of exiting process
+pub fn observe() -> Observer {
+    OBSERVERS.fetch_add(1, Ordering::Relaxed);
+    Observer(())
+}
+/// Whether error is caused by interruption
+pub fn is_interrupted(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| cause.is::<Interrupted>())
+}
+/// Runs future unless Ctrl-C cancels it first
+///
+/// # Parameters
+/// * `future` - work to run
+///
+/// # Returns
+/// Future's result, or `Interrupted` error if it was cancelled
+pub async fn interruptible<T>(
+    future: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    tokio::select! {
+        result = future => result,
+        () = TOKEN.cancelled() => Err(Interrupted.into()),
+    }
+}
@@ -0,0 +1,185 @@
+//! Boxed summary cards with condition art, in spirit of wttr.in
+use crate::provider::{WeatherInfo, WeatherKind};
+use crate::units::Units;
+
+/// Width of condition art, in characters
+const ART_WIDTH: usize = 13;
+/// Card width used when terminal width cannot be detected
+const DEFAULT_WIDTH: usize = 48;
+/// Narrowest card which still fits art and stats
+const MIN_WIDTH: usize = 32;
+
+/// Detects width of card which fits into terminal
+///
+/// # Returns
+/// Card width, in characters
+pub fn detect_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(width), _)| usize::from(width))
+        .unwrap_or(DEFAULT_WIDTH)
+        .clamp(MIN_WIDTH, DEFAULT_WIDTH)
+}
+/// Renders forecast as boxed card
+///
+/// # Parameters
+/// * `title` - card's header, like location and date
+/// * `info` - forecast to render
+/// * `range` - optional temperature range, minimum and maximum in Celsius degrees,
+///   like day's range or one across several days, to show along with temperature
+/// * `width` - total card width, including borders
+/// * `units` - units in which values are shown
+///
+/// # Returns
+/// Multi-line card text
+pub fn render(
+    title: &str,
+    info: &WeatherInfo,
+    range: Option<(f32, f32)>,
+    width: usize,
+    units: Units,
+) -> String {
+    let inner = width.saturating_sub(4).max(ART_WIDTH);
+    let temp_unit = units.temperature_unit();
+    let temperature = units.temperature(info.temperature);
+
+    let mut stats = vec![
+        capitalize(&info.weather.to_string()),
+        format!("{temperature:.1}{temp_unit}"),
+        format!(
+            "Wind: {:.1} {}",
+            units.speed(info.wind_speed),
+            units.speed_unit()
+        ),
+        format!("Humidity: {:.0}%", info.humidity),
+    ];
+    if let Some((min, max)) = range {
+        let (min, max) = (units.temperature(min), units.temperature(max));
+        stats[1] = format!("{temperature:.1}{temp_unit} ({min:.1}…{max:.1}{temp_unit})");
+    }
+    if let Some(precipitation) = info.precipitation {
+        stats.push(format!(
+            "Precipitation: {:.1} {}",
+            units.precipitation(precipitation),
+            units.precipitation_unit()
+        ));
+    }
+
+    let art = art(&info.weather);
+    let rows = art.len().max(stats.len());
+
+    let mut buf = String::new();
+    buf.push_str(&format!("┌{}┐\n", "─".repeat(inner + 2)));
+    buf.push_str(&format!("│ {} │\n", fit(title, inner)));
+    buf.push_str(&format!("├{}┤\n", "─".repeat(inner + 2)));
+    for row in 0..rows {
+        let line = format!(
+            "{:<ART_WIDTH$} {}",
+            art.get(row).copied().unwrap_or_default(),
+            stats.get(row).map(String::as_str).unwrap_or_default()
+        );
+        buf.push_str(&format!("│ {} │\n", fit(&line, inner)));
+    }
+    buf.push_str(&format!("└{}┘\n", "─".repeat(inner + 2)));
+    buf
+}
+/// Pads or truncates line to exactly specified number of characters
+fn fit(line: &str, width: usize) -> String {
+    let len = line.chars().count();
+    if len > width {
+        let mut line: String = line.chars().take(width.saturating_sub(1)).collect();
+        line.push('…');
+        line
+    } else {
+        format!("{line}{}", " ".repeat(width - len))
+    }
+}
+/// Makes first letter of text uppercase
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+/// Large condition picture, each line is exactly `ART_WIDTH` characters wide
+fn art(kind: &WeatherKind) -> [&'static str; 5] {
+    match kind {
+        WeatherKind::Clear => [
+            "    \\   /    ",
+            "     .-.     ",
+            "  ― (   ) ―  ",
+            "     `-'     ",
+            "    /   \\    ",
+        ],
+        WeatherKind::Clouds => [
+            "             ",
+            "     .--.    ",
+            "  .-(    ).  ",
+            " (___.__)__) ",
+            "             ",
+        ],
+        WeatherKind::Fog => [
+            "             ",
+            " _ - _ - _ - ",
+            "  _ - _ - _  ",
+            " _ - _ - _ - ",
+            "             ",
+        ],
+        WeatherKind::Rain => [
+            "     .-.     ",
+            "    (   ).   ",
+            "   (___(__)  ",
+            "    ‘ ‘ ‘ ‘  ",
+            "   ‘ ‘ ‘ ‘   ",
+        ],
+        WeatherKind::Snow => [
+            "     .-.     ",
+            "    (   ).   ",
+            "   (___(__)  ",
+            "    *  *  *  ",
+            "   *  *  *   ",
+        ],
+        WeatherKind::Drizzle => [
+            "     .-.     ",
+            "    (   ).   ",
+            "   (___(__)  ",
+            "    ‚  ‚  ‚  ",
+            "   ‚  ‚  ‚   ",
+        ],
+        WeatherKind::Thunderstorm => [
+            "     .-.     ",
+            "    (   ).   ",
+            "   (___(__)  ",
+            "    ‘ /_ ‘   ",
+            "   ‘  /  ‘   ",
+        ],
+        WeatherKind::Sleet => [
+            "     .-.     ",
+            "    (   ).   ",
+            "   (___(__)  ",
+            "    ‘ * ‘ *  ",
+            "   * ‘ * ‘   ",
+        ],
+        WeatherKind::Hail => [
+            "     .-.     ",
+            "    (   ).   ",
+            "   (___(__)  ",
+            "    o  o  o  ",
+            "   o  o  o   ",
+        ],
+        WeatherKind::Windy => [
+            "             ",
+            "  ~~~~~~~~>  ",
+            "   ~~~~~~>   ",
+            "  ~~~~~~~~>  ",
+            "             ",
+        ],
+        WeatherKind::Unknown => [
+            "    .-.      ",
+            "     __)     ",
+            "    (        ",
+            "     `-’     ",
+            "      •      ",
+        ],
+    }
+}
@@ -0,0 +1,189 @@
+//! Terminal charts for multi-entry forecasts
+use std::fmt::Write;
+
+use crate::provider::WeatherInfo;
+use crate::units::Units;
+
+/// Block characters used for sparklines, from lowest to highest
+const SPARK_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Width of longest bar in bar charts, in characters
+const BAR_WIDTH: usize = 30;
+
+/// Renders values as single-line sparkline
+///
+/// # Parameters
+/// * `values` - values to render
+///
+/// # Returns
+/// String with one block character per value
+pub fn sparkline(values: &[f32]) -> String {
+    let (min, max) = bounds(values);
+    values
+        .iter()
+        .map(|value| {
+            let level = if max > min {
+                ((value - min) / (max - min) * (SPARK_BLOCKS.len() - 1) as f32).round() as usize
+            } else {
+                SPARK_BLOCKS.len() / 2
+            };
+            SPARK_BLOCKS[level.min(SPARK_BLOCKS.len() - 1)]
+        })
+        .collect()
+}
+/// Renders labelled values as horizontal bar chart, one line per value
+///
+/// Bars start at minimum of zero and smallest value, so negative temperatures are still visible
+///
+/// # Parameters
+/// * `entries` - pairs of label and value
+/// * `unit` - unit suffix printed after each value
+///
+/// # Returns
+/// Multi-line chart text
+pub fn bar_chart(entries: &[(String, f32)], unit: &str) -> String {
+    let values: Vec<f32> = entries.iter().map(|(_, value)| *value).collect();
+    let (min, max) = bounds(&values);
+    let base = min.min(0.0);
+    let span = (max - base).max(f32::EPSILON);
+    let label_width = entries
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut buf = String::new();
+    for (label, value) in entries {
+        let len = ((value - base) / span * BAR_WIDTH as f32).round() as usize;
+        let _ = writeln!(
+            buf,
+            "{label:<label_width$} │{:<BAR_WIDTH$} {value:.1}{unit}",
+            "█".repeat(len)
+        );
+    }
+    buf
+}
+/// Renders forecast entries as temperature and, if available, precipitation charts
+///
+/// # Parameters
+/// * `entries` - pairs of entry label, like date, and forecast
+/// * `units` - units in which values are shown
+///
+/// # Returns
+/// Multi-line chart text
+pub fn render_forecast(entries: &[(String, WeatherInfo)], units: Units) -> String {
+    let temperatures: Vec<(String, f32)> = entries
+        .iter()
+        .map(|(label, info)| (label.clone(), units.temperature(info.temperature)))
+        .collect();
+
+    let mut buf = String::new();
+    let _ = writeln!(
+        buf,
+        "Temperature {}",
+        sparkline(&temperatures.iter().map(|(_, t)| *t).collect::<Vec<_>>())
+    );
+    buf.push_str(&bar_chart(&temperatures, units.temperature_unit()));
+
+    let precipitation: Vec<(String, f32)> = entries
+        .iter()
+        .filter_map(|(label, info)| {
+            info.precipitation
+                .map(|p| (label.clone(), units.precipitation(p)))
+        })
+        .collect();
+    if !precipitation.is_empty() {
+        buf.push_str("\nPrecipitation\n");
+        buf.push_str(&bar_chart(
+            &precipitation,
+            &format!(" {}", units.precipitation_unit()),
+        ));
+    }
+    buf
+}
+/// Minimum and maximum of values; zeroes if there are no values
+fn bounds(values: &[f32]) -> (f32, f32) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    values.iter().fold((f32::MAX, f32::MIN), |(min, max), v| {
+        (min.min(*v), max.max(*v))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bar_chart, render_forecast, sparkline};
+    use crate::provider::{WeatherInfo, WeatherKind};
+    use crate::units::Units;
+
+    #[test]
+    fn sparkline_spans_all_levels() {
+        assert_eq!(
+            sparkline(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]),
+            "▁▂▃▄▅▆▇█"
+        );
+        // Levels are relative to range, whatever its offset and scale
+        assert_eq!(sparkline(&[-20.0, -10.0, 0.0]), "▁▅█");
+        assert_eq!(sparkline(&[100.0, 100.5]), "▁█");
+    }
+
+    #[test]
+    fn constant_series_is_mid_level() {
+        assert_eq!(sparkline(&[3.0, 3.0, 3.0]), "▅▅▅");
+        assert_eq!(
+            bar_chart(&[("a".into(), 0.0), ("b".into(), 0.0)], "°C"),
+            format!("a │{:30} 0.0°C\nb │{:30} 0.0°C\n", "", "")
+        );
+    }
+
+    #[test]
+    fn empty_input_renders_nothing() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(bar_chart(&[], "°C"), "");
+        assert_eq!(render_forecast(&[], Units::default()), "Temperature \n");
+    }
+
+    #[test]
+    fn bars_are_scaled_from_zero() {
+        let chart = bar_chart(
+            &[
+                ("Mon".into(), 10.0),
+                ("Tuesday".into(), 20.0),
+                ("Wed".into(), -10.0),
+            ],
+            "°C",
+        );
+        let bars: Vec<_> = chart
+            .lines()
+            .map(|line| line.matches('█').count())
+            .collect();
+        // Bars start at smallest value when it's negative, and longest one fills whole width
+        assert_eq!(bars, [20, 30, 0]);
+        assert!(chart.starts_with("Mon     │"), "{chart}");
+        assert!(
+            chart.lines().nth(1).unwrap().ends_with(" 20.0°C"),
+            "{chart}"
+        );
+    }
+
+    #[test]
+    fn precipitation_is_charted_when_known() {
+        let info = |temperature, precipitation| WeatherInfo {
+            wind_speed: 1.0,
+            humidity: 80.0,
+            precipitation,
+            ..WeatherInfo::sample(WeatherKind::Rain, temperature)
+        };
+        let chart = render_forecast(
+            &[
+                ("12:00".into(), info(10.0, None)),
+                ("13:00".into(), info(12.0, Some(2.0))),
+            ],
+            Units::default(),
+        );
+        assert!(chart.starts_with("Temperature ▁█\n"), "{chart}");
+        let (_, precipitation) = chart.split_once("\nPrecipitation\n").unwrap();
+        assert!(precipitation.starts_with("13:00 │"), "{chart}");
+        assert!(!precipitation.contains("12:00"), "{chart}");
+    }
+}
@@ -0,0 +1,158 @@
+//! Trace file in Chrome's Trace Event format, which shows where time of command goes
+//!
+//! Each closed span, like provider call or HTTP request, becomes complete event with span's fields
+//! as arguments. File can be opened in `chrome://tracing`, Perfetto UI or Speedscope.
+//! Spans are laid out in rows by their root span, so concurrent queries don't overlap
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context};
+use serde_json::{json, Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context as LayerContext, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::redact;
+
+/// Layer which collects closed spans as trace events
+pub struct ChromeLayer {
+    /// Moment from which events' timestamps are counted
+    start: Instant,
+    events: Arc<Mutex<Vec<Value>>>,
+}
+/// Writes collected events into trace file when dropped, i.e. when command completes
+pub struct TraceGuard {
+    path: PathBuf,
+    events: Arc<Mutex<Vec<Value>>>,
+}
+/// Span's start and fields, kept in span's extensions while it's open
+struct Timing {
+    start: Instant,
+    fields: Map<String, Value>,
+}
+/// Collects span's fields into JSON object
+struct FieldVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), json!(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+}
+/// Creates layer along with guard which writes trace file
+///
+/// # Parameters
+/// * `path` - trace file, overwritten if it exists
+pub fn layer(path: PathBuf) -> (ChromeLayer, TraceGuard) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    (
+        ChromeLayer {
+            start: Instant::now(),
+            events: events.clone(),
+        },
+        TraceGuard { path, events },
+    )
+}
+
+impl<S> Layer<S> for ChromeLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Map::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        span.extensions_mut().insert(Timing {
+            start: Instant::now(),
+            fields,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<Timing>() {
+            values.record(&mut FieldVisitor(&mut timing.fields));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<Timing>() else {
+            return;
+        };
+        let row = span
+            .scope()
+            .from_root()
+            .next()
+            .map_or(id.into_u64(), |root| root.id().into_u64());
+        let event = json!({
+            "name": span.name(),
+            "cat": span.metadata().target(),
+            "ph": "X",
+            "ts": timing.start.duration_since(self.start).as_micros() as u64,
+            "dur": timing.start.elapsed().as_micros() as u64,
+            "pid": std::process::id(),
+            "tid": row,
+            "args": timing.fields,
+        });
+        self.events
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .push(event);
+    }
+}
+
+impl TraceGuard {
+    /// Writes trace file
+    fn write(&self) -> anyhow::Result<()> {
+        let events = std::mem::take(
+            &mut *self
+                .events
+                .lock()
+                .unwrap_or_else(|error| error.into_inner()),
+        );
+        // Fields may carry locations and URLs, so known secrets are masked as in logs
+        let text = redact::redact(&json!({ "traceEvents": events }).to_string());
+        fs::write(&self.path, text)
+            .with_context(|| anyhow!("When writing trace file {}", self.path.display()))
+    }
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        if let Err(error) = self.write() {
+            eprintln!("{error:#}");
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Guard against skewed local clock
+//!
+//! Date-sensitive logic, like resolving "today" or checking cache age, relies on local clock.
+//! To detect broken clocks, `Date` headers of HTTP responses are compared with local time;
+//! significant difference is reported once, and, if compensation is enabled,
+//! corrects time returned by `now` from then on
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+
+use chrono::{DateTime, Duration, Local, Utc};
+
+/// Difference between server and local clocks, in seconds, at which user is warned
+const SKEW_THRESHOLD_SECONDS: i64 = 300;
+
+/// Whether detected skew is added to local time
+static COMPENSATE: AtomicBool = AtomicBool::new(false);
+/// Latest detected skew, server time minus local time, in seconds
+static SKEW_SECONDS: AtomicI64 = AtomicI64::new(0);
+/// Whether user was already warned about skew
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Turns compensation of detected skew on or off
+pub fn set_compensation(enabled: bool) {
+    COMPENSATE.store(enabled, Ordering::Relaxed);
+}
+/// Compares server's time with local one
+///
+/// # Parameters
+/// * `date` - value of HTTP `Date` header, like "Sun, 06 Nov 1994 08:49:37 GMT";
+///   unparseable values are ignored
+pub fn observe(date: &str) {
+    let Ok(server) = DateTime::parse_from_rfc2822(date) else {
+        return;
+    };
+    let skew = (server.with_timezone(&Utc) - Utc::now()).num_seconds();
+    if skew.abs() < SKEW_THRESHOLD_SECONDS {
+        SKEW_SECONDS.store(0, Ordering::Relaxed);
+        return;
+    }
+    SKEW_SECONDS.store(skew, Ordering::Relaxed);
+
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        let compensation = if COMPENSATE.load(Ordering::Relaxed) {
+            "it's compensated"
+        } else {
+            "use '--compensate-clock-skew' to compensate it"
+        };
+        tracing::warn!(
+            "Local clock is {} minutes {} server's one, so dates like \"today\" may be wrong; {compensation}",
+            skew.abs() / 60,
+            if skew > 0 { "behind" } else { "ahead of" }
+        );
+    }
+}
+/// Current local time, corrected by detected skew if compensation is enabled
+pub fn now() -> DateTime<Local> {
+    let now = Local::now();
+    if COMPENSATE.load(Ordering::Relaxed) {
+        now + Duration::seconds(SKEW_SECONDS.load(Ordering::Relaxed))
+    } else {
+        now
+    }
+}
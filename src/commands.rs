@@ -0,0 +1,221 @@
+//! Handlers of CLI commands, along with helpers shared by them
+use anyhow::{anyhow, ensure, Context};
+use chrono::Timelike;
+
+use crate::condition_mapping::{ConditionMapping, MappedProvider};
+use crate::config::Config;
+use crate::date::Date;
+use crate::geocode::Geocoder;
+use crate::provider_registry::ProviderRegistry;
+use crate::{geocode, layered_config, run_future, timezone, ACTIVE_ENTRY, MAX_PLACE_CANDIDATES};
+
+pub mod configure;
+pub mod dashboard;
+pub mod forecast;
+pub mod manage;
+pub mod providers;
+pub mod reports;
+
+/// Creates provider instance by its name or, if name isn't specified, default provider
+///
+/// # Returns
+/// Actual provider name and provider instance with user's condition mapping overrides
+pub fn create_provider(
+    registry: &ProviderRegistry,
+    config: &Config,
+    provider: Option<String>,
+) -> anyhow::Result<(String, MappedProvider)> {
+    // Fetch actual provider name
+    let provider_name = if let Some(provider) = provider {
+        provider
+    } else {
+        config.globals.get(ACTIVE_ENTRY)
+            .ok_or_else(|| anyhow!(
+                "Active provider not specified. Please use `-sp <provider_name>` to specify new default one"
+            ))?
+            .clone()
+    };
+    tracing::info!("Using provider '{provider_name}'");
+    // Create factory
+    let factory = registry
+        .get(provider_name.as_str())
+        .ok_or_else(|| anyhow!("No such provider: {provider_name}"))?;
+    // Get provider's config, with environment overrides
+    let prov_config =
+        layered_config::provider_section(config, &provider_name, factory.info().params)
+            .ok_or_else(|| anyhow!("Missing config for provider '{provider_name}'"))?;
+    // Spawn provider
+    let provider = factory
+        .create(&prov_config)
+        .with_context(|| anyhow!("When trying to construct provider '{provider_name}'"))?;
+    // Apply user's overrides of condition mappings, if any
+    let mapping = match config.mappings.get(provider_name.as_str()) {
+        Some(section) => ConditionMapping::from_section(section).with_context(|| {
+            anyhow!("When reading condition mappings of provider '{provider_name}'")
+        })?,
+        None => ConditionMapping::default(),
+    };
+
+    let provider = MappedProvider::new(&provider_name, provider, mapping);
+    Ok((provider_name, provider))
+}
+/// Resolves days of requested forecast
+///
+/// # Parameters
+/// * `date` - first day, "YYYY-MM-DD", "YYYY-MM-DD HH:MM", weekday like "friday" or "now"
+/// * `days` - number of consecutive days
+/// * `tz` - location's timezone, in which "now" and weekdays are resolved; `None` means machine's local one
+///
+/// # Returns
+/// Each day along with date it's requested for; "now" means current conditions,
+/// so only following days are requested by date
+pub fn forecast_days(
+    date: &str,
+    days: u32,
+    tz: Option<chrono_tz::Tz>,
+) -> anyhow::Result<Vec<(chrono::NaiveDate, Option<Date>)>> {
+    ensure!(days > 0, "Number of days should be positive");
+    let date = if date == "now" {
+        None
+    } else {
+        Some(Date::parse_in(date, tz).with_context(|| anyhow!("Could not parse forecast date"))?)
+    };
+    let start = match date {
+        Some(date) => date
+            .to_naive()
+            .ok_or_else(|| anyhow!("Forecast date {date} doesn't exist"))?,
+        None => timezone::today(tz),
+    };
+    // Hour of day, if specified, is requested on each day
+    let hour = date.and_then(|date| date.hour);
+    Ok(start
+        .iter_days()
+        .take(days as usize)
+        .map(|day| {
+            let request_date =
+                (date.is_some() || day != start).then(|| Date::from(day).with_hour(hour));
+            (day, request_date)
+        })
+        .collect())
+}
+/// First hour of hourly forecast
+///
+/// # Parameters
+/// * `day` - first day of forecast
+/// * `request_date` - date requested from provider for that day; `None` means current conditions
+/// * `tz` - location's timezone; `None` means machine's local one
+///
+/// # Returns
+/// Requested hour of day, current hour for current conditions, or midnight
+pub fn first_hour(
+    day: chrono::NaiveDate,
+    request_date: Option<Date>,
+    tz: Option<chrono_tz::Tz>,
+) -> Date {
+    let hour = match request_date {
+        Some(date) => date.hour.unwrap_or(0),
+        None => timezone::now(tz).hour() as u8,
+    };
+    Date::from(day).with_hour(Some(hour))
+}
+/// Result of looking address up
+pub enum Lookup {
+    /// Location passed to provider, which needs no choice
+    Resolved(String),
+    /// Places matching address, most relevant first; never empty
+    Places(Vec<geocode::Candidate>),
+}
+/// Looks address up, without picking place if address is ambiguous
+///
+/// Addresses which geocoding service doesn't know, or which can't be looked up due to service failure,
+/// are passed to provider as is, since provider's own lookup may still know them
+///
+/// # Parameters
+/// * `geocoder` - geocoding service
+/// * `address` - address or coordinates
+pub async fn lookup_address(geocoder: &dyn Geocoder, address: &str) -> Lookup {
+    if geocode::parse_coordinates(address).is_some() {
+        return Lookup::Resolved(address.to_string());
+    }
+    if let Some(place) = geocode::remembered(geocoder, address) {
+        tracing::debug!(
+            "Using remembered {place} ({}) for '{address}'",
+            place.coordinates()
+        );
+        return Lookup::Resolved(place.coordinates());
+    }
+    match geocode::search(geocoder, address, MAX_PLACE_CANDIDATES).await {
+        Ok(candidates) if !candidates.is_empty() => Lookup::Places(candidates),
+        Ok(_) => Lookup::Resolved(address.to_string()),
+        Err(error) => {
+            tracing::warn!(
+                "Could not look up '{address}', passing it to provider as is: {error:#}"
+            );
+            Lookup::Resolved(address.to_string())
+        }
+    }
+}
+/// Picks single place out of looked up ones, asking user if address is ambiguous
+///
+/// Places picked for addresses are remembered, so following runs don't look them up again
+///
+/// # Parameters
+/// * `geocoder` - geocoding service which looked address up
+/// * `address` - address or coordinates
+/// * `lookup` - result of looking address up
+/// * `first` - pick most relevant place without asking
+///
+/// # Returns
+/// Location passed to provider
+pub fn pick_place(
+    geocoder: &dyn Geocoder,
+    address: &str,
+    lookup: Lookup,
+    first: bool,
+) -> anyhow::Result<String> {
+    let candidates = match lookup {
+        Lookup::Resolved(location) => return Ok(location),
+        Lookup::Places(candidates) => candidates,
+    };
+    let place = geocode::choose(address, candidates, first)?;
+    tracing::info!("Using {place} ({}) for '{address}'", place.coordinates());
+    if let Err(error) = geocode::remember(geocoder, address, &place) {
+        tracing::warn!("Could not remember place of '{address}': {error:#}");
+    }
+    Ok(place.coordinates())
+}
+/// Resolves address into coordinates of single place, asking user to pick one if address is ambiguous
+///
+/// # Parameters
+/// * `geocoder` - geocoding service
+/// * `address` - address or coordinates
+/// * `first` - pick most relevant place without asking
+///
+/// # Returns
+/// Location passed to provider
+pub fn resolve_address(
+    geocoder: &dyn Geocoder,
+    address: &str,
+    first: bool,
+) -> anyhow::Result<String> {
+    let lookup = run_future(async { Ok(lookup_address(geocoder, address).await) })?;
+    pick_place(geocoder, address, lookup, first)
+}
+/// Replaces location alias with address it stands for; other addresses are returned as is
+///
+/// # Parameters
+/// * `config` - configuration with `[locations]` section
+/// * `address` - alias or address
+pub fn expand_alias(config: &Config, address: String) -> String {
+    config.locations.get(&address).cloned().unwrap_or(address)
+}
+/// Names of providers configured either in config file or in environment
+pub fn configured_providers(registry: &ProviderRegistry, config: &Config) -> Vec<String> {
+    registry
+        .iter()
+        .filter(|(id, factory)| {
+            layered_config::provider_section(config, id, factory.info().params).is_some()
+        })
+        .map(|(id, _)| id.to_string())
+        .collect()
+}
@@ -0,0 +1,265 @@
+//! Configuration of providers, either with command's parameters or by guided setup
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Context};
+
+use crate::config::{Config, Section};
+use crate::provider::{ParamDesc, ParamKind, ProviderInfo};
+use crate::provider_registry::ProviderRegistry;
+use crate::{
+    config, geocode, layered_config, logging, run_future, units, ACTIVE_ENTRY,
+    DEFAULT_CONFIGURE_LOCATION, PROFILE_ENTRY, PROFILE_LOCATION, PROFILE_PROVIDER, SETUP_PROFILE,
+};
+
+use super::{expand_alias, resolve_address};
+
+/// Configures specified provider, either with provided key-value parameters or interactively
+///
+/// Parameters given on command line update existing provider's configuration, unless `reset` is set
+///
+/// # Parameters
+/// * `test_location` - location of weather request which checks configuration; `None` skips the check
+/// * `reset` - whether to drop existing provider's configuration instead of updating it
+pub fn configure_provider(
+    registry: &ProviderRegistry,
+    config: &mut Config,
+    provider: String,
+    parameters: Vec<String>,
+    test_location: Option<String>,
+    reset: bool,
+) -> anyhow::Result<()> {
+    // Check that provider is valid and get factory
+    let factory = registry
+        .get(provider.as_str())
+        .ok_or_else(|| anyhow!("No such provider: {provider}"))?;
+
+    let ProviderInfo { params, .. } = factory.info();
+    // Generate new config
+    let mut new_config = Section::new();
+    // Interactive configuration; optional parameters aren't asked for
+    if parameters.is_empty() && params.iter().any(|param| param.required) {
+        for ParamDesc { id, name, kind, .. } in params.iter().filter(|param| param.required) {
+            // Ask again until value fits
+            loop {
+                println!("Please enter {name}:");
+                let mut buffer = String::new();
+                if std::io::stdin().read_line(&mut buffer)? == 0 {
+                    bail!("Input ended before parameter '{id}' was entered");
+                }
+                let value = buffer.trim();
+                match kind.validate(value) {
+                    Ok(()) => {
+                        new_config.insert(id.to_string(), value.to_string());
+                        break;
+                    }
+                    Err(error) => println!("{error}"),
+                }
+            }
+        }
+    }
+    // Batch configuration
+    else {
+        // Only specified parameters are changed, unless configuration starts from scratch
+        if !reset {
+            if let Some(existing) = config.sections.get(&provider) {
+                new_config = existing.clone();
+            }
+        }
+        for param in parameters {
+            let (name, value) = param.split_once('=').ok_or_else(|| {
+                anyhow!("Argument '{param}' cannot be parsed as '<name>=<value>' parameter")
+            })?;
+            // Check that parameter is accepted by provider
+            // NB: Yes, it's a linear search.
+            // Doesn't matter here - we have very few parameters,
+            // so may be even faster than build dictionary
+            let desc = params
+                .iter()
+                .find(|param| param.id == name)
+                .ok_or_else(|| {
+                    anyhow!("Parameter '{name}' isn't accepted by provider '{provider}'")
+                })?;
+            desc.kind
+                .validate(value)
+                .with_context(|| anyhow!("Invalid value of parameter '{name}'"))?;
+
+            new_config.insert(name.to_string(), value.to_string());
+        }
+        // Check that all required parameters are present
+        for ParamDesc { id, .. } in params.iter().filter(|param| param.required) {
+            ensure!(
+                new_config.contains_key(*id),
+                "Parameter '{id}' is required by provider '{provider}'"
+            )
+        }
+    }
+    // Perform simple request to check configuration is actually valid, unless it's skipped
+    {
+        let prov_config_error = || || anyhow!("When configuring {provider}");
+
+        // Defaults aren't written into config, so they may change along with provider
+        let mut effective = new_config.clone();
+        layered_config::apply_config_defaults(config, &provider, &mut effective, params);
+        layered_config::apply_defaults(&mut effective, params);
+        let provider = factory
+            .create(&effective)
+            .with_context(prov_config_error())?;
+
+        if let Some(location) = test_location {
+            let _ = run_future(provider.get_weather(location.into(), None))
+                .with_context(prov_config_error())?;
+        }
+    }
+    // If check succeeded, write new config entry; if config was empty prior to first configure,
+    // set new provider as default one
+    if config.sections.is_empty() {
+        config.globals.insert(ACTIVE_ENTRY.into(), provider.clone());
+    }
+    config.sections.insert(provider, new_config);
+    config.mark_dirty();
+
+    Ok(())
+}
+/// Whether application runs for the first time, i.e. interactively and without config file
+pub fn first_run() -> bool {
+    use std::io::IsTerminal;
+
+    std::io::stdin().is_terminal()
+        && std::io::stdout().is_terminal()
+        && config::default_path().is_ok_and(|path| !path.exists())
+}
+/// Guided setup: lists providers, configures and checks one of them, then asks for default
+/// location and units, which are written as default profile
+///
+/// # Parameters
+/// * `registry` - known providers
+/// * `config` - configuration to fill in
+pub fn run_setup(registry: &ProviderRegistry, config: &mut Config) -> anyhow::Result<()> {
+    println!("Welcome! Let's choose weather provider. Available ones are:");
+    let providers: Vec<_> = registry.iter().collect();
+    for (index, (id, factory)) in providers.iter().enumerate() {
+        let ProviderInfo {
+            description,
+            params,
+            ..
+        } = factory.info();
+        let mut required = params.iter().filter(|param| param.required).peekable();
+        let needs = if required
+            .clone()
+            .any(|param| matches!(param.kind, ParamKind::Secret))
+        {
+            "needs API key"
+        } else if required.peek().is_some() {
+            "needs account or station settings"
+        } else {
+            "works without key"
+        };
+        println!("{:>3}. {id} ({needs}): {description}", index + 1);
+    }
+    // Ask again until provider is configured and responds
+    let provider = loop {
+        let answer = ask("Provider number or name:", None)?;
+        let name = match answer.parse::<usize>() {
+            Ok(number) if (1..=providers.len()).contains(&number) => {
+                providers[number - 1].0.to_string()
+            }
+            _ => answer,
+        };
+        match configure_provider(
+            registry,
+            config,
+            name.clone(),
+            Vec::new(),
+            Some(DEFAULT_CONFIGURE_LOCATION.to_string()),
+            false,
+        ) {
+            Ok(()) => break name,
+            Err(error) => println!("{error:#}; let's try again"),
+        }
+    };
+    // Location is kept as typed, so profile stays readable and follows alias changes;
+    // place picked for it is remembered, so it isn't looked up or asked for again
+    let location = loop {
+        let address = ask("Default location:", Some(DEFAULT_CONFIGURE_LOCATION))?;
+        let geocoder = geocode::from_config(config)?;
+        match resolve_address(
+            geocoder.as_ref(),
+            &expand_alias(config, address.clone()),
+            false,
+        ) {
+            Ok(_) => break address,
+            Err(error) => println!("{error:#}; let's try again"),
+        }
+    };
+    let units = loop {
+        let answer = ask("Units, metric or imperial:", Some("metric"))?;
+        match units::UnitSystem::from_str(&answer) {
+            Ok(_) => break answer,
+            Err(error) => println!("{error:#}"),
+        }
+    };
+    let profile = config.section_mut(Some(&format!("profile.{SETUP_PROFILE}")));
+    profile.insert(PROFILE_PROVIDER.into(), provider.clone());
+    profile.insert(PROFILE_LOCATION.into(), location);
+    profile.insert(units::PROFILE_UNITS.into(), units);
+    config.globals.insert(ACTIVE_ENTRY.into(), provider);
+    config
+        .globals
+        .insert(PROFILE_ENTRY.into(), SETUP_PROFILE.into());
+    config.mark_dirty();
+    logging::status("All set; try 'weather get' to see today's weather");
+    Ok(())
+}
+/// Asks user question on stdout and reads answer from stdin
+///
+/// # Parameters
+/// * `question` - question text
+/// * `default` - answer assumed when user enters nothing
+///
+/// # Returns
+/// Trimmed answer
+fn ask(question: &str, default: Option<&str>) -> anyhow::Result<String> {
+    match default {
+        Some(default) => println!("{question} [{default}]"),
+        None => println!("{question}"),
+    }
+    let mut buffer = String::new();
+    if std::io::stdin().read_line(&mut buffer)? == 0 {
+        bail!("Input ended before setup was complete");
+    }
+    let answer = buffer.trim();
+    Ok(match (answer, default) {
+        ("", Some(default)) => default.to_string(),
+        _ => answer.to_string(),
+    })
+}
+/// Clear either specified or all providers
+pub fn clear_providers(
+    registry: &ProviderRegistry,
+    config: &mut Config,
+    providers: Vec<String>,
+) -> anyhow::Result<()> {
+    // Walk all mentioned providers and remove them
+    for prov_name in &providers {
+        // "all" means all providers
+        if prov_name == "all" {
+            for name in registry.keys() {
+                config.sections.remove(name.as_ref());
+            }
+        } else if registry.contains_key(prov_name.as_str()) {
+            config.sections.remove(prov_name);
+        } else {
+            bail!("No such provider: {prov_name}");
+        }
+    }
+    // If there's default entry, and default provider isn't registered,
+    // clear it
+    if let Some(default_entry) = config.globals.get(ACTIVE_ENTRY) {
+        if !config.sections.contains_key(default_entry.as_str()) {
+            config.globals.remove(ACTIVE_ENTRY);
+        }
+    }
+    config.mark_dirty();
+
+    Ok(())
+}
@@ -0,0 +1,300 @@
+//! Commands which handle several places at once: dashboard, prefetching, publishing and serving
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, ensure, Context};
+use serde_json::json;
+
+use crate::attribution::Credits;
+use crate::condition::Condition;
+use crate::condition_mapping::MappedProvider;
+use crate::config::Config;
+use crate::date::Date;
+use crate::provider_registry::ProviderRegistry;
+use crate::sink::Sink;
+use crate::units::Units;
+use crate::{
+    layered_config, metrics, places, rules, run_future, server, terminal, ACTIVE_ENTRY,
+    PROFILE_ALERT, PROFILE_LOCATION, PROFILE_PROVIDER,
+};
+
+use super::forecast::{forecasts_json, forecasts_text};
+use super::{create_provider, expand_alias};
+
+/// Publishes current weather to sink, either once or periodically
+///
+/// # Parameters
+/// * `address` - location for which weather is published
+/// * `provider` - name of provider, included into published object, and provider instance
+/// * `sink` - destination of published weather
+/// * `interval` - interval between publications; if not specified, weather is published once
+/// * `alerts` - alerts published when their conditions start to hold
+/// * `notify` - whether triggered alerts are also posted as desktop notifications
+/// * `units` - units of formatted weather
+pub async fn publish_weather(
+    address: String,
+    (provider_name, provider): (String, MappedProvider),
+    mut sink: Box<dyn Sink>,
+    interval: Option<Duration>,
+    alerts: rules::Rules,
+    notify: bool,
+    units: Units,
+) -> anyhow::Result<()> {
+    // Alerts which held on previous refresh, so they aren't repeated while condition persists
+    let mut active = BTreeSet::new();
+    loop {
+        let info = provider
+            .get_weather(address.clone().into(), None)
+            .await
+            .with_context(|| anyhow!("When performing forecast request"))?;
+        let forecasts = [(Date::today_at(&address), info)];
+        let payload = json!({
+            "location": address,
+            "provider": provider_name,
+            "forecasts": forecasts_json(&forecasts),
+            "text": forecasts_text(&address, &forecasts, units),
+        });
+        sink.publish(&payload.to_string())
+            .await
+            .with_context(|| anyhow!("When publishing weather"))?;
+
+        let [(_, info)] = &forecasts;
+        let triggered: BTreeSet<_> = alerts.matching(info).map(str::to_string).collect();
+        for alert in triggered.difference(&active) {
+            tracing::info!("Alert '{alert}' triggered at {address}");
+            let mut payload = payload.clone();
+            payload["alert"] = json!(alert);
+            sink.publish(&payload.to_string())
+                .await
+                .with_context(|| anyhow!("When publishing alert '{alert}'"))?;
+            if notify {
+                notify_rust::Notification::new()
+                    .appname("weather")
+                    .summary(&format!("Weather alert '{alert}' in {address}"))
+                    .body(&units.display(info).to_string())
+                    .show()
+                    .with_context(|| anyhow!("Could not post desktop notification"))?;
+            }
+        }
+        active = triggered;
+
+        let Some(interval) = interval else {
+            return Ok(());
+        };
+        sink.idle(interval).await?;
+    }
+}
+/// Prints dashboard with current weather at locations of all profiles
+///
+/// # Parameters
+/// * `max_age` - how long recently obtained weather is reused, in seconds
+/// * `units` - units in which weather is shown
+pub fn places_dashboard(
+    registry: &ProviderRegistry,
+    config: &Config,
+    max_age: i64,
+    units: Units,
+) -> anyhow::Result<()> {
+    let mut places = Vec::new();
+    let mut providers = BTreeMap::new();
+    for (name, profile) in &config.profiles {
+        let Some(location) = profile.get(PROFILE_LOCATION) else {
+            continue;
+        };
+        let alert = profile
+            .get(PROFILE_ALERT)
+            .map(|alert| Condition::from_str(alert))
+            .transpose()
+            .with_context(|| anyhow!("Could not parse alert condition of profile '{name}'"))?;
+        let provider = profile.get(PROFILE_PROVIDER).cloned();
+        let (provider_name, provider) = create_provider(registry, config, provider)?;
+        providers
+            .entry(provider_name.clone())
+            .or_insert_with(|| Arc::new(provider));
+        places.push(places::Place {
+            name: name.clone(),
+            location: expand_alias(config, location.clone()),
+            provider: provider_name,
+            alert,
+        });
+    }
+    ensure!(
+        !places.is_empty(),
+        "No profiles with locations. Please add 'location' entries to '[profile.<name>]' sections"
+    );
+
+    let jobs = places.len();
+    let results = run_future(places::fetch(
+        &places,
+        &providers,
+        chrono::Duration::seconds(max_age),
+        jobs,
+    ))?;
+    let credits = Credits::new(
+        config,
+        providers
+            .keys()
+            .filter_map(|name| registry.get(name.as_str()))
+            .map(|factory| factory.info()),
+    )?;
+    if let Some(header) = credits.header() {
+        println!("{header}\n");
+    }
+    print!(
+        "{}",
+        terminal::fit(&places::render(&places, &results, units))
+    );
+    if let Some(footer) = credits.footer() {
+        println!("\n{footer}");
+    }
+    Ok(())
+}
+/// Refreshes cached current weather at locations of profiles and location aliases;
+/// failures are logged
+///
+/// # Parameters
+/// * `jobs` - number of locations fetched concurrently
+///
+/// # Returns
+/// Number of refreshed locations and number of all saved ones
+pub fn prefetch_places(
+    registry: &ProviderRegistry,
+    config: &Config,
+    jobs: usize,
+) -> anyhow::Result<(usize, usize)> {
+    // Aliases are fetched with default provider, same as 'get' uses for them
+    let saved = config
+        .profiles
+        .iter()
+        .filter_map(|(name, profile)| {
+            let location = profile.get(PROFILE_LOCATION)?;
+            Some((name, location, profile.get(PROFILE_PROVIDER).cloned()))
+        })
+        .chain(config.locations.keys().map(|alias| (alias, alias, None)));
+    let mut places: Vec<places::Place> = Vec::new();
+    let mut providers = BTreeMap::new();
+    for (name, location, provider) in saved {
+        let (provider_name, provider) = create_provider(registry, config, provider)?;
+        let location = expand_alias(config, location.clone());
+        if places
+            .iter()
+            .any(|place| place.provider == provider_name && place.location == location)
+        {
+            continue;
+        }
+        providers
+            .entry(provider_name.clone())
+            .or_insert_with(|| Arc::new(provider));
+        places.push(places::Place {
+            name: name.clone(),
+            location,
+            provider: provider_name,
+            alert: None,
+        });
+    }
+    ensure!(
+        !places.is_empty(),
+        "No saved locations. Please add profiles with 'location' entries or location aliases"
+    );
+    // Zero age makes every place refreshed
+    let results = run_future(places::fetch(
+        &places,
+        &providers,
+        chrono::Duration::zero(),
+        jobs,
+    ))?;
+    let mut refreshed = 0;
+    for (place, result) in places.iter().zip(&results) {
+        match result {
+            Ok(_) => refreshed += 1,
+            Err(error) => tracing::warn!(
+                "Could not refresh '{}' with provider '{}': {error}",
+                place.location,
+                place.provider
+            ),
+        }
+    }
+    Ok((refreshed, places.len()))
+}
+/// Serves forecasts of all configured providers over HTTP until process is terminated
+///
+/// # Parameters
+/// * `default_provider` - provider used when request doesn't specify one;
+///   if not specified, default provider from config is used
+/// * `listen` - address to listen on
+/// * `cache_ttl` - how long successful responses are reused, in seconds
+/// * `metrics_locations` - locations to export metrics for, besides ones of profiles
+/// * `metrics_interval` - how often metrics are refreshed, in seconds
+pub fn serve_forecasts(
+    registry: &ProviderRegistry,
+    config: &Config,
+    default_provider: Option<String>,
+    listen: SocketAddr,
+    cache_ttl: u64,
+    metrics_locations: Vec<String>,
+    metrics_interval: u64,
+) -> anyhow::Result<()> {
+    let mut providers = BTreeMap::new();
+    let mut attributions = BTreeMap::new();
+    for (name, factory) in registry.iter() {
+        if layered_config::provider_section(config, name, factory.info().params).is_some() {
+            let (name, provider) = create_provider(registry, config, Some(name.to_string()))?;
+            attributions.insert(name.clone(), Credits::new(config, [factory.info()])?.json());
+            providers.insert(name, provider);
+        }
+    }
+    ensure!(
+        !providers.is_empty(),
+        "No providers are configured. Please use `configure` command first"
+    );
+    let default_provider = default_provider.or_else(|| config.globals.get(ACTIVE_ENTRY).cloned());
+
+    // Export metrics for explicitly specified locations and ones of profiles,
+    // either of which may be alias
+    let profile_locations = config
+        .profiles
+        .values()
+        .filter_map(|profile| profile.get(PROFILE_LOCATION).cloned());
+    let mut locations = Vec::new();
+    for location in metrics_locations.into_iter().chain(profile_locations) {
+        let location = expand_alias(config, location);
+        if !locations.contains(&location) {
+            locations.push(location);
+        }
+    }
+    let metrics_locations = locations;
+    let metrics = if metrics_locations.is_empty() {
+        None
+    } else {
+        let provider = default_provider.clone().ok_or_else(|| {
+            anyhow!(
+                "Metrics require default provider. Please use `-sp <provider_name>` to specify one"
+            )
+        })?;
+        ensure!(
+            providers.contains_key(&provider),
+            "Provider '{provider}' used for metrics isn't configured"
+        );
+        ensure!(
+            metrics_interval > 0,
+            "Metrics refresh interval should be positive"
+        );
+        Some(metrics::Metrics::new(
+            provider,
+            metrics_locations,
+            Duration::from_secs(metrics_interval),
+        ))
+    };
+
+    let server = server::Server::new(
+        providers,
+        attributions,
+        default_provider,
+        Duration::from_secs(cache_ttl),
+        metrics,
+    );
+    run_future(server.serve(listen))
+}
@@ -0,0 +1,728 @@
+//! Obtaining forecasts of single location and showing them
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Context};
+use chrono::Timelike;
+use serde_json::json;
+
+use crate::attribution::Credits;
+use crate::condition::Condition;
+use crate::condition_mapping::MappedProvider;
+use crate::config::Config;
+use crate::date::Date;
+use crate::output::OutputFormat;
+use crate::progress::Event;
+use crate::provider::WeatherInfo;
+use crate::provider_registry::ProviderRegistry;
+#[cfg(feature = "sqlite")]
+use crate::store;
+use crate::units::Units;
+use crate::{
+    aggregate, card, chart, ensemble, geocode, geojson, ics, normals, places, progress, redact,
+    rules, run_future, summary, terminal, timezone, ACTIVE_ENTRY, MAX_PLACE_CANDIDATES,
+    TREND_HOURS,
+};
+
+use super::{
+    configured_providers, create_provider, first_hour, forecast_days, lookup_address, pick_place,
+    resolve_address, Lookup,
+};
+
+/// Warns that provider without hourly data ignores requested hour of day
+///
+/// # Parameters
+/// * `provider` - provider's name
+/// * `days` - requested days, as returned by `forecast_days`
+fn warn_if_daily(
+    registry: &ProviderRegistry,
+    provider: &str,
+    days: &[(chrono::NaiveDate, Option<Date>)],
+) {
+    let hourly = registry
+        .get(provider)
+        .is_some_and(|factory| factory.info().hourly);
+    if !hourly
+        && days
+            .iter()
+            .any(|(_, date)| date.is_some_and(|date| date.hour.is_some()))
+    {
+        tracing::warn!(
+            "Provider '{provider}' doesn't provide hourly data, conditions for whole day are shown"
+        );
+    }
+}
+/// Shows requests which forecast would need, without performing them
+pub fn plan_forecast(
+    registry: &ProviderRegistry,
+    config: &Config,
+    address: &str,
+    date: &str,
+    days: u32,
+    provider: Option<String>,
+) -> anyhow::Result<()> {
+    let (provider_name, provider) = create_provider(registry, config, provider)?;
+    println!("Provider: {provider_name}");
+    // Address is resolved once, before any forecast request
+    let geocoder = geocode::from_config(config)?;
+    // Remembered places need no lookup
+    let remembered =
+        geocode::remembered(geocoder.as_ref(), address).map(|place| place.coordinates());
+    let location = match (
+        remembered.as_deref(),
+        geocode::plan_with(geocoder.as_ref(), address, MAX_PLACE_CANDIDATES),
+    ) {
+        (Some(coordinates), _) => coordinates,
+        (None, Some(request)) => {
+            println!(
+                "\nLocation:\n  {}",
+                request.to_string().replace('\n', "\n  ")
+            );
+            geocode::PLACEHOLDER
+        }
+        (None, None) => address,
+    };
+    let forecast_days = forecast_days(date, days, timezone::of_location(address))?;
+    warn_if_daily(registry, &provider_name, &forecast_days);
+    for (day, request_date) in forecast_days {
+        println!("\n{day}:");
+        let requests = provider
+            .plan_weather(location, request_date)
+            .with_context(|| anyhow!("When planning forecast request for {day}"))?;
+        for request in requests {
+            println!("  {}", request.to_string().replace('\n', "\n  "));
+        }
+    }
+    Ok(())
+}
+/// Checks whether provider can serve requested days, without performing any requests
+///
+/// # Parameters
+/// * `provider` - provider instance
+/// * `date` - first day, as in `ForecastQuery`
+/// * `days` - number of consecutive days
+/// * `tz` - timezone in which days are counted, same as in `forecast_days`
+///
+/// # Returns
+/// Problems found, empty if there are none
+fn check_forecast(
+    provider: &MappedProvider,
+    date: &str,
+    days: u32,
+    tz: Option<chrono_tz::Tz>,
+) -> Vec<anyhow::Error> {
+    match forecast_days(date, days, tz) {
+        Ok(forecast_days) => check_days(provider, &forecast_days),
+        Err(error) => vec![error],
+    }
+}
+/// Checks whether provider can serve days, without performing any requests
+///
+/// # Parameters
+/// * `provider` - provider instance
+/// * `forecast_days` - days along with dates they're requested for, as returned by `forecast_days`
+///
+/// # Returns
+/// Problems found, empty if there are none
+fn check_days(
+    provider: &MappedProvider,
+    forecast_days: &[(chrono::NaiveDate, Option<Date>)],
+) -> Vec<anyhow::Error> {
+    forecast_days
+        .iter()
+        .filter_map(|&(day, request_date)| {
+            provider
+                .plan_weather(geocode::PLACEHOLDER, request_date)
+                .err()
+                .map(|error| error.context(format!("Forecast for {day} can't be requested")))
+        })
+        .collect()
+}
+/// Extra sections shown along with forecasts in human-readable formats
+pub struct Sections {
+    /// Show forecasts' metadata
+    pub detailed: bool,
+    /// Summarize forecasts in sentences
+    pub summary: bool,
+    /// Advise on forecasts by these rules
+    pub advice: Option<rules::Rules>,
+    /// Statistics across days
+    pub aggregate: Option<aggregate::Aggregate>,
+    /// Climate normal of each forecast's day, if it was obtained; empty if not requested
+    pub normals: Vec<Option<aggregate::Aggregate>>,
+    /// Temperatures of following hours, shown as sparkline in one-line output
+    pub trend: Vec<f32>,
+}
+/// Forecast requested by command
+pub struct ForecastQuery {
+    /// Address of location
+    pub address: String,
+    /// First day, "YYYY-MM-DD", "YYYY-MM-DD HH:MM", weekday like "friday" or "now"
+    pub date: String,
+    /// Number of consecutive days
+    pub days: u32,
+    /// Number of consecutive hours, from first day's hour, to forecast instead of days
+    pub hours: Option<u32>,
+    /// Provider to use instead of default one
+    pub provider: Option<String>,
+    /// Pick most relevant place if address is ambiguous, without asking
+    pub first: bool,
+    /// How old cached current weather may be to be used instead of request, in seconds
+    pub max_age: Option<i64>,
+}
+/// Gets weather forecast using specified provider
+///
+/// # Parameters
+/// * `query` - requested forecast
+/// * `on_day` - called with actual provider name and each day's forecast as soon as it's obtained,
+///   e.g. to stream it
+///
+/// # Returns
+/// Actual provider name and forecast for each requested day
+pub fn get_forecast(
+    registry: &ProviderRegistry,
+    config: &Config,
+    query: ForecastQuery,
+    on_day: &mut dyn FnMut(&str, &Date, &WeatherInfo),
+) -> anyhow::Result<(String, Vec<(Date, WeatherInfo)>)> {
+    let ForecastQuery {
+        address,
+        date,
+        days,
+        hours,
+        provider,
+        first,
+        max_age,
+    } = query;
+    if let Some(max_age) = max_age.filter(|_| date == "now" && days == 1 && hours.is_none()) {
+        let provider_name = provider
+            .clone()
+            .or_else(|| config.globals.get(ACTIVE_ENTRY).cloned());
+        let cached = provider_name.and_then(|name| {
+            places::cached(&name, &address, chrono::Duration::seconds(max_age))
+                .map(|(fetched, info)| (name, fetched, info))
+        });
+        if let Some((provider_name, fetched, info)) = cached {
+            tracing::info!("Using weather of '{address}' cached at {fetched}");
+            let date = Date::today_at(&address);
+            #[cfg(feature = "sqlite")]
+            record_query(&provider_name, &address, "now", 1);
+            on_day(&provider_name, &date, &info);
+            return Ok((provider_name, vec![(date, info)]));
+        }
+    }
+    let (provider_name, provider) = create_provider(registry, config, provider)?;
+    let geocoder = geocode::from_config(config)?;
+    // Provider's checks need no location, so they're done while address is looked up;
+    // location's timezone is known by then only if address is coordinates,
+    // otherwise days are checked again once place is picked
+    let address_tz = timezone::of_location(&address);
+    let (lookup, mut problems) = run_future(async {
+        Ok(tokio::join!(
+            lookup_address(geocoder.as_ref(), &address),
+            async { check_forecast(&provider, &date, days, address_tz) }
+        ))
+    })?;
+    if !problems.is_empty() {
+        // Asking user to pick place is pointless when forecast fails anyway
+        if let Lookup::Places(candidates) = &lookup {
+            if candidates.len() > 1 && !first {
+                problems.push(anyhow!(
+                    "Location '{address}' is ambiguous, {} places match it",
+                    candidates.len()
+                ));
+            }
+        }
+        return Err(forecast_problems(&problems));
+    }
+    let location = pick_place(geocoder.as_ref(), &address, lookup, first)?;
+    // Days are counted in location's timezone, if it's known
+    let tz = timezone::of_location(&location);
+    match tz {
+        Some(tz) => tracing::info!("Local time at {location}: {}", timezone::describe_now(tz)),
+        None => tracing::debug!("Timezone of '{address}' is unknown, using local one"),
+    }
+    let forecast_days = forecast_days(&date, days, tz)?;
+    if tz != address_tz {
+        let problems = check_days(&provider, &forecast_days);
+        if !problems.is_empty() {
+            return Err(forecast_problems(&problems));
+        }
+    }
+    if let (Some(hours), Some(&(day, request_date))) = (hours, forecast_days.first()) {
+        let hourly = registry
+            .get(provider_name.as_str())
+            .is_some_and(|factory| factory.info().hourly);
+        ensure!(
+            hourly,
+            "Provider '{provider_name}' doesn't provide hourly data"
+        );
+        let start = first_hour(day, request_date, tz);
+        let infos = run_future(provider.get_hourly(location.into(), start, hours))
+            .with_context(|| anyhow!("When performing hourly forecast request for {start}"))?;
+        let first = day.and_time(chrono::NaiveTime::MIN)
+            + chrono::Duration::hours(start.hour.unwrap_or(0).into());
+        let mut result = Vec::new();
+        for (index, info) in infos.into_iter().enumerate() {
+            let time = first + chrono::Duration::hours(index as i64);
+            let date = Date::from(time.date()).with_hour(Some(time.hour() as u8));
+            on_day(&provider_name, &date, &info);
+            result.push((date, info));
+        }
+        #[cfg(feature = "sqlite")]
+        record_query(&provider_name, &address, &date, days);
+        return Ok((provider_name, result));
+    }
+    warn_if_daily(registry, &provider_name, &forecast_days);
+    let mut result = Vec::new();
+    for (step, (day, request_date)) in forecast_days.into_iter().enumerate() {
+        let forecast = run_future(provider.get_weather(location.clone().into(), request_date))
+            .with_context(|| anyhow!("When performing forecast request for {day}"))?;
+        progress::emit(Event::Fetched {
+            location: &address,
+            date: Some(day.to_string()),
+            step: step + 1,
+            total: days as usize,
+        });
+        let date = Date::from(day).with_hour(request_date.and_then(|date| date.hour));
+        on_day(&provider_name, &date, &forecast);
+        result.push((date, forecast));
+    }
+    #[cfg(feature = "sqlite")]
+    record_query(&provider_name, &address, &date, days);
+    Ok((provider_name, result))
+}
+/// Combines problems which prevent obtaining forecast into single error
+///
+/// # Parameters
+/// * `problems` - problems found by `check_forecast` or `check_days`
+fn forecast_problems(problems: &[anyhow::Error]) -> anyhow::Error {
+    let problems: Vec<_> = problems
+        .iter()
+        .map(|problem| format!("{problem:#}"))
+        .collect();
+    anyhow!("Forecast can't be obtained:\n  {}", problems.join("\n  "))
+}
+/// Appends forecast query to history kept in database; failure is only logged,
+/// since history doesn't affect forecast
+#[cfg(feature = "sqlite")]
+fn record_query(provider: &str, address: &str, date: &str, days: u32) {
+    if let Err(error) = store::record_query(provider, address, date, days) {
+        tracing::warn!("Could not record query in history: {error:#}");
+    }
+}
+/// Resolves address into coordinates, same way as forecast requests do
+///
+/// # Parameters
+/// * `address` - address or coordinates
+/// * `first` - pick most relevant place if address is ambiguous, without asking
+///
+/// # Returns
+/// Latitude and longitude, or `None` if address isn't known to geocoding service
+pub fn address_coordinates(
+    config: &Config,
+    address: &str,
+    first: bool,
+) -> anyhow::Result<Option<(f64, f64)>> {
+    let geocoder = geocode::from_config(config)?;
+    let location = resolve_address(geocoder.as_ref(), address, first)?;
+    Ok(geocode::parse_coordinates(&location))
+}
+/// Gets climate normals of forecasts' days, from weather of same days in previous years
+///
+/// Failures are only logged, since comparison is optional part of output
+///
+/// # Parameters
+/// * `provider_name` - provider which obtained forecasts
+/// * `address` - location for which forecasts are requested
+/// * `first` - pick most relevant place if address is ambiguous, without asking
+/// * `forecasts` - forecasts to compare
+/// * `years` - number of previous years
+///
+/// # Returns
+/// Normal of each forecast's day, if it was obtained, or nothing if none could be
+pub fn climate_normals(
+    registry: &ProviderRegistry,
+    config: &Config,
+    provider_name: &str,
+    address: &str,
+    first: bool,
+    forecasts: &[(Date, WeatherInfo)],
+    years: u32,
+) -> Vec<Option<aggregate::Aggregate>> {
+    let normals = || -> anyhow::Result<Vec<_>> {
+        let (_, provider) = create_provider(registry, config, Some(provider_name.to_string()))?;
+        let provider = Arc::new(provider);
+        let geocoder = geocode::from_config(config)?;
+        // Places found when getting forecasts are cached, so no lookup is repeated
+        let location = resolve_address(geocoder.as_ref(), address, first)?;
+        run_future(async {
+            let mut normals = Vec::new();
+            for (date, _) in forecasts {
+                normals.push(normals::normal(provider.clone(), &location, *date, years).await?);
+            }
+            anyhow::Ok(normals)
+        })
+    };
+    normals().unwrap_or_else(|error| {
+        tracing::warn!("Could not get climate normals: {error:#}");
+        Vec::new()
+    })
+}
+/// Gets hourly forecast from requested hour till end of its day
+///
+/// # Parameters
+/// * `provider` - provider to use instead of default one; it should provide hourly data
+/// * `address` - location of forecast
+/// * `date` - "YYYY-MM-DD", "YYYY-MM-DD HH:MM", weekday like "friday" or "now";
+///   whole day is taken unless hour is specified or date is "now"
+/// * `first` - pick most relevant place if address is ambiguous, without asking
+///
+/// # Returns
+/// Forecast of each hour, along with its date and hour
+pub fn day_hours(
+    registry: &ProviderRegistry,
+    config: &Config,
+    provider: Option<String>,
+    address: &str,
+    date: &str,
+    first: bool,
+) -> anyhow::Result<Vec<(Date, WeatherInfo)>> {
+    let (provider_name, provider) = create_provider(registry, config, provider)?;
+    let hourly = registry
+        .get(provider_name.as_str())
+        .is_some_and(|factory| factory.info().hourly);
+    ensure!(
+        hourly,
+        "Provider '{provider_name}' doesn't provide hourly data, so threshold can't be checked"
+    );
+    let geocoder = geocode::from_config(config)?;
+    let location = resolve_address(geocoder.as_ref(), address, first)?;
+    let tz = timezone::of_location(&location);
+    let (day, request_date) = forecast_days(date, 1, tz)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Date '{date}' has no days"))?;
+    let start = first_hour(day, request_date, tz);
+    let hour = start.hour.unwrap_or(0);
+    let infos = run_future(provider.get_hourly(location.into(), start, 24 - u32::from(hour)))?;
+    Ok((hour..24)
+        .map(|hour| start.with_hour(Some(hour)))
+        .zip(infos)
+        .collect())
+}
+/// Gets temperatures of following hours, for trend shown in one-line output
+///
+/// Failures are only logged, since trend is optional part of output
+///
+/// # Parameters
+/// * `provider_name` - provider which obtained current conditions
+/// * `address` - location for which forecast is requested
+/// * `first` - pick most relevant place if address is ambiguous, without asking
+///
+/// # Returns
+/// Temperatures, or nothing if provider doesn't offer hourly data
+pub fn hourly_trend(
+    registry: &ProviderRegistry,
+    config: &Config,
+    provider_name: &str,
+    address: &str,
+    first: bool,
+) -> Vec<f32> {
+    let hourly = registry
+        .get(provider_name)
+        .is_some_and(|factory| factory.info().hourly);
+    if !hourly {
+        tracing::debug!("Provider '{provider_name}' doesn't provide hourly data, no trend shown");
+        return Vec::new();
+    }
+    let trend = || -> anyhow::Result<Vec<f32>> {
+        let (_, provider) = create_provider(registry, config, Some(provider_name.to_string()))?;
+        let geocoder = geocode::from_config(config)?;
+        // Places found when getting current conditions are cached, so no lookup is repeated
+        let location = resolve_address(geocoder.as_ref(), address, first)?;
+        let next_hour = timezone::now(timezone::of_location(&location)).naive_local()
+            + chrono::Duration::hours(1);
+        let start = Date::from(next_hour.date()).with_hour(Some(next_hour.hour() as u8));
+        let infos = run_future(provider.get_hourly(location.into(), start, TREND_HOURS))?;
+        Ok(infos.iter().map(|info| info.temperature).collect())
+    };
+    trend().unwrap_or_else(|error| {
+        tracing::warn!("Could not get hourly trend: {error:#}");
+        Vec::new()
+    })
+}
+/// Converts forecasts into JSON array of objects, each with forecast's date and fields
+pub fn forecasts_json(forecasts: &[(Date, WeatherInfo)]) -> serde_json::Value {
+    forecasts
+        .iter()
+        .map(|(date, info)| {
+            let mut forecast = json!(info);
+            forecast["date"] = json!(date.to_string());
+            forecast
+        })
+        .collect()
+}
+/// Formats forecasts as plain text message, like ones posted to chats
+pub fn forecasts_text(address: &str, forecasts: &[(Date, WeatherInfo)], units: Units) -> String {
+    let mut text = format!("Weather in {address}");
+    for (date, forecast) in forecasts {
+        text.push_str(&format!(
+            "\n\n{}\n{}",
+            date.formatted(),
+            units.display(forecast)
+        ));
+    }
+    text
+}
+/// Prints forecasts obtained by `get` command in specified format
+///
+/// # Parameters
+/// * `address` - location for which forecasts were requested
+/// * `forecasts` - forecasts to print
+/// * `output` - output format
+/// * `units` - units in which forecasts are shown
+/// * `credits` - provider's attribution
+/// * `coordinates` - location's latitude and longitude, if known, for formats which show them
+/// * `sections` - extra sections shown in human-readable formats
+pub fn print_forecasts(
+    address: &str,
+    forecasts: Vec<(Date, WeatherInfo)>,
+    output: OutputFormat,
+    units: Units,
+    credits: &Credits,
+    coordinates: Option<(f64, f64)>,
+    sections: Sections,
+) {
+    let Sections {
+        detailed,
+        summary,
+        advice,
+        aggregate,
+        normals,
+        trend,
+    } = sections;
+    let human = !matches!(
+        output,
+        OutputFormat::Ics | OutputFormat::Ndjson | OutputFormat::Geojson | OutputFormat::Oneline
+    );
+    // Advice is rendered upfront, since forecasts are moved into renderers
+    let advice = advice
+        .filter(|_| human)
+        .map(|rules| advice_text(&forecasts, &rules));
+    let normals = (human && normals.iter().any(Option::is_some))
+        .then(|| normals::render(&forecasts, &normals, units));
+    if let Some(header) = credits.header().filter(|_| human) {
+        println!("{header}\n");
+    }
+    if summary && human {
+        println!("{}", terminal::fit(&summary::render(&forecasts, units)));
+    }
+    match output {
+        OutputFormat::Text => {
+            let meta = |forecast: &WeatherInfo| {
+                if detailed {
+                    format!("\n{}", forecast.meta)
+                } else {
+                    String::new()
+                }
+            };
+            if let [(_, forecast)] = forecasts.as_slice() {
+                let text = format!("{}{}", units.display(forecast), meta(forecast));
+                println!("{}", terminal::fit(&text));
+            } else {
+                for (date, forecast) in &forecasts {
+                    let text = format!(
+                        "{}\n{}{}\n",
+                        date.formatted(),
+                        units.display(forecast),
+                        meta(forecast)
+                    );
+                    println!("{}", terminal::fit(&text));
+                }
+                let entries: Vec<_> = forecasts
+                    .into_iter()
+                    .map(|(date, forecast)| (date.formatted(), forecast))
+                    .collect();
+                print!(
+                    "{}",
+                    terminal::fit(&chart::render_forecast(&entries, units))
+                );
+            }
+        }
+        OutputFormat::Card => {
+            let width = card::detect_width();
+            // Temperature range makes sense only across several days
+            let range = (forecasts.len() > 1).then(|| {
+                forecasts
+                    .iter()
+                    .fold((f32::MAX, f32::MIN), |(min, max), (_, info)| {
+                        (min.min(info.temperature), max.max(info.temperature))
+                    })
+            });
+            for (date, forecast) in &forecasts {
+                let card = card::render(
+                    &format!("{address}, {}", date.formatted()),
+                    forecast,
+                    // Day's own range is more precise than one across days
+                    forecast.temperature_range().or(range),
+                    width,
+                    units,
+                );
+                print!("{}", terminal::fit(&card));
+                if detailed {
+                    println!("{}", forecast.meta);
+                }
+            }
+        }
+        OutputFormat::Ics => print!("{}", ics::write_forecasts(address, &forecasts, units)),
+        // Already written while forecasts were obtained
+        OutputFormat::Ndjson => {}
+        OutputFormat::Geojson => {
+            let mut collection = geojson::write_forecasts(address, coordinates, &forecasts);
+            // Provider's terms travel with data, as in JSON output
+            collection["attribution"] = credits.json();
+            println!("{collection}");
+        }
+        OutputFormat::Oneline => {
+            for (index, (date, forecast)) in forecasts.iter().enumerate() {
+                let mut line = format!(
+                    "{:.0}{}",
+                    units.temperature(forecast.temperature),
+                    units.temperature_unit()
+                );
+                // Trend follows current conditions, which come first
+                if index == 0 && !trend.is_empty() {
+                    line = format!("{line} {}", chart::sparkline(&trend));
+                }
+                if forecasts.len() > 1 {
+                    line = format!("{} {line}", date.formatted());
+                }
+                println!("{}", terminal::fit(&format!("{line} {}", forecast.weather)));
+            }
+        }
+    }
+    if let Some(aggregate) = aggregate.filter(|_| human) {
+        print!("\n{}", terminal::fit(&aggregate.render(units)));
+    }
+    if let Some(normals) = normals {
+        print!("\n{}", terminal::fit(&normals));
+    }
+    if let Some(advice) = advice.filter(|advice| !advice.is_empty()) {
+        print!("\nAdvice:\n{advice}");
+    }
+    if let Some(footer) = credits.footer().filter(|_| human) {
+        println!("\n{footer}");
+    }
+}
+/// Renders advice for forecasts, one line per matching rule, grouped by day for multi-day forecasts
+///
+/// # Parameters
+/// * `forecasts` - forecasts to advise on
+/// * `rules` - advice rules
+fn advice_text(forecasts: &[(Date, WeatherInfo)], rules: &rules::Rules) -> String {
+    let mut text = String::new();
+    for (date, forecast) in forecasts {
+        let advice: Vec<_> = rules.matching(forecast).collect();
+        if advice.is_empty() {
+            continue;
+        }
+        if forecasts.len() > 1 {
+            text.push_str(&format!("{}:\n", date.formatted()));
+        }
+        for line in advice {
+            text.push_str(&format!("* {line}\n"));
+        }
+    }
+    text
+}
+/// Posts forecast as desktop notification, if it satisfies condition
+///
+/// # Parameters
+/// * `address` - location for which forecast was requested
+/// * `forecasts` - forecast to post
+/// * `condition` - optional condition which forecast should satisfy to be posted
+/// * `units` - units in which forecast is shown
+pub fn notify_forecast(
+    address: &str,
+    forecasts: Vec<(Date, WeatherInfo)>,
+    condition: Option<&Condition>,
+    units: Units,
+) -> anyhow::Result<()> {
+    for (date, forecast) in forecasts {
+        if condition.is_some_and(|condition| !condition.evaluate(&forecast)) {
+            continue;
+        }
+        notify_rust::Notification::new()
+            .appname("weather")
+            .summary(&format!("Weather in {address}, {}", date.formatted()))
+            .body(&units.display(&forecast).to_string())
+            .show()
+            .with_context(|| anyhow!("Could not post desktop notification"))?;
+    }
+    Ok(())
+}
+/// Gets forecast from all configured providers concurrently and combines it into consensus;
+/// providers which fail are left out
+///
+/// # Parameters
+/// * `registry` - registered providers
+/// * `config` - configuration
+/// * `address` - location of forecast
+/// * `date` - day of forecast, "YYYY-MM-DD", "YYYY-MM-DD HH:MM", weekday like "friday" or "now"
+/// * `first` - pick most relevant place if address is ambiguous, without asking
+pub fn ensemble_forecast(
+    registry: &ProviderRegistry,
+    config: &Config,
+    address: &str,
+    date: &str,
+    first: bool,
+) -> anyhow::Result<ensemble::Ensemble> {
+    let names = configured_providers(registry, config);
+    ensure!(
+        !names.is_empty(),
+        "No providers configured; configure one with 'weather configure <provider>'"
+    );
+    let geocoder = geocode::from_config(config)?;
+    let location = resolve_address(geocoder.as_ref(), address, first)?;
+    let (_, request_date) = forecast_days(date, 1, timezone::of_location(&location))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No forecast day for date '{date}'"))?;
+    let results = run_future(async {
+        let tasks: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let provider = create_provider(registry, config, Some(name.clone()));
+                let (name, location) = (name.clone(), location.clone());
+                tokio::spawn(async move {
+                    let (_, provider) = provider?;
+                    let info = provider.get_weather(location.into(), request_date).await?;
+                    anyhow::Ok((name, info))
+                })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for task in tasks {
+            results.push(task.await?);
+        }
+        anyhow::Ok(results)
+    })?;
+    let mut members = Vec::new();
+    let mut failures = Vec::new();
+    for (name, result) in names.iter().zip(results) {
+        match result {
+            Ok(member) => members.push(member),
+            Err(error) => {
+                let error = redact::redact(&format!("{error:#}"));
+                tracing::warn!("Leaving out provider '{name}': {error}");
+                failures.push(format!("{name}: {error}"));
+            }
+        }
+    }
+    ensemble::combine(members).ok_or_else(|| {
+        anyhow!(
+            "No provider could obtain forecast:\n  {}",
+            failures.join("\n  ")
+        )
+    })
+}
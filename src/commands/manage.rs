@@ -0,0 +1,161 @@
+//! Management of databases, location aliases, configuration transfer and language packs
+use anyhow::{anyhow, ensure, Context};
+
+use crate::config::Config;
+use crate::provider_registry::ProviderRegistry;
+#[cfg(feature = "sqlite")]
+use crate::store;
+#[cfg(feature = "sqlite")]
+use crate::DbCmd;
+use crate::{config_transfer, geocode, i18n, logging, run_future, ConfigCmd, LangCmd, LocationCmd};
+
+use super::expand_alias;
+
+/// Executes database maintenance command
+///
+/// # Parameters
+/// * `action` - command to execute
+#[cfg(feature = "sqlite")]
+pub fn manage_database(action: DbCmd) -> anyhow::Result<()> {
+    match action {
+        DbCmd::Info => {
+            let info = store::info()?;
+            println!("Database: {}", info.path.display());
+            println!("Schema version: {}", info.version);
+            println!("Size: {} bytes", info.bytes);
+            println!("Rows:");
+            for (table, rows) in &info.rows {
+                println!("  {table}: {rows}");
+            }
+            if info.requests_today.is_empty() {
+                println!("No requests today");
+            } else {
+                println!("Requests today:");
+                for (host, requests) in &info.requests_today {
+                    println!("  {host}: {requests}");
+                }
+            }
+        }
+        DbCmd::Vacuum { keep_days } => {
+            let (dropped, before, after) = store::vacuum(keep_days)?;
+            logging::status(format!(
+                "Dropped {dropped} old rows; database size changed from {before} to {after} bytes"
+            ));
+        }
+    }
+    Ok(())
+}
+/// Executes location alias command
+///
+/// # Parameters
+/// * `config` - configuration, where aliases are stored
+/// * `action` - command to execute
+pub fn manage_locations(config: &mut Config, action: LocationCmd) -> anyhow::Result<()> {
+    match action {
+        LocationCmd::Add { name, address } => {
+            ensure!(!name.trim().is_empty(), "Alias name cannot be empty");
+            ensure!(!address.trim().is_empty(), "Alias address cannot be empty");
+            logging::status(format!("Added location '{name}' for '{address}'"));
+            config.locations.insert(name, address);
+            config.mark_dirty();
+        }
+        LocationCmd::List => {
+            if config.locations.is_empty() {
+                println!("No locations added");
+            }
+            for (name, address) in &config.locations {
+                println!("{name}: {address}");
+            }
+        }
+        LocationCmd::Show { name } => {
+            let address = expand_alias(config, name.clone());
+            if address != name {
+                println!("{name}: {address}");
+            }
+            let geocoder = geocode::from_config(config)?;
+            match geocode::remembered(geocoder.as_ref(), &address) {
+                Some(place) => println!("Resolved into {place} ({})", place.coordinates()),
+                None => println!("Not resolved yet; it's resolved by first query"),
+            }
+        }
+        LocationCmd::Remove { name } => {
+            config
+                .locations
+                .remove(&name)
+                .ok_or_else(|| anyhow!("No such location: {name}"))?;
+            config.mark_dirty();
+            logging::status(format!("Removed location '{name}'"));
+        }
+    }
+    Ok(())
+}
+/// Exports configuration into document or imports it
+///
+/// # Parameters
+/// * `config` - configuration
+/// * `registry` - providers, whose secret parameters are left out of redacted export
+/// * `action` - export or import
+pub fn transfer_config(
+    config: &mut Config,
+    registry: &ProviderRegistry,
+    action: ConfigCmd,
+) -> anyhow::Result<()> {
+    match action {
+        ConfigCmd::Export {
+            redact,
+            format,
+            output,
+        } => {
+            let text = config_transfer::export(config, registry, format, redact)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, text)
+                        .with_context(|| anyhow!("When writing '{}'", path.display()))?;
+                    logging::status(format!("Exported configuration into '{}'", path.display()));
+                }
+                None => print!("{text}"),
+            }
+        }
+        ConfigCmd::Import { file, format } => {
+            let text = std::fs::read_to_string(&file)
+                .with_context(|| anyhow!("When reading '{}'", file.display()))?;
+            let format = format.unwrap_or_else(|| config_transfer::Format::guess(&file));
+            let count = config_transfer::import(config, &text, format)
+                .with_context(|| anyhow!("When importing '{}'", file.display()))?;
+            logging::status(format!(
+                "Imported {count} entries from '{}'",
+                file.display()
+            ));
+        }
+    }
+    Ok(())
+}
+/// Executes language pack command
+///
+/// # Parameters
+/// * `action` - command to execute
+pub fn manage_languages(action: LangCmd) -> anyhow::Result<()> {
+    match action {
+        LangCmd::Install { language, source } => {
+            let name = run_future(i18n::install(&source, &language))?;
+            logging::status(format!("Installed language pack '{language}' ({name})"));
+        }
+        LangCmd::List => {
+            let packs = i18n::installed()?;
+            if packs.is_empty() {
+                println!("No language packs installed");
+            }
+            for (language, name) in packs {
+                match name {
+                    Ok(name) => println!("{language}: {name}"),
+                    Err(error) => println!("{language}: broken pack, {error}"),
+                }
+            }
+        }
+        LangCmd::Remove { language } => {
+            i18n::remove(&language)?;
+            logging::status(format!("Removed language pack '{language}'"));
+        }
+    }
+    Ok(())
+}
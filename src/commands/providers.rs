@@ -0,0 +1,393 @@
+//! Listing, checking and diagnosing providers
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context};
+use clap::CommandFactory;
+use serde_json::json;
+
+use crate::config::{Config, Section};
+use crate::output::ListFormat;
+use crate::provider::{ParamDesc, ParamKind, ProviderInfo};
+use crate::provider_registry::ProviderRegistry;
+use crate::{
+    app_dirs, date, doctor, flood, hazard, layered_config, manual, places, redact, run_future, Cli,
+    ACTIVE_ENTRY,
+};
+
+use super::{configured_providers, create_provider};
+
+/// Checks configured providers and application's directories, printing findings along with fixes
+///
+/// # Parameters
+/// * `registry` - registered providers
+/// * `config` - configuration
+/// * `config_path` - path of configuration file
+/// * `location` - location of weather requests which check providers
+///
+/// # Returns
+/// Whether no problems were found
+pub fn diagnose(
+    registry: &ProviderRegistry,
+    config: &Config,
+    config_path: &Path,
+    location: &str,
+) -> anyhow::Result<bool> {
+    let mut checks = Vec::new();
+    for (id, factory) in registry.iter() {
+        let params = factory.info().params;
+        if layered_config::provider_section(config, id, params).is_none() {
+            continue;
+        }
+        tracing::info!("Checking provider '{id}'");
+        let check = match create_provider(registry, config, Some(id.to_string())) {
+            Ok((name, provider)) => run_future(async {
+                anyhow::Ok(doctor::check_provider(&name, &provider, location).await)
+            })?,
+            Err(error) => doctor::Check::failed(
+                format!("Provider '{id}'"),
+                format!("{error:#}"),
+                format!("fix provider's parameters with 'weather configure {id}'"),
+            ),
+        };
+        checks.push(check);
+    }
+    if checks.is_empty() {
+        checks.push(doctor::Check::failed(
+            "Providers",
+            "none configured",
+            "configure one with 'weather configure <provider>'; 'weather list' shows available ones",
+        ));
+    }
+    checks.extend(doctor::check_dirs(config_path));
+    print!("{}", doctor::render(&checks));
+    Ok(checks.iter().all(|check| check.outcome.is_ok()))
+}
+/// Checks providers concurrently, each with weather request, and prints table of results
+///
+/// # Parameters
+/// * `registry` - registered providers
+/// * `config` - configuration
+/// * `providers` - names of providers to check; empty to check all configured ones
+/// * `location` - location of weather requests
+///
+/// # Returns
+/// Whether all providers passed
+pub fn test_providers(
+    registry: &ProviderRegistry,
+    config: &Config,
+    providers: Vec<String>,
+    location: &str,
+) -> anyhow::Result<bool> {
+    let names = if providers.is_empty() {
+        configured_providers(registry, config)
+    } else {
+        providers
+    };
+    ensure!(
+        !names.is_empty(),
+        "No providers configured; configure one with 'weather configure <provider>'"
+    );
+    let checks = run_future(async {
+        let tasks: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let provider = create_provider(registry, config, Some(name.clone()));
+                let (name, location) = (name.clone(), location.to_string());
+                tokio::spawn(async move {
+                    match provider {
+                        Ok((name, provider)) => {
+                            doctor::check_provider(&name, &provider, &location).await
+                        }
+                        Err(error) => doctor::Check::failed(
+                            format!("Provider '{name}'"),
+                            redact::redact(&format!("{error:#}")),
+                            format!("fix provider's parameters with 'weather configure {name}'"),
+                        ),
+                    }
+                })
+            })
+            .collect();
+        let mut checks = Vec::new();
+        for task in tasks {
+            checks.push(task.await?);
+        }
+        anyhow::Ok(checks)
+    })?;
+    print!("{}", doctor::render_providers(&names, &checks));
+    Ok(checks.iter().all(|check| check.outcome.is_ok()))
+}
+/// Prints current configuration state, with provider parameters masked
+///
+/// # Parameters
+/// * `registry` - registered providers
+/// * `config` - configuration
+/// * `config_path` - path of configuration file
+/// * `profile_name` - name of selected profile, if any
+/// * `profile` - selected profile's entries
+pub fn show_status(
+    registry: &ProviderRegistry,
+    config: &Config,
+    config_path: &Path,
+    profile_name: Option<&str>,
+    profile: &Section,
+) {
+    let exists = if config_path.is_file() {
+        ""
+    } else {
+        " (not created yet)"
+    };
+    println!("Config file: {}{exists}", config_path.display());
+    for (name, dir) in [
+        ("Cache directory", app_dirs::cache_dir()),
+        ("Data directory", app_dirs::data_dir()),
+        ("State directory", app_dirs::state_dir()),
+    ] {
+        let dir = dir.map_or_else(|| "unknown".to_string(), |dir| dir.display().to_string());
+        println!("{name}: {dir}");
+    }
+    println!(
+        "Current provider: {}",
+        config
+            .globals
+            .get(ACTIVE_ENTRY)
+            .map_or("none", String::as_str)
+    );
+    println!(
+        "Geocoder: {}",
+        config
+            .globals
+            .get("geocoder")
+            .map_or("nominatim", String::as_str)
+    );
+
+    println!("\nConfigured providers:");
+    let mut configured = 0;
+    for (id, factory) in registry.iter() {
+        let params = factory.info().params;
+        let Some(section) = layered_config::provider_section(config, id, params) else {
+            continue;
+        };
+        configured += 1;
+        println!("  {id}");
+        for (name, value) in &section {
+            // Environment overrides take precedence over file entries
+            let env = layered_config::env_name(id, name);
+            let source = if std::env::var_os(&env).is_some() {
+                format!(" (from {env})")
+            } else {
+                String::new()
+            };
+            // Only parameters known not to be credentials, like API root, are shown as is
+            let plain = params
+                .iter()
+                .find(|param| param.id == name)
+                .is_some_and(|param| !matches!(param.kind, ParamKind::Secret));
+            let value = if plain {
+                value.clone()
+            } else {
+                redact::mask_value(value)
+            };
+            println!("    {name:<16} = {value}{source}");
+        }
+    }
+    if configured == 0 {
+        println!("  none; use 'weather configure <provider>' to configure one");
+    }
+
+    match profile_name {
+        Some(name) => {
+            println!("\nProfile '{name}':");
+            for (key, value) in profile {
+                println!("  {key:<16} = {value}");
+            }
+        }
+        None => println!("\nProfile: none selected"),
+    }
+    let profiles: Vec<_> = config.profiles.keys().map(String::as_str).collect();
+    if !profiles.is_empty() {
+        println!("  available: {}", profiles.join(", "));
+    }
+    println!("Location aliases: {}", config.locations.len());
+
+    match places::cache_stats() {
+        Some(stats) => {
+            println!(
+                "\nPlaces cache: {}, {} entries, {} bytes",
+                stats.path.display(),
+                stats.entries,
+                stats.bytes
+            );
+            if let Some(newest) = stats.newest {
+                println!(
+                    "  newest entry fetched {}",
+                    date::format_time(newest.with_timezone(&chrono::Local).naive_local())
+                );
+            }
+        }
+        None => println!("\nPlaces cache: unavailable"),
+    }
+}
+/// Prints or writes application's manual
+///
+/// # Parameters
+/// * `registry` - registered providers, whose parameters are documented
+/// * `markdown` - whether Markdown help is generated instead of man page
+/// * `output` - directory where manual is written, instead of printing it
+pub fn generate_manual(
+    registry: &ProviderRegistry,
+    markdown: bool,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    match (markdown, output) {
+        (true, None) => print!("{}", manual::markdown(Cli::command(), registry)),
+        (true, Some(dir)) => {
+            let path = dir.join("weather.md");
+            std::fs::create_dir_all(&dir)
+                .and_then(|_| std::fs::write(&path, manual::markdown(Cli::command(), registry)))
+                .with_context(|| anyhow!("When writing help into '{}'", path.display()))?;
+        }
+        (false, None) => {
+            std::io::stdout().write_all(&manual::man_page(Cli::command(), registry)?)?
+        }
+        (false, Some(dir)) => manual::write_man_pages(Cli::command(), registry, &dir)?,
+    }
+    Ok(())
+}
+/// List supported providers with their ids and some info on required parameters
+///
+/// # Parameters
+/// * `registry` - registered providers
+/// * `config` - configuration, used to tell which providers are configured
+/// * `output` - listing format
+pub fn list_providers(
+    registry: &ProviderRegistry,
+    config: &Config,
+    output: ListFormat,
+) -> anyhow::Result<()> {
+    if let ListFormat::Json = output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&providers_json(registry, config))?
+        );
+        return Ok(());
+    }
+    for (id, factory) in registry.iter() {
+        let ProviderInfo {
+            description,
+            attribution,
+            params,
+            hourly,
+            lightning,
+        } = factory.info();
+        println!("{id}: {description}");
+        if *hourly {
+            println!("  Provides conditions at specific hour of day");
+        }
+        if *lightning {
+            println!("  Reports lightning strikes");
+        }
+        if let Some(attribution) = attribution {
+            let required = if attribution.required {
+                "required"
+            } else {
+                "optional"
+            };
+            println!("  Attribution ({required}): {attribution}");
+        }
+        if !params.is_empty() {
+            println!("  Parameters:");
+            for ParamDesc {
+                id: param_id,
+                name,
+                description,
+                required,
+                default,
+                kind,
+            } in *params
+            {
+                let optional = match (required, default) {
+                    (true, _) => String::new(),
+                    (false, Some(default)) => format!(", optional, default '{default}'"),
+                    (false, None) => ", optional".to_string(),
+                };
+                println!("    {param_id:<16} - {name}, {description} ({kind}{optional})");
+                println!(
+                    "    {:<16}   can be overridden with {} environment variable",
+                    "",
+                    layered_config::env_name(id, param_id)
+                );
+            }
+        }
+        println!();
+    }
+    println!("Hydrology services, used by 'flood' command:");
+    for (id, source) in flood::sources() {
+        println!("  {id:<18} - {}", source.description());
+    }
+    println!();
+    println!("Hazard feeds, used by 'hazards' command:");
+    for (id, feed) in hazard::sources() {
+        println!("  {id:<18} - {}", feed.description());
+    }
+    Ok(())
+}
+/// Describes registered providers, along with hydrology services and hazard feeds, as JSON
+///
+/// # Parameters
+/// * `registry` - registered providers
+/// * `config` - configuration, used to tell which providers are configured and which is current
+fn providers_json(registry: &ProviderRegistry, config: &Config) -> serde_json::Value {
+    let current = config.globals.get(ACTIVE_ENTRY);
+    let providers: Vec<_> = registry
+        .iter()
+        .map(|(id, factory)| {
+            let info = factory.info();
+            let capabilities: Vec<_> = [(info.hourly, "hourly"), (info.lightning, "lightning")]
+                .into_iter()
+                .filter_map(|(supported, name)| supported.then_some(name))
+                .collect();
+            let params: Vec<_> = info
+                .params
+                .iter()
+                .map(|param| {
+                    json!({
+                        "id": param.id,
+                        "name": param.name,
+                        "description": param.description,
+                        "required": param.required,
+                        "kind": param.kind.id(),
+                        "choices": param.kind.choices(),
+                        "default": param.default,
+                        "env": layered_config::env_name(id, param.id),
+                    })
+                })
+                .collect();
+            json!({
+                "id": id,
+                "description": info.description,
+                "attribution": info.attribution.as_ref().map(|attribution| json!({
+                    "text": attribution.text,
+                    "url": attribution.url,
+                    "required": attribution.required,
+                })),
+                "params": params,
+                "capabilities": capabilities,
+                "configured": config.sections.contains_key(id.as_ref()),
+                "current": current.is_some_and(|current| current == id.as_ref()),
+            })
+        })
+        .collect();
+    let describe = |id: &str, description: String| json!({ "id": id, "description": description });
+    json!({
+        "providers": providers,
+        "flood_sources": flood::sources()
+            .into_iter()
+            .map(|(id, source)| describe(id, source.description().to_string()))
+            .collect::<Vec<_>>(),
+        "hazard_feeds": hazard::sources()
+            .into_iter()
+            .map(|(id, feed)| describe(id, feed.description().to_string()))
+            .collect::<Vec<_>>(),
+    })
+}
@@ -0,0 +1,413 @@
+//! Commands which produce reports besides forecasts: history export, batches, calendars, tracks and hazards
+use std::collections::{btree_map, BTreeMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, ensure, Context};
+use chrono::Timelike;
+
+use crate::config::Config;
+use crate::date::Date;
+use crate::progress::Event;
+use crate::provider_registry::ProviderRegistry;
+use crate::units::Units;
+use crate::{
+    batch, clock, date, export, flood, geocode, hazard, ics, logging, metar, progress, rate_limit,
+    redact, run_future, storms, timezone, track, ACTIVE_ENTRY,
+};
+
+use super::forecast::address_coordinates;
+use super::{create_provider, expand_alias};
+
+/// Exports historical weather day by day, appending to dataset file
+pub fn export_history(
+    registry: &ProviderRegistry,
+    config: &Config,
+    args: export::ExportArgs,
+) -> anyhow::Result<()> {
+    let (_, provider) = create_provider(registry, config, args.provider.clone())?;
+    let mut exporter = export::Exporter::open(&args)?;
+    let days: Vec<_> = exporter.remaining().collect();
+    let total = days.len();
+    if total == 0 {
+        logging::status(format!(
+            "All days are already exported to '{}'",
+            args.file.display()
+        ));
+        return Ok(());
+    }
+
+    for (step, day) in days.into_iter().enumerate() {
+        if step > 0 {
+            std::thread::sleep(Duration::from_millis(args.delay_ms));
+        }
+        let date = Date::from(day);
+        let info = run_future(rate_limit::retrying(|| {
+            provider.get_weather(args.address.clone().into(), Some(date))
+        }))
+        .with_context(|| {
+            anyhow!("When requesting weather for {day}; run same command again to resume")
+        })?;
+        exporter.append(&date, &info)?;
+        progress::emit(Event::Fetched {
+            location: &args.address,
+            date: Some(date.to_string()),
+            step: step + 1,
+            total,
+        });
+        if !logging::quiet() {
+            eprint!("\rExported {}/{total} days, up to {day}", step + 1);
+        }
+    }
+    if !logging::quiet() {
+        eprintln!();
+    }
+    logging::status(format!("Exported weather to '{}'", args.file.display()));
+    Ok(())
+}
+/// Executes batch of queries, writing their results to stdout
+///
+/// # Parameters
+/// * `args` - batch command's arguments; provider is default one of queries
+///
+/// # Returns
+/// Number of failed queries
+pub fn batch_queries(
+    registry: &ProviderRegistry,
+    config: &Config,
+    args: batch::BatchArgs,
+) -> anyhow::Result<usize> {
+    let mut queries = batch::read_queries(&args.input)?;
+    let default_provider = match args.provider {
+        Some(provider) => provider,
+        None => config
+            .globals
+            .get(ACTIVE_ENTRY)
+            .cloned()
+            .unwrap_or_default(),
+    };
+    // Each provider is created once; failure to create one fails only its queries
+    let mut providers = BTreeMap::new();
+    for query in &mut queries {
+        query.address = expand_alias(config, std::mem::take(&mut query.address));
+        // Features are put onto map, so addresses are resolved upfront; ambiguous ones aren't asked about
+        if let batch::BatchFormat::Geojson = args.output {
+            query.coordinates = address_coordinates(config, &query.address, true)?;
+        }
+        let name = query.provider.clone().unwrap_or(default_provider.clone());
+        if let btree_map::Entry::Vacant(entry) = providers.entry(name) {
+            let provider = if entry.key().is_empty() {
+                Err("Provider not specified, and there's no default one".to_string())
+            } else {
+                create_provider(registry, config, Some(entry.key().clone()))
+                    .map(|(_, provider)| Arc::new(provider))
+                    .map_err(|error| format!("{error:#}"))
+            };
+            entry.insert(provider);
+        }
+    }
+    let total = queries.len();
+    let failed = run_future(batch::run(
+        queries,
+        &providers,
+        &default_provider,
+        args.jobs,
+        args.output,
+        &mut std::io::stdout().lock(),
+    ))?;
+    if failed > 0 {
+        tracing::warn!("{failed} of {total} queries failed");
+    }
+    Ok(failed)
+}
+/// Prints forecasts for upcoming calendar events which have location
+pub fn calendar_forecast(
+    registry: &ProviderRegistry,
+    config: &Config,
+    ics: String,
+    provider: Option<String>,
+    limit: usize,
+    units: Units,
+) -> anyhow::Result<()> {
+    let (provider_name, provider) = create_provider(registry, config, provider)?;
+    let hourly = registry
+        .get(provider_name.as_str())
+        .is_some_and(|factory| factory.info().hourly);
+    let text = run_future(ics::load(&ics))?;
+    let now = clock::now().naive_local();
+
+    // All-day events of today are still upcoming
+    let events: Vec<_> = ics::parse_events(&text)
+        .into_iter()
+        .filter(|event| event.start >= now || (event.all_day && event.start.date() >= now.date()))
+        .take(limit)
+        .collect();
+    let total = events.len();
+
+    for (
+        step,
+        ics::Event {
+            summary,
+            location,
+            start,
+            all_day,
+        },
+    ) in events.into_iter().enumerate()
+    {
+        println!("{} {summary} @ {location}", date::format_time(start));
+        // Forecast is requested for event's time at its location, when it's known
+        let tz = timezone::of_location(&location);
+        let local = timezone::from_local(start, tz).map_or(start, |time| time.naive_local());
+        let day = Date::from(local.date());
+        let date = if hourly && !all_day {
+            Some(day.with_hour(Some(local.hour() as u8)))
+        } else {
+            // Today's events are requested as current conditions, since not all providers
+            // support dates
+            (local.date() != timezone::today(tz)).then_some(day)
+        };
+        match run_future(provider.get_weather(location.clone().into(), date)) {
+            Ok(forecast) => println!("{}", units.display(&forecast)),
+            Err(error) => println!(
+                "Could not obtain forecast: {}",
+                redact::redact(&format!("{error:#}"))
+            ),
+        }
+        progress::emit(Event::Fetched {
+            location: &location,
+            date: Some(start.date().to_string()),
+            step: step + 1,
+            total,
+        });
+        println!();
+    }
+
+    Ok(())
+}
+/// Prints forecasts for points sampled along track, optionally exporting annotated track
+pub fn track_forecast(
+    registry: &ProviderRegistry,
+    config: &Config,
+    file: PathBuf,
+    sampling: track::Sampling,
+    provider: Option<String>,
+    export: Option<PathBuf>,
+    units: Units,
+) -> anyhow::Result<()> {
+    let (_, provider) = create_provider(registry, config, provider)?;
+    let points = track::read_from_file(&file)?;
+
+    let sampled = track::sample(&points, &sampling)?;
+    let total = sampled.len();
+    let mut samples = Vec::new();
+    for (step, point) in sampled.into_iter().enumerate() {
+        let location = point.location();
+        // Point's time and date are shown in its own timezone, which may differ from machine's one
+        let tz = timezone::of_location(&location);
+        let time = point.time.and_then(|time| timezone::from_local(time, tz));
+        let date = time
+            .map(|time| time.date_naive())
+            .filter(|date| *date != timezone::today(tz))
+            .map(Date::from);
+
+        match &time {
+            Some(time) => println!(
+                "{location} at {} {}",
+                date::format_time(time.naive_local()),
+                time.format("%:z")
+            ),
+            None => println!("{location}"),
+        }
+        let forecast = run_future(provider.get_weather(location.clone().into(), date))
+            .map_err(|error| redact::redact(&format!("{error:#}")));
+        progress::emit(Event::Fetched {
+            location: &location,
+            date: point.time.map(|time| time.date().to_string()),
+            step: step + 1,
+            total,
+        });
+        match &forecast {
+            Ok(forecast) => println!("{}", units.display(forecast)),
+            Err(error) => println!("Could not obtain forecast: {error}"),
+        }
+        println!();
+        samples.push((point, forecast));
+    }
+
+    if let Some(export) = export {
+        track::export(&export, &samples)?;
+    }
+
+    Ok(())
+}
+/// Prints gauge levels and flood warnings near location
+///
+/// # Parameters
+/// * `radius` - search radius, in specified units
+/// * `units` - units in which distances are specified and shown
+pub fn flood_report(
+    address: String,
+    radius: f64,
+    source: Option<String>,
+    units: Units,
+) -> anyhow::Result<()> {
+    let sources = flood::sources();
+    let radius_km = units.distance_to_km(radius);
+    let report = run_future(async {
+        let (lat, lon) = geocode::coordinates(&address).await?;
+        let source = match &source {
+            Some(name) => sources
+                .get(name.as_str())
+                .ok_or_else(|| anyhow!("No such hydrology service: {name}"))?,
+            None => sources
+                .values()
+                .find(|source| source.covers(lat, lon))
+                .ok_or_else(|| anyhow!("No hydrology service covers location '{address}'"))?,
+        };
+        source
+            .get_flood(&flood::FloodQuery {
+                lat,
+                lon,
+                radius_km,
+            })
+            .await
+    })?;
+
+    if report.warnings.is_empty() {
+        println!("No active flood warnings");
+    } else {
+        println!("Flood warnings:");
+        for warning in &report.warnings {
+            println!("  {warning}");
+        }
+    }
+    if report.gauges.is_empty() {
+        println!("No river gauges within {radius} {}", units.distance_unit());
+    } else {
+        println!("River gauges:");
+        for gauge in &report.gauges {
+            println!("  {}", gauge.render(units));
+        }
+    }
+
+    Ok(())
+}
+/// Lists recent hazards near location from specified or all feeds
+///
+/// # Parameters
+/// * `radius` - search radius, in specified units
+/// * `units` - units in which distances are specified and shown
+pub fn list_hazards(
+    address: String,
+    radius: f64,
+    days: u32,
+    sources: Vec<String>,
+    units: Units,
+) -> anyhow::Result<()> {
+    let radius_km = units.distance_to_km(radius);
+    let mut feeds = hazard::sources();
+    if !sources.is_empty() {
+        for source in &sources {
+            ensure!(
+                feeds.contains_key(source.as_str()),
+                "No such hazard feed: {source}; available feeds are {}",
+                feeds.keys().copied().collect::<Vec<_>>().join(", ")
+            );
+        }
+        feeds.retain(|name, _| sources.iter().any(|source| source == name));
+    }
+
+    let mut hazards = run_future(async {
+        let (lat, lon) = geocode::coordinates(&address).await?;
+        let query = hazard::HazardQuery {
+            lat,
+            lon,
+            radius_km,
+            days,
+        };
+        let mut hazards = Vec::new();
+        for (name, feed) in &feeds {
+            hazards.extend(
+                feed.get_hazards(&query)
+                    .await
+                    .with_context(|| anyhow!("When querying hazard feed '{name}'"))?,
+            );
+        }
+        Ok(hazards)
+    })?;
+
+    if hazards.is_empty() {
+        println!(
+            "No hazards within {radius} {} during last {days} days",
+            units.distance_unit()
+        );
+    }
+    hazards.sort_by_key(|hazard| std::cmp::Reverse(hazard.time));
+    for hazard in hazards {
+        println!("{}", hazard.render(units));
+    }
+
+    Ok(())
+}
+/// Lists active tropical storms, optionally filtered by basin and proximity to location
+///
+/// # Parameters
+/// * `radius` - distance from location within which storms are shown, in specified units
+/// * `units` - units in which distances and speeds are specified and shown
+pub fn list_storms(
+    basin: Option<storms::Basin>,
+    near: Option<String>,
+    radius: f64,
+    units: Units,
+) -> anyhow::Result<()> {
+    let (storms, near) = run_future(async {
+        let storms = storms::active_storms().await?;
+        let near = match &near {
+            Some(address) => Some(geocode::coordinates(address).await?),
+            None => None,
+        };
+        Ok((storms, near))
+    })?;
+
+    let mut found = false;
+    for storm in storms {
+        if basin.is_some() && storm.basin != basin {
+            continue;
+        }
+        match near {
+            Some((lat, lon)) => {
+                let distance = units.distance(storm.distance_km(lat, lon));
+                if distance > radius {
+                    continue;
+                }
+                println!(
+                    "{}; {distance:.0} {} away",
+                    storm.render(units),
+                    units.distance_unit()
+                );
+            }
+            None => println!("{}", storm.render(units)),
+        }
+        found = true;
+    }
+    if !found {
+        println!("No active storms");
+    }
+
+    Ok(())
+}
+/// Shows latest aviation weather report of airport, raw and decoded
+///
+/// # Parameters
+/// * `kind` - kind of report
+/// * `station` - ICAO code of airport
+pub fn aviation_report(kind: metar::ReportKind, station: &str) -> anyhow::Result<()> {
+    let report = run_future(metar::latest(kind, station))?;
+    println!("{report}");
+    println!();
+    for line in metar::decode(kind, &report) {
+        println!("  {line}");
+    }
+    Ok(())
+}
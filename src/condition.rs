@@ -0,0 +1,296 @@
+//! Small expression language for checking forecast conditions
+//!
+//! Grammar:
+//! ```text
+//! expr       := and_expr ("or" and_expr)*
+//! and_expr   := term ("and" term)*
+//! term       := "not" term | "(" expr ")" | comparison | kind
+//! comparison := field op value
+//! field      := "weather" | "kind" | "temperature" | "temp" | "wind" | "humidity" | "precipitation" | "precip"
+//! op         := "==" | "!=" | "<" | "<=" | ">" | ">="
+//! ```
+//! Bare weather kind, like `rain`, is shortcut for `weather == rain`.
+//! Numeric fields use same units as `WeatherInfo`, i.e. Celsius degrees, m/s, percents and millimeters
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure};
+
+use crate::provider::{WeatherInfo, WeatherKind};
+
+/// Parsed condition expression
+pub enum Condition {
+    /// Both conditions hold
+    And(Box<Condition>, Box<Condition>),
+    /// Any of conditions holds
+    Or(Box<Condition>, Box<Condition>),
+    /// Condition doesn't hold
+    Not(Box<Condition>),
+    /// Weather kind is, or isn't, specified one
+    Kind(bool, WeatherKind),
+    /// Numeric field compared with value
+    Compare(Field, Op, f32),
+}
+
+/// Numeric forecast field
+#[derive(Clone, Copy)]
+pub enum Field {
+    Temperature,
+    Wind,
+    Humidity,
+    Precipitation,
+}
+
+/// Comparison operator
+#[derive(Clone, Copy)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn apply(&self, left: f32, right: f32) -> bool {
+        match self {
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+        }
+    }
+}
+
+impl Condition {
+    /// Checks whether forecast satisfies condition
+    ///
+    /// Missing precipitation data is treated as zero precipitation
+    pub fn evaluate(&self, info: &WeatherInfo) -> bool {
+        match self {
+            Condition::And(left, right) => left.evaluate(info) && right.evaluate(info),
+            Condition::Or(left, right) => left.evaluate(info) || right.evaluate(info),
+            Condition::Not(inner) => !inner.evaluate(info),
+            Condition::Kind(equal, kind) => (info.weather == *kind) == *equal,
+            Condition::Compare(field, op, value) => {
+                let actual = match field {
+                    Field::Temperature => info.temperature,
+                    Field::Wind => info.wind_speed,
+                    Field::Humidity => info.humidity,
+                    Field::Precipitation => info.precipitation.unwrap_or_default(),
+                };
+                op.apply(actual, *value)
+            }
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let condition = parser.expr()?;
+        ensure!(
+            parser.pos == parser.tokens.len(),
+            "Unexpected '{}' in condition",
+            parser.tokens[parser.pos]
+        );
+        Ok(condition)
+    }
+}
+/// Splits expression into words, numbers, operators and parentheses
+fn tokenize(s: &str) -> anyhow::Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if "=!<>".contains(c) {
+            let mut op = String::from(c);
+            chars.next();
+            if chars.peek() == Some(&'=') {
+                op.push('=');
+                chars.next();
+            }
+            tokens.push(op);
+        } else if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '.' || c == '-' || c == '_') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(word);
+        } else {
+            bail!("Unexpected character '{c}' in condition");
+        }
+    }
+    Ok(tokens)
+}
+/// Recursive descent parser over tokens
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> anyhow::Result<String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unexpected end of condition"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expr(&mut self) -> anyhow::Result<Condition> {
+        let mut left = self.and_expr()?;
+        while self.peek() == Some("or") {
+            self.pos += 1;
+            left = Condition::Or(Box::new(left), Box::new(self.and_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> anyhow::Result<Condition> {
+        let mut left = self.term()?;
+        while self.peek() == Some("and") {
+            self.pos += 1;
+            left = Condition::And(Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> anyhow::Result<Condition> {
+        let token = self.next()?;
+        match token.as_str() {
+            "not" => Ok(Condition::Not(Box::new(self.term()?))),
+            "(" => {
+                let inner = self.expr()?;
+                ensure!(self.next()? == ")", "Missing ')' in condition");
+                Ok(inner)
+            }
+            "weather" | "kind" => {
+                let equal = match self.next()?.as_str() {
+                    "==" => true,
+                    "!=" => false,
+                    op => bail!("Weather kind can be compared only with '==' or '!=', not '{op}'"),
+                };
+                Ok(Condition::Kind(equal, self.next()?.parse()?))
+            }
+            "temperature" | "temp" | "wind" | "humidity" | "precipitation" | "precip" => {
+                let field = match token.as_str() {
+                    "temperature" | "temp" => Field::Temperature,
+                    "wind" => Field::Wind,
+                    "humidity" => Field::Humidity,
+                    _ => Field::Precipitation,
+                };
+                let op = match self.next()?.as_str() {
+                    "==" => Op::Eq,
+                    "!=" => Op::Ne,
+                    "<" => Op::Lt,
+                    "<=" => Op::Le,
+                    ">" => Op::Gt,
+                    ">=" => Op::Ge,
+                    op => bail!("Unknown comparison operator '{op}'"),
+                };
+                let value = self.next()?;
+                let value = value
+                    .parse()
+                    .map_err(|_| anyhow!("'{value}' isn't a number"))?;
+                Ok(Condition::Compare(field, op, value))
+            }
+            // Bare weather kind
+            kind => Ok(Condition::Kind(true, kind.parse()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::Condition;
+    use crate::provider::{WeatherInfo, WeatherKind};
+
+    fn info(weather: WeatherKind, temperature: f32, wind_speed: f32) -> WeatherInfo {
+        WeatherInfo {
+            wind_speed,
+            ..WeatherInfo::sample(weather, temperature)
+        }
+    }
+
+    fn holds(condition: &str, info: &WeatherInfo) -> bool {
+        Condition::from_str(condition).unwrap().evaluate(info)
+    }
+
+    #[test]
+    fn kinds_are_matched() {
+        let rain = info(WeatherKind::Rain, 15.0, 3.0);
+        assert!(holds("rain", &rain));
+        assert!(holds("weather == rain", &rain));
+        assert!(!holds("kind != rain", &rain));
+        assert!(!holds("snow", &rain));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let snow = info(WeatherKind::Snow, -2.0, 5.0);
+        assert!(holds("snow or rain and wind > 10", &snow));
+        assert!(!holds("(snow or rain) and wind > 10", &snow));
+        // Negation applies to nearest term only
+        let clear = info(WeatherKind::Clear, 20.0, 1.0);
+        assert!(!holds("not rain and wind > 3", &clear));
+        assert!(holds("not (rain and wind > 3)", &clear));
+    }
+
+    #[test]
+    fn numbers_are_compared() {
+        let warm = info(WeatherKind::Clear, 20.0, 4.5);
+        assert!(holds("temperature >= 20", &warm));
+        assert!(!holds("temperature > 20", &warm));
+        assert!(holds("temp < 20.5", &warm));
+        assert!(!holds("temp <= 19", &warm));
+        assert!(holds("temp == 20 and temp != -5", &warm));
+        assert!(holds("wind > 4 and humidity < 61", &warm));
+        // Missing precipitation counts as none
+        assert!(holds("precip == 0", &warm));
+        assert!(!holds("precipitation > 0", &warm));
+    }
+
+    #[test]
+    fn malformed_conditions_are_rejected() {
+        for condition in [
+            "",
+            "temperature >",
+            "temperature > warm",
+            "temp ~ 5",
+            "weather > rain",
+            "(rain or snow",
+            "rain )",
+            "rain snow",
+            "sunshine",
+        ] {
+            assert!(
+                Condition::from_str(condition).is_err(),
+                "'{condition}' is accepted"
+            );
+        }
+        let error = Condition::from_str("temperature > warm").err().unwrap();
+        assert_eq!(error.to_string(), "'warm' isn't a number");
+    }
+}
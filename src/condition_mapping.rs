@@ -0,0 +1,252 @@
+//! User overrides of provider condition code mappings
+//!
+//! Each provider maps its own condition codes into `WeatherKind` using built-in tables.
+//! Users may fix particular mappings with `[mappings.<provider>]` config sections,
+//! e.g. `1087 = rain`, which take precedence over built-in ones
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Instant;
+
+use anyhow::{anyhow, Context};
+use tracing::Instrument;
+
+use crate::config::Section;
+use crate::date::Date;
+use crate::provider::{
+    DataKind, LightningInfo, PlannedRequest, Provider, WeatherInfo, WeatherKind,
+};
+use crate::CowString;
+
+/// Overrides of condition code mappings for single provider
+#[derive(Default)]
+pub struct ConditionMapping(HashMap<String, WeatherKind>);
+
+impl ConditionMapping {
+    /// Parses overrides from config section
+    ///
+    /// # Parameters
+    /// * `section` - section with "<code> = <kind>" entries
+    ///
+    /// # Returns
+    /// Parsed mapping or error if some kind isn't recognized
+    pub fn from_section(section: &Section) -> anyhow::Result<Self> {
+        section
+            .iter()
+            .map(|(code, kind)| {
+                let kind = WeatherKind::from_str(kind)
+                    .with_context(|| anyhow!("When parsing mapping of condition code '{code}'"))?;
+                Ok((code.clone(), kind))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+    /// Replaces forecast's weather kind if its condition code is overridden
+    fn apply(&self, info: &mut WeatherInfo) {
+        if let Some(kind) = info
+            .condition_code
+            .as_ref()
+            .and_then(|code| self.0.get(code))
+        {
+            info.weather = *kind;
+        }
+    }
+}
+/// Provider instance along with its name and mapping overrides
+///
+/// Mirrors provider's request methods, applying overrides to their results
+/// and completing their metadata
+pub struct MappedProvider {
+    name: String,
+    provider: Box<dyn Provider>,
+    mapping: ConditionMapping,
+}
+
+impl MappedProvider {
+    /// Wraps provider with mapping overrides
+    ///
+    /// # Parameters
+    /// * `name` - name under which provider is configured
+    /// * `provider` - provider instance
+    /// * `mapping` - overrides of its condition code mappings
+    pub fn new(name: &str, provider: Box<dyn Provider>, mapping: ConditionMapping) -> Self {
+        Self {
+            name: name.to_string(),
+            provider,
+            mapping,
+        }
+    }
+    /// Same as `Provider::get_weather`, with overrides applied and metadata completed
+    pub async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let span = tracing::info_span!(
+            "provider",
+            provider = self.name,
+            call = "weather",
+            location = %location,
+            date = date.map(|date| date.to_string()),
+            duration_ms = tracing::field::Empty
+        );
+        let mut info = timed(span, self.provider.get_weather(location, date)).await?;
+        self.mapping.apply(&mut info);
+        info.meta.provider.clone_from(&self.name);
+        // Providers which don't tell kind of data are assumed to observe present and past
+        // and forecast future
+        if info.meta.kind.is_none() {
+            let today = chrono::Local::now().date_naive();
+            let past = date.is_none_or(|date| date.to_naive().is_some_and(|day| day < today));
+            info.meta.kind = Some(if past {
+                DataKind::Observation
+            } else {
+                DataKind::Forecast
+            });
+        }
+        Ok(info)
+    }
+    /// Same as `Provider::get_hourly`, with overrides applied and metadata completed
+    pub async fn get_hourly(
+        &self,
+        location: CowString,
+        start: Date,
+        hours: u32,
+    ) -> anyhow::Result<Vec<WeatherInfo>> {
+        let span = tracing::info_span!(
+            "provider",
+            provider = self.name,
+            call = "hourly",
+            location = %location,
+            date = %start,
+            hours,
+            duration_ms = tracing::field::Empty
+        );
+        let mut infos = timed(span, self.provider.get_hourly(location, start, hours)).await?;
+        for info in &mut infos {
+            self.mapping.apply(info);
+            info.meta.provider.clone_from(&self.name);
+            info.meta.kind.get_or_insert(DataKind::Forecast);
+        }
+        Ok(infos)
+    }
+    /// Same as `Provider::plan_weather`
+    pub fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        self.provider.plan_weather(location, date)
+    }
+    /// Same as `Provider::get_lightning`
+    pub async fn get_lightning(
+        &self,
+        location: CowString,
+        radius_km: f32,
+        period_minutes: u32,
+    ) -> anyhow::Result<LightningInfo> {
+        let span = tracing::info_span!(
+            "provider",
+            provider = self.name,
+            call = "lightning",
+            location = %location,
+            radius_km,
+            period_minutes,
+            duration_ms = tracing::field::Empty
+        );
+        timed(
+            span,
+            self.provider
+                .get_lightning(location, radius_km, period_minutes),
+        )
+        .await
+    }
+}
+/// Awaits provider call within its span, recording call's duration into span's `duration_ms` field
+///
+/// # Parameters
+/// * `span` - call's span
+/// * `call` - call's future
+async fn timed<T>(span: tracing::Span, call: impl Future<Output = T>) -> T {
+    let started = Instant::now();
+    let result = call.instrument(span.clone()).await;
+    span.record("duration_ms", started.elapsed().as_millis() as u64);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::{ConditionMapping, MappedProvider};
+    use crate::config::Section;
+    use crate::date::Date;
+    use crate::provider::{PlannedRequest, Provider, ProviderInfo, WeatherInfo, WeatherKind};
+    use crate::CowString;
+
+    /// Reports location's name as condition code, which built-in table maps to rain
+    struct Fake;
+
+    #[async_trait]
+    impl Provider for Fake {
+        fn new(_: &Section) -> anyhow::Result<Self> {
+            Ok(Self)
+        }
+        fn info() -> &'static ProviderInfo {
+            unimplemented!()
+        }
+        async fn get_weather(
+            &self,
+            location: CowString,
+            _: Option<Date>,
+        ) -> anyhow::Result<WeatherInfo> {
+            Ok(WeatherInfo {
+                condition_code: Some(location.to_string()),
+                ..WeatherInfo::sample(WeatherKind::Rain, 10.0)
+            })
+        }
+        fn plan_weather(&self, _: &str, _: Option<Date>) -> anyhow::Result<Vec<PlannedRequest>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn mapping(entries: &[(&str, &str)]) -> anyhow::Result<ConditionMapping> {
+        let section: Section = entries
+            .iter()
+            .map(|(code, kind)| (code.to_string(), kind.to_string()))
+            .collect();
+        ConditionMapping::from_section(&section)
+    }
+
+    #[tokio::test]
+    async fn overridden_code_replaces_built_in_kind() {
+        let provider = MappedProvider::new(
+            "fake",
+            Box::new(Fake),
+            mapping(&[("1087", "thunderstorm")]).unwrap(),
+        );
+        let info = provider.get_weather("1087".into(), None).await.unwrap();
+        assert_eq!(info.weather, WeatherKind::Thunderstorm);
+        assert_eq!(info.meta.provider, "fake");
+    }
+
+    #[tokio::test]
+    async fn other_codes_keep_built_in_kind() {
+        let provider = MappedProvider::new(
+            "fake",
+            Box::new(Fake),
+            mapping(&[("1087", "thunderstorm")]).unwrap(),
+        );
+        let info = provider.get_weather("1063".into(), None).await.unwrap();
+        assert_eq!(info.weather, WeatherKind::Rain);
+    }
+
+    #[test]
+    fn unknown_kind_is_rejected() {
+        let error = mapping(&[("1087", "meteors")]).err().unwrap();
+        assert_eq!(
+            format!("{error:#}"),
+            "When parsing mapping of condition code '1087': Unknown weather kind 'meteors'"
+        );
+    }
+}
@@ -1,28 +1,312 @@
 use std::{
     collections::BTreeMap,
     convert::Infallible,
-    fs,
+    fmt::{Display, Formatter},
+    fs::{self, File, OpenOptions, TryLockError},
     path::{Path, PathBuf},
     str::FromStr,
 };
 
 use anyhow::{anyhow, bail, Context};
 use light_ini::{IniHandler, IniParser};
+
+use crate::app_dirs;
 /// Representation of INI file section
 /// BTreeMap is used to preserve nice alphabetic order of keys
 pub type Section = BTreeMap<String, String>;
 
+/// Prefix of sections which describe named profiles, like `[profile.work]`
+const PROFILE_PREFIX: &str = "profile.";
+/// Prefix of sections with provider's condition code overrides, like `[mappings.weatherapi]`
+const MAPPINGS_PREFIX: &str = "mappings.";
+/// Prefix of sections with provider's default parameters, like `[defaults.openweather]`
+const DEFAULTS_PREFIX: &str = "defaults.";
+/// Section with commands executed before and after CLI commands
+const HOOKS_SECTION: &str = "hooks";
+/// Section with location aliases, like `home = Baker Street 221b, London`
+const LOCATIONS_SECTION: &str = "locations";
+/// Section with alert rules checked by 'publish' command, like `frost = temperature < 0`
+const ALERTS_SECTION: &str = "alerts";
+/// Global entry with path to config file which is merged beneath this one, like `include = /shared/team.ini`
+pub const INCLUDE_ENTRY: &str = "include";
+
 /// Application's parsed raw configuration file
 #[derive(Default)]
 pub struct Config {
     pub globals: Section,
     pub sections: BTreeMap<String, Section>,
+    /// Named profiles, each with its own defaults, keyed by name without prefix
+    pub profiles: BTreeMap<String, Section>,
+    /// User overrides of condition code mappings, keyed by provider name without prefix
+    pub mappings: BTreeMap<String, Section>,
+    /// User defaults of providers' non-secret parameters, keyed by provider name without prefix
+    pub defaults: BTreeMap<String, Section>,
+    /// Command hooks and their settings, `[hooks]` section
+    pub hooks: Section,
+    /// Location aliases and addresses they stand for, `[locations]` section
+    pub locations: Section,
+    /// Alert names and conditions which trigger them, `[alerts]` section
+    pub alerts: Section,
+    /// Contents of included files; entries taken from there aren't written back
+    included: Option<Box<Config>>,
+    /// Original text of config file; it's edited in place when written back,
+    /// so user's comments, ordering and formatting survive
+    source: Option<String>,
+    /// Whether config was changed since it was read, and thus should be written back
+    dirty: bool,
+    /// Lock of config file, held while config is alive
+    lock: Option<ConfigLock>,
 }
 
+/// Section name, `None` for globals, and entries which should be written into it
+type OwnSection<'a> = (Option<String>, Vec<(&'a String, &'a String)>);
+
 impl Config {
     pub fn new() -> Self {
         Self::default()
     }
+    /// Lays this config on top of `base`, so own entries take precedence over included ones
+    ///
+    /// # Parameters
+    /// * `base` - merged contents of included files
+    ///
+    /// # Returns
+    /// Merged config, which remembers `base` to omit its entries when written
+    fn merge_over(self, base: Config) -> Config {
+        let merge_sections = |base: &BTreeMap<String, Section>, own: BTreeMap<String, Section>| {
+            let mut merged = base.clone();
+            for (name, section) in own {
+                merged.entry(name).or_default().extend(section);
+            }
+            merged
+        };
+
+        let mut globals = base.globals.clone();
+        globals.extend(self.globals);
+        let mut hooks = base.hooks.clone();
+        hooks.extend(self.hooks);
+        let mut locations = base.locations.clone();
+        locations.extend(self.locations);
+        let mut alerts = base.alerts.clone();
+        alerts.extend(self.alerts);
+        Config {
+            globals,
+            hooks,
+            locations,
+            alerts,
+            sections: merge_sections(&base.sections, self.sections),
+            profiles: merge_sections(&base.profiles, self.profiles),
+            mappings: merge_sections(&base.mappings, self.mappings),
+            defaults: merge_sections(&base.defaults, self.defaults),
+            included: Some(Box::new(base)),
+            source: self.source,
+            dirty: false,
+            lock: None,
+        }
+    }
+    /// All sections along with their names in config file, `None` for globals
+    ///
+    /// # Returns
+    /// Sections in order they're written to file, with entries of included files merged in;
+    /// providers' sections are listed even if empty
+    pub fn named_sections(&self) -> Vec<(Option<String>, &Section)> {
+        let mut sections = vec![(None, &self.globals)];
+        for (name, section) in &self.sections {
+            sections.push((Some(name.clone()), section));
+        }
+        for (name, section) in [
+            (HOOKS_SECTION, &self.hooks),
+            (LOCATIONS_SECTION, &self.locations),
+            (ALERTS_SECTION, &self.alerts),
+        ] {
+            sections.push((Some(name.to_string()), section));
+        }
+        for (name, section) in &self.profiles {
+            sections.push((Some(format!("{PROFILE_PREFIX}{name}")), section));
+        }
+        for (name, section) in &self.mappings {
+            sections.push((Some(format!("{MAPPINGS_PREFIX}{name}")), section));
+        }
+        for (name, section) in &self.defaults {
+            sections.push((Some(format!("{DEFAULTS_PREFIX}{name}")), section));
+        }
+        sections.retain(|(name, section)| {
+            !section.is_empty()
+                || name
+                    .as_ref()
+                    .is_some_and(|name| self.sections.contains_key(name))
+        });
+        sections
+    }
+    /// Section by its name in config file, created if it doesn't exist yet
+    ///
+    /// # Parameters
+    /// * `name` - section name, like `weatherapi` or `profile.work`; `None` for globals
+    pub fn section_mut(&mut self, name: Option<&str>) -> &mut Section {
+        let Some(name) = name else {
+            return &mut self.globals;
+        };
+        if let Some(profile) = name.strip_prefix(PROFILE_PREFIX) {
+            self.profiles.entry(profile.to_string()).or_default()
+        } else if let Some(provider) = name.strip_prefix(MAPPINGS_PREFIX) {
+            self.mappings.entry(provider.to_string()).or_default()
+        } else if let Some(provider) = name.strip_prefix(DEFAULTS_PREFIX) {
+            self.defaults.entry(provider.to_string()).or_default()
+        } else if name == HOOKS_SECTION {
+            &mut self.hooks
+        } else if name == LOCATIONS_SECTION {
+            &mut self.locations
+        } else if name == ALERTS_SECTION {
+            &mut self.alerts
+        } else {
+            self.sections.entry(name.to_string()).or_default()
+        }
+    }
+    /// Marks config as changed, so it's written back when command completes
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+    /// Checks whether config was changed since it was read
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+    /// Ensures config doesn't lack entries of included files, since writing it back can't remove
+    /// them; they'd be read from included files again
+    ///
+    /// # Returns
+    /// Error which lists removed sections and entries, if there are any
+    fn check_included(&self) -> anyhow::Result<()> {
+        let Some(base) = self.included.as_deref() else {
+            return Ok(());
+        };
+        let own = self.named_sections();
+        let empty = Section::new();
+        let mut removed = Vec::new();
+        for (name, section) in base.named_sections() {
+            let own_section = own
+                .iter()
+                .find(|(own_name, _)| *own_name == name)
+                .map(|(_, section)| *section);
+            // Providers are configured by section itself, other sections only hold entries
+            match (&name, own_section) {
+                (Some(name), None) if base.sections.contains_key(name) => {
+                    removed.push(format!("[{name}]"));
+                }
+                (_, own_section) => {
+                    let own_section = own_section.unwrap_or(&empty);
+                    let header = name.as_ref().map(|name| format!(" in [{name}]"));
+                    removed.extend(
+                        section
+                            .keys()
+                            .filter(|key| !own_section.contains_key(*key))
+                            .map(|key| format!("'{key}'{}", header.as_deref().unwrap_or_default())),
+                    );
+                }
+            }
+        }
+        if !removed.is_empty() {
+            bail!(
+                "Config can't be saved, since {} come from included file '{}'; edit that file instead",
+                removed.join(", "),
+                self.globals.get(INCLUDE_ENTRY).map_or("", String::as_str)
+            );
+        }
+        Ok(())
+    }
+    /// Collects sections which should be written back, in order they're generated
+    ///
+    /// # Returns
+    /// Non-empty sections, without entries which come unchanged from included files;
+    /// providers' sections are kept even if empty, since providers without parameters
+    /// are configured with empty section
+    fn own_sections(&self) -> Vec<OwnSection<'_>> {
+        fn add_section<'a>(
+            sections: &mut Vec<OwnSection<'a>>,
+            name: Option<String>,
+            section: &'a Section,
+            base: Option<&Section>,
+        ) {
+            // Entries which come unchanged from included files stay there
+            let own: Vec<_> = section
+                .iter()
+                .filter(|(key, value)| base.and_then(|base| base.get(*key)) != Some(*value))
+                .collect();
+            if !own.is_empty() {
+                sections.push((name, own));
+            }
+        }
+
+        let included = self.included.as_deref();
+        let mut sections = Vec::new();
+
+        add_section(
+            &mut sections,
+            None,
+            &self.globals,
+            included.map(|base| &base.globals),
+        );
+
+        for (name, section) in &self.sections {
+            let base = included.and_then(|base| base.sections.get(name));
+            if section.is_empty() && base.is_none() {
+                sections.push((Some(name.clone()), Vec::new()));
+            }
+            add_section(&mut sections, Some(name.clone()), section, base);
+        }
+
+        add_section(
+            &mut sections,
+            Some(HOOKS_SECTION.to_string()),
+            &self.hooks,
+            included.map(|base| &base.hooks),
+        );
+
+        add_section(
+            &mut sections,
+            Some(LOCATIONS_SECTION.to_string()),
+            &self.locations,
+            included.map(|base| &base.locations),
+        );
+
+        add_section(
+            &mut sections,
+            Some(ALERTS_SECTION.to_string()),
+            &self.alerts,
+            included.map(|base| &base.alerts),
+        );
+
+        for (name, section) in &self.profiles {
+            let base = included.and_then(|base| base.profiles.get(name));
+            add_section(
+                &mut sections,
+                Some(format!("{PROFILE_PREFIX}{name}")),
+                section,
+                base,
+            );
+        }
+
+        for (name, section) in &self.mappings {
+            let base = included.and_then(|base| base.mappings.get(name));
+            add_section(
+                &mut sections,
+                Some(format!("{MAPPINGS_PREFIX}{name}")),
+                section,
+                base,
+            );
+        }
+
+        for (name, section) in &self.defaults {
+            let base = included.and_then(|base| base.defaults.get(name));
+            add_section(
+                &mut sections,
+                Some(format!("{DEFAULTS_PREFIX}{name}")),
+                section,
+                base,
+            );
+        }
+
+        sections
+    }
 }
 
 impl FromStr for Config {
@@ -32,37 +316,194 @@ impl FromStr for Config {
         let mut visitor = IniVisitor::new();
         let mut parser = IniParser::with_start_comment(&mut visitor, '#');
         parser.parse(s.as_bytes())?;
-        Ok(visitor.build())
+        Ok(Config {
+            source: Some(s.to_string()),
+            ..visitor.build()
+        })
     }
 }
 
-impl ToString for Config {
-    fn to_string(&self) -> String {
-        let mut buf = String::new();
-
-        let mut write_section = |name: Option<&str>, section: &Section| {
-            if section.is_empty() {
-                return;
-            }
+impl Display for Config {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sections = self.own_sections();
+        if let Some(source) = &self.source {
+            return f.write_str(&edit_in_place(source, &sections));
+        }
 
+        let mut buf = String::new();
+        for (name, entries) in sections {
             if let Some(name) = name {
                 buf.push_str(&format!("[{name}]\n"));
             }
-
-            for (name, value) in section {
+            for (name, value) in entries {
                 buf.push_str(&format!("{name} = {value}\n"));
             }
             buf.push('\n');
+        }
+
+        f.write_str(&buf)
+    }
+}
+/// Rewrites original config text so it contains exactly specified sections
+///
+/// Comments, blank lines, line endings and order of entries are kept. Changed entries are rewritten
+/// where they are, new ones are put after last entry of their section, and new sections
+/// are appended to the end of file. Sections whose entries were all removed are dropped
+/// along with their comments
+///
+/// # Parameters
+/// * `source` - original text of config file
+/// * `sections` - sections which should be written, with their entries
+///
+/// # Returns
+/// Edited config text
+fn edit_in_place(source: &str, sections: &[OwnSection]) -> String {
+    let newline = if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    // Leading globals block, then one block per section header
+    let mut blocks: Vec<(Option<&str>, Vec<&str>)> = vec![(None, Vec::new())];
+    for line in source.lines() {
+        match parse_header(line) {
+            Some(name) => blocks.push((Some(name), vec![line])),
+            None => blocks
+                .last_mut()
+                .expect("There's always globals block")
+                .1
+                .push(line),
+        }
+    }
+
+    let mut output: Vec<String> = Vec::new();
+    let mut seen_sections: Vec<Option<&str>> = Vec::new();
+    let mut written: Vec<(Option<&str>, &str)> = Vec::new();
+    for (name, lines) in blocks {
+        let entries = sections
+            .iter()
+            .find(|(section, _)| section.as_deref() == name)
+            .map_or(&[][..], |(_, entries)| entries.as_slice());
+        let (header, body) = match name {
+            Some(_) => (&lines[..1], &lines[1..]),
+            None => (&lines[..0], &lines[..]),
         };
 
-        write_section(None, &self.globals);
+        let mut out: Vec<String> = header.iter().map(|line| line.to_string()).collect();
+        let mut had_entries = false;
+        let mut last_entry = None;
+        for line in body {
+            let Some((key, value)) = parse_entry(line) else {
+                out.push(line.to_string());
+                continue;
+            };
+            had_entries = true;
+            if written.contains(&(name, key)) {
+                continue;
+            }
+            let Some((key, new_value)) = entries.iter().find(|(own, _)| own.as_str() == key) else {
+                // Comments right above removed entry describe it, so they go too
+                while out.len() > header.len()
+                    && out.last().is_some_and(|line| line.trim().starts_with('#'))
+                {
+                    out.pop();
+                }
+                continue;
+            };
+            if value == new_value.as_str() {
+                out.push(line.to_string());
+            } else {
+                out.push(format!("{key} = {new_value}"));
+            }
+            written.push((name, key.as_str()));
+            last_entry = Some(out.len());
+        }
 
-        for (name, section) in &self.sections {
-            write_section(Some(name.as_str()), section);
+        // New entries go only into the first block of section
+        if !seen_sections.contains(&name) {
+            seen_sections.push(name);
+            let missing: Vec<_> = entries
+                .iter()
+                .filter(|(key, _)| !written.contains(&(name, key.as_str())))
+                .collect();
+            if !missing.is_empty() {
+                let position = last_entry.unwrap_or_else(|| {
+                    out.iter()
+                        .rposition(|line| !line.trim().is_empty())
+                        .map_or(0, |index| index + 1)
+                });
+                let was_empty = out.is_empty();
+                out.splice(
+                    position..position,
+                    missing
+                        .iter()
+                        .map(|(key, value)| format!("{key} = {value}")),
+                );
+                if was_empty {
+                    out.push(String::new());
+                }
+                for (key, _) in missing {
+                    written.push((name, key.as_str()));
+                }
+                last_entry = Some(position);
+            }
+        }
+
+        // Section whose entries are all gone disappears with its comments, as does removed
+        // section which had neither entries nor comments, like one of provider without parameters
+        let kept = sections
+            .iter()
+            .any(|(section, _)| section.as_deref() == name);
+        let bare = body.iter().all(|line| line.trim().is_empty());
+        if name.is_some() && (had_entries || bare) && last_entry.is_none() && !kept {
+            // Comments right above header describe section, so they go too
+            while output
+                .last()
+                .is_some_and(|line| line.trim().starts_with('#'))
+            {
+                output.pop();
+            }
+            continue;
+        }
+        output.extend(out);
+    }
+
+    for (name, entries) in sections {
+        if seen_sections.contains(&name.as_deref()) {
+            continue;
+        }
+        if output.last().is_some_and(|line| !line.trim().is_empty()) {
+            output.push(String::new());
+        }
+        if let Some(name) = name {
+            output.push(format!("[{name}]"));
         }
+        for (key, value) in entries {
+            output.push(format!("{key} = {value}"));
+        }
+    }
 
-        buf
+    output.into_iter().map(|line| line + newline).collect()
+}
+/// Parses section header line, like `[profile.work]`
+///
+/// # Returns
+/// Section name, or `None` if line isn't header
+fn parse_header(line: &str) -> Option<&str> {
+    let name = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    Some(name.trim())
+}
+/// Parses entry line, like `apikey = 0123`
+///
+/// # Returns
+/// Entry's key and value, or `None` if line is comment, blank or otherwise not entry
+fn parse_entry(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.starts_with('#') || line.starts_with('[') {
+        return None;
     }
+    let (key, value) = line.split_once('=')?;
+    Some((key.trim(), value.trim()))
 }
 /// Simple visitor for parsing INI files
 #[derive(Default)]
@@ -81,25 +522,22 @@ impl IniVisitor {
     /// Build `Config` out of visitor
     fn build(mut self) -> Config {
         self.flush_current();
-        Config {
+        let mut config = Config {
             globals: self.globals.into_iter().collect(),
-            sections: self
-                .sections
-                .into_iter()
-                .map(|(name, items)| (name, items.into_iter().collect()))
-                .collect(),
+            ..Config::default()
+        };
+        for (name, items) in self.sections {
+            *config.section_mut(Some(&name)) = items.into_iter().collect();
         }
+        config
     }
     /// Move currently collected section to either globals or new named section
     fn flush_current(&mut self) {
         let (current_name, current_items) = std::mem::take(&mut self.current);
-        if current_items.is_empty() {
-            return;
-        }
-
+        // Empty sections are kept, since they configure providers without parameters
         if let Some(name) = current_name {
             self.sections.push((name, current_items));
-        } else {
+        } else if !current_items.is_empty() {
             self.globals = current_items;
         }
     }
@@ -122,33 +560,105 @@ impl IniHandler for IniVisitor {
     }
 }
 
+/// Advisory lock of config file, released when dropped
+///
+/// Lock is taken on separate `<config>.lock` file next to config, so it doesn't depend
+/// on how config itself is written
+pub struct ConfigLock {
+    _file: File,
+}
+
+impl ConfigLock {
+    /// Locks config file, waiting for other invocations to release it
+    ///
+    /// # Parameters
+    /// * `config_path` - path to config file
+    /// * `exclusive` - whether config is going to be modified; otherwise lock is shared with other readers
+    ///
+    /// # Returns
+    /// Lock, or `None` if config is only read and lock file cannot be created,
+    /// like in read-only config deployments
+    fn acquire(config_path: &Path, exclusive: bool) -> anyhow::Result<Option<Self>> {
+        let mut lock_path = config_path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+
+        if exclusive {
+            if let Some(dir) = lock_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)
+                    .with_context(|| anyhow!("When creating config directory {}", dir.display()))?;
+            }
+        }
+        let file = match OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+        {
+            Ok(file) => file,
+            Err(error) if !exclusive => {
+                tracing::debug!(
+                    "Reading config without lock, as {} cannot be opened: {error}",
+                    lock_path.display()
+                );
+                return Ok(None);
+            }
+            Err(error) => {
+                return Err(error).with_context(|| {
+                    anyhow!("When opening config lock file {}", lock_path.display())
+                })
+            }
+        };
+
+        let locked = if exclusive {
+            file.try_lock()
+        } else {
+            file.try_lock_shared()
+        };
+        match locked {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                tracing::warn!("Config file is in use by another invocation, waiting for it");
+                if exclusive {
+                    file.lock()
+                } else {
+                    file.lock_shared()
+                }
+                .with_context(|| anyhow!("When locking config file {}", config_path.display()))?;
+            }
+            Err(TryLockError::Error(error)) => {
+                return Err(error)
+                    .with_context(|| anyhow!("When locking config file {}", config_path.display()))
+            }
+        }
+        Ok(Some(Self { _file: file }))
+    }
+}
 /// Read app's configuration at specified path; if path isn't provided, default config path is used
 ///
+/// Config file stays locked while returned config is alive: exclusively if it's going to be modified,
+/// so concurrent invocations don't overwrite each other's changes, or shared otherwise
+///
 /// # Parameters
 /// * `path` - optional config path
+/// * `for_update` - whether config is going to be modified and written back
 ///
 /// # Returns
 /// Parsed configuration as TOML table and path to it
-pub fn read_from_file(path: Option<PathBuf>) -> anyhow::Result<(Config, PathBuf)> {
+pub fn read_from_file(
+    path: Option<PathBuf>,
+    for_update: bool,
+) -> anyhow::Result<(Config, PathBuf)> {
     // Fetch path to config file
-    let config_path = if let Some(path) = path {
-        path
-    } else if let Some(path) = dirs::config_dir() {
-        path.join("weather-cli").join("config.ini")
-    } else if let Some(path) = dirs::home_dir() {
-        path.join(".weather-cli.ini")
-    } else {
-        bail!(
-            "Current OS doesn't seem to have notion of either user's config directory or user's home directory. Please use explicit '--config' argument"
-        )
+    let config_path = match path {
+        Some(path) => path,
+        None => default_path()?,
     };
 
+    let lock = ConfigLock::acquire(&config_path, for_update)?;
     // Read config file itself - if it exists
-    let config = if config_path.is_file() {
-        let contents = fs::read_to_string(&config_path)
-            .with_context(|| anyhow!("When reading config file '{}'", config_path.display()))?;
-        Config::from_str(&contents)
-            .with_context(|| anyhow!("When parsing config file '{}'", config_path.display()))?
+    let mut config = if config_path.is_file() {
+        read_with_includes(&config_path, &mut Vec::new())?
     } else if config_path.exists() {
         bail!(
             "Path '{}' exists yet points not to file",
@@ -157,9 +667,66 @@ pub fn read_from_file(path: Option<PathBuf>) -> anyhow::Result<(Config, PathBuf)
     } else {
         Config::new()
     };
+    config.lock = lock;
 
     Ok((config, config_path))
 }
+/// Path to config file used when it isn't specified explicitly
+///
+/// # Returns
+/// Path in user's config directory or, if there's no such, in home directory
+pub fn default_path() -> anyhow::Result<PathBuf> {
+    if let Some(path) = app_dirs::config_dir() {
+        Ok(path.join("config.ini"))
+    } else if let Some(path) = dirs::home_dir() {
+        Ok(path.join(".weather-cli.ini"))
+    } else {
+        bail!(
+            "Current OS doesn't seem to have notion of either user's config directory or user's home directory. Please use explicit '--config' argument"
+        )
+    }
+}
+/// Reads config file and merges files it includes beneath it, recursively
+///
+/// Relative include paths are resolved against directory of including file
+///
+/// # Parameters
+/// * `path` - path to config file
+/// * `chain` - canonical paths of files which include this one, used to detect cycles
+///
+/// # Returns
+/// Config merged with all its includes
+fn read_with_includes(path: &Path, chain: &mut Vec<PathBuf>) -> anyhow::Result<Config> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| anyhow!("When resolving config file '{}'", path.display()))?;
+    if chain.contains(&canonical) {
+        let cycle: Vec<_> = chain
+            .iter()
+            .chain([&canonical])
+            .map(|path| path.display().to_string())
+            .collect();
+        bail!("Config files include each other: {}", cycle.join(" -> "))
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| anyhow!("When reading config file '{}'", path.display()))?;
+    let config = Config::from_str(&contents)
+        .with_context(|| anyhow!("When parsing config file '{}'", path.display()))?;
+
+    let Some(include) = config.globals.get(INCLUDE_ENTRY) else {
+        return Ok(config);
+    };
+    let include_path = canonical
+        .parent()
+        .map_or_else(|| PathBuf::from(include), |dir| dir.join(include));
+    chain.push(canonical);
+    let base = read_with_includes(&include_path, chain)
+        .with_context(|| anyhow!("When including config from '{}'", path.display()))?;
+    chain.pop();
+
+    Ok(config.merge_over(base))
+}
 /// Writes app's configuration at specified path
 ///
 /// # Parameters
@@ -167,6 +734,7 @@ pub fn read_from_file(path: Option<PathBuf>) -> anyhow::Result<(Config, PathBuf)
 /// * `path` - path where to write configuration
 pub fn write_to_file(config: &Config, path: impl AsRef<Path>) -> anyhow::Result<()> {
     let config_path = path.as_ref();
+    config.check_included()?;
     // Write config back to file
     if !config_path.is_file() {
         let config_dir_path = config_path.parent().ok_or_else(|| {
@@ -182,6 +750,254 @@ pub fn write_to_file(config: &Config, path: impl AsRef<Path>) -> anyhow::Result<
         })?;
     }
 
-    fs::write(config_path, config.to_string())
-        .with_context(|| anyhow!("When writing configuration to {}", config_path.display()))
+    // Config is replaced at once, so concurrent readers and crashes never see it half-written;
+    // symlinked config is replaced at its target, keeping link itself
+    let target = fs::canonicalize(config_path).unwrap_or_else(|_| config_path.to_path_buf());
+    let mut temp_path = target.as_os_str().to_owned();
+    temp_path.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = PathBuf::from(temp_path);
+    let written = fs::write(&temp_path, config.to_string()).and_then(|()| {
+        // Config holds API keys, so its restricted permissions are kept
+        if let Ok(meta) = fs::metadata(&target) {
+            fs::set_permissions(&temp_path, meta.permissions())?;
+        }
+        fs::rename(&temp_path, &target)
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written.with_context(|| anyhow!("When writing configuration to {}", config_path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::{read_from_file, read_with_includes, write_to_file, Config};
+
+    /// Writes config files into temporary directory
+    fn files(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (name, contents) in files {
+            fs::write(dir.path().join(name), contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn include_cycle_is_reported() {
+        let dir = files(&[
+            ("a.ini", "include = b.ini\n"),
+            ("b.ini", "include = c.ini\n"),
+            ("c.ini", "include = a.ini\n"),
+        ]);
+        let error = read_with_includes(&dir.path().join("a.ini"), &mut Vec::new())
+            .err()
+            .unwrap();
+        let message = format!("{error:#}");
+        assert!(
+            message.contains("Config files include each other"),
+            "{message}"
+        );
+        assert!(message.contains("c.ini -> "), "{message}");
+    }
+
+    #[test]
+    fn own_entries_take_precedence() {
+        let dir = files(&[
+            (
+                "team.ini",
+                "provider = weatherapi\n[weatherapi]\napikey = team\nlang = de\n",
+            ),
+            (
+                "base.ini",
+                "include = team.ini\nprovider = brightsky\n[weatherapi]\napikey = own\n",
+            ),
+            (
+                "config.ini",
+                "include = base.ini\n[locations]\nhome = Berlin\n",
+            ),
+        ]);
+        let config = read_with_includes(&dir.path().join("config.ini"), &mut Vec::new()).unwrap();
+        // Nearer file wins, both over its includes and over their includes
+        assert_eq!(config.globals["provider"], "brightsky");
+        assert_eq!(config.globals["include"], "base.ini");
+        assert_eq!(config.sections["weatherapi"]["apikey"], "own");
+        assert_eq!(config.sections["weatherapi"]["lang"], "de");
+        assert_eq!(config.locations["home"], "Berlin");
+        // Included entries aren't written back
+        assert_eq!(
+            config.to_string(),
+            "include = base.ini\n[locations]\nhome = Berlin\n"
+        );
+    }
+
+    #[test]
+    fn included_entries_cannot_be_removed() {
+        let dir = files(&[
+            (
+                "team.ini",
+                "[weatherapi]\napikey = team\n[locations]\noffice = Berlin\n",
+            ),
+            (
+                "config.ini",
+                "include = team.ini\n[pirateweather]\napikey = own\n",
+            ),
+        ]);
+        let path = dir.path().join("config.ini");
+        let mut config = read_with_includes(&path, &mut Vec::new()).unwrap();
+        config.sections.remove("pirateweather");
+        config.sections.remove("weatherapi");
+        config.locations.remove("office");
+
+        let error = write_to_file(&config, &path).err().unwrap().to_string();
+        assert!(error.contains("[weatherapi]"), "{error}");
+        assert!(error.contains("'office' in [locations]"), "{error}");
+        assert!(error.contains("'team.ini'"), "{error}");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "include = team.ini\n[pirateweather]\napikey = own\n"
+        );
+
+        // Removing own entries is fine
+        let mut config = read_with_includes(&path, &mut Vec::new()).unwrap();
+        config.sections.remove("pirateweather");
+        write_to_file(&config, &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "include = team.ini\n");
+    }
+
+    /// Config file with comments, unsorted keys and section unknown to application
+    const SOURCE: &str = "\
+# Personal weather config
+provider = weatherapi
+
+# Key from https://www.weatherapi.com/my/
+[weatherapi]
+zeta = 1
+# Leave empty for English
+lang = de
+apikey = 0123
+
+[brightsky]
+
+[custom]
+# Kept as is
+answer = 42
+";
+
+    fn edited(source: &str, edit: impl FnOnce(&mut Config)) -> String {
+        let mut config: Config = source.parse().unwrap();
+        edit(&mut config);
+        config.to_string()
+    }
+
+    #[test]
+    fn unchanged_config_is_written_verbatim() {
+        assert_eq!(edited(SOURCE, |_| {}), SOURCE);
+    }
+
+    #[test]
+    fn changed_entries_stay_in_place() {
+        let output = edited(SOURCE, |config| {
+            let section = config.section_mut(Some("weatherapi"));
+            section.insert("lang".into(), "fr".into());
+            section.insert("units".into(), "metric".into());
+        });
+        // Comments and order of keys survive, new key goes after last one of its section
+        assert_eq!(
+            output,
+            SOURCE.replace(
+                "lang = de\napikey = 0123\n",
+                "lang = fr\napikey = 0123\nunits = metric\n"
+            )
+        );
+    }
+
+    #[test]
+    fn removed_entries_and_sections_are_dropped() {
+        let output = edited(SOURCE, |config| {
+            config.section_mut(Some("weatherapi")).remove("lang");
+            config.sections.remove("brightsky");
+        });
+        assert_eq!(
+            output,
+            SOURCE
+                .replace("# Leave empty for English\nlang = de\n", "")
+                .replace("[brightsky]\n\n", "")
+        );
+
+        let output = edited(SOURCE, |config| {
+            config.sections.remove("weatherapi");
+        });
+        assert_eq!(
+            output,
+            "# Personal weather config\nprovider = weatherapi\n\n\
+             [brightsky]\n\n[custom]\n# Kept as is\nanswer = 42\n"
+        );
+    }
+
+    #[test]
+    fn new_sections_are_appended() {
+        let output = edited(SOURCE, |config| {
+            config
+                .section_mut(Some("locations"))
+                .insert("home".into(), "Berlin".into());
+            config.globals.insert("units".into(), "metric".into());
+        });
+        assert_eq!(
+            output,
+            SOURCE.replace(
+                "provider = weatherapi\n",
+                "provider = weatherapi\nunits = metric\n"
+            ) + "\n[locations]\nhome = Berlin\n"
+        );
+    }
+
+    #[test]
+    fn crlf_line_endings_are_kept() {
+        let source = SOURCE.replace('\n', "\r\n");
+        assert_eq!(edited(&source, |_| {}), source);
+        let output = edited(&source, |config| {
+            config
+                .section_mut(Some("weatherapi"))
+                .insert("lang".into(), "fr".into());
+            config
+                .section_mut(Some("locations"))
+                .insert("home".into(), "Berlin".into());
+        });
+        assert_eq!(
+            output,
+            source.replace("lang = de", "lang = fr") + "\r\n[locations]\r\nhome = Berlin\r\n"
+        );
+    }
+
+    #[test]
+    fn concurrent_writers_keep_both_changes() {
+        let dir = files(&[("config.ini", "# Shared config\n")]);
+        let path = dir.path().join("config.ini");
+        let barrier = std::sync::Barrier::new(2);
+        std::thread::scope(|scope| {
+            for writer in ["first", "second"] {
+                let (path, barrier) = (&path, &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    // Lock is held from reading config till it's dropped after writing
+                    let (mut config, path) = read_from_file(Some(path.clone()), true).unwrap();
+                    config.locations.insert(writer.into(), writer.into());
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    write_to_file(&config, path).unwrap();
+                });
+            }
+        });
+
+        let (config, _) = read_from_file(Some(path.clone()), false).unwrap();
+        assert_eq!(config.locations.len(), 2, "{config}");
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("# Shared config\n"));
+        // Nothing but config and its lock is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
 }
@@ -0,0 +1,192 @@
+//! Exporting configuration into JSON or TOML document and importing it back, to move setups between machines
+//!
+//! Document mirrors config file: global entries are top-level keys, while each section,
+//! like `weatherapi` or `profile.work`, is object or table of its entries
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use serde_json::{Map, Value};
+
+use crate::config::{self, Config};
+use crate::provider::ParamKind;
+use crate::provider_registry::ProviderRegistry;
+
+/// Parts of entry names which suggest secret, for sections which aren't built-in providers' ones
+const SECRET_HINTS: [&str; 4] = ["key", "password", "secret", "token"];
+
+/// Format of exported configuration
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// Guesses format of file by its extension; JSON is assumed unless it's `.toml`
+    ///
+    /// # Parameters
+    /// * `path` - path to file
+    pub fn guess(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+}
+/// Renders whole configuration, along with entries of included files, as self-contained document
+///
+/// # Parameters
+/// * `config` - configuration
+/// * `registry` - providers, whose secret parameters are known
+/// * `format` - document format
+/// * `redact` - leave out secrets, like API keys and passwords
+///
+/// # Returns
+/// Document text
+pub fn export(
+    config: &Config,
+    registry: &ProviderRegistry,
+    format: Format,
+    redact: bool,
+) -> anyhow::Result<String> {
+    let mut document = Map::new();
+    for (name, section) in config.named_sections() {
+        let entries: Map<_, _> = section
+            .iter()
+            // Includes are already merged in
+            .filter(|(key, _)| name.is_some() || key.as_str() != config::INCLUDE_ENTRY)
+            .filter(|(key, _)| !(redact && is_secret(registry, name.as_deref(), key)))
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect();
+        match name {
+            Some(name) => {
+                document.insert(name, Value::Object(entries));
+            }
+            None => document.extend(entries),
+        }
+    }
+    let document = Value::Object(document);
+    Ok(match format {
+        Format::Json => format!("{}\n", serde_json::to_string_pretty(&document)?),
+        Format::Toml => toml::to_string(&document).with_context(|| anyhow!("When writing TOML"))?,
+    })
+}
+/// Merges entries of exported document into configuration; entries which document doesn't have,
+/// like secrets left out of it, are kept
+///
+/// # Parameters
+/// * `config` - configuration to update
+/// * `text` - document text
+/// * `format` - document format
+///
+/// # Returns
+/// Number of imported entries
+pub fn import(config: &mut Config, text: &str, format: Format) -> anyhow::Result<usize> {
+    let document: Value = match format {
+        Format::Json => serde_json::from_str(text)?,
+        Format::Toml => toml::from_str(text)?,
+    };
+    let Value::Object(document) = document else {
+        bail!("Configuration should be object of entries and sections");
+    };
+    let mut count = 0;
+    for (key, value) in document {
+        match value {
+            Value::Object(entries) => count += import_section(config, &key, entries)?,
+            value => {
+                config
+                    .section_mut(None)
+                    .insert(key.clone(), scalar(&key, value)?);
+                count += 1;
+            }
+        }
+    }
+    config.mark_dirty();
+    Ok(count)
+}
+/// Merges entries of section into configuration; nested tables, like `[profile.work]` parsed
+/// from TOML, become sections with dotted names
+///
+/// # Returns
+/// Number of imported entries
+fn import_section(
+    config: &mut Config,
+    name: &str,
+    entries: Map<String, Value>,
+) -> anyhow::Result<usize> {
+    // Section without entries still configures provider which has no parameters
+    config.section_mut(Some(name));
+    let mut count = 0;
+    for (key, value) in entries {
+        match value {
+            Value::Object(nested) => {
+                count += import_section(config, &format!("{name}.{key}"), nested)?
+            }
+            value => {
+                let value = scalar(&key, value).with_context(|| anyhow!("In section '{name}'"))?;
+                config.section_mut(Some(name)).insert(key, value);
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+/// Converts entry's value into config text
+///
+/// # Parameters
+/// * `key` - entry's name
+/// * `value` - entry's value; numbers and booleans are accepted along with strings
+fn scalar(key: &str, value: Value) -> anyhow::Result<String> {
+    match value {
+        Value::String(value) => Ok(value),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(value.to_string()),
+        _ => bail!("Entry '{key}' should be string, number or boolean"),
+    }
+}
+/// Whether entry holds secret; provider's parameters say so themselves,
+/// other entries are judged by their names
+///
+/// # Parameters
+/// * `registry` - providers
+/// * `section` - entry's section, `None` for globals
+/// * `key` - entry's name
+fn is_secret(registry: &ProviderRegistry, section: Option<&str>, key: &str) -> bool {
+    if let Some(factory) = section.and_then(|section| registry.get(section)) {
+        if let Some(param) = factory.info().params.iter().find(|param| param.id == key) {
+            return matches!(param.kind, ParamKind::Secret);
+        }
+    }
+    let key = key.to_ascii_lowercase();
+    SECRET_HINTS.iter().any(|hint| key.contains(hint))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{export, import, Format};
+    use crate::config::Config;
+    use crate::provider_registry::ProviderRegistry;
+
+    const CONFIG: &str = "units = metric\n\n[meteomatics]\nusername = me\npassword = pass\n\n[profile.work]\nlocation = Berlin\n";
+
+    #[test]
+    fn toml_export_is_imported_back() {
+        let config = Config::from_str(CONFIG).unwrap();
+        let registry = ProviderRegistry::new();
+        let text = export(&config, &registry, Format::Toml, false).unwrap();
+
+        let mut imported = Config::new();
+        assert_eq!(import(&mut imported, &text, Format::Toml).unwrap(), 4);
+        assert_eq!(imported.named_sections(), config.named_sections());
+    }
+
+    #[test]
+    fn redacted_export_leaves_out_secrets() {
+        let config = Config::from_str(CONFIG).unwrap();
+        let text = export(&config, &ProviderRegistry::new(), Format::Json, true).unwrap();
+        assert!(text.contains("\"username\": \"me\""), "{text}");
+        assert!(!text.contains("pass"), "{text}");
+    }
+}
@@ -1,9 +1,20 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+use std::sync::OnceLock;
 
-use chrono::Datelike;
+use anyhow::bail;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Weekday};
+use chrono_tz::Tz;
 
-/// Simple representation of calendar date, parsed and represented as YYYY-MM-DD
+/// Format of dates shown to user, like "%d.%m.%Y"; ISO 8601 one unless set
+static FORMAT: OnceLock<String> = OnceLock::new();
+/// ISO 8601 date format, used by machine-readable output and when locale has no convention
+const ISO_FORMAT: &str = "%Y-%m-%d";
+
+/// Simple representation of calendar date, optionally with hour of day,
+/// parsed and represented as "YYYY-MM-DD" or "YYYY-MM-DD HH:00"
+#[derive(Clone, Copy)]
 pub struct Date {
     /// Year, usually 1970+
     pub year: u16,
@@ -11,16 +22,166 @@ pub struct Date {
     pub month: u8,
     /// Month's day, 1-31
     pub day: u8,
+    /// Hour of day in location's local time, 0-23, if conditions at specific hour are requested
+    pub hour: Option<u8>,
 }
 
 impl Date {
-    /// Get today's date
-    pub fn today() -> Self {
-        let date = chrono::Local::now().date_naive();
+    /// Get today's date at location, by local clock corrected for detected skew;
+    /// date is taken in location's timezone if location is specified with coordinates
+    ///
+    /// # Parameters
+    /// * `location` - address or coordinates
+    pub fn today_at(location: &str) -> Self {
+        crate::timezone::today(crate::timezone::of_location(location)).into()
+    }
+    /// Same date with specified hour of day
+    ///
+    /// # Parameters
+    /// * `hour` - hour of day, 0-23, or `None` for whole day
+    pub fn with_hour(self, hour: Option<u8>) -> Self {
+        Self { hour, ..self }
+    }
+    /// Same date without hour of day
+    pub fn without_hour(self) -> Self {
+        self.with_hour(None)
+    }
+    /// Parse date like `from_str` does, also accepting weekday, like "friday", "fri"
+    /// or "next-monday", optionally followed by time of day, like "friday 15:00"
+    ///
+    /// # Parameters
+    /// * `s` - date
+    /// * `tz` - location's timezone, in which weekday is resolved; `None` means machine's local one
+    pub fn parse_in(s: &str, tz: Option<Tz>) -> Result<Self, DateParseError> {
+        Self::parse_from(s, crate::timezone::today(tz))
+    }
+    /// Parse date like `from_str` does, also accepting weekday relative to specified day
+    ///
+    /// # Parameters
+    /// * `s` - date; weekday means nearest such day starting from `today`,
+    ///   "next-" weekday means such day of following week, which starts on Monday
+    /// * `today` - day from which weekdays are counted
+    pub fn parse_from(s: &str, today: NaiveDate) -> Result<Self, DateParseError> {
+        let (day, time) = match s.trim().split_once(' ') {
+            Some((day, time)) => (day, Some(time.trim())),
+            None => (s.trim(), None),
+        };
+        let (day, next_week) = match day.strip_prefix("next-") {
+            Some(day) => (day, true),
+            None => (day, false),
+        };
+        let Ok(weekday) = Weekday::from_str(day) else {
+            return Self::from_str(s);
+        };
+        let date = if next_week {
+            let monday = today.week(Weekday::Mon).first_day() + chrono::Days::new(7);
+            monday + chrono::Days::new(weekday.num_days_from_monday().into())
+        } else {
+            let offset =
+                (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+            today + chrono::Days::new(offset.into())
+        };
+        Ok(Self::from(date).with_hour(time.map(parse_hour).transpose()?))
+    }
+    /// Convert to `chrono` date
+    ///
+    /// # Returns
+    /// Date or `None` if components don't form valid calendar date
+    pub fn to_naive(self) -> Option<chrono::NaiveDate> {
+        chrono::NaiveDate::from_ymd_opt(self.year.into(), self.month.into(), self.day.into())
+    }
+    /// Date as it's shown to user, in format selected by `set_format`, with hour of day if any
+    pub fn formatted(self) -> String {
+        let Some(day) = self.to_naive() else {
+            return self.to_string();
+        };
+        match self.hour {
+            Some(hour) => format!("{} {hour:02}:00", format_day(day)),
+            None => format_day(day),
+        }
+    }
+}
+/// Selects format of dates shown to user; should be called once, before any output
+///
+/// Machine-readable output, like JSON, CSV or iCalendar, always uses ISO 8601 dates
+///
+/// # Parameters
+/// * `format` - format selected on command line, checked with `parse_format`;
+///   default one is conventional for locale of `LC_ALL`, `LC_TIME` or `LANG` environment variables
+pub fn set_format(format: Option<String>) {
+    let format = format.unwrap_or_else(|| {
+        let locale = ["LC_ALL", "LC_TIME", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .unwrap_or_default();
+        locale_format(&locale).to_string()
+    });
+    let _ = FORMAT.set(format);
+}
+/// Parses `strftime`-like date format, like "%d %b %Y"
+///
+/// # Returns
+/// Format, or error if it has unknown specifiers
+pub fn parse_format(format: &str) -> anyhow::Result<String> {
+    if format.is_empty() || StrftimeItems::new(format).any(|item| item == Item::Error) {
+        bail!("Invalid date format '{format}'");
+    }
+    Ok(format.to_string())
+}
+/// Date format conventional for locale
+///
+/// # Parameters
+/// * `locale` - locale, like "en_US.UTF-8" or "uk_UA"
+fn locale_format(locale: &str) -> &'static str {
+    let mut parts = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .split(['_', '-']);
+    let language = parts.next().unwrap_or_default();
+    let region = parts.next().unwrap_or_default();
+    match (language, region) {
+        ("en", "US" | "PH") => "%m/%d/%Y",
+        ("en", "CA") | ("fr", "CA") => ISO_FORMAT,
+        ("en", "GB" | "AU" | "NZ" | "IE" | "IN" | "ZA")
+        | ("fr" | "es" | "it" | "pt" | "el" | "ca" | "vi" | "id", _) => "%d/%m/%Y",
+        (
+            "de" | "ru" | "uk" | "be" | "pl" | "cs" | "sk" | "fi" | "nb" | "nn" | "da" | "tr"
+            | "ro" | "bg" | "kk",
+            _,
+        ) => "%d.%m.%Y",
+        ("nl", _) => "%d-%m-%Y",
+        ("ja" | "zh", _) => "%Y/%m/%d",
+        _ => ISO_FORMAT,
+    }
+}
+/// Selected format of dates shown to user
+fn format() -> &'static str {
+    FORMAT.get().map_or(ISO_FORMAT, String::as_str)
+}
+/// Formats day as it's shown to user
+///
+/// # Parameters
+/// * `day` - calendar day
+pub fn format_day(day: NaiveDate) -> String {
+    day.format(format()).to_string()
+}
+/// Formats time as it's shown to user, date in selected format followed by hours and minutes
+///
+/// # Parameters
+/// * `time` - local time in some timezone, which caller tells if needed
+pub fn format_time(time: NaiveDateTime) -> String {
+    time.format(&format!("{} %H:%M", format())).to_string()
+}
+
+impl From<chrono::NaiveDate> for Date {
+    fn from(date: chrono::NaiveDate) -> Self {
         Self {
             year: date.year() as u16,
             month: date.month() as u8,
             day: date.day() as u8,
+            hour: None,
         }
     }
 }
@@ -30,7 +191,11 @@ impl Display for Date {
         f.write_fmt(format_args!(
             "{:04}-{:02}-{:02}",
             self.year, self.month, self.day
-        ))
+        ))?;
+        if let Some(hour) = self.hour {
+            f.write_fmt(format_args!(" {hour:02}:00"))?;
+        }
+        Ok(())
     }
 }
 
@@ -45,12 +210,19 @@ pub enum DateParseError {
     MonthParseError,
     #[error("Error parsing date's day component")]
     DayParseError,
+    #[error("Error parsing time of day, expected HH:MM")]
+    TimeParseError,
 }
 
 impl FromStr for Date {
     type Err = DateParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Time of day is separated either by space or ISO 8601 'T'
+        let (s, time) = match s.trim().split_once([' ', 'T']) {
+            Some((date, time)) => (date, Some(time.trim())),
+            None => (s.trim(), None),
+        };
         let mut parts = s.split('-');
         let year = parts.next().ok_or(Self::Err::InvalidComponents)?;
         let month = parts.next().ok_or(Self::Err::InvalidComponents)?;
@@ -64,6 +236,48 @@ impl FromStr for Date {
             year: year.parse().map_err(|_| Self::Err::YearParseError)?,
             month: month.parse().map_err(|_| Self::Err::MonthParseError)?,
             day: day.parse().map_err(|_| Self::Err::DayParseError)?,
+            hour: time.map(parse_hour).transpose()?,
         })
     }
 }
+/// Parses time of day, "HH:MM"; since providers offer hourly data, minutes are dropped
+fn parse_hour(time: &str) -> Result<u8, DateParseError> {
+    let (hour, minute) = time.split_once(':').ok_or(DateParseError::TimeParseError)?;
+    let hour: u8 = hour.parse().map_err(|_| DateParseError::TimeParseError)?;
+    let minute: u8 = minute.parse().map_err(|_| DateParseError::TimeParseError)?;
+    if hour > 23 || minute > 59 {
+        return Err(DateParseError::TimeParseError);
+    }
+    Ok(hour)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 7, 3).unwrap()
+    }
+
+    #[test]
+    fn weekday_is_resolved_from_today() {
+        let resolve = |s| Date::parse_from(s, today()).unwrap().to_string();
+        assert_eq!(resolve("friday"), "2024-07-05");
+        assert_eq!(resolve("Wed"), "2024-07-03");
+        assert_eq!(resolve("monday"), "2024-07-08");
+        assert_eq!(resolve("next-wednesday"), "2024-07-10");
+        assert_eq!(resolve("next-thu"), "2024-07-11");
+        assert_eq!(resolve("next-monday"), "2024-07-08");
+        assert_eq!(resolve("next-sun"), "2024-07-14");
+        assert_eq!(resolve("sat 15:30"), "2024-07-06 15:00");
+    }
+
+    #[test]
+    fn plain_date_is_parsed_as_is() {
+        let date = Date::parse_from("2024-12-31 08:00", today()).unwrap();
+        assert_eq!(date.to_string(), "2024-12-31 08:00");
+        assert!(Date::parse_from("someday", today()).is_err());
+        assert!(Date::parse_from("friday 25:00", today()).is_err());
+    }
+}
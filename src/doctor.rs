@@ -0,0 +1,238 @@
+//! Diagnostics of application's environment: whether configured providers are reachable
+//! and accept their credentials, and whether application's directories are usable
+//!
+//! Each finding comes with a hint how to fix it, so most problems are solved
+//! without looking into logs
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::app_dirs;
+use crate::condition_mapping::MappedProvider;
+use crate::redact;
+
+/// Result of single check
+pub struct Check {
+    /// What was checked
+    pub subject: String,
+    /// Short description of success, or description of problem
+    pub outcome: Result<String, String>,
+    /// How to fix problem, if known
+    pub fix: Option<String>,
+    /// How long check took, for checks which perform requests
+    pub latency: Option<Duration>,
+}
+
+impl Check {
+    /// Successful check
+    pub fn passed(subject: impl Into<String>, summary: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            outcome: Ok(summary.into()),
+            fix: None,
+            latency: None,
+        }
+    }
+    /// Failed check
+    pub fn failed(
+        subject: impl Into<String>,
+        problem: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            subject: subject.into(),
+            outcome: Err(problem.into()),
+            fix: Some(fix.into()),
+            latency: None,
+        }
+    }
+}
+/// Checks provider with minimal weather request, measuring its latency
+///
+/// # Parameters
+/// * `name` - provider's name
+/// * `provider` - provider instance
+/// * `location` - location of weather request
+pub async fn check_provider(name: &str, provider: &MappedProvider, location: &str) -> Check {
+    let subject = format!("Provider '{name}'");
+    let started = Instant::now();
+    let result = provider
+        .get_weather(location.to_string().into(), None)
+        .await;
+    let check = match result {
+        Ok(_) => Check::passed(subject, "responded"),
+        Err(error) => {
+            let problem = redact::redact(&format!("{error:#}"));
+            let fix = provider_fix(name, &problem);
+            Check::failed(subject, problem, fix)
+        }
+    };
+    Check {
+        latency: Some(started.elapsed()),
+        ..check
+    }
+}
+/// Guesses fix of provider's failure from its description
+fn provider_fix(name: &str, problem: &str) -> String {
+    let problem = problem.to_lowercase();
+    let mentions = |words: &[&str]| words.iter().any(|word| problem.contains(word));
+    if mentions(&[
+        "key",
+        "unauthori",
+        "forbidden",
+        "credential",
+        "password",
+        "401",
+        "403",
+    ]) {
+        format!("check API key or credentials with 'weather configure {name}'")
+    } else if mentions(&[
+        "request failed",
+        "timed out",
+        "no data for",
+        "dns",
+        "connect",
+    ]) {
+        "check network connection, proxy settings and provider's 'base_url' parameter, if set"
+            .to_string()
+    } else if mentions(&["quota", "limit", "429"]) {
+        "wait until provider's quota resets, or upgrade provider's plan".to_string()
+    } else {
+        "run command with '-v' for details".to_string()
+    }
+}
+/// Checks that config file's directory and application's directories are writable
+///
+/// Missing directories are fine as long as they can be created
+///
+/// # Parameters
+/// * `config_path` - path of config file
+pub fn check_dirs(config_path: &Path) -> Vec<Check> {
+    let config_dir = config_path.parent().map(Path::to_path_buf);
+    [
+        ("Config directory", config_dir),
+        ("Cache directory", app_dirs::cache_dir()),
+        ("Data directory", app_dirs::data_dir()),
+        ("State directory", app_dirs::state_dir()),
+    ]
+    .into_iter()
+    .map(|(name, dir)| match dir {
+        Some(dir) => check_dir(name, &dir),
+        None => Check::failed(
+            name,
+            "OS doesn't provide such directory",
+            "specify directory explicitly with '--cache-dir' or '--data-dir'",
+        ),
+    })
+    .collect()
+}
+/// Checks that directory is writable or, if it doesn't exist, can be created
+fn check_dir(name: &str, dir: &Path) -> Check {
+    let subject = format!("{name} {}", dir.display());
+    if dir.exists() {
+        if !dir.is_dir() {
+            return Check::failed(
+                subject,
+                "exists but isn't a directory",
+                "remove or rename that file",
+            );
+        }
+        // Permissions alone don't tell the truth on every OS, so actual write is attempted
+        let probe = dir.join(".doctor-probe");
+        return match std::fs::write(&probe, b"") {
+            Ok(()) => {
+                let _ = std::fs::remove_file(&probe);
+                Check::passed(subject, "writable")
+            }
+            Err(error) => Check::failed(
+                subject,
+                format!("not writable: {error}"),
+                format!("grant your user write access to {}", dir.display()),
+            ),
+        };
+    }
+    // Directory is created on first use, inside its nearest existing ancestor
+    let ancestor = dir.ancestors().skip(1).find(|path| path.is_dir());
+    match ancestor.map(|path| (path, std::fs::metadata(path))) {
+        Some((_, Ok(meta))) if !meta.permissions().readonly() => {
+            Check::passed(subject, "not created yet")
+        }
+        Some((path, _)) => Check::failed(
+            subject,
+            format!("can't be created, {} is read-only", path.display()),
+            format!(
+                "create {} and grant your user write access to it",
+                dir.display()
+            ),
+        ),
+        None => Check::failed(
+            subject,
+            "no parent directory exists",
+            format!("create {}", dir.display()),
+        ),
+    }
+}
+/// Renders checks' results, one per line, with fixes of failed ones
+///
+/// # Parameters
+/// * `checks` - results of checks
+pub fn render(checks: &[Check]) -> String {
+    let mut text = String::new();
+    for check in checks {
+        let latency = check.latency.map(|latency| latency.as_millis());
+        match (&check.outcome, latency) {
+            (Ok(summary), Some(latency)) => {
+                let _ = writeln!(text, "[ OK ] {}: {summary} in {latency} ms", check.subject);
+            }
+            (Ok(summary), None) => {
+                let _ = writeln!(text, "[ OK ] {}: {summary}", check.subject);
+            }
+            (Err(problem), _) => {
+                let after =
+                    latency.map_or(String::new(), |latency| format!(" (after {latency} ms)"));
+                let _ = writeln!(text, "[FAIL] {}: {problem}{after}", check.subject);
+                if let Some(fix) = &check.fix {
+                    let _ = writeln!(text, "       fix: {fix}");
+                }
+            }
+        }
+    }
+    let failed = checks.iter().filter(|check| check.outcome.is_err()).count();
+    if failed == 0 {
+        text.push_str("\nNo problems found\n");
+    } else {
+        let _ = writeln!(text, "\n{failed} problem(s) found");
+    }
+    text
+}
+/// Renders checks of providers as table with provider's name, result, latency
+/// and problem along with its fix
+///
+/// # Parameters
+/// * `names` - providers' names
+/// * `checks` - results of providers' checks, in same order as names
+pub fn render_providers(names: &[String], checks: &[Check]) -> String {
+    let width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .chain(["PROVIDER".len()])
+        .max()
+        .unwrap_or_default();
+    let mut text = format!("{:<width$}  RESULT  LATENCY  DETAILS\n", "PROVIDER");
+    for (name, check) in names.iter().zip(checks) {
+        let latency = check.latency.map_or("-".to_string(), |latency| {
+            format!("{} ms", latency.as_millis())
+        });
+        let (result, details) = match &check.outcome {
+            Ok(_) => ("pass", String::new()),
+            Err(problem) => match &check.fix {
+                Some(fix) => ("FAIL", format!("{problem}; fix: {fix}")),
+                None => ("FAIL", problem.clone()),
+            },
+        };
+        let _ = writeln!(text, "{name:<width$}  {result:<6}  {latency:>7}  {details}");
+    }
+    let failed = checks.iter().filter(|check| check.outcome.is_err()).count();
+    let _ = writeln!(text, "\n{} passed, {failed} failed", checks.len() - failed);
+    text
+}
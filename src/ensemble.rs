@@ -0,0 +1,201 @@
+//! Poor man's ensemble forecast: forecasts of several providers combined into consensus,
+//! along with spread which shows how much providers disagree
+use std::fmt::Write as _;
+
+use serde_json::{json, Value};
+
+use crate::i18n;
+use crate::provider::{WeatherInfo, WeatherKind};
+use crate::units::Units;
+
+/// Consensus of providers' forecasts
+pub struct Ensemble {
+    /// Forecasts of providers which responded, along with providers' names
+    pub members: Vec<(String, WeatherInfo)>,
+    /// Weather kind reported by most providers, along with number of them
+    pub weather: (WeatherKind, usize),
+    /// Median temperature, in Celsius degrees
+    pub temperature: f32,
+    /// Lowest and highest temperatures among providers
+    pub temperature_spread: (f32, f32),
+    /// Median wind speed, in m/s
+    pub wind_speed: f32,
+    /// Lowest and highest wind speeds among providers
+    pub wind_spread: (f32, f32),
+    /// Median humidity, in percents
+    pub humidity: f32,
+    /// Median precipitation, in millimeters, of providers which report it
+    pub precipitation: Option<f32>,
+}
+/// Combines providers' forecasts into consensus
+///
+/// # Parameters
+/// * `members` - forecasts along with names of providers which made them
+///
+/// # Returns
+/// Consensus, or `None` if there are no forecasts
+pub fn combine(members: Vec<(String, WeatherInfo)>) -> Option<Ensemble> {
+    let values = |field: fn(&WeatherInfo) -> f32| -> Vec<f32> {
+        members.iter().map(|(_, info)| field(info)).collect()
+    };
+    let temperatures = values(|info| info.temperature);
+    let winds = values(|info| info.wind_speed);
+    let precipitation: Vec<_> = members
+        .iter()
+        .filter_map(|(_, info)| info.precipitation)
+        .collect();
+    // Ties go to kind reported by provider listed first
+    let mut votes: Vec<(WeatherKind, usize)> = Vec::new();
+    for (_, info) in &members {
+        match votes.iter_mut().find(|(kind, _)| *kind == info.weather) {
+            Some((_, count)) => *count += 1,
+            None => votes.push((info.weather, 1)),
+        }
+    }
+    let weather = votes
+        .into_iter()
+        .reduce(|best, vote| if vote.1 > best.1 { vote } else { best })?;
+    Some(Ensemble {
+        weather,
+        temperature: median(&temperatures)?,
+        temperature_spread: spread(&temperatures)?,
+        wind_speed: median(&winds)?,
+        wind_spread: spread(&winds)?,
+        humidity: median(&values(|info| info.humidity))?,
+        precipitation: median(&precipitation),
+        members,
+    })
+}
+/// Median of values; mean of two middle ones if their number is even
+fn median(values: &[f32]) -> Option<f32> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((sorted[middle - 1] + sorted[middle]) / 2.0),
+        _ => Some(sorted[middle]),
+    }
+}
+/// Lowest and highest of values
+fn spread(values: &[f32]) -> Option<(f32, f32)> {
+    let min = values.iter().copied().reduce(f32::min)?;
+    let max = values.iter().copied().reduce(f32::max)?;
+    Some((min, max))
+}
+
+impl Ensemble {
+    /// Consensus as JSON object, in metric units like forecasts themselves,
+    /// with each provider's forecast
+    pub fn json(&self) -> Value {
+        let members: serde_json::Map<_, _> = self
+            .members
+            .iter()
+            .map(|(name, info)| (name.clone(), json!(info)))
+            .collect();
+        let mut value = json!({
+            "weather": self.weather.0,
+            "weather_agreement": self.weather.1,
+            "temperature": self.temperature,
+            "temperature_spread": [self.temperature_spread.0, self.temperature_spread.1],
+            "wind_speed": self.wind_speed,
+            "wind_speed_spread": [self.wind_spread.0, self.wind_spread.1],
+            "humidity": self.humidity,
+            "providers": members,
+        });
+        if let Some(precipitation) = self.precipitation {
+            value["precipitation"] = json!(precipitation);
+        }
+        value
+    }
+    /// Renders consensus along with its spread, followed by each provider's forecast
+    ///
+    /// # Parameters
+    /// * `units` - units in which values are shown
+    pub fn render(&self, units: Units) -> String {
+        let (temp_unit, speed_unit) = (units.temperature_unit(), units.speed_unit());
+        let (kind, agreement) = self.weather;
+        let kind = kind.to_string();
+        let mut text = format!("Consensus of {} providers:\n", self.members.len());
+        let _ = writeln!(
+            text,
+            "  {}: {} ({agreement} of {})",
+            i18n::tr("label.weather", "Weather"),
+            i18n::tr(&format!("weather.{kind}"), &kind),
+            self.members.len()
+        );
+        let _ = writeln!(
+            text,
+            "  {}: {:.1}{temp_unit}, from {:.1} to {:.1}{temp_unit}",
+            i18n::tr("label.temperature", "Temperature"),
+            units.temperature(self.temperature),
+            units.temperature(self.temperature_spread.0),
+            units.temperature(self.temperature_spread.1),
+        );
+        let _ = writeln!(
+            text,
+            "  {}: {:.1} {speed_unit}, from {:.1} to {:.1} {speed_unit}",
+            i18n::tr("label.wind_speed", "Wind speed"),
+            units.speed(self.wind_speed),
+            units.speed(self.wind_spread.0),
+            units.speed(self.wind_spread.1),
+        );
+        let _ = writeln!(
+            text,
+            "  {}: {:.0}%",
+            i18n::tr("label.humidity", "Humidity"),
+            self.humidity
+        );
+        if let Some(precipitation) = self.precipitation {
+            let _ = writeln!(
+                text,
+                "  {}: {:.1} {}",
+                i18n::tr("label.precipitation", "Precipitation"),
+                units.precipitation(precipitation),
+                units.precipitation_unit()
+            );
+        }
+        text.push_str("Providers:\n");
+        let width = self
+            .members
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (name, info) in &self.members {
+            let _ = writeln!(
+                text,
+                "  {name:<width$}  {:>6.1}{temp_unit}  {}",
+                units.temperature(info.temperature),
+                info.weather
+            );
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::combine;
+    use crate::provider::{WeatherInfo, WeatherKind};
+
+    fn member(name: &str, weather: WeatherKind, temperature: f32) -> (String, WeatherInfo) {
+        (name.to_string(), WeatherInfo::sample(weather, temperature))
+    }
+
+    #[test]
+    fn consensus_is_median_and_majority() {
+        let ensemble = combine(vec![
+            member("a", WeatherKind::Rain, 10.0),
+            member("b", WeatherKind::Clouds, 14.0),
+            member("c", WeatherKind::Clouds, 11.0),
+            member("d", WeatherKind::Clear, 20.0),
+        ])
+        .unwrap();
+        assert_eq!(ensemble.weather, (WeatherKind::Clouds, 2));
+        assert_eq!(ensemble.temperature, 12.5);
+        assert_eq!(ensemble.temperature_spread, (10.0, 20.0));
+        assert_eq!(ensemble.precipitation, None);
+        assert!(combine(Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,170 @@
+//! Bulk export of historical weather into dataset files
+//!
+//! Export appends one row per day and flushes it immediately, so interrupted export
+//! can be resumed by running same command again: days already present in file are skipped
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, ensure, Context};
+use chrono::NaiveDate;
+
+use crate::date::Date;
+use crate::provider::WeatherInfo;
+
+/// CSV header, matching fields written by `ExportFormat::row`
+const CSV_HEADER: &str =
+    "date,weather,temperature,wind_speed,humidity,precipitation,condition_code";
+
+/// Dataset file format
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Comma-separated values with header; values are in metric units
+    #[default]
+    Csv,
+    /// Newline-delimited JSON, one object per day
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// Formats single day as file's line, without line terminator
+    fn row(&self, date: &Date, info: &WeatherInfo) -> anyhow::Result<String> {
+        Ok(match self {
+            ExportFormat::Csv => {
+                let optional = |value: Option<String>| value.unwrap_or_default();
+                format!(
+                    "{date},{},{},{},{},{},{}",
+                    info.weather,
+                    info.temperature,
+                    info.wind_speed,
+                    info.humidity,
+                    optional(info.precipitation.map(|p| p.to_string())),
+                    optional(info.condition_code.as_deref().map(csv_field)),
+                )
+            }
+            ExportFormat::Ndjson => {
+                let mut value = serde_json::to_value(info)?;
+                value["date"] = serde_json::json!(date.to_string());
+                value.to_string()
+            }
+        })
+    }
+    /// Finds last exported day in existing file's contents
+    fn last_date(&self, contents: &str) -> Option<NaiveDate> {
+        let line = contents
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())?;
+        let date = match self {
+            ExportFormat::Csv => line.split(',').next()?.to_string(),
+            ExportFormat::Ndjson => serde_json::from_str::<serde_json::Value>(line)
+                .ok()?
+                .get("date")?
+                .as_str()?
+                .to_string(),
+        };
+        NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()
+    }
+}
+/// Quotes CSV field if it contains separators or quotes
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Arguments of `export` command
+#[derive(clap::Args)]
+pub struct ExportArgs {
+    /// Address of location for which weather is exported
+    pub address: String,
+    /// First exported day, "YYYY-MM-DD"
+    #[arg(long)]
+    pub from: Date,
+    /// Last exported day, inclusive, "YYYY-MM-DD"
+    #[arg(long)]
+    pub to: Date,
+    /// Dataset format
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: ExportFormat,
+    /// Dataset file; if it already exists, export resumes after last day written there
+    #[arg(short, long)]
+    pub file: PathBuf,
+    /// Delay between requests, in milliseconds, to stay within provider's rate limits
+    #[arg(long, default_value_t = 1000)]
+    pub delay_ms: u64,
+    /// Use specified provider instead of default one
+    #[arg(short, long)]
+    pub provider: Option<String>,
+}
+
+/// Export in progress, appending days to dataset file
+pub struct Exporter {
+    file: fs::File,
+    format: ExportFormat,
+    /// Day after last exported one
+    next: NaiveDate,
+    /// Last day to export
+    last: NaiveDate,
+}
+
+impl Exporter {
+    /// Opens dataset file, detecting where to resume if it already has exported days
+    ///
+    /// # Parameters
+    /// * `args` - export command's arguments
+    pub fn open(args: &ExportArgs) -> anyhow::Result<Self> {
+        let first = args
+            .from
+            .to_naive()
+            .ok_or_else(|| anyhow!("Date {} doesn't exist", args.from))?;
+        let last = args
+            .to
+            .to_naive()
+            .ok_or_else(|| anyhow!("Date {} doesn't exist", args.to))?;
+        ensure!(first <= last, "Export range should start before it ends");
+
+        let path = &args.file;
+        let existing = if path.is_file() {
+            fs::read_to_string(path)
+                .with_context(|| anyhow!("When reading dataset file '{}'", path.display()))?
+        } else {
+            String::new()
+        };
+        let next = match args.output.last_date(&existing) {
+            Some(date) => date.succ_opt().unwrap_or(date).max(first),
+            None => first,
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| anyhow!("When opening dataset file '{}'", path.display()))?;
+        if existing.trim().is_empty() {
+            if let ExportFormat::Csv = args.output {
+                writeln!(file, "{CSV_HEADER}")?;
+            }
+        }
+
+        Ok(Self {
+            file,
+            format: args.output,
+            next,
+            last,
+        })
+    }
+    /// Days which are still to be exported
+    pub fn remaining(&self) -> impl Iterator<Item = NaiveDate> {
+        let last = self.last;
+        self.next.iter_days().take_while(move |day| *day <= last)
+    }
+    /// Appends single day to dataset, flushing it immediately
+    pub fn append(&mut self, date: &Date, info: &WeatherInfo) -> anyhow::Result<()> {
+        writeln!(self.file, "{}", self.format.row(date, info)?)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
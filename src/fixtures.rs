@@ -0,0 +1,132 @@
+//! Recording of raw HTTP responses into fixture files and their replaying without network
+//!
+//! Meant for debugging parsing issues users hit in the wild: user records responses
+//! with `--record <dir>`, shares directory, and developer replays them with `--replay <dir>`.
+//! Each request is stored in its own JSON file, named after request's URL with secrets masked,
+//! so fixtures carry no credentials and match regardless of which ones are configured
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+/// How responses are intercepted
+pub enum Mode {
+    /// Responses are stored into directory
+    Record(PathBuf),
+    /// Responses are taken from directory instead of network
+    Replay(PathBuf),
+}
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+
+/// Sets interception mode; should be called once, before any request is made
+///
+/// # Parameters
+/// * `mode` - interception mode
+pub fn set_mode(mode: Mode) {
+    if MODE.set(mode).is_err() {
+        tracing::warn!("Fixture mode was already set");
+    }
+}
+
+/// Recorded response, along with request which identifies it
+#[derive(Serialize, Deserialize)]
+pub struct Fixture {
+    method: String,
+    /// Request URL with secrets masked
+    url: String,
+    /// HTTP status code
+    pub status: u16,
+    /// Media type of response, if it's specified
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Response text with secrets masked
+    pub body: String,
+}
+/// Looks up recorded response, if replay mode is on
+///
+/// # Parameters
+/// * `method` - request method
+/// * `url` - request URL with secrets masked
+///
+/// # Returns
+/// Recorded response, `None` if responses aren't replayed, or error if there's no recorded response
+pub fn replayed(method: &str, url: &str) -> anyhow::Result<Option<Fixture>> {
+    let Some(Mode::Replay(dir)) = MODE.get() else {
+        return Ok(None);
+    };
+    let path = fixture_path(dir, method, url);
+    let text = std::fs::read_to_string(&path).with_context(|| {
+        anyhow!(
+            "No recorded response for {method} {url} in '{}'",
+            dir.display()
+        )
+    })?;
+    let fixture = serde_json::from_str(&text)
+        .with_context(|| anyhow!("Could not parse fixture '{}'", path.display()))?;
+    tracing::debug!("Replayed response from '{}'", path.display());
+    Ok(Some(fixture))
+}
+/// Stores response, if record mode is on
+///
+/// # Parameters
+/// * `method` - request method
+/// * `url` - request URL with secrets masked
+/// * `status` - HTTP status code
+/// * `content_type` - media type of response, if it's specified
+/// * `body` - response text with secrets masked
+pub fn record(
+    method: &str,
+    url: &str,
+    status: u16,
+    content_type: Option<&str>,
+    body: &str,
+) -> anyhow::Result<()> {
+    let Some(Mode::Record(dir)) = MODE.get() else {
+        return Ok(());
+    };
+    let path = fixture_path(dir, method, url);
+    let fixture = Fixture {
+        method: method.to_string(),
+        url: url.to_string(),
+        status,
+        content_type: content_type.map(str::to_string),
+        body: body.to_string(),
+    };
+    std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&path, serde_json::to_string_pretty(&fixture)?))
+        .with_context(|| anyhow!("Could not record response into '{}'", path.display()))?;
+    tracing::debug!("Recorded response into '{}'", path.display());
+    Ok(())
+}
+/// Path of request's fixture file: readable host and path, followed by hash of whole URL,
+/// since queries are too long and too alike to be readable
+///
+/// # Parameters
+/// * `dir` - fixtures directory
+/// * `method` - request method
+/// * `url` - request URL with secrets masked
+fn fixture_path(dir: &Path, method: &str, url: &str) -> PathBuf {
+    const MAX_NAME: usize = 80;
+    let readable = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+    let mut name: String = format!("{method}_{readable}")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .take(MAX_NAME)
+        .collect();
+    name.push_str(&format!("-{:016x}.json", fnv1a(&format!("{method} {url}"))));
+    dir.join(name)
+}
+/// FNV-1a hash, which unlike std hashers is stable between releases,
+/// so fixtures stay valid with newer toolchains
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
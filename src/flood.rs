@@ -0,0 +1,120 @@
+//! River gauge levels and flood warnings near location, from national hydrology services
+//!
+//! Organized same way as hazard feeds: each service implements `FloodSource`,
+//! and service is picked by whether it covers requested coordinates
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::units::Units;
+
+pub mod environment_agency;
+pub mod usgs_water;
+
+/// Latest reading of river level gauge
+pub struct Gauge {
+    /// Gauge station's name
+    pub name: String,
+    /// River or water body name, if known
+    pub river: Option<String>,
+    /// Latest measured level
+    pub level: f64,
+    /// Unit of level, like "m" or "ft"
+    pub unit: String,
+    /// When level was measured
+    pub time: Option<DateTime<Utc>>,
+    /// Distance from requested location, in kilometers
+    pub distance_km: f64,
+}
+
+impl Gauge {
+    /// Describes gauge and its latest level
+    ///
+    /// # Parameters
+    /// * `units` - units in which distance is shown; level is shown in gauge's own unit
+    pub fn render(&self, units: Units) -> String {
+        let mut text = self.name.clone();
+        if let Some(river) = &self.river {
+            text += &format!(" ({river})");
+        }
+        text += &format!(
+            ", {:.0} {} away: {:.2} {}",
+            units.distance(self.distance_km),
+            units.distance_unit(),
+            self.level,
+            self.unit
+        );
+        if let Some(time) = self.time {
+            text += &format!(" at {} UTC", crate::date::format_time(time.naive_utc()));
+        }
+        text
+    }
+}
+
+/// Active flood warning
+pub struct FloodWarning {
+    /// Warning's severity, as reported by service
+    pub severity: String,
+    /// Short description, like affected area
+    pub title: String,
+}
+
+impl Display for FloodWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.title)
+    }
+}
+
+/// Hydrological situation near location
+#[derive(Default)]
+pub struct FloodReport {
+    /// Nearby gauges, nearest first
+    pub gauges: Vec<Gauge>,
+    /// Active warnings for location
+    pub warnings: Vec<FloodWarning>,
+}
+
+/// Area within which gauges and warnings are searched
+pub struct FloodQuery {
+    /// Latitude of search center, in degrees
+    pub lat: f64,
+    /// Longitude of search center, in degrees
+    pub lon: f64,
+    /// Search radius, in kilometers
+    pub radius_km: f64,
+}
+
+/// Defines any national hydrology service
+#[async_trait]
+pub trait FloodSource: Send + Sync {
+    /// Short description, used when listing services
+    fn description(&self) -> &'static str;
+    /// Checks whether service has data for specified location
+    ///
+    /// # Parameters
+    /// * `lat`, `lon` - location's coordinates, in degrees
+    fn covers(&self, lat: f64, lon: f64) -> bool;
+    /// Fetches gauges and warnings within query's area
+    ///
+    /// # Parameters
+    /// * `query` - search area
+    ///
+    /// # Returns
+    /// Flood report or error
+    async fn get_flood(&self, query: &FloodQuery) -> anyhow::Result<FloodReport>;
+}
+/// Creates all supported hydrology services
+///
+/// # Returns
+/// Services keyed by their names, in alphabetic order
+pub fn sources() -> BTreeMap<&'static str, Box<dyn FloodSource>> {
+    let mut sources: BTreeMap<&'static str, Box<dyn FloodSource>> = BTreeMap::new();
+    sources.insert(
+        "environment-agency",
+        Box::new(environment_agency::EnvironmentAgency),
+    );
+    sources.insert("usgs-water", Box::new(usgs_water::UsgsWater));
+    sources
+}
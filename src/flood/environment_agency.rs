@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::utils::{distance_km, restful_get};
+
+use super::{FloodQuery, FloodReport, FloodSource, FloodWarning, Gauge};
+/// Real-time flood monitoring API root
+const API_URL: &str = "https://environment.data.gov.uk/flood-monitoring";
+/// Maximal number of nearest gauges whose readings are requested
+const MAX_GAUGES: usize = 10;
+/// UK Environment Agency flood monitoring, covers England
+pub struct EnvironmentAgency;
+
+//
+// Error handling structures
+//
+
+/// API reports errors as plain text or HTML
+#[derive(Debug, thiserror::Error)]
+#[error("Environment Agency API error: {0}")]
+struct ApiError(String);
+
+impl FromStr for ApiError {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.trim().to_string()))
+    }
+}
+
+//
+// Response structures
+//
+
+/// Every response is list of items
+#[derive(Deserialize)]
+struct Items<T> {
+    items: Vec<T>,
+}
+
+impl<T: serde::de::DeserializeOwned> FromStr for Items<T> {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Station {
+    station_reference: String,
+    /// Usually string, but may be array for stations with several names
+    label: serde_json::Value,
+    river_name: Option<String>,
+    lat: Option<f64>,
+    long: Option<f64>,
+    #[serde(default)]
+    measures: Vec<Measure>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Measure {
+    unit_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reading {
+    date_time: DateTime<Utc>,
+    value: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Flood {
+    description: String,
+    severity: String,
+}
+
+#[async_trait]
+impl FloodSource for EnvironmentAgency {
+    fn description(&self) -> &'static str {
+        "UK Environment Agency flood monitoring, England (https://environment.data.gov.uk/flood-monitoring/)"
+    }
+
+    fn covers(&self, lat: f64, lon: f64) -> bool {
+        (49.8..=55.9).contains(&lat) && (-6.5..=2.0).contains(&lon)
+    }
+
+    async fn get_flood(&self, query: &FloodQuery) -> anyhow::Result<FloodReport> {
+        let FloodQuery {
+            lat,
+            lon,
+            radius_km,
+        } = *query;
+        // Stations and warnings are searched within same area
+        let area = move |url: ApiUrl| {
+            url.query("lat", format_args!("{lat:.4}"))
+                .query("long", format_args!("{lon:.4}"))
+                .query("dist", format_args!("{radius_km:.1}"))
+        };
+        let stations = restful_get::<Items<Station>, ApiError>(area(
+            ApiUrl::new(API_URL)
+                .segment("id")
+                .segment("stations")
+                .query("parameter", "level"),
+        ))
+        .await
+        .with_context(|| anyhow!("Could not obtain gauge stations"))?
+        .items;
+
+        let mut stations: Vec<_> = stations
+            .into_iter()
+            .filter_map(|station| {
+                let distance = distance_km((lat, lon), (station.lat?, station.long?));
+                Some((distance, station))
+            })
+            .collect();
+        stations.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let mut report = FloodReport::default();
+        for (distance, station) in stations.into_iter().take(MAX_GAUGES) {
+            let url = ApiUrl::new(API_URL)
+                .segment("id")
+                .segment("stations")
+                .segment(&station.station_reference)
+                .segment("readings")
+                .flag("latest");
+            let readings = restful_get::<Items<Reading>, ApiError>(url)
+                .await
+                .with_context(|| anyhow!("Could not obtain gauge readings"))?
+                .items;
+            let Some(reading) = readings.into_iter().next() else {
+                continue;
+            };
+            let name = match station.label {
+                serde_json::Value::String(label) => label,
+                serde_json::Value::Array(labels) => labels
+                    .first()
+                    .and_then(|label| label.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                _ => station.station_reference,
+            };
+            report.gauges.push(Gauge {
+                name,
+                river: station.river_name,
+                level: reading.value,
+                unit: station
+                    .measures
+                    .into_iter()
+                    .find_map(|measure| measure.unit_name)
+                    .unwrap_or_else(|| "m".into()),
+                time: Some(reading.date_time),
+                distance_km: distance,
+            });
+        }
+
+        report.warnings = restful_get::<Items<Flood>, ApiError>(area(
+            ApiUrl::new(API_URL).segment("id").segment("floods"),
+        ))
+        .await
+        .with_context(|| anyhow!("Could not obtain flood warnings"))?
+        .items
+        .into_iter()
+        .map(|flood| FloodWarning {
+            severity: flood.severity,
+            title: flood.description,
+        })
+        .collect();
+
+        Ok(report)
+    }
+}
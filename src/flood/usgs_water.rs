@@ -0,0 +1,220 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::http_backend::Request;
+use crate::utils::{distance_km, restful_get, restful_request};
+
+use super::{FloodQuery, FloodReport, FloodSource, FloodWarning, Gauge};
+/// Instantaneous values service
+const API_URL: &str = "https://waterservices.usgs.gov/nwis/iv/";
+/// National Weather Service active alerts, used for flood warnings
+const ALERTS_URL: &str = "https://api.weather.gov/alerts/active";
+/// USGS parameter code of gage height
+const GAGE_HEIGHT: &str = "00065";
+/// Kilometers per degree of latitude, used to build search box
+const KM_PER_DEGREE: f64 = 111.0;
+/// Largest search box side accepted by service, in degrees
+const MAX_BOX_SIDE: f64 = 5.0;
+/// USGS water services, covers United States
+pub struct UsgsWater;
+
+//
+// Error handling structures
+//
+
+/// Services report errors as plain text or HTML
+#[derive(Debug, thiserror::Error)]
+#[error("USGS water services error: {0}")]
+struct ApiError(String);
+
+impl FromStr for ApiError {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.trim().to_string()))
+    }
+}
+
+//
+// Instantaneous values response structures
+//
+
+/// Response root
+#[derive(Deserialize)]
+struct Response {
+    value: ResponseValue,
+}
+
+impl FromStr for Response {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResponseValue {
+    time_series: Vec<TimeSeries>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimeSeries {
+    source_info: SourceInfo,
+    variable: Variable,
+    values: Vec<Values>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceInfo {
+    site_name: String,
+    geo_location: GeoLocation,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeoLocation {
+    geog_location: GeogLocation,
+}
+
+#[derive(Deserialize)]
+struct GeogLocation {
+    latitude: f64,
+    longitude: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Variable {
+    unit: Unit,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Unit {
+    unit_code: String,
+}
+
+#[derive(Deserialize)]
+struct Values {
+    value: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Value {
+    value: String,
+    date_time: DateTime<Utc>,
+}
+
+//
+// Alerts response structures
+//
+
+#[derive(Deserialize)]
+struct Alerts {
+    features: Vec<AlertFeature>,
+}
+
+impl FromStr for Alerts {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+#[derive(Deserialize)]
+struct AlertFeature {
+    properties: AlertProperties,
+}
+
+#[derive(Deserialize)]
+struct AlertProperties {
+    event: String,
+    severity: String,
+    headline: Option<String>,
+}
+
+#[async_trait]
+impl FloodSource for UsgsWater {
+    fn description(&self) -> &'static str {
+        "USGS water services with NWS flood alerts, United States (https://waterservices.usgs.gov/)"
+    }
+
+    fn covers(&self, lat: f64, lon: f64) -> bool {
+        (18.0..=72.0).contains(&lat) && (-180.0..=-64.0).contains(&lon)
+    }
+
+    async fn get_flood(&self, query: &FloodQuery) -> anyhow::Result<FloodReport> {
+        let FloodQuery {
+            lat,
+            lon,
+            radius_km,
+        } = *query;
+        let dlat = (radius_km / KM_PER_DEGREE).min(MAX_BOX_SIDE / 2.0);
+        let dlon = (dlat / lat.to_radians().cos().max(0.01)).min(MAX_BOX_SIDE / 2.0);
+        let url = ApiUrl::new(API_URL)
+            .query("format", "json")
+            .query("siteStatus", "active")
+            .query("parameterCd", GAGE_HEIGHT)
+            .query(
+                "bBox",
+                format_args!(
+                    "{:.6},{:.6},{:.6},{:.6}",
+                    lon - dlon,
+                    lat - dlat,
+                    lon + dlon,
+                    lat + dlat
+                ),
+            );
+        // NWS API requires identifying user agent
+        let alerts_url = ApiUrl::new(ALERTS_URL).query("point", format_args!("{lat:.4},{lon:.4}"));
+        let alerts_request = Request::get(alerts_url.as_str()).header("User-Agent", "weather-cli");
+        let series = restful_get::<Response, ApiError>(url)
+            .await
+            .with_context(|| anyhow!("Could not obtain gauge readings"))?
+            .value
+            .time_series;
+
+        let mut gauges: Vec<Gauge> = series
+            .into_iter()
+            .filter_map(|series| {
+                let location = &series.source_info.geo_location.geog_location;
+                let distance = distance_km((lat, lon), (location.latitude, location.longitude));
+                let latest = series.values.into_iter().next()?.value.into_iter().last()?;
+                (distance <= radius_km).then_some(Gauge {
+                    name: series.source_info.site_name,
+                    river: None,
+                    level: latest.value.parse().ok()?,
+                    unit: series.variable.unit.unit_code,
+                    time: Some(latest.date_time),
+                    distance_km: distance,
+                })
+            })
+            .collect();
+        gauges.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+
+        let warnings = restful_request::<Alerts, ApiError>(alerts_request)
+            .await
+            .with_context(|| anyhow!("Could not obtain flood alerts"))?
+            .features
+            .into_iter()
+            .filter(|alert| alert.properties.event.contains("Flood"))
+            .map(|alert| FloodWarning {
+                severity: alert.properties.severity,
+                title: alert.properties.headline.unwrap_or(alert.properties.event),
+            })
+            .collect();
+
+        Ok(FloodReport { gauges, warnings })
+    }
+}
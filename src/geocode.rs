@@ -0,0 +1,369 @@
+//! Resolving addresses into coordinates, shared by providers and features which work with coordinates
+//!
+//! Each geocoding service implements `Geocoder`; one used to resolve addresses before forecast requests
+//! is selected with global `geocoder` config entry. Providers receive resolved "<lat>,<lon>" locations,
+//! so they don't need own lookups. Search results are cached for the rest of the run,
+//! and places which addresses were resolved into are remembered in state directory,
+//! so following runs skip geocoding entirely
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::{anyhow, bail, ensure, Context};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::app_dirs;
+use crate::config::Config;
+#[cfg(feature = "openweather")]
+use crate::layered_config;
+use crate::progress::{self, Event};
+#[cfg(feature = "openweather")]
+use crate::provider::openweather::OpenWeather;
+use crate::provider::PlannedRequest;
+#[cfg(feature = "openweather")]
+use crate::provider::Provider;
+
+pub mod nominatim;
+#[cfg(feature = "openweather")]
+pub mod openweather;
+
+/// Name of global config entry which selects geocoder
+const GEOCODER_ENTRY: &str = "geocoder";
+
+/// Search results, keyed by geocoder name, location and limit
+type SearchCache = BTreeMap<(&'static str, String, usize), Vec<Candidate>>;
+/// Search results of this run
+static CACHE: Mutex<SearchCache> = Mutex::new(BTreeMap::new());
+
+/// Place matching searched location
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    /// City, town or other place name
+    pub name: String,
+    /// State, province or county, if known
+    pub region: Option<String>,
+    /// Country, if known
+    pub country: Option<String>,
+    /// Latitude, in degrees
+    pub lat: f64,
+    /// Longitude, in degrees
+    pub lon: f64,
+}
+
+impl Candidate {
+    /// Location string which providers accept, "<lat>,<lon>"
+    pub fn coordinates(&self) -> String {
+        format!("{:.4},{:.4}", self.lat, self.lon)
+    }
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        for part in [&self.region, &self.country].into_iter().flatten() {
+            f.write_fmt(format_args!(", {part}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Defines any geocoding service
+#[async_trait]
+pub trait Geocoder: Send + Sync {
+    /// Short name, used in config and as cache key
+    fn name(&self) -> &'static str;
+    /// Finds places matching address, most relevant first
+    ///
+    /// # Parameters
+    /// * `location` - address
+    /// * `limit` - maximal number of matches
+    ///
+    /// # Returns
+    /// Matching places, empty if there are none, or error
+    async fn search(&self, location: &str, limit: usize) -> anyhow::Result<Vec<Candidate>>;
+    /// Describes request which `search` would perform, for dry runs
+    ///
+    /// # Returns
+    /// Request, if geocoder performs any
+    fn plan(&self, location: &str, limit: usize) -> Option<PlannedRequest>;
+}
+
+/// Leaves addresses to provider's own lookup, for providers which know places better
+/// than general-purpose services
+pub struct Native;
+
+#[async_trait]
+impl Geocoder for Native {
+    fn name(&self) -> &'static str {
+        "provider"
+    }
+
+    async fn search(&self, _location: &str, _limit: usize) -> anyhow::Result<Vec<Candidate>> {
+        Ok(Vec::new())
+    }
+
+    fn plan(&self, _location: &str, _limit: usize) -> Option<PlannedRequest> {
+        None
+    }
+}
+/// Creates geocoder selected by global `geocoder` config entry; Nominatim is used by default
+///
+/// # Parameters
+/// * `config` - configuration, which also holds credentials of geocoders which need them
+///
+/// # Returns
+/// Selected geocoder
+pub fn from_config(config: &Config) -> anyhow::Result<Box<dyn Geocoder>> {
+    let name = config
+        .globals
+        .get(GEOCODER_ENTRY)
+        .map(String::as_str)
+        .unwrap_or("nominatim");
+    Ok(match name {
+        "nominatim" => Box::new(nominatim::Nominatim),
+        #[cfg(feature = "openweather")]
+        "openweather" => {
+            let apikey = layered_config::provider_section(config, "openweather", OpenWeather::info().params)
+                .and_then(|section| section.get("apikey").cloned())
+                .ok_or_else(|| anyhow!("OpenWeather geocoder requires configured 'openweather' provider"))?;
+            Box::new(openweather::OpenWeatherGeocoder::new(apikey))
+        }
+        #[cfg(not(feature = "openweather"))]
+        "openweather" => bail!("OpenWeather geocoder isn't available, application is built without 'openweather' feature"),
+        "provider" => Box::new(Native),
+        _ => bail!(
+            "Unknown geocoder '{name}' in '{GEOCODER_ENTRY}' config entry, expected 'nominatim', 'openweather' or 'provider'"
+        ),
+    })
+}
+/// Finds places matching address using geocoder, reusing results of same search during this run
+///
+/// # Parameters
+/// * `geocoder` - geocoding service
+/// * `location` - address
+/// * `limit` - maximal number of matches
+///
+/// # Returns
+/// Matching places, most relevant first, without ones which look same to user
+pub async fn search(
+    geocoder: &dyn Geocoder,
+    location: &str,
+    limit: usize,
+) -> anyhow::Result<Vec<Candidate>> {
+    let key = (geocoder.name(), location.to_string(), limit);
+    let span = tracing::info_span!(
+        "geocode",
+        geocoder = geocoder.name(),
+        location,
+        cache_hit = tracing::field::Empty
+    );
+    let cached = cache().get(&key).cloned();
+    span.record("cache_hit", cached.is_some());
+    if let Some(candidates) = cached {
+        let _entered = span.enter();
+        tracing::debug!("Using cached places for '{location}'");
+        return Ok(candidates);
+    }
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for candidate in geocoder.search(location, limit).instrument(span).await? {
+        // City and its administrative area often match both, and look same to user
+        if !candidates
+            .iter()
+            .any(|known| known.to_string() == candidate.to_string())
+        {
+            candidates.push(candidate);
+        }
+    }
+    cache().insert(key, candidates.clone());
+    Ok(candidates)
+}
+/// Locks search results cache, even if some thread panicked while holding it
+fn cache() -> MutexGuard<'static, SearchCache> {
+    CACHE.lock().unwrap_or_else(|error| error.into_inner())
+}
+/// Places which addresses were resolved into, keyed by geocoder name and address
+#[derive(Default, Serialize, Deserialize)]
+struct Resolved(BTreeMap<String, Candidate>);
+
+impl Resolved {
+    /// Path of file with resolved places, if OS has notion of state directory
+    fn path() -> Option<PathBuf> {
+        app_dirs::state_dir().map(|path| path.join("resolved.json"))
+    }
+    /// Reads resolved places; missing or broken file means there are none
+    fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+    /// Writes resolved places
+    fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| anyhow!("When writing resolved places to {}", path.display()))
+    }
+
+    fn key(geocoder: &dyn Geocoder, address: &str) -> String {
+        format!("{}\t{address}", geocoder.name())
+    }
+}
+/// Place which address was resolved into by geocoder in one of previous runs
+///
+/// # Parameters
+/// * `geocoder` - geocoding service
+/// * `address` - address, like one location alias stands for
+pub fn remembered(geocoder: &dyn Geocoder, address: &str) -> Option<Candidate> {
+    Resolved::load().0.remove(&Resolved::key(geocoder, address))
+}
+/// Remembers place which address was resolved into, so following runs skip geocoding
+///
+/// # Parameters
+/// * `geocoder` - geocoding service
+/// * `address` - resolved address
+/// * `place` - place picked for address
+pub fn remember(geocoder: &dyn Geocoder, address: &str, place: &Candidate) -> anyhow::Result<()> {
+    let mut resolved = Resolved::load();
+    resolved
+        .0
+        .insert(Resolved::key(geocoder, address), place.clone());
+    resolved.save()
+}
+/// Resolves location into coordinates, using geocoder's most relevant match;
+/// "<lat>,<lon>" locations are used as is
+///
+/// # Parameters
+/// * `geocoder` - geocoding service
+/// * `location` - address or coordinates
+///
+/// # Returns
+/// Latitude and longitude, in degrees
+pub async fn coordinates_with(
+    geocoder: &dyn Geocoder,
+    location: &str,
+) -> anyhow::Result<(f64, f64)> {
+    if let Some(coords) = parse_coordinates(location) {
+        return Ok(coords);
+    }
+    let Candidate { lat, lon, .. } = search(geocoder, location, 1)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Could not obtain coordinates of location '{location}'"))?;
+    progress::emit(Event::Geocoded { location, lat, lon });
+    Ok((lat, lon))
+}
+/// Same as `coordinates_with`, using Nominatim
+pub async fn coordinates(location: &str) -> anyhow::Result<(f64, f64)> {
+    coordinates_with(&nominatim::Nominatim, location).await
+}
+/// Describes geocoding request which `coordinates` would perform, for dry runs
+///
+/// # Parameters
+/// * `location` - address or coordinates
+///
+/// # Returns
+/// Request, unless location is specified as coordinates and needs no request
+pub fn plan(location: &str) -> Option<PlannedRequest> {
+    plan_with(&nominatim::Nominatim, location, 1)
+}
+/// Describes geocoding request which `search` would perform, for dry runs
+///
+/// # Parameters
+/// * `geocoder` - geocoding service
+/// * `location` - address or coordinates
+/// * `limit` - maximal number of matches
+///
+/// # Returns
+/// Request, unless location is specified as coordinates or geocoder performs no requests
+pub fn plan_with(geocoder: &dyn Geocoder, location: &str, limit: usize) -> Option<PlannedRequest> {
+    if coordinates_text(location).is_some() {
+        return None;
+    }
+    geocoder.plan(location, limit)
+}
+/// Location which dry runs pass to providers in place of coordinates,
+/// which are known only after geocoding request is performed
+pub const PLACEHOLDER: &str = "{lat},{lon}";
+/// Splits location specified as coordinates, or `PLACEHOLDER`, into latitude and longitude,
+/// formatted for requests
+///
+/// # Parameters
+/// * `location` - location string
+///
+/// # Returns
+/// Latitude and longitude, if location is coordinates or placeholder
+pub fn coordinates_text(location: &str) -> Option<(String, String)> {
+    if location == PLACEHOLDER {
+        return Some(("{lat}".to_string(), "{lon}".to_string()));
+    }
+    let (lat, lon) = parse_coordinates(location)?;
+    Some((format!("{lat:.4}"), format!("{lon:.4}")))
+}
+/// Parses location specified directly as "<lat>,<lon>"
+///
+/// # Parameters
+/// * `location` - location string
+///
+/// # Returns
+/// Latitude and longitude, if location is pair of numbers
+pub fn parse_coordinates(location: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = location.split_once(',')?;
+    Some((lat.trim().parse().ok()?, lon.trim().parse().ok()?))
+}
+/// Picks one of several places matching address; asks user when running interactively
+///
+/// # Parameters
+/// * `location` - searched address
+/// * `candidates` - matching places, most relevant first; shouldn't be empty
+/// * `first` - pick most relevant place without asking
+///
+/// # Returns
+/// Picked place
+pub fn choose(
+    location: &str,
+    mut candidates: Vec<Candidate>,
+    first: bool,
+) -> anyhow::Result<Candidate> {
+    ensure!(
+        !candidates.is_empty(),
+        "Could not find location '{location}'"
+    );
+    if candidates.len() == 1 || first {
+        return Ok(candidates.swap_remove(0));
+    }
+    if !std::io::stdin().is_terminal() {
+        tracing::warn!(
+            "Location '{location}' is ambiguous, using {}; pass more specific address or '--first' to silence this warning",
+            candidates[0]
+        );
+        return Ok(candidates.swap_remove(0));
+    }
+    eprintln!("Several places match '{location}':");
+    for (index, candidate) in candidates.iter().enumerate() {
+        eprintln!("  {}. {candidate}", index + 1);
+    }
+    loop {
+        eprint!("Pick one [1-{}]: ", candidates.len());
+        let mut buffer = String::new();
+        if std::io::stdin().read_line(&mut buffer)? == 0 {
+            bail!("No place picked for location '{location}'");
+        }
+        match buffer.trim().parse::<usize>() {
+            Ok(number) if (1..=candidates.len()).contains(&number) => {
+                return Ok(candidates.swap_remove(number - 1));
+            }
+            _ => eprintln!("Please enter number from 1 to {}", candidates.len()),
+        }
+    }
+}
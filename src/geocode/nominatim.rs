@@ -0,0 +1,145 @@
+//! OpenStreetMap's Nominatim geocoding service; free, but asks for identifying user agent
+//! and at most one request per second
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::http_backend::Request;
+use crate::provider::PlannedRequest;
+use crate::rate_limit;
+use crate::utils::restful_request;
+
+use super::{Candidate, Geocoder};
+
+/// Geocoding service's search endpoint
+const API_URL: &str = "https://nominatim.openstreetmap.org/search";
+/// Host of geocoding service, whose requests are spaced
+const HOST: &str = "nominatim.openstreetmap.org";
+/// Minimal time between requests, as usage policy asks
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+/// User agent which identifies application to geocoding service
+const USER_AGENT: &str = "weather-cli";
+
+/// Geocoding service failure, reported as raw response text
+#[derive(Debug)]
+struct ServiceError(String);
+
+impl FromStr for ServiceError {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.trim().to_string()))
+    }
+}
+
+impl Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Geocoding service error: {}", self.0))
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+/// Geocoding response root
+struct Places(Vec<Place>);
+
+impl FromStr for Places {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(serde_json::from_str(s)?))
+    }
+}
+
+#[derive(Deserialize)]
+struct Place {
+    lat: String,
+    lon: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    display_name: String,
+    address: Option<Address>,
+}
+
+#[derive(Deserialize, Default)]
+struct Address {
+    city: Option<String>,
+    town: Option<String>,
+    village: Option<String>,
+    state: Option<String>,
+    county: Option<String>,
+    country: Option<String>,
+}
+
+impl TryFrom<Place> for Candidate {
+    type Error = anyhow::Error;
+
+    fn try_from(place: Place) -> Result<Self, Self::Error> {
+        let (lat, lon) = (place.lat.parse()?, place.lon.parse()?);
+        let address = place.address.unwrap_or_default();
+        let name = [address.city, address.town, address.village]
+            .into_iter()
+            .flatten()
+            .next()
+            .or_else(|| (!place.name.is_empty()).then_some(place.name))
+            .unwrap_or(place.display_name);
+        Ok(Self {
+            name,
+            region: address.state.or(address.county),
+            country: address.country,
+            lat,
+            lon,
+        })
+    }
+}
+
+/// Nominatim geocoder
+pub struct Nominatim;
+
+#[async_trait]
+impl Geocoder for Nominatim {
+    fn name(&self) -> &'static str {
+        "nominatim"
+    }
+
+    async fn search(&self, location: &str, limit: usize) -> anyhow::Result<Vec<Candidate>> {
+        // Nominatim's usage policy requires identifying user agent
+        let request =
+            Request::get(search_url(location, limit).as_str()).header("User-Agent", USER_AGENT);
+        // Batch jobs geocode concurrently, while usage policy allows single request per second
+        rate_limit::space(HOST, REQUEST_INTERVAL).await;
+        restful_request::<Places, ServiceError>(request)
+            .await
+            .with_context(|| anyhow!("Could not obtain location's coordinates"))?
+            .0
+            .into_iter()
+            .map(Candidate::try_from)
+            .collect()
+    }
+
+    fn plan(&self, location: &str, limit: usize) -> Option<PlannedRequest> {
+        Some(
+            PlannedRequest::get("location's coordinates", search_url(location, limit))
+                .header("User-Agent", USER_AGENT),
+        )
+    }
+}
+/// URL of geocoding request for location
+///
+/// # Parameters
+/// * `location` - address
+/// * `limit` - maximal number of matches
+fn search_url(location: &str, limit: usize) -> ApiUrl {
+    ApiUrl::new(API_URL)
+        .query("q", location)
+        .query("format", "json")
+        .query("addressdetails", 1)
+        .query("limit", limit)
+}
@@ -0,0 +1,97 @@
+//! OpenWeather's geocoding API; requires OpenWeather API key, taken from `openweather` provider's config
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::provider::openweather::ApiError;
+use crate::provider::PlannedRequest;
+use crate::utils::restful_get;
+
+use super::{Candidate, Geocoder};
+
+/// Direct geocoding endpoint
+const API_URL: &str = "https://api.openweathermap.org/geo/1.0/direct";
+
+/// Location response root
+struct Places(Vec<Place>);
+
+impl FromStr for Places {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(serde_json::from_str(s)?))
+    }
+}
+
+#[derive(Deserialize)]
+struct Place {
+    name: String,
+    state: Option<String>,
+    country: Option<String>,
+    lat: f64,
+    lon: f64,
+}
+
+impl From<Place> for Candidate {
+    fn from(place: Place) -> Self {
+        Self {
+            name: place.name,
+            region: place.state,
+            country: place.country,
+            lat: place.lat,
+            lon: place.lon,
+        }
+    }
+}
+
+/// OpenWeather geocoder
+pub struct OpenWeatherGeocoder {
+    apikey: String,
+}
+
+impl OpenWeatherGeocoder {
+    /// Creates geocoder
+    ///
+    /// # Parameters
+    /// * `apikey` - OpenWeather API key
+    pub fn new(apikey: impl Into<String>) -> Self {
+        Self {
+            apikey: apikey.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Geocoder for OpenWeatherGeocoder {
+    fn name(&self) -> &'static str {
+        "openweather"
+    }
+
+    async fn search(&self, location: &str, limit: usize) -> anyhow::Result<Vec<Candidate>> {
+        let url = search_url(&self.apikey, location, limit);
+        Ok(restful_get::<Places, ApiError>(url)
+            .await
+            .with_context(|| anyhow!("Could not obtain location's coordinates"))?
+            .0
+            .into_iter()
+            .map(Candidate::from)
+            .collect())
+    }
+
+    fn plan(&self, location: &str, limit: usize) -> Option<PlannedRequest> {
+        Some(PlannedRequest::get(
+            "location's coordinates",
+            search_url(&self.apikey, location, limit),
+        ))
+    }
+}
+/// URL of request which resolves location into coordinates
+fn search_url(apikey: &str, location: &str, limit: usize) -> ApiUrl {
+    ApiUrl::new(API_URL)
+        .query("q", location)
+        .query("limit", limit)
+        .secret_query("appid", apikey)
+}
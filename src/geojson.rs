@@ -0,0 +1,55 @@
+//! GeoJSON (RFC 7946) writer, so forecasts can be put onto map in tools like QGIS or Leaflet
+use serde_json::{json, Value};
+
+use crate::date::Date;
+use crate::provider::WeatherInfo;
+
+/// Header of feature collection, which is followed by features separated by commas
+pub const COLLECTION_START: &str = r#"{"type":"FeatureCollection","features":["#;
+/// Footer of feature collection
+pub const COLLECTION_END: &str = "]}";
+
+/// Makes point feature with forecast fields as its properties
+///
+/// # Parameters
+/// * `coordinates` - latitude and longitude; feature without them has no geometry,
+///   which mapping tools skip when drawing yet keep in attribute table
+/// * `properties` - forecast fields and their context, like address and date
+pub fn feature(coordinates: Option<(f64, f64)>, properties: Value) -> Value {
+    // GeoJSON puts longitude first
+    let geometry =
+        coordinates.map(|(lat, lon)| json!({"type": "Point", "coordinates": [lon, lat]}));
+    json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties,
+    })
+}
+/// Renders forecasts for single location as feature collection, one feature per day
+///
+/// # Parameters
+/// * `address` - location of forecasts
+/// * `coordinates` - location's latitude and longitude, if it's known
+/// * `forecasts` - forecasts by day
+///
+/// # Returns
+/// Collection as JSON value
+pub fn write_forecasts(
+    address: &str,
+    coordinates: Option<(f64, f64)>,
+    forecasts: &[(Date, WeatherInfo)],
+) -> Value {
+    let features: Vec<_> = forecasts
+        .iter()
+        .map(|(date, info)| {
+            let mut properties = json!(info);
+            properties["location"] = json!(address);
+            properties["date"] = json!(date.to_string());
+            feature(coordinates, properties)
+        })
+        .collect();
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
@@ -0,0 +1,114 @@
+//! Natural hazards near location, like earthquakes and floods, from public feeds
+//!
+//! Feeds are organized similarly to weather providers: each feed implements `HazardSource`
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::units::Units;
+
+pub mod gdacs;
+pub mod usgs;
+
+/// Kind of natural hazard
+#[derive(Clone, Copy, PartialEq)]
+pub enum HazardKind {
+    Earthquake,
+    Flood,
+    Cyclone,
+    Volcano,
+    Drought,
+    Wildfire,
+    Other,
+}
+
+impl Display for HazardKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HazardKind::Earthquake => "earthquake",
+            HazardKind::Flood => "flood",
+            HazardKind::Cyclone => "tropical cyclone",
+            HazardKind::Volcano => "volcano",
+            HazardKind::Drought => "drought",
+            HazardKind::Wildfire => "wildfire",
+            HazardKind::Other => "other",
+        })
+    }
+}
+
+/// Single hazard event
+pub struct Hazard {
+    /// Kind of event
+    pub kind: HazardKind,
+    /// Human-readable title, like place name
+    pub title: String,
+    /// Severity as reported by feed, like magnitude or alert level
+    pub severity: String,
+    /// When event started
+    pub time: DateTime<Utc>,
+    /// Distance from requested location, in kilometers
+    pub distance_km: f64,
+    /// Link to event details, if any
+    pub url: Option<String>,
+}
+
+impl Hazard {
+    /// Describes hazard, with link to its details
+    ///
+    /// # Parameters
+    /// * `units` - units in which distance is shown
+    pub fn render(&self, units: Units) -> String {
+        let mut text = format!(
+            "{} UTC {}, {}: {}; {:.0} {} away",
+            crate::date::format_time(self.time.naive_utc()),
+            self.kind,
+            self.severity,
+            self.title,
+            units.distance(self.distance_km),
+            units.distance_unit()
+        );
+        if let Some(url) = &self.url {
+            text += &format!("\n  {url}");
+        }
+        text
+    }
+}
+
+/// Area and period within which hazards are searched
+pub struct HazardQuery {
+    /// Latitude of search center, in degrees
+    pub lat: f64,
+    /// Longitude of search center, in degrees
+    pub lon: f64,
+    /// Search radius, in kilometers
+    pub radius_km: f64,
+    /// How many days into past events are searched
+    pub days: u32,
+}
+
+/// Defines any public feed of natural hazards
+#[async_trait]
+pub trait HazardSource: Send + Sync {
+    /// Short description, used when listing feeds
+    fn description(&self) -> &'static str;
+    /// Fetches hazards within query's area and period
+    ///
+    /// # Parameters
+    /// * `query` - search area and period
+    ///
+    /// # Returns
+    /// Found hazards or error
+    async fn get_hazards(&self, query: &HazardQuery) -> anyhow::Result<Vec<Hazard>>;
+}
+/// Creates all supported hazard feeds
+///
+/// # Returns
+/// Feeds keyed by their names, in alphabetic order
+pub fn sources() -> BTreeMap<&'static str, Box<dyn HazardSource>> {
+    let mut sources: BTreeMap<&'static str, Box<dyn HazardSource>> = BTreeMap::new();
+    sources.insert("gdacs", Box::new(gdacs::Gdacs));
+    sources.insert("usgs", Box::new(usgs::Usgs));
+    sources
+}
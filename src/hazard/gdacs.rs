@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::utils::{distance_km, restful_get};
+
+use super::{Hazard, HazardKind, HazardQuery, HazardSource};
+/// Event search endpoint
+const API_URL: &str = "https://www.gdacs.org/gdacsapi/api/events/geteventlist/SEARCH";
+/// Event types requested from feed; earthquakes are left to USGS, which has better coverage
+const EVENT_TYPES: &str = "FL;TC;VO;DR;WF";
+/// Global Disaster Alert and Coordination System
+pub struct Gdacs;
+
+//
+// Error handling structures
+//
+
+/// API reports errors as plain text
+#[derive(Debug, thiserror::Error)]
+#[error("GDACS API error: {0}")]
+struct ApiError(String);
+
+impl FromStr for ApiError {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.trim().to_string()))
+    }
+}
+
+//
+// Response structures
+//
+
+/// GeoJSON response root
+#[derive(Deserialize)]
+struct Events {
+    features: Vec<Feature>,
+}
+
+impl FromStr for Events {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Absence of events is reported as empty body
+        if s.trim().is_empty() {
+            return Ok(Self {
+                features: Vec::new(),
+            });
+        }
+        serde_json::from_str(s)
+    }
+}
+
+#[derive(Deserialize)]
+struct Feature {
+    properties: Properties,
+    geometry: Geometry,
+}
+
+#[derive(Deserialize)]
+struct Properties {
+    eventtype: String,
+    name: String,
+    alertlevel: String,
+    /// Start time, like "2023-10-15T00:00:00"
+    fromdate: String,
+    url: Option<Urls>,
+}
+
+#[derive(Deserialize)]
+struct Urls {
+    report: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Geometry {
+    /// Longitude and latitude for point geometries
+    coordinates: serde_json::Value,
+}
+
+#[async_trait]
+impl HazardSource for Gdacs {
+    fn description(&self) -> &'static str {
+        "GDACS floods, cyclones, volcanoes, droughts and wildfires (https://www.gdacs.org/)"
+    }
+
+    async fn get_hazards(&self, query: &HazardQuery) -> anyhow::Result<Vec<Hazard>> {
+        let HazardQuery {
+            lat,
+            lon,
+            radius_km,
+            days,
+        } = *query;
+        let now = Utc::now();
+        let url = ApiUrl::new(API_URL)
+            .query("eventlist", EVENT_TYPES)
+            .query(
+                "fromDate",
+                (now - Duration::days(days.into())).format("%Y-%m-%d"),
+            )
+            .query("toDate", now.format("%Y-%m-%d"));
+        let events = restful_get::<Events, ApiError>(url)
+            .await
+            .with_context(|| anyhow!("Could not obtain disaster events list"))?;
+        // Feed doesn't support area filtering, so events are filtered by distance here
+        Ok(events
+            .features
+            .into_iter()
+            .filter_map(
+                |Feature {
+                     properties,
+                     geometry,
+                 }| {
+                    let event_lon = geometry.coordinates.get(0)?.as_f64()?;
+                    let event_lat = geometry.coordinates.get(1)?.as_f64()?;
+                    let distance = distance_km((lat, lon), (event_lat, event_lon));
+                    (distance <= radius_km).then(|| Hazard {
+                        kind: match properties.eventtype.as_str() {
+                            "FL" => HazardKind::Flood,
+                            "TC" => HazardKind::Cyclone,
+                            "VO" => HazardKind::Volcano,
+                            "DR" => HazardKind::Drought,
+                            "WF" => HazardKind::Wildfire,
+                            "EQ" => HazardKind::Earthquake,
+                            _ => HazardKind::Other,
+                        },
+                        title: properties.name,
+                        severity: format!("{} alert", properties.alertlevel.to_lowercase()),
+                        time: NaiveDateTime::parse_from_str(
+                            &properties.fromdate,
+                            "%Y-%m-%dT%H:%M:%S",
+                        )
+                        .map(|time| DateTime::from_naive_utc_and_offset(time, Utc))
+                        .unwrap_or_default(),
+                        distance_km: distance,
+                        url: properties.url.and_then(|url| url.report),
+                    })
+                },
+            )
+            .collect())
+    }
+}
@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::utils::{distance_km, restful_get};
+
+use super::{Hazard, HazardKind, HazardQuery, HazardSource};
+/// Earthquake catalog query endpoint
+const API_URL: &str = "https://earthquake.usgs.gov/fdsnws/event/1/query";
+/// Smallest magnitude considered significant
+const MIN_MAGNITUDE: f32 = 4.0;
+/// Largest search radius accepted by API
+const MAX_RADIUS_KM: f64 = 20001.6;
+/// USGS earthquake catalog
+pub struct Usgs;
+
+//
+// Error handling structures
+//
+
+/// API reports errors as plain text
+#[derive(Debug, thiserror::Error)]
+#[error("USGS API error: {0}")]
+struct ApiError(String);
+
+impl FromStr for ApiError {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.trim().to_string()))
+    }
+}
+
+//
+// Response structures
+//
+
+/// GeoJSON response root
+#[derive(Deserialize)]
+struct Earthquakes {
+    features: Vec<Feature>,
+}
+
+impl FromStr for Earthquakes {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+#[derive(Deserialize)]
+struct Feature {
+    properties: Properties,
+    geometry: Geometry,
+}
+
+#[derive(Deserialize)]
+struct Properties {
+    mag: Option<f32>,
+    place: Option<String>,
+    /// Event time, in milliseconds since UNIX epoch
+    time: i64,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Geometry {
+    /// Longitude, latitude and depth
+    coordinates: Vec<f64>,
+}
+
+#[async_trait]
+impl HazardSource for Usgs {
+    fn description(&self) -> &'static str {
+        "USGS earthquake catalog (https://earthquake.usgs.gov/)"
+    }
+
+    async fn get_hazards(&self, query: &HazardQuery) -> anyhow::Result<Vec<Hazard>> {
+        let HazardQuery {
+            lat,
+            lon,
+            radius_km,
+            days,
+        } = *query;
+        let start = (Utc::now() - Duration::days(days.into())).format("%Y-%m-%d");
+        let url = ApiUrl::new(API_URL)
+            .query("format", "geojson")
+            .query("orderby", "time")
+            .query("latitude", format_args!("{lat:.4}"))
+            .query("longitude", format_args!("{lon:.4}"))
+            .query(
+                "maxradiuskm",
+                format_args!("{:.1}", radius_km.min(MAX_RADIUS_KM)),
+            )
+            .query("starttime", start)
+            .query("minmagnitude", MIN_MAGNITUDE);
+        let quakes = restful_get::<Earthquakes, ApiError>(url)
+            .await
+            .with_context(|| anyhow!("Could not obtain earthquakes list"))?;
+
+        Ok(quakes
+            .features
+            .into_iter()
+            .map(
+                |Feature {
+                     properties,
+                     geometry,
+                 }| {
+                    let coords = match geometry.coordinates.as_slice() {
+                        [quake_lon, quake_lat, ..] => Some((*quake_lat, *quake_lon)),
+                        _ => None,
+                    };
+                    Hazard {
+                        kind: HazardKind::Earthquake,
+                        title: properties.place.unwrap_or_default(),
+                        severity: properties
+                            .mag
+                            .map(|mag| format!("magnitude {mag:.1}"))
+                            .unwrap_or_else(|| "unknown magnitude".into()),
+                        time: DateTime::from_timestamp(properties.time / 1000, 0)
+                            .unwrap_or_default(),
+                        distance_km: coords
+                            .map(|coords| distance_km((lat, lon), coords))
+                            .unwrap_or_default(),
+                        url: properties.url,
+                    }
+                },
+            )
+            .collect())
+    }
+}
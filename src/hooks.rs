@@ -0,0 +1,277 @@
+//! User-configured commands executed before and after CLI commands
+//!
+//! Hooks are configured in `[hooks]` section, as `pre_<command>` and `post_<command>` entries,
+//! like `post_get = notify-send "Weather" "$WEATHER_HOOK_WEATHER"`. Each hook is executed by system shell.
+//! Command's outcome is passed to hook as JSON object on stdin; its top-level scalar fields,
+//! as well as fields of first forecast, are also exposed as `WEATHER_HOOK_<FIELD>` environment variables.
+//!
+//! Special entries `timeout`, in seconds, and `on_failure`, one of `ignore`, `warn` or `abort`,
+//! control how long hooks may run and what happens when hook fails or times out
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context};
+use serde_json::Value;
+
+use crate::config::Section;
+
+/// Name of hooks entry with hook timeout, in seconds
+const TIMEOUT_ENTRY: &str = "timeout";
+/// Name of hooks entry with failure policy
+const ON_FAILURE_ENTRY: &str = "on_failure";
+/// Default hook timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often running hook is checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What to do when hook fails or times out
+#[derive(Clone, Copy, Default)]
+pub enum FailurePolicy {
+    /// Silently continue
+    Ignore,
+    /// Print warning and continue
+    #[default]
+    Warn,
+    /// Fail whole command
+    Abort,
+}
+
+impl FromStr for FailurePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "ignore" => Ok(FailurePolicy::Ignore),
+            "warn" => Ok(FailurePolicy::Warn),
+            "abort" => Ok(FailurePolicy::Abort),
+            _ => bail!("Unknown hook failure policy '{s}', expected 'ignore', 'warn' or 'abort'"),
+        }
+    }
+}
+
+/// Configured hooks
+pub struct Hooks {
+    /// Hook commands, keyed by `pre_<command>` or `post_<command>`
+    commands: Section,
+    /// How long each hook may run
+    timeout: Duration,
+    /// What to do when hook fails
+    on_failure: FailurePolicy,
+}
+
+impl Hooks {
+    /// Reads hooks from `[hooks]` config section
+    pub fn from_section(section: &Section) -> anyhow::Result<Self> {
+        let timeout = match section.get(TIMEOUT_ENTRY) {
+            Some(timeout) => Duration::from_secs_f64(
+                timeout
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Hook timeout '{timeout}' isn't a number of seconds"))?,
+            ),
+            None => DEFAULT_TIMEOUT,
+        };
+        let on_failure = section
+            .get(ON_FAILURE_ENTRY)
+            .map(|policy| policy.parse())
+            .transpose()?
+            .unwrap_or_default();
+        let commands = section
+            .iter()
+            .filter(|(name, _)| name.starts_with("pre_") || name.starts_with("post_"))
+            .map(|(name, command)| (name.clone(), command.clone()))
+            .collect();
+        Ok(Self {
+            commands,
+            timeout,
+            on_failure,
+        })
+    }
+    /// Runs hook, if it's configured, applying failure policy
+    ///
+    /// # Parameters
+    /// * `name` - hook name, like `post_get`
+    /// * `payload` - JSON object passed to hook
+    ///
+    /// # Returns
+    /// Error only if hook failed and failure policy is `abort`
+    pub fn run(&self, name: &str, payload: &Value) -> anyhow::Result<()> {
+        let Some(command) = self.commands.get(name) else {
+            return Ok(());
+        };
+        let result = self
+            .execute(command, payload)
+            .with_context(|| anyhow!("Hook '{name}' failed"));
+        match (result, self.on_failure) {
+            (Ok(()), _) | (Err(_), FailurePolicy::Ignore) => Ok(()),
+            (Err(error), FailurePolicy::Warn) => {
+                tracing::warn!("{error:#}");
+                Ok(())
+            }
+            (Err(error), FailurePolicy::Abort) => Err(error),
+        }
+    }
+    /// Executes hook command with shell, waiting for it no longer than timeout
+    fn execute(&self, command: &str, payload: &Value) -> anyhow::Result<()> {
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        shell
+            .arg(command)
+            .envs(environment(payload))
+            .stdin(Stdio::piped());
+        let mut child = shell
+            .spawn()
+            .with_context(|| anyhow!("Could not start '{command}'"))?;
+        // Hook may not read its stdin at all, so broken pipe isn't an error
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(payload.to_string().as_bytes());
+        }
+
+        let started = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                if status.success() {
+                    return Ok(());
+                }
+                bail!("'{command}' exited with {status}");
+            }
+            if started.elapsed() >= self.timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                bail!(
+                    "'{command}' didn't finish in {} seconds",
+                    self.timeout.as_secs_f64()
+                );
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+/// Builds `WEATHER_HOOK_<FIELD>` environment variables out of payload's scalar fields
+/// and fields of its first forecast
+fn environment(payload: &Value) -> Vec<(String, String)> {
+    let first_forecast = payload
+        .get("forecasts")
+        .and_then(|forecasts| forecasts.get(0))
+        .and_then(Value::as_object);
+    payload
+        .as_object()
+        .into_iter()
+        .chain(first_forecast)
+        .flatten()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Number(_) | Value::Bool(_) => value.to_string(),
+                _ => return None,
+            };
+            Some((format!("WEATHER_HOOK_{}", name.to_ascii_uppercase()), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{environment, Hooks};
+    use crate::config::Section;
+
+    fn hooks(entries: &[(&str, &str)]) -> anyhow::Result<Hooks> {
+        let section: Section = entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Hooks::from_section(&section)
+    }
+
+    #[test]
+    fn payload_fields_become_variables() {
+        let payload = json!({
+            "command": "get",
+            "count": 2,
+            "cached": false,
+            "forecasts": [
+                {"location": "Oslo", "temperature": -1.5, "meta": {"provider": "openweather"}},
+                {"location": "Bergen", "temperature": 4.0},
+            ],
+        });
+        let mut variables = environment(&payload);
+        variables.sort();
+        let expected = [
+            ("WEATHER_HOOK_CACHED", "false"),
+            ("WEATHER_HOOK_COMMAND", "get"),
+            ("WEATHER_HOOK_COUNT", "2"),
+            ("WEATHER_HOOK_LOCATION", "Oslo"),
+            ("WEATHER_HOOK_TEMPERATURE", "-1.5"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        assert_eq!(variables, expected);
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let error = hooks(&[("timeout", "soon")]).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Hook timeout 'soon' isn't a number of seconds"
+        );
+        let error = hooks(&[("on_failure", "retry")]).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Unknown hook failure policy 'retry', expected 'ignore', 'warn' or 'abort'"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hook_gets_payload_on_stdin_and_in_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("output");
+        let command = format!(
+            "echo \"$WEATHER_HOOK_LOCATION $WEATHER_HOOK_TEMPERATURE\" > '{0}'; cat >> '{0}'",
+            output.display()
+        );
+        let hooks = hooks(&[("post_get", &command), ("pre_get", "exit 1")]).unwrap();
+        let payload = json!({"forecasts": [{"location": "Oslo", "temperature": 3}]});
+        hooks.run("post_get", &payload).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            format!("Oslo 3\n{payload}")
+        );
+        // Unconfigured hooks do nothing, failures only warn by default
+        hooks.run("pre_list", &payload).unwrap();
+        hooks.run("pre_get", &payload).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn abort_policy_fails_on_error_and_timeout() {
+        let hooks = hooks(&[
+            ("on_failure", "abort"),
+            ("timeout", "0.2"),
+            ("pre_get", "exit 3"),
+            ("post_get", "sleep 5"),
+        ])
+        .unwrap();
+        let error = hooks.run("pre_get", &json!({})).err().unwrap();
+        assert_eq!(
+            format!("{error:#}"),
+            "Hook 'pre_get' failed: 'exit 3' exited with exit status: 3"
+        );
+        let error = hooks.run("post_get", &json!({})).err().unwrap();
+        assert_eq!(
+            format!("{error:#}"),
+            "Hook 'post_get' failed: 'sleep 5' didn't finish in 0.2 seconds"
+        );
+    }
+}
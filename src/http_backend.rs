@@ -0,0 +1,432 @@
+//! Thin layer over HTTP client library, so application doesn't depend on particular one
+//!
+//! Backend is selected at build time with cargo features: `native-tls` uses `reqwest`
+//! with system's TLS library, `rustls` uses `reqwest` with rustls, and `minimal-http` uses
+//! lighter blocking `ureq` with rustls, which has much smaller dependency tree and allows
+//! fully static builds, e.g. for musl-based containers and routers
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls", feature = "minimal-http")))]
+compile_error!(
+    "One of HTTP backend features should be enabled: native-tls, rustls or minimal-http"
+);
+
+/// HTTP request
+pub struct Request {
+    pub method: &'static str,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// Limit for whole request, including reading response
+    pub timeout: Option<Duration>,
+}
+/// Failure of HTTP request
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Request didn't complete within its timeout
+    #[error("Request timed out")]
+    Timeout,
+    /// Any other failure, like unreachable server or broken connection
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+/// HTTP response, whose body is read as it arrives
+#[async_trait]
+pub trait Response: Send {
+    /// Status code
+    fn status(&self) -> u16;
+    /// Value of header, if it's present and is valid text
+    ///
+    /// # Parameters
+    /// * `name` - header's name, case-insensitive
+    fn header(&self, name: &str) -> Option<String>;
+    /// Declared size of body, if it's known
+    fn content_length(&self) -> Option<u64>;
+    /// Reads next chunk of body
+    ///
+    /// # Returns
+    /// Chunk, or `None` if whole body is read
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>, Error>;
+}
+/// HTTP client library
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Sends request and waits for response's status and headers
+    ///
+    /// # Parameters
+    /// * `request` - request to send
+    async fn execute(&self, request: Request) -> Result<Box<dyn Response>, Error>;
+}
+
+/// Backend shared by all requests
+static BACKEND: OnceLock<Box<dyn Backend>> = OnceLock::new();
+
+/// Backend shared by all requests, so connections are kept alive and reused,
+/// e.g. by provider which resolves location before requesting weather;
+/// it accepts compressed responses
+pub fn backend() -> &'static dyn Backend {
+    BACKEND
+        .get_or_init(|| {
+            #[cfg(feature = "minimal-http")]
+            return Box::new(ureq_backend::UreqBackend::new());
+            #[cfg(all(
+                any(feature = "native-tls", feature = "rustls"),
+                not(feature = "minimal-http")
+            ))]
+            return Box::new(reqwest_backend::ReqwestBackend::new());
+            // Build already fails with `compile_error!` above
+            #[cfg(not(any(feature = "native-tls", feature = "rustls", feature = "minimal-http")))]
+            unreachable!("no HTTP backend is compiled in")
+        })
+        .as_ref()
+}
+
+impl Request {
+    /// GET request
+    ///
+    /// # Parameters
+    /// * `url` - request's URL
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new("GET", url)
+    }
+    /// POST request
+    ///
+    /// # Parameters
+    /// * `url` - request's URL
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new("POST", url)
+    }
+
+    fn new(method: &'static str, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+            timeout: None,
+        }
+    }
+    /// Adds header
+    ///
+    /// # Parameters
+    /// * `name` - header's name
+    /// * `value` - header's value
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+    /// Adds `Authorization` header with Basic scheme credentials
+    ///
+    /// # Parameters
+    /// * `username` - account's username
+    /// * `password` - account's password
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+        self.header("Authorization", format!("Basic {credentials}"))
+    }
+    /// Sets request's body
+    ///
+    /// # Parameters
+    /// * `body` - body text
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+    /// Sends request with shared backend
+    pub async fn send(self) -> Result<Box<dyn Response>, Error> {
+        backend().execute(self).await
+    }
+}
+/// Sends request and reads whole response body
+///
+/// # Parameters
+/// * `request` - request to send
+///
+/// # Returns
+/// Status code and body, or error if request failed
+pub async fn fetch_bytes(request: Request) -> anyhow::Result<(u16, Vec<u8>)> {
+    let mut response = request.send().await?;
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+    }
+    Ok((response.status(), body))
+}
+/// Same as `fetch_bytes`, but returns body as text
+pub async fn fetch_text(request: Request) -> anyhow::Result<(u16, String)> {
+    let (status, body) = fetch_bytes(request).await?;
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+/// Describes status code along with its reason, like "404 Not Found"
+///
+/// # Parameters
+/// * `code` - status code
+pub fn status_text(code: u16) -> String {
+    match reason(code) {
+        Some(reason) => format!("{code} {reason}"),
+        None => code.to_string(),
+    }
+}
+/// Standard reason phrase of status code, like "Not Found"
+///
+/// # Parameters
+/// * `code` - status code
+fn reason(code: u16) -> Option<&'static str> {
+    http::StatusCode::from_u16(code)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+}
+/// Whether status code means success
+///
+/// # Parameters
+/// * `code` - status code
+pub fn is_success(code: u16) -> bool {
+    (200..300).contains(&code)
+}
+#[cfg(all(
+    any(feature = "native-tls", feature = "rustls"),
+    not(feature = "minimal-http")
+))]
+mod reqwest_backend {
+    use async_trait::async_trait;
+
+    use super::{Backend, Error, Request, Response};
+
+    /// Backend based on `reqwest`, which negotiates HTTP/2 with servers which support it
+    pub struct ReqwestBackend(reqwest::Client);
+
+    impl ReqwestBackend {
+        pub fn new() -> Self {
+            let builder = reqwest::Client::builder().gzip(true).deflate(true);
+            #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+            let builder = builder.use_rustls_tls();
+            Self(
+                builder
+                    .build()
+                    .expect("HTTP client should be created with selected TLS backend"),
+            )
+        }
+    }
+    /// Converts error, stripping URL from it, since URL may contain secrets
+    fn convert(error: reqwest::Error) -> Error {
+        if error.is_timeout() {
+            Error::Timeout
+        } else {
+            Error::Other(error.without_url().into())
+        }
+    }
+
+    #[async_trait]
+    impl Backend for ReqwestBackend {
+        async fn execute(&self, request: Request) -> Result<Box<dyn Response>, Error> {
+            let method = reqwest::Method::from_bytes(request.method.as_bytes())
+                .map_err(anyhow::Error::from)?;
+            let mut builder = self.0.request(method, &request.url);
+            for (name, value) in request.headers {
+                builder = builder.header(name, value);
+            }
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+            if let Some(timeout) = request.timeout {
+                builder = builder.timeout(timeout);
+            }
+            let response = builder.send().await.map_err(convert)?;
+            Ok(Box::new(ReqwestResponse(response)))
+        }
+    }
+
+    struct ReqwestResponse(reqwest::Response);
+
+    #[async_trait]
+    impl Response for ReqwestResponse {
+        fn status(&self) -> u16 {
+            self.0.status().as_u16()
+        }
+
+        fn header(&self, name: &str) -> Option<String> {
+            self.0
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        }
+
+        fn content_length(&self) -> Option<u64> {
+            self.0.content_length()
+        }
+
+        async fn chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+            let chunk = self.0.chunk().await.map_err(convert)?;
+            Ok(chunk.map(|chunk| chunk.to_vec()))
+        }
+    }
+}
+
+#[cfg(feature = "minimal-http")]
+mod ureq_backend {
+    use std::io::{ErrorKind, Read};
+
+    use anyhow::anyhow;
+    use async_trait::async_trait;
+
+    use super::{Backend, Error, Request, Response};
+
+    /// Size of chunks in which body is read
+    const CHUNK_SIZE: usize = 16 * 1024;
+
+    /// Backend based on `ureq`, which performs requests on blocking threads
+    pub struct UreqBackend(ureq::Agent);
+
+    impl UreqBackend {
+        pub fn new() -> Self {
+            // Same as `reqwest`, proxy is taken from environment
+            Self(ureq::AgentBuilder::new().try_proxy_from_env(true).build())
+        }
+    }
+    /// Converts error, stripping URL from it, since URL may contain secrets
+    fn convert(error: ureq::Transport) -> Error {
+        if timed_out(&error) {
+            Error::Timeout
+        } else {
+            let mut message = error.kind().to_string();
+            if let Some(detail) = error.message() {
+                message = format!("{message}: {detail}");
+            }
+            Error::Other(anyhow!(message))
+        }
+    }
+    /// Whether error is caused by timeout
+    fn timed_out(error: &(dyn std::error::Error + 'static)) -> bool {
+        let mut source = Some(error);
+        while let Some(error) = source {
+            if let Some(error) = error.downcast_ref::<std::io::Error>() {
+                if matches!(error.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) {
+                    return true;
+                }
+            }
+            source = error.source();
+        }
+        false
+    }
+
+    #[async_trait]
+    impl Backend for UreqBackend {
+        async fn execute(&self, request: Request) -> Result<Box<dyn Response>, Error> {
+            let mut builder = self.0.request(request.method, &request.url);
+            for (name, value) in &request.headers {
+                builder = builder.set(name, value);
+            }
+            if let Some(timeout) = request.timeout {
+                builder = builder.timeout(timeout);
+            }
+            let response = tokio::task::spawn_blocking(move || {
+                let response = match &request.body {
+                    Some(body) => builder.send_string(body),
+                    None => builder.call(),
+                };
+                // Failing status is still a response, which application handles itself
+                match response {
+                    Ok(response) | Err(ureq::Error::Status(_, response)) => Ok(response),
+                    Err(ureq::Error::Transport(error)) => Err(convert(error)),
+                }
+            })
+            .await
+            .map_err(anyhow::Error::from)??;
+            Ok(Box::new(UreqResponse {
+                status: response.status(),
+                headers: response
+                    .headers_names()
+                    .into_iter()
+                    .filter_map(|name| {
+                        let value = response.header(&name)?.to_string();
+                        Some((name, value))
+                    })
+                    .collect(),
+                reader: Some(response.into_reader()),
+            }))
+        }
+    }
+
+    struct UreqResponse {
+        status: u16,
+        headers: Vec<(String, String)>,
+        /// Body reader; it's moved to blocking thread while chunk is read
+        reader: Option<Box<dyn Read + Send + Sync>>,
+    }
+
+    #[async_trait]
+    impl Response for UreqResponse {
+        fn status(&self) -> u16 {
+            self.status
+        }
+
+        fn header(&self, name: &str) -> Option<String> {
+            self.headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        }
+
+        fn content_length(&self) -> Option<u64> {
+            // Decompressed body may be larger than declared one
+            if self.header("Content-Encoding").is_some() {
+                return None;
+            }
+            self.header("Content-Length")?.parse().ok()
+        }
+
+        async fn chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+            let Some(mut reader) = self.reader.take() else {
+                return Ok(None);
+            };
+            let (reader, chunk) = tokio::task::spawn_blocking(move || {
+                let mut chunk = vec![0; CHUNK_SIZE];
+                let read = reader.read(&mut chunk);
+                (
+                    reader,
+                    read.map(|size| {
+                        chunk.truncate(size);
+                        chunk
+                    }),
+                )
+            })
+            .await
+            .map_err(anyhow::Error::from)?;
+            let chunk = chunk.map_err(|error| {
+                if timed_out(&error) {
+                    Error::Timeout
+                } else {
+                    Error::Other(error.into())
+                }
+            })?;
+            if chunk.is_empty() {
+                return Ok(None);
+            }
+            self.reader = Some(reader);
+            Ok(Some(chunk))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Request;
+
+    #[test]
+    fn basic_auth_is_encoded() {
+        let request = Request::get("https://example.com").basic_auth("user", "pa");
+        assert_eq!(
+            request.headers,
+            [(
+                "Authorization".to_string(),
+                "Basic dXNlcjpwYQ==".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Guards of HTTP layer against misbehaving endpoints, which could otherwise
+//! balloon memory with huge responses or hang application with stalled ones
+//!
+//! Limits are set from global config entries once config is read
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{anyhow, ensure, Context};
+
+use crate::config::Config;
+
+/// Config entry with limit of whole request's duration, in seconds
+pub const TIMEOUT_ENTRY: &str = "http_timeout";
+/// Config entry with limit of time response's body may send no data, in seconds
+pub const READ_TIMEOUT_ENTRY: &str = "http_read_timeout";
+/// Config entry with limit of response's size, in kilobytes
+pub const MAX_RESPONSE_ENTRY: &str = "http_max_response_kb";
+
+/// Limits applied to each HTTP request
+pub struct HttpLimits {
+    /// How long whole request, including reading response, may take
+    pub timeout: Duration,
+    /// How long response may send no data
+    pub read_timeout: Duration,
+    /// Maximum size of response's body, in bytes
+    pub max_response_bytes: u64,
+}
+
+impl Default for HttpLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            read_timeout: Duration::from_secs(20),
+            // Multi-day hourly forecasts take few megabytes at most
+            max_response_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl HttpLimits {
+    /// Reads limits from global config entries; missing entries keep default limits
+    ///
+    /// # Parameters
+    /// * `config` - configuration
+    ///
+    /// # Returns
+    /// Limits, or error if any entry isn't positive integer
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let entry = |name: &str| -> anyhow::Result<Option<u64>> {
+            let Some(value) = config.globals.get(name) else {
+                return Ok(None);
+            };
+            let value: u64 = value
+                .trim()
+                .parse()
+                .with_context(|| anyhow!("Invalid '{name}' config entry"))?;
+            ensure!(value > 0, "Config entry '{name}' should be positive");
+            Ok(Some(value))
+        };
+        let defaults = Self::default();
+        Ok(Self {
+            timeout: entry(TIMEOUT_ENTRY)?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.timeout),
+            read_timeout: entry(READ_TIMEOUT_ENTRY)?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.read_timeout),
+            max_response_bytes: entry(MAX_RESPONSE_ENTRY)?
+                .map(|kb| kb.saturating_mul(1024))
+                .unwrap_or(defaults.max_response_bytes),
+        })
+    }
+    /// Checks size of response's body, either declared by server or received so far
+    ///
+    /// # Parameters
+    /// * `bytes` - size of body, in bytes
+    ///
+    /// # Returns
+    /// Error if body exceeds limit
+    pub fn check_response_size(&self, bytes: u64) -> anyhow::Result<()> {
+        ensure!(
+            bytes <= self.max_response_bytes,
+            "Response is larger than {} KB, see '{}' config entry",
+            self.max_response_bytes / 1024,
+            MAX_RESPONSE_ENTRY
+        );
+        Ok(())
+    }
+}
+
+static LIMITS: OnceLock<HttpLimits> = OnceLock::new();
+
+/// Sets limits; should be called once, before any request is made
+///
+/// # Parameters
+/// * `limits` - limits applied to each request
+pub fn set(limits: HttpLimits) {
+    if LIMITS.set(limits).is_err() {
+        tracing::warn!("HTTP limits were already set");
+    }
+}
+/// Limits applied to each request; default ones unless they were set
+pub fn get() -> &'static HttpLimits {
+    LIMITS.get_or_init(HttpLimits::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use super::HttpLimits;
+    use crate::config::Config;
+
+    fn limits(config: &str) -> anyhow::Result<HttpLimits> {
+        HttpLimits::from_config(&Config::from_str(config).unwrap())
+    }
+
+    #[test]
+    fn limits_are_read_from_config() {
+        let limits = limits("http_timeout = 5\nhttp_max_response_kb = 2\n").unwrap();
+        assert_eq!(limits.timeout, Duration::from_secs(5));
+        assert_eq!(limits.read_timeout, HttpLimits::default().read_timeout);
+        assert_eq!(limits.max_response_bytes, 2048);
+    }
+
+    #[test]
+    fn invalid_limits_are_rejected() {
+        let error = limits("http_read_timeout = soon\n").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Invalid 'http_read_timeout' config entry"
+        );
+        let error = limits("http_max_response_kb = 0\n").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Config entry 'http_max_response_kb' should be positive"
+        );
+    }
+
+    #[test]
+    fn over_limit_body_is_rejected() {
+        let limits = limits("http_max_response_kb = 2\n").unwrap();
+        assert!(limits.check_response_size(2048).is_ok());
+        let error = limits.check_response_size(2049).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Response is larger than 2 KB, see 'http_max_response_kb' config entry"
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Localization of human-readable output
+//!
+//! Only English is built in; other languages come as packs, JSON files installed into data directory
+//! on demand, so binary stays small. Pack looks like
+//! `{"language": "uk", "name": "Українська", "messages": {"label.temperature": "Температура", ...}}`,
+//! and messages missing from pack fall back to English ones.
+//!
+//! Packs are published along with `index.json`, which maps language codes to pack files
+//! and their SHA-256 digests; downloaded pack is installed only if its digest matches.
+//! Packs maintained along with application reside in repository's `lang` directory
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, ensure, Context};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::app_dirs;
+use crate::http_backend::{self, Request};
+
+/// Where packs are published by default, either URL or local directory with `index.json`
+pub const DEFAULT_SOURCE: &str =
+    "https://raw.githubusercontent.com/target-san/weather-cli/main/lang";
+/// Environment variable which selects language, when it's not specified on command line
+const LANG_VAR: &str = "WEATHER_LANG";
+
+/// Messages of selected language; empty if English is used
+static MESSAGES: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+
+/// Language pack
+#[derive(Deserialize)]
+struct Pack {
+    /// Language code, like "uk"
+    language: String,
+    /// Human-readable language name
+    name: String,
+    /// Localized messages, by key
+    messages: BTreeMap<String, String>,
+}
+
+/// Entry of packs index
+#[derive(Deserialize)]
+struct IndexEntry {
+    /// Pack file, relative to index
+    file: String,
+    /// Hex-encoded SHA-256 digest of pack file
+    sha256: String,
+}
+
+/// Directory where packs are installed, if OS has notion of data directory
+fn packs_dir() -> anyhow::Result<PathBuf> {
+    app_dirs::data_dir()
+        .map(|path| path.join("lang"))
+        .ok_or_else(|| anyhow!("Could not determine data directory"))
+}
+/// Path of installed pack
+fn pack_path(dir: &Path, language: &str) -> PathBuf {
+    dir.join(format!("{language}.json"))
+}
+/// Checks that language code is sane, since it's used as file name
+fn check_code(language: &str) -> anyhow::Result<()> {
+    ensure!(
+        !language.is_empty()
+            && language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        "Invalid language code '{language}'"
+    );
+    Ok(())
+}
+/// Checks that pack file listed in index is plain file name,
+/// so index can't make installer read anything outside of packs source
+fn check_file(file: &str) -> anyhow::Result<()> {
+    ensure!(
+        !file.is_empty() && !file.starts_with('.') && !file.contains(['/', '\\', ':']),
+        "Invalid language pack file '{file}' in index"
+    );
+    Ok(())
+}
+/// Hex-encoded SHA-256 digest of pack file
+fn digest(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+/// Loads messages of selected language; should be called once, before any output
+///
+/// Language is taken from argument, then `WEATHER_LANG` and `LANG` environment variables.
+/// English is used if language isn't selected or its pack isn't installed;
+/// latter is reported only if language was selected explicitly
+///
+/// # Parameters
+/// * `language` - language selected on command line
+pub fn init(language: Option<String>) {
+    let explicit = language.is_some() || std::env::var_os(LANG_VAR).is_some();
+    let language = language
+        .or_else(|| std::env::var(LANG_VAR).ok())
+        .or_else(|| {
+            // Like "uk_UA.UTF-8"
+            let lang = std::env::var("LANG").ok()?;
+            lang.split(['_', '.', '@']).next().map(str::to_string)
+        })
+        .filter(|language| !matches!(language.as_str(), "" | "en" | "C" | "POSIX"));
+    let Some(language) = language else {
+        return;
+    };
+
+    let load = || -> anyhow::Result<Pack> {
+        check_code(&language)?;
+        let path = pack_path(&packs_dir()?, &language);
+        let text = fs::read_to_string(&path).with_context(|| {
+            anyhow!("Language pack isn't installed; run 'weather lang install {language}'")
+        })?;
+        serde_json::from_str(&text)
+            .with_context(|| anyhow!("Broken language pack '{}'", path.display()))
+    };
+    match load() {
+        Ok(pack) => {
+            let _ = MESSAGES.set(pack.messages);
+        }
+        Err(error) if explicit => tracing::warn!("Using English output: {error:#}"),
+        Err(_) => {}
+    }
+}
+/// Localized message
+///
+/// # Parameters
+/// * `key` - message key, like "label.temperature"
+/// * `english` - English message, used if selected language doesn't have one
+pub fn tr<'a>(key: &str, english: &'a str) -> &'a str {
+    MESSAGES
+        .get()
+        .and_then(|messages| messages.get(key))
+        .map_or(english, String::as_str)
+}
+/// Downloads, verifies and installs language pack
+///
+/// # Parameters
+/// * `source` - URL or local directory where packs and their index are published
+/// * `language` - code of language to install
+///
+/// # Returns
+/// Human-readable name of installed language
+pub async fn install(source: &str, language: &str) -> anyhow::Result<String> {
+    check_code(language)?;
+    let index = fetch(source, "index.json")
+        .await
+        .with_context(|| anyhow!("When obtaining language packs index"))?;
+    let mut index: BTreeMap<String, IndexEntry> = serde_json::from_slice(&index)
+        .with_context(|| anyhow!("Could not parse language packs index"))?;
+    let entry = index
+        .remove(language)
+        .ok_or_else(|| anyhow!("No language pack for '{language}'"))?;
+    check_file(&entry.file)?;
+
+    let contents = fetch(source, &entry.file)
+        .await
+        .with_context(|| anyhow!("When downloading language pack '{}'", entry.file))?;
+    let digest = digest(&contents);
+    ensure!(
+        digest.eq_ignore_ascii_case(&entry.sha256),
+        "Language pack '{}' is corrupted: its SHA-256 is {digest}, expected {}",
+        entry.file,
+        entry.sha256
+    );
+    let pack: Pack = serde_json::from_slice(&contents)
+        .with_context(|| anyhow!("Could not parse language pack '{}'", entry.file))?;
+    ensure!(
+        pack.language == language,
+        "Language pack '{}' is for '{}' instead of '{language}'",
+        entry.file,
+        pack.language
+    );
+
+    // Pack is written under temporary name and renamed, so broken pack is never left installed
+    let dir = packs_dir()?;
+    fs::create_dir_all(&dir)
+        .with_context(|| anyhow!("When creating directory '{}'", dir.display()))?;
+    let path = pack_path(&dir, language);
+    let temp = path.with_extension("json.part");
+    fs::write(&temp, &contents)
+        .and_then(|_| fs::rename(&temp, &path))
+        .with_context(|| anyhow!("When writing language pack '{}'", path.display()))?;
+    Ok(pack.name)
+}
+/// Obtains file from packs source
+async fn fetch(source: &str, file: &str) -> anyhow::Result<Vec<u8>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let url = format!("{}/{file}", source.trim_end_matches('/'));
+        let (status, body) = http_backend::fetch_bytes(Request::get(&url))
+            .await
+            .with_context(|| anyhow!("HTTP GET request to '{url}' failed"))?;
+        if !http_backend::is_success(status) {
+            bail!(
+                "'{url}' responded with {}",
+                http_backend::status_text(status)
+            );
+        }
+        Ok(body)
+    } else {
+        let path = Path::new(source).join(file);
+        fs::read(&path).with_context(|| anyhow!("When reading '{}'", path.display()))
+    }
+}
+/// Removes installed language pack
+///
+/// # Parameters
+/// * `language` - code of language to remove
+pub fn remove(language: &str) -> anyhow::Result<()> {
+    check_code(language)?;
+    let path = pack_path(&packs_dir()?, language);
+    ensure!(path.is_file(), "Language pack '{language}' isn't installed");
+    fs::remove_file(&path).with_context(|| anyhow!("When removing '{}'", path.display()))
+}
+/// Lists installed language packs
+///
+/// # Returns
+/// Code and name of each installed language, or error description for broken packs
+pub fn installed() -> anyhow::Result<Vec<(String, Result<String, String>)>> {
+    let dir = packs_dir()?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut packs = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(language) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let name = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| serde_json::from_str::<Pack>(&text).map_err(|error| error.to_string()))
+            .map(|pack| pack.name);
+        packs.push((language.to_string(), name));
+    }
+    packs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(packs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use super::{check_file, digest, install, IndexEntry, Pack};
+
+    /// Directory with packs maintained along with application
+    fn shipped() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/lang"))
+    }
+
+    #[test]
+    fn shipped_index_matches_packs() {
+        let index = std::fs::read(shipped().join("index.json")).unwrap();
+        let index: BTreeMap<String, IndexEntry> = serde_json::from_slice(&index).unwrap();
+        assert!(!index.is_empty());
+        for (language, entry) in index {
+            check_file(&entry.file).unwrap();
+            let contents = std::fs::read(shipped().join(&entry.file)).unwrap();
+            assert_eq!(
+                digest(&contents),
+                entry.sha256,
+                "Digest of '{}'",
+                entry.file
+            );
+            let pack: Pack = serde_json::from_slice(&contents).unwrap();
+            assert_eq!(pack.language, language);
+        }
+    }
+
+    #[test]
+    fn only_plain_file_names_are_accepted() {
+        check_file("uk.json").unwrap();
+        for file in [
+            "",
+            "../uk.json",
+            ".hidden",
+            "packs/uk.json",
+            "/etc/passwd",
+            "C:uk.json",
+        ] {
+            assert!(check_file(file).is_err(), "{file}");
+        }
+    }
+
+    #[tokio::test]
+    async fn corrupted_pack_is_not_installed() {
+        let source = tempfile::tempdir().unwrap();
+        let pack = r#"{"language": "uk", "name": "Українська", "messages": {}}"#;
+        std::fs::write(source.path().join("uk.json"), pack).unwrap();
+        let index = format!(
+            r#"{{"uk": {{"file": "uk.json", "sha256": "{}"}}, "de": {{"file": "../de.json", "sha256": ""}}}}"#,
+            digest(b"something else")
+        );
+        std::fs::write(source.path().join("index.json"), index).unwrap();
+        let source = source.path().to_str().unwrap();
+
+        let error = install(source, "uk").await.err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Language pack 'uk.json' is corrupted: its SHA-256 is {}, expected {}",
+                digest(pack.as_bytes()),
+                digest(b"something else")
+            )
+        );
+        let error = install(source, "de").await.err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Invalid language pack file '../de.json' in index"
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Minimal iCalendar (RFC 5545) reader and writer
+//!
+//! Supports just enough of format to extract events, their start times and locations,
+//! and to export forecasts as all-day events
+use anyhow::{anyhow, ensure, Context};
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::clock;
+use crate::date::Date;
+use crate::http_backend::{self, Request};
+use crate::provider::WeatherInfo;
+use crate::units::Units;
+
+/// Maximal length of content line, in octets, after which it's folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Calendar event which has place and time
+pub struct Event {
+    /// Event's title, empty if not specified
+    pub summary: String,
+    /// Event's location as free-form address
+    pub location: String,
+    /// Event's start, in machine's local time; times without timezone are taken as local ones
+    pub start: NaiveDateTime,
+    /// Whether event lasts whole day, so its start has no time of day
+    pub all_day: bool,
+}
+
+/// Event's start in local time, and whether it's date without time
+type Start = (NaiveDateTime, bool);
+
+/// Load iCalendar data either from local file or from HTTP(S) URL
+///
+/// # Parameters
+/// * `source` - file path or URL
+///
+/// # Returns
+/// Calendar text
+pub async fn load(source: &str) -> anyhow::Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let (status, text) = http_backend::fetch_text(Request::get(source))
+            .await
+            .with_context(|| anyhow!("HTTP GET request to '{source}' failed"))?;
+        ensure!(
+            http_backend::is_success(status),
+            "'{source}' responded with {}",
+            http_backend::status_text(status)
+        );
+        Ok(text)
+    } else {
+        tokio::fs::read_to_string(source)
+            .await
+            .with_context(|| anyhow!("When reading calendar file '{source}'"))
+    }
+}
+
+/// Parse all events which have both location and start time
+///
+/// Events without location or with unparseable start time are skipped,
+/// since there's no way to get forecast for them anyway
+///
+/// # Parameters
+/// * `text` - iCalendar text
+///
+/// # Returns
+/// Events sorted by start time
+pub fn parse_events(text: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut current: Option<(String, Option<String>, Option<Start>)> = None;
+
+    for line in unfold(text) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Property parameters, like in `DTSTART;TZID=Europe/Berlin`, are kept aside
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+
+        match (name, &mut current) {
+            ("BEGIN", None) if value == "VEVENT" => current = Some((String::new(), None, None)),
+            ("END", Some(_)) if value == "VEVENT" => {
+                if let Some((summary, Some(location), Some((start, all_day)))) = current.take() {
+                    events.push(Event {
+                        summary,
+                        location,
+                        start,
+                        all_day,
+                    });
+                }
+            }
+            ("SUMMARY", Some((summary, _, _))) => *summary = unescape(value),
+            ("LOCATION", Some((_, location, _))) => {
+                let value = unescape(value);
+                if !value.trim().is_empty() {
+                    *location = Some(value);
+                }
+            }
+            ("DTSTART", Some((_, _, start))) => *start = parse_date_time(value, params),
+            _ => {}
+        }
+    }
+
+    events.sort_by_key(|event| event.start);
+    events
+}
+/// Join folded lines, i.e. lines which continue on next physical line started with whitespace
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(tail), Some(last)) => last.push_str(tail),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+/// Remove iCalendar text escapes
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => result.push(' '),
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+/// Parse either `YYYYMMDD` date or `YYYYMMDDTHHMMSS[Z]` date-time
+///
+/// # Parameters
+/// * `value` - property value
+/// * `params` - property parameters, like `TZID=Europe/Berlin`
+///
+/// # Returns
+/// Start in machine's local time and whether it's date without time. UTC times and times
+/// with known `TZID` are converted into local time; others are taken as local ones already
+fn parse_date_time(value: &str, params: &str) -> Option<Start> {
+    let date_only = || {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .map(|date| (date.and_time(NaiveTime::MIN), true))
+    };
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") {
+        return date_only();
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((time.and_utc().with_timezone(&Local).naive_local(), false));
+    }
+    let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") else {
+        return date_only();
+    };
+    // Timezones unknown to tz database, like Windows ones, leave time as is
+    let tz = params
+        .split(';')
+        .find_map(|param| param.strip_prefix("TZID="))
+        .and_then(|tz| tz.trim_matches('"').parse::<Tz>().ok());
+    let time = tz
+        .and_then(|tz| tz.from_local_datetime(&time).earliest())
+        .map_or(time, |time| time.with_timezone(&Local).naive_local());
+    Some((time, false))
+}
+/// Renders forecasts as calendar with one all-day event per forecast day
+///
+/// # Parameters
+/// * `address` - location of forecasts, used as events' location
+/// * `forecasts` - forecasts by day
+/// * `units` - units in which forecasts are described
+///
+/// # Returns
+/// Calendar text, with CRLF line endings
+pub fn write_forecasts(address: &str, forecasts: &[(Date, WeatherInfo)], units: Units) -> String {
+    let stamp = clock::now().with_timezone(&Utc).format("%Y%m%dT%H%M%SZ");
+    // UID should be stable, so re-imported forecast replaces previous one
+    let uid_location: String = address
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//weather-cli//forecast//EN".to_string(),
+    ];
+    for (date, info) in forecasts {
+        let Some(day) = date.to_naive() else {
+            continue;
+        };
+        let next_day = day.succ_opt().unwrap_or(day);
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}-{uid_location}@weather-cli", day.format("%Y%m%d")),
+            format!("DTSTAMP:{stamp}"),
+            format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", next_day.format("%Y%m%d")),
+            format!(
+                "SUMMARY:{}",
+                escape(&match info.temperature_range() {
+                    Some((min, max)) => format!(
+                        "{}, {:.0}…{:.0}{}",
+                        info.weather,
+                        units.temperature(min),
+                        units.temperature(max),
+                        units.temperature_unit()
+                    ),
+                    None => format!(
+                        "{}, {:.0}{}",
+                        info.weather,
+                        units.temperature(info.temperature),
+                        units.temperature_unit()
+                    ),
+                })
+            ),
+            format!("LOCATION:{}", escape(address)),
+            format!("DESCRIPTION:{}", escape(&units.display(info).to_string())),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+/// Add iCalendar text escapes
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+/// Split long line into several physical ones, continued with leading space
+fn fold(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            result.push_str("\r\n ");
+            // Leading space counts towards line length
+            octets = 1;
+        }
+        result.push(c);
+        octets += c.len_utf8();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+    use chrono_tz::America::New_York;
+
+    use super::{parse_events, write_forecasts};
+    use crate::date::Date;
+    use crate::provider::{WeatherInfo, WeatherKind};
+    use crate::units::Units;
+
+    fn time(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .unwrap()
+    }
+
+    #[test]
+    fn events_are_parsed() {
+        let events = parse_events(include_str!("../fixtures/ics/events.ics"));
+        let found: Vec<_> = events
+            .iter()
+            .map(|event| (event.summary.as_str(), event.location.as_str()))
+            .collect();
+        // Event without location is skipped, others are sorted by start
+        assert_eq!(
+            found,
+            [
+                ("Festival", "Glastonbury"),
+                ("Standup", "Alexanderplatz, Berlin"),
+                ("Lunch", "Times Square"),
+                (
+                    "Hike with friends who are going to walk all the way up to the summit and back",
+                    "Zugspitze, Garmisch-Partenkirchen"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn start_is_converted_into_local_time() {
+        let events = parse_events(include_str!("../fixtures/ics/events.ics"));
+        let local = |time| Local.from_utc_datetime(&time).naive_local();
+
+        assert!(events[0].all_day);
+        assert_eq!(events[0].start, time(2024, 7, 1, 0, 0));
+        assert!(!events[1].all_day);
+        assert_eq!(events[1].start, local(time(2024, 7, 2, 8, 30)));
+        let lunch = New_York
+            .from_local_datetime(&time(2024, 7, 2, 12, 0))
+            .unwrap()
+            .with_timezone(&Utc)
+            .naive_utc();
+        assert_eq!(events[2].start, local(lunch));
+        // Floating time is already local
+        assert_eq!(events[3].start, time(2024, 7, 3, 7, 0));
+    }
+
+    #[test]
+    fn unknown_timezone_leaves_time_as_is() {
+        let events = parse_events(
+            "BEGIN:VEVENT\nDTSTART;TZID=W. Europe Standard Time:20240702T090000\n\
+             LOCATION:Berlin\nEND:VEVENT\n",
+        );
+        assert_eq!(events[0].start, time(2024, 7, 2, 9, 0));
+    }
+
+    #[test]
+    fn written_forecasts_are_folded_and_read_back() {
+        let info = WeatherInfo {
+            temperature_min: Some(12.0),
+            temperature_max: Some(21.0),
+            wind_speed: 4.0,
+            humidity: 80.0,
+            precipitation: Some(3.5),
+            ..WeatherInfo::sample(WeatherKind::Rain, 18.0)
+        };
+        let address = "Very long address, which surely doesn't fit into single line of calendar";
+        let date: Date = "2024-07-01".parse().unwrap();
+        let text = write_forecasts(address, &[(date, info)], Units::default());
+
+        assert!(text.lines().all(|line| line.len() <= 76), "{text}");
+        assert!(text.contains("DTSTART;VALUE=DATE:20240701\r\n"), "{text}");
+        let events = parse_events(&text);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].location, address);
+        assert!(events[0].all_day);
+    }
+}
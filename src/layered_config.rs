@@ -0,0 +1,301 @@
+//! Effective configuration, built from several layers
+//!
+//! Layers, from lowest to highest priority:
+//! 0. Defaults of optional provider parameters, applied only to configured providers
+//! 1. User's defaults of non-secret provider parameters, `[defaults.<provider>]` config sections,
+//!    also applied only to configured providers
+//! 2. Config file sections
+//! 3. Environment variables `WEATHER_CLI_<PROVIDER>_<PARAMETER>`, e.g. `WEATHER_CLI_OPENWEATHER_APIKEY`;
+//!    these may also come from `.env` file, see `load_env_file`
+//!
+//! Values from environment are never written back to config file
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+
+use crate::config::{Config, Section};
+use crate::provider::{ParamDesc, ParamKind};
+
+/// Prefix of environment variables which override provider parameters
+const ENV_PREFIX: &str = "WEATHER_CLI_";
+/// Env file loaded from current directory if no explicit one is specified
+const DEFAULT_ENV_FILE: &str = ".env";
+
+/// Name of environment variable which overrides provider's parameter
+///
+/// # Parameters
+/// * `provider` - provider's name
+/// * `param` - parameter's identifier
+///
+/// # Returns
+/// Uppercase variable name, with all non-alphanumeric characters replaced by underscores
+pub fn env_name(provider: &str, param: &str) -> String {
+    format!("{ENV_PREFIX}{provider}_{param}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+/// Builds effective provider configuration
+///
+/// # Parameters
+/// * `config` - configuration read from file
+/// * `provider` - provider's name
+/// * `params` - parameters accepted by provider
+///
+/// # Returns
+/// Provider's config section with environment overrides and parameter defaults applied,
+/// or `None` if provider is configured neither in file nor in environment
+pub fn provider_section(config: &Config, provider: &str, params: &[ParamDesc]) -> Option<Section> {
+    let mut section = config.sections.get(provider).cloned();
+    for ParamDesc { id, .. } in params {
+        if let Ok(value) = std::env::var(env_name(provider, id)) {
+            section
+                .get_or_insert_with(Section::new)
+                .insert(id.to_string(), value);
+        }
+    }
+    if let Some(section) = &mut section {
+        apply_config_defaults(config, provider, section, params);
+        apply_defaults(section, params);
+    }
+    section
+}
+/// Fills in parameters which aren't specified from provider's `[defaults.<provider>]` section;
+/// secrets there are ignored, since they belong to provider's own section
+///
+/// # Parameters
+/// * `config` - configuration read from file
+/// * `provider` - provider's name
+/// * `section` - provider's config section
+/// * `params` - parameters accepted by provider
+pub fn apply_config_defaults(
+    config: &Config,
+    provider: &str,
+    section: &mut Section,
+    params: &[ParamDesc],
+) {
+    let Some(defaults) = config.defaults.get(provider) else {
+        return;
+    };
+    for (key, value) in defaults {
+        let secret = params
+            .iter()
+            .any(|param| param.id == key && matches!(param.kind, ParamKind::Secret));
+        if secret {
+            tracing::warn!("Ignoring secret '{key}' in [defaults.{provider}]; configure it with 'weather configure {provider}'");
+            continue;
+        }
+        section.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+}
+/// Fills in defaults of parameters which aren't specified
+///
+/// # Parameters
+/// * `section` - provider's config section
+/// * `params` - parameters accepted by provider
+pub fn apply_defaults(section: &mut Section, params: &[ParamDesc]) {
+    for ParamDesc { id, default, .. } in params {
+        if let Some(default) = default {
+            section
+                .entry(id.to_string())
+                .or_insert_with(|| default.to_string());
+        }
+    }
+}
+/// Loads variables from env file into process environment
+///
+/// File consists of `NAME=value` lines, optionally prefixed with `export`;
+/// empty lines and lines starting with `#` are ignored, values may be quoted.
+/// Variables already present in environment are not overridden,
+/// so explicitly exported values always win.
+///
+/// # Parameters
+/// * `path` - explicit env file path; if not specified, `.env` in current directory
+///   is loaded if it exists
+pub fn load_env_file(path: Option<PathBuf>) -> anyhow::Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(DEFAULT_ENV_FILE).is_file() => PathBuf::from(DEFAULT_ENV_FILE),
+        None => return Ok(()),
+    };
+    let contents = fs::read_to_string(&path)
+        .with_context(|| anyhow!("When reading env file '{}'", path.display()))?;
+    let variables = parse_env_file(&contents)
+        .with_context(|| anyhow!("When reading env file '{}'", path.display()))?;
+
+    for (name, value) in variables {
+        if std::env::var_os(name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+    Ok(())
+}
+/// Parses env file's contents
+///
+/// # Parameters
+/// * `contents` - text of env file
+///
+/// # Returns
+/// Variables' names and values, with quotes removed, in order of appearance,
+/// or error if there's line which is neither entry nor comment
+fn parse_env_file(contents: &str) -> anyhow::Result<Vec<(&str, &str)>> {
+    let mut variables = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Line {} isn't '<name>=<value>' entry", number + 1))?;
+        let (name, value) = (name.trim(), value.trim());
+        let value = ['"', '\'']
+            .into_iter()
+            .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(value);
+        variables.push((name, value));
+    }
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{env_name, load_env_file, parse_env_file, provider_section};
+    use crate::config::Config;
+    use crate::provider::{ParamDesc, ParamKind};
+
+    const PARAMS: [ParamDesc; 3] = [
+        ParamDesc {
+            id: "apikey",
+            name: "API key",
+            description: "",
+            required: true,
+            default: None,
+            kind: ParamKind::Secret,
+        },
+        ParamDesc {
+            id: "lang",
+            name: "Language",
+            description: "",
+            required: false,
+            default: Some("en"),
+            kind: ParamKind::String,
+        },
+        ParamDesc {
+            id: "units",
+            name: "Units",
+            description: "",
+            required: false,
+            default: Some("metric"),
+            kind: ParamKind::String,
+        },
+    ];
+
+    #[test]
+    fn env_name_is_uppercase_with_underscores() {
+        assert_eq!(
+            env_name("openweather", "apikey"),
+            "WEATHER_CLI_OPENWEATHER_APIKEY"
+        );
+        assert_eq!(
+            env_name("my-provider", "base.url"),
+            "WEATHER_CLI_MY_PROVIDER_BASE_URL"
+        );
+    }
+
+    #[test]
+    fn environment_overrides_file_and_defaults() {
+        // Provider names are unique to each test, since environment is shared by whole process
+        std::env::set_var(env_name("layered1", "apikey"), "from-env");
+        let config = Config::from_str(
+            "[layered1]\napikey = from-file\nlang = de\n\n\
+             [defaults.layered1]\nlang = fr\nunits = imperial\napikey = from-defaults\n",
+        )
+        .unwrap();
+        let section = provider_section(&config, "layered1", &PARAMS).unwrap();
+        assert_eq!(section["apikey"], "from-env");
+        // Own section wins over user's defaults, which win over parameter's default
+        assert_eq!(section["lang"], "de");
+        assert_eq!(section["units"], "imperial");
+    }
+
+    #[test]
+    fn environment_alone_configures_provider() {
+        let config = Config::from_str("[defaults.layered2]\napikey = from-defaults\n").unwrap();
+        assert!(provider_section(&config, "layered2", &PARAMS).is_none());
+
+        std::env::set_var(env_name("layered2", "apikey"), "from-env");
+        let section = provider_section(&config, "layered2", &PARAMS).unwrap();
+        assert_eq!(section["apikey"], "from-env");
+        assert_eq!(section["lang"], "en");
+        assert_eq!(section["units"], "metric");
+    }
+
+    #[test]
+    fn secrets_are_not_taken_from_defaults() {
+        let config =
+            Config::from_str("[layered3]\nlang = de\n\n[defaults.layered3]\napikey = shared\n")
+                .unwrap();
+        let section = provider_section(&config, "layered3", &PARAMS).unwrap();
+        assert!(!section.contains_key("apikey"));
+    }
+
+    #[test]
+    fn env_file_entries_are_unquoted() {
+        let variables = parse_env_file(
+            "# Provider keys\n\
+             WEATHER_CLI_OPENWEATHER_APIKEY=plain\n\
+             export WEATHER_CLI_WEATHERAPI_APIKEY = \"double quoted\"\n\
+             \n\
+             NAME='single # quoted'\n\
+             EMPTY=\n\
+             HALF=\"unbalanced\n",
+        )
+        .unwrap();
+        assert_eq!(
+            variables,
+            [
+                ("WEATHER_CLI_OPENWEATHER_APIKEY", "plain"),
+                ("WEATHER_CLI_WEATHERAPI_APIKEY", "double quoted"),
+                ("NAME", "single # quoted"),
+                ("EMPTY", ""),
+                ("HALF", "\"unbalanced"),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_env_file_line_is_reported() {
+        let error = parse_env_file("A=1\nnot an entry\n").unwrap_err();
+        assert_eq!(error.to_string(), "Line 2 isn't '<name>=<value>' entry");
+    }
+
+    #[test]
+    fn env_file_does_not_override_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.env");
+        std::fs::write(
+            &path,
+            "ENV_FILE_TEST_PRESET=from-file\nexport ENV_FILE_TEST_NEW='from-file'\n",
+        )
+        .unwrap();
+        std::env::set_var("ENV_FILE_TEST_PRESET", "from-environment");
+        load_env_file(Some(path)).unwrap();
+        assert_eq!(
+            std::env::var("ENV_FILE_TEST_PRESET").unwrap(),
+            "from-environment"
+        );
+        assert_eq!(std::env::var("ENV_FILE_TEST_NEW").unwrap(), "from-file");
+        assert!(load_env_file(Some(dir.path().join("missing.env"))).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! Diagnostic logging and output verbosity
+//!
+//! Diagnostics, like HTTP requests, cache hits and provider selection, are logged with `tracing`
+//! to stderr; `-v` and `-vv` reveal them. Quiet mode hides everything but data and errors.
+//! Known secrets are masked in every log record. Spans, like provider calls and HTTP requests,
+//! may also be written into trace file, along with their durations
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::chrome_trace::{self, TraceGuard};
+use crate::redact::RedactingWriter;
+use crate::terminal;
+
+/// Whether non-data output is suppressed
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Installs logger; should be called once, before anything is logged
+///
+/// # Parameters
+/// * `verbosity` - number of `-v` flags; warnings are logged by default,
+///   informational messages with one flag, debug ones with two and everything with more
+/// * `quiet` - suppress warnings and status messages
+/// * `trace_output` - file where spans are written in Chrome's Trace Event format
+///
+/// # Returns
+/// Guard which writes trace file when dropped, if it's requested
+pub fn init(verbosity: u8, quiet: bool, trace_output: Option<PathBuf>) -> Option<TraceGuard> {
+    QUIET.store(quiet, Ordering::Relaxed);
+    let level = match (quiet, verbosity) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(RedactingWriter::stderr)
+        .with_ansi(terminal::ansi())
+        // Timestamps and module paths are noise unless user digs into details
+        .without_time()
+        .with_target(verbosity >= 2)
+        .with_filter(level);
+    let (trace, guard) = trace_output.map(chrome_trace::layer).unzip();
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(trace)
+        .init();
+    guard
+}
+/// Whether non-data output is suppressed
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+/// Prints status message, which isn't part of command's data, unless quiet mode is on
+///
+/// # Parameters
+/// * `message` - message to print
+pub fn status(message: impl Display) {
+    if !quiet() {
+        println!("{message}");
+    }
+}
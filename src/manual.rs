@@ -0,0 +1,240 @@
+//! Manual generation: man pages and Markdown help, built from command-line definition
+//! along with documentation of providers' parameters, taken from provider registry,
+//! so manual covers external providers and plugins installed on user's machine as well
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use clap::Command;
+use roff::{bold, italic, roman, Roff};
+
+use crate::layered_config;
+use crate::provider::{ParamDesc, ProviderInfo};
+use crate::provider_registry::ProviderRegistry;
+
+/// Renders application's man page, with section describing providers
+///
+/// # Parameters
+/// * `cmd` - application's command
+/// * `registry` - registered providers
+///
+/// # Returns
+/// Man page in ROFF format
+pub fn man_page(cmd: Command, registry: &ProviderRegistry) -> anyhow::Result<Vec<u8>> {
+    let man = clap_mangen::Man::new(cmd.clone());
+    let mut page = Vec::new();
+    man.render_title(&mut page)?;
+    man.render_name_section(&mut page)?;
+    man.render_synopsis_section(&mut page)?;
+    man.render_description_section(&mut page)?;
+    man.render_options_section(&mut page)?;
+    man.render_subcommands_section(&mut page)?;
+    providers_section(registry).to_writer(&mut page)?;
+    if cmd.get_version().is_some() {
+        man.render_version_section(&mut page)?;
+    }
+    // Each section is rendered with same preamble, which defines apostrophe; it's needed only once
+    let page = String::from_utf8(page)?;
+    let preamble: String = page.split_inclusive('\n').take(2).collect();
+    let body = page[preamble.len()..].replace(&preamble, "");
+    Ok(format!("{preamble}{body}").into_bytes())
+}
+/// Writes man pages of application and each of its subcommands into directory
+///
+/// # Parameters
+/// * `cmd` - application's command
+/// * `registry` - registered providers
+/// * `dir` - output directory, created if missing
+pub fn write_man_pages(
+    cmd: Command,
+    registry: &ProviderRegistry,
+    dir: &Path,
+) -> anyhow::Result<()> {
+    let context = || anyhow!("When writing man pages into '{}'", dir.display());
+    std::fs::create_dir_all(dir).with_context(context)?;
+    clap_mangen::generate_to(cmd.clone(), dir).with_context(context)?;
+    // Main page is replaced with one which documents providers too
+    let name = format!("{}.1", cmd.get_name());
+    std::fs::write(dir.join(name), man_page(cmd, registry)?).with_context(context)?;
+    Ok(())
+}
+/// PROVIDERS section of man page
+fn providers_section(registry: &ProviderRegistry) -> Roff {
+    let mut roff = Roff::new();
+    roff.control("SH", ["PROVIDERS"]);
+    roff.text([roman(
+        "Providers are configured with 'configure' command; each parameter can also be \
+         specified with environment variable.",
+    )]);
+    for (id, factory) in registry.iter() {
+        let info = factory.info();
+        roff.control("SS", [id.as_ref()]);
+        roff.text([roman(info.description)]);
+        for capability in capabilities(info) {
+            roff.control("br", []);
+            roff.text([roman(capability)]);
+        }
+        for param in info.params {
+            roff.control("TP", []);
+            roff.text([
+                bold(param.id),
+                roman(" ("),
+                italic(param.kind.id()),
+                roman(")"),
+            ]);
+            roff.text([roman(param_text(param))]);
+            roff.control("br", []);
+            roff.text([
+                roman("Environment variable: "),
+                bold(layered_config::env_name(id, param.id)),
+            ]);
+        }
+    }
+    roff
+}
+/// Renders application's help in Markdown, with every command and section describing providers
+///
+/// # Parameters
+/// * `cmd` - application's command
+/// * `registry` - registered providers
+pub fn markdown(mut cmd: Command, registry: &ProviderRegistry) -> String {
+    cmd.build();
+    let mut text = String::new();
+    command_markdown(&mut text, &cmd, &[], 1);
+    text.push_str("## Providers\n\n");
+    for (id, factory) in registry.iter() {
+        let info = factory.info();
+        let _ = writeln!(text, "### {id}\n\n{}\n", info.description);
+        let capabilities = capabilities(info);
+        for capability in &capabilities {
+            let _ = writeln!(text, "{capability}  ");
+        }
+        if !capabilities.is_empty() {
+            text.push('\n');
+        }
+        if !info.params.is_empty() {
+            text.push_str("| Parameter | Kind | Description | Environment variable |\n");
+            text.push_str("|---|---|---|---|\n");
+            for param in info.params {
+                let _ = writeln!(
+                    text,
+                    "| `{}` | {} | {} | `{}` |",
+                    param.id,
+                    param.kind.id(),
+                    escape_cell(&param_text(param)),
+                    layered_config::env_name(id, param.id)
+                );
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+/// Appends Markdown help of command and its subcommands
+///
+/// # Parameters
+/// * `text` - output
+/// * `cmd` - command
+/// * `parents` - names of enclosing commands, starting from application's one
+/// * `level` - heading level of command
+fn command_markdown(text: &mut String, cmd: &Command, parents: &[&str], level: usize) {
+    let mut path = parents.to_vec();
+    path.push(cmd.get_name());
+    let _ = writeln!(text, "{} `{}`\n", "#".repeat(level), path.join(" "));
+    if let Some(about) = cmd.get_long_about().or(cmd.get_about()) {
+        let _ = writeln!(text, "{about}\n");
+    }
+    let usage = cmd.clone().render_usage().to_string();
+    let _ = writeln!(
+        text,
+        "```text\n{}\n```\n",
+        usage.trim_start_matches("Usage: ")
+    );
+    let args: Vec<_> = cmd
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && (level == 1 || !arg.is_global_set()))
+        .collect();
+    if !args.is_empty() {
+        for arg in args {
+            let mut name = match (arg.get_short(), arg.get_long()) {
+                (Some(short), Some(long)) => format!("-{short}, --{long}"),
+                (None, Some(long)) => format!("--{long}"),
+                (Some(short), None) => format!("-{short}"),
+                (None, None) => String::new(),
+            };
+            if arg.get_action().takes_values() {
+                let values = arg
+                    .get_value_names()
+                    .map(|names| names.join(" "))
+                    .unwrap_or_else(|| arg.get_id().as_str().to_uppercase());
+                if !name.is_empty() {
+                    name.push(' ');
+                }
+                name.push_str(&format!("<{values}>"));
+            }
+            let help = arg
+                .get_long_help()
+                .or(arg.get_help())
+                .map(|help| help.to_string().replace('\n', " "))
+                .unwrap_or_default();
+            let _ = write!(text, "* `{name}` - {help}");
+            // Flags have implicit defaults, which tell nothing
+            if !arg.get_action().takes_values() {
+                text.push('\n');
+                continue;
+            }
+            let choices: Vec<_> = arg
+                .get_possible_values()
+                .into_iter()
+                .filter(|value| !value.is_hide_set())
+                .map(|value| format!("`{}`", value.get_name()))
+                .collect();
+            if !choices.is_empty() {
+                let _ = write!(text, "; one of {}", choices.join(", "));
+            }
+            let defaults: Vec<_> = arg
+                .get_default_values()
+                .iter()
+                .map(|value| value.to_string_lossy())
+                .collect();
+            if !defaults.is_empty() {
+                let _ = write!(text, " (default: `{}`)", defaults.join(" "));
+            }
+            text.push('\n');
+        }
+        text.push('\n');
+    }
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        if sub.get_name() != "help" {
+            command_markdown(text, sub, &path, (level + 1).min(6));
+        }
+    }
+}
+/// Provider's capabilities, as sentences
+fn capabilities(info: &ProviderInfo) -> Vec<&'static str> {
+    let mut capabilities = Vec::new();
+    if info.hourly {
+        capabilities.push("Provides conditions at specific hour of day.");
+    }
+    if info.lightning {
+        capabilities.push("Reports lightning strikes.");
+    }
+    capabilities
+}
+/// Parameter's description, along with whether it's required and its default
+fn param_text(param: &ParamDesc) -> String {
+    let mut text = format!("{}, {}", param.name, param.description);
+    match (param.required, param.default) {
+        (true, _) => text.push_str("; required"),
+        (false, Some(default)) => text.push_str(&format!("; optional, default '{default}'")),
+        (false, None) => text.push_str("; optional"),
+    }
+    if let crate::provider::ParamKind::Choice(choices) = param.kind {
+        text.push_str(&format!("; one of {}", choices.join(", ")));
+    }
+    text
+}
+/// Escapes text put into Markdown table cell
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
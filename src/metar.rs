@@ -0,0 +1,565 @@
+//! Aviation weather reports: METAR observations and TAF forecasts of airports,
+//! fetched from aviationweather.gov and decoded into readable form
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::api_url::ApiUrl;
+use crate::utils::restful_get;
+
+/// Data API of Aviation Weather Center
+const API_URL: &str = "https://aviationweather.gov/api/data";
+/// Meters per second in knot
+const KNOT_MS: f32 = 0.514_444;
+/// Hectopascals in inch of mercury
+const INHG_HPA: f32 = 33.8639;
+
+/// Kind of aviation weather report
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReportKind {
+    /// Routine or special observation
+    Metar,
+    /// Terminal aerodrome forecast
+    Taf,
+}
+
+impl Display for ReportKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReportKind::Metar => "METAR",
+            ReportKind::Taf => "TAF",
+        })
+    }
+}
+
+/// Reports as served, in raw format
+struct RawReports(String);
+
+impl FromStr for RawReports {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_string()))
+    }
+}
+
+/// API serves failures as plain text
+#[derive(Debug, thiserror::Error)]
+#[error("Aviation Weather Center error: {0}")]
+struct ApiError(String);
+
+impl FromStr for ApiError {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.trim().to_string()))
+    }
+}
+/// Fetches latest report of airport
+///
+/// # Parameters
+/// * `kind` - kind of report
+/// * `station` - ICAO code of airport, like "KJFK"
+///
+/// # Returns
+/// Raw report, as single line
+pub async fn latest(kind: ReportKind, station: &str) -> anyhow::Result<String> {
+    let station = station.trim().to_ascii_uppercase();
+    if station.len() != 4 || !station.chars().all(|c| c.is_ascii_alphanumeric()) {
+        bail!(
+            "'{station}' isn't ICAO airport code, which consists of 4 letters or digits, like KJFK"
+        );
+    }
+    let url = ApiUrl::new(API_URL)
+        .segment(kind.to_string().to_ascii_lowercase())
+        .query("ids", &station)
+        .query("format", "raw");
+    let reports = restful_get::<RawReports, ApiError>(url)
+        .await
+        .with_context(|| anyhow!("Could not obtain {kind} of {station}"))?;
+    // METAR takes single line, while TAF's change groups are put on separate lines
+    let report = match kind {
+        ReportKind::Metar => reports.0.lines().find(|line| !line.trim().is_empty()),
+        ReportKind::Taf => reports
+            .0
+            .split("\n\n")
+            .find(|report| !report.trim().is_empty()),
+    };
+    report
+        .map(|report| report.split_whitespace().collect::<Vec<_>>().join(" "))
+        .ok_or_else(|| anyhow!("No {kind} of {station} available"))
+}
+/// Decodes report into readable lines; lines of forecast's change groups are indented
+///
+/// Groups which aren't recognized are reported as not decoded, remarks are shown as is
+///
+/// # Parameters
+/// * `kind` - kind of report, which tells meaning of its time
+/// * `report` - raw report
+pub fn decode(kind: ReportKind, report: &str) -> Vec<String> {
+    let mut tokens = report.split_whitespace().peekable();
+    let mut lines = Vec::new();
+    let mut indent = "";
+    let mut station_seen = false;
+    while let Some(token) = tokens.next() {
+        let text = match token {
+            "METAR" | "TAF" => continue,
+            "SPECI" => "Special report, issued as conditions changed".to_string(),
+            "AMD" => "Amended forecast".to_string(),
+            "COR" => "Corrected report".to_string(),
+            "AUTO" => "Automated observation".to_string(),
+            "NIL" => "Report is missing".to_string(),
+            "CAVOK" => {
+                "Ceiling and visibility OK: visibility 10 km or more, no significant clouds or weather"
+                    .to_string()
+            }
+            "NSW" => "Weather: no significant weather".to_string(),
+            "NOSIG" => "No significant change expected".to_string(),
+            "SKC" | "CLR" => "Sky: clear".to_string(),
+            "NSC" => "Sky: no significant clouds".to_string(),
+            "NCD" => "Sky: no clouds detected".to_string(),
+            "$" => "Station needs maintenance".to_string(),
+            "RMK" => {
+                let remarks: Vec<_> = tokens.by_ref().collect();
+                lines.push(format!("{indent}Remarks: {}", remarks.join(" ")));
+                break;
+            }
+            "TEMPO" | "BECMG" => {
+                indent = "  ";
+                let times = tokens.next_if(|token| period(token).is_some());
+                lines.push(change(token, None, times));
+                continue;
+            }
+            _ if token.starts_with("PROB") => {
+                indent = "  ";
+                let tempo = tokens.next_if(|token| *token == "TEMPO");
+                let times = tokens.next_if(|token| period(token).is_some());
+                lines.push(change(tempo.unwrap_or_default(), token.get(4..), times));
+                continue;
+            }
+            _ if token.starts_with("FM") && token.len() == 8 => match day_time(&token[2..]) {
+                Some(time) => {
+                    indent = "  ";
+                    lines.push(format!("From {time}:"));
+                    continue;
+                }
+                None => format!("Not decoded: {token}"),
+            },
+            _ if !station_seen && is_station(token) => {
+                station_seen = true;
+                format!("Station: {token}")
+            }
+            _ => {
+                // Fractional visibility may follow whole miles, like "1 1/2SM"
+                let merged;
+                let token = match tokens.next_if(|next| {
+                    token.bytes().all(|b| b.is_ascii_digit()) && next.ends_with("SM")
+                }) {
+                    Some(fraction) => {
+                        merged = format!("{token} {fraction}");
+                        merged.as_str()
+                    }
+                    None => token,
+                };
+                group(kind, token).unwrap_or_else(|| format!("Not decoded: {token}"))
+            }
+        };
+        lines.push(format!("{indent}{text}"));
+    }
+    lines
+}
+/// Whether token is ICAO airport code
+fn is_station(token: &str) -> bool {
+    token.len() == 4
+        && token.starts_with(|c: char| c.is_ascii_uppercase())
+        && token
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+/// Heading of forecast's change group
+///
+/// # Parameters
+/// * `kind` - "TEMPO", "BECMG" or empty for probability alone
+/// * `probability` - probability percents, for "PROB" groups
+/// * `times` - period of change, like "1312/1316"
+fn change(kind: &str, probability: Option<&str>, times: Option<&str>) -> String {
+    let mut text = match kind {
+        "TEMPO" => "Temporarily".to_string(),
+        "BECMG" => "Becoming".to_string(),
+        _ => String::new(),
+    };
+    if let Some(probability) = probability {
+        if text.is_empty() {
+            text = format!("With {probability}% probability");
+        } else {
+            text = format!("{text}, with {probability}% probability");
+        }
+    }
+    if let Some((from, to)) = times.and_then(period) {
+        text = format!("{text}, from {from} to {to}");
+    }
+    format!("{text}:")
+}
+/// Decodes group which describes weather
+///
+/// # Parameters
+/// * `kind` - kind of report
+/// * `token` - group
+fn group(kind: ReportKind, token: &str) -> Option<String> {
+    if let Some(time) = token.strip_suffix('Z').filter(|time| time.len() == 6) {
+        let label = match kind {
+            ReportKind::Metar => "Observed",
+            ReportKind::Taf => "Issued",
+        };
+        return day_time(time).map(|time| format!("{label}: {time}"));
+    }
+    if let Some((from, to)) = period(token) {
+        return Some(format!("Valid: from {from} to {to}"));
+    }
+    wind(token)
+        .or_else(|| wind_variation(token))
+        .or_else(|| visibility(token))
+        .or_else(|| runway_range(token))
+        .or_else(|| clouds(token))
+        .or_else(|| temperatures(token))
+        .or_else(|| pressure(token))
+        .or_else(|| extreme_temperature(token))
+        .or_else(|| weather(token).map(|weather| format!("Weather: {weather}")))
+}
+/// Decodes day of month and time, like "121851" or "1218"
+fn day_time(text: &str) -> Option<String> {
+    if !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (day, hour, minute) = match text.len() {
+        6 => (&text[..2], &text[2..4], &text[4..]),
+        4 => (&text[..2], &text[2..], "00"),
+        _ => return None,
+    };
+    Some(format!("day {day}, {hour}:{minute} UTC"))
+}
+/// Decodes forecast period, like "1218/1324"
+fn period(token: &str) -> Option<(String, String)> {
+    let (from, to) = token.split_once('/')?;
+    if from.len() != 4 || to.len() != 4 {
+        return None;
+    }
+    Some((day_time(from)?, day_time(to)?))
+}
+/// Decodes wind, like "18010G20KT", "VRB03KT" or "00000KT"
+fn wind(token: &str) -> Option<String> {
+    let (speeds, unit, to_ms) = [
+        ("KT", "kt", KNOT_MS),
+        ("MPS", "m/s", 1.0),
+        ("KMH", "km/h", 1.0 / 3.6),
+    ]
+    .into_iter()
+    .find_map(|(suffix, unit, to_ms)| Some((token.strip_suffix(suffix)?, unit, to_ms)))?;
+    let direction = speeds.get(..3)?;
+    let (speed, gust) = match speeds[3..].split_once('G') {
+        Some((speed, gust)) => (speed, Some(gust.parse::<u32>().ok()?)),
+        None => (&speeds[3..], None),
+    };
+    let speed: u32 = speed.parse().ok()?;
+    let mut text = match direction {
+        "000" if speed == 0 => return Some("Wind: calm".to_string()),
+        "VRB" => "Wind: variable".to_string(),
+        _ => format!("Wind: from {}°", direction.parse::<u32>().ok()?),
+    };
+    text.push_str(&format!(" at {speed} {unit}"));
+    if unit != "m/s" {
+        text.push_str(&format!(" ({:.0} m/s)", speed as f32 * to_ms));
+    }
+    if let Some(gust) = gust {
+        text.push_str(&format!(", gusting {gust} {unit}"));
+    }
+    Some(text)
+}
+/// Decodes variation of wind direction, like "180V240"
+fn wind_variation(token: &str) -> Option<String> {
+    let (from, to) = token.split_once('V')?;
+    let from: u32 = from.parse().ok().filter(|_| from.len() == 3)?;
+    let to: u32 = to.parse().ok().filter(|_| to.len() == 3)?;
+    Some(format!("Wind direction: varying from {from}° to {to}°"))
+}
+/// Decodes prevailing visibility, like "9999", "0800", "10SM", "P6SM" or "1 1/2SM"
+fn visibility(token: &str) -> Option<String> {
+    if let Some(miles) = token.strip_suffix("SM") {
+        let (prefix, miles) = match miles.as_bytes().first()? {
+            b'P' => ("more than ", &miles[1..]),
+            b'M' => ("less than ", &miles[1..]),
+            _ => ("", miles),
+        };
+        let valid = miles
+            .split([' ', '/'])
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+        if !valid {
+            return None;
+        }
+        let unit = if miles == "1" { "mile" } else { "miles" };
+        return Some(format!("Visibility: {prefix}{miles} statute {unit}"));
+    }
+    if token.len() != 4 || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(match token.parse::<u32>().ok()? {
+        9999 => "Visibility: 10 km or more".to_string(),
+        0 => "Visibility: less than 50 m".to_string(),
+        meters => format!("Visibility: {meters} m"),
+    })
+}
+/// Decodes runway visual range, like "R04/1200FT", "R22L/P6000FT" or "R09/0800V1200/U"
+fn runway_range(token: &str) -> Option<String> {
+    let (runway, range) = token.strip_prefix('R')?.split_once('/')?;
+    if !runway.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let (range, trend) = match range.split_once('/') {
+        Some((range, "U")) => (range, ", increasing"),
+        Some((range, "D")) => (range, ", decreasing"),
+        Some((range, _)) => (range, ""),
+        None => (range, ""),
+    };
+    let (range, unit) = match range.strip_suffix("FT") {
+        Some(range) => (range, "ft"),
+        None => (range, "m"),
+    };
+    let distance = |value: &str| {
+        let (prefix, value) = match value.as_bytes().first()? {
+            b'P' => ("more than ", &value[1..]),
+            b'M' => ("less than ", &value[1..]),
+            _ => ("", value),
+        };
+        let value: u32 = value.parse().ok()?;
+        Some(format!("{prefix}{value} {unit}"))
+    };
+    let range = match range.split_once('V') {
+        Some((from, to)) => format!("from {} to {}", distance(from)?, distance(to)?),
+        None => distance(range)?,
+    };
+    Some(format!("Runway {runway} visual range: {range}{trend}"))
+}
+/// Decodes cloud layer, like "BKN025CB", or vertical visibility, like "VV002"
+fn clouds(token: &str) -> Option<String> {
+    let height = |text: &str| match text {
+        "///" => Some("unknown height".to_string()),
+        _ => text
+            .parse::<u32>()
+            .ok()
+            .filter(|_| text.len() == 3)
+            .map(|height| format!("{} ft", height * 100)),
+    };
+    if let Some(vertical) = token.strip_prefix("VV") {
+        return Some(format!(
+            "Sky obscured, vertical visibility {}",
+            height(vertical)?
+        ));
+    }
+    let cover = match token.get(..3)? {
+        "FEW" => "few",
+        "SCT" => "scattered",
+        "BKN" => "broken",
+        "OVC" => "overcast",
+        _ => return None,
+    };
+    let kind = match token.get(6..)? {
+        "" | "///" => "",
+        "CB" => ", cumulonimbus",
+        "TCU" => ", towering cumulus",
+        _ => return None,
+    };
+    Some(format!(
+        "Clouds: {cover} at {}{kind}",
+        height(token.get(3..6)?)?
+    ))
+}
+/// Decodes temperature, possibly negative like "M05"
+fn temperature(text: &str) -> Option<i32> {
+    let (sign, value) = match text.strip_prefix('M') {
+        Some(value) => (-1, value),
+        None => (1, text),
+    };
+    (value.len() == 2)
+        .then(|| value.parse::<i32>().ok())
+        .flatten()
+        .map(|value| sign * value)
+}
+/// Decodes temperature and dew point, like "24/12" or "M05/M10"
+fn temperatures(token: &str) -> Option<String> {
+    let (air, dew) = token.split_once('/')?;
+    let air = temperature(air)?;
+    Some(match temperature(dew) {
+        Some(dew) => format!("Temperature: {air}°C, dew point {dew}°C"),
+        None if dew.is_empty() => format!("Temperature: {air}°C"),
+        None => return None,
+    })
+}
+/// Decodes forecast's maximal or minimal temperature, like "TX25/1215Z"
+fn extreme_temperature(token: &str) -> Option<String> {
+    let (label, rest) = match token.get(..2)? {
+        "TX" => ("Maximal temperature", &token[2..]),
+        "TN" => ("Minimal temperature", &token[2..]),
+        _ => return None,
+    };
+    let (value, time) = rest.split_once('/')?;
+    let time = day_time(time.strip_suffix('Z')?)?;
+    Some(format!("{label}: {}°C, {time}", temperature(value)?))
+}
+/// Decodes altimeter setting, like "Q1013" in hectopascals or "A3002" in hundredths of inches of mercury
+fn pressure(token: &str) -> Option<String> {
+    let value = token.get(1..).filter(|value| value.len() == 4)?;
+    let value: u32 = value.parse().ok()?;
+    match token.as_bytes()[0] {
+        b'Q' => Some(format!("Pressure: {value} hPa")),
+        b'A' => {
+            let inches = value as f32 / 100.0;
+            Some(format!(
+                "Pressure: {inches:.2} inHg ({:.0} hPa)",
+                inches * INHG_HPA
+            ))
+        }
+        _ => None,
+    }
+}
+/// Decodes present or forecast weather, like "-RA", "+TSRA" or "VCSH"
+///
+/// # Returns
+/// Description, like "heavy thunderstorm with rain", or `None` if token isn't weather group
+fn weather(token: &str) -> Option<String> {
+    let (intensity, codes) = match token.as_bytes().first()? {
+        b'-' => (Some("light"), &token[1..]),
+        b'+' => (Some("heavy"), &token[1..]),
+        _ => (None, token),
+    };
+    let (vicinity, codes) = match codes.strip_prefix("VC") {
+        Some(codes) => (true, codes),
+        None => (false, codes),
+    };
+    if codes.is_empty() || codes.len() % 2 != 0 {
+        return None;
+    }
+    let mut descriptor = None;
+    let mut phenomena = Vec::new();
+    for index in (0..codes.len()).step_by(2) {
+        let code = codes.get(index..index + 2)?;
+        let descriptor_name = match code {
+            "MI" => Some("shallow"),
+            "PR" => Some("partial"),
+            "BC" => Some("patches of"),
+            "DR" => Some("low drifting"),
+            "BL" => Some("blowing"),
+            "SH" => Some("showers of"),
+            "TS" => Some("thunderstorm"),
+            "FZ" => Some("freezing"),
+            _ => None,
+        };
+        if let Some(name) = descriptor_name {
+            if descriptor.is_some() || !phenomena.is_empty() {
+                return None;
+            }
+            descriptor = Some((code, name));
+            continue;
+        }
+        phenomena.push(match code {
+            "DZ" => "drizzle",
+            "RA" => "rain",
+            "SN" => "snow",
+            "SG" => "snow grains",
+            "IC" => "ice crystals",
+            "PL" => "ice pellets",
+            "GR" => "hail",
+            "GS" => "small hail",
+            "UP" => "unknown precipitation",
+            "BR" => "mist",
+            "FG" => "fog",
+            "FU" => "smoke",
+            "VA" => "volcanic ash",
+            "DU" => "dust",
+            "SA" => "sand",
+            "HZ" => "haze",
+            "PY" => "spray",
+            "PO" => "dust whirls",
+            "SQ" => "squalls",
+            "FC" => "funnel cloud",
+            "SS" => "sandstorm",
+            "DS" => "duststorm",
+            _ => return None,
+        });
+    }
+    let phenomena = phenomena.join(" and ");
+    let mut text = match descriptor {
+        Some(("TS", name)) if phenomena.is_empty() => name.to_string(),
+        Some(("TS", name)) => format!("{name} with {phenomena}"),
+        Some(("SH", _)) if phenomena.is_empty() => "showers".to_string(),
+        Some((_, _)) if phenomena.is_empty() => return None,
+        Some((_, name)) => format!("{name} {phenomena}"),
+        None => phenomena,
+    };
+    if let Some(intensity) = intensity {
+        text = format!("{intensity} {text}");
+    }
+    if vicinity {
+        text.push_str(" in vicinity");
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metar_is_decoded() {
+        let lines = decode(
+            ReportKind::Metar,
+            "METAR KJFK 121851Z 18010G20KT 150V210 1 1/2SM R04R/P6000FT -TSRA BR BKN025CB OVC100 M02/M05 A3002 RMK AO2 SLP167",
+        );
+        assert_eq!(
+            lines,
+            [
+                "Station: KJFK",
+                "Observed: day 12, 18:51 UTC",
+                "Wind: from 180° at 10 kt (5 m/s), gusting 20 kt",
+                "Wind direction: varying from 150° to 210°",
+                "Visibility: 1 1/2 statute miles",
+                "Runway 04R visual range: more than 6000 ft",
+                "Weather: light thunderstorm with rain",
+                "Weather: mist",
+                "Clouds: broken at 2500 ft, cumulonimbus",
+                "Clouds: overcast at 10000 ft",
+                "Temperature: -2°C, dew point -5°C",
+                "Pressure: 30.02 inHg (1017 hPa)",
+                "Remarks: AO2 SLP167",
+            ]
+        );
+    }
+
+    #[test]
+    fn taf_change_groups_are_nested() {
+        let lines = decode(
+            ReportKind::Taf,
+            "TAF EGLL 121100Z 1212/1318 24010KT 9999 SCT030 TEMPO 1214/1218 +SHRA PROB30 TEMPO 1300/1304 0800 FG FM130600 VRB03KT CAVOK XYZ",
+        );
+        assert_eq!(
+            lines,
+            [
+                "Station: EGLL",
+                "Issued: day 12, 11:00 UTC",
+                "Valid: from day 12, 12:00 UTC to day 13, 18:00 UTC",
+                "Wind: from 240° at 10 kt (5 m/s)",
+                "Visibility: 10 km or more",
+                "Clouds: scattered at 3000 ft",
+                "Temporarily, from day 12, 14:00 UTC to day 12, 18:00 UTC:",
+                "  Weather: heavy showers of rain",
+                "Temporarily, with 30% probability, from day 13, 00:00 UTC to day 13, 04:00 UTC:",
+                "  Visibility: 800 m",
+                "  Weather: fog",
+                "From day 13, 06:00 UTC:",
+                "  Wind: variable at 3 kt (2 m/s)",
+                "  Ceiling and visibility OK: visibility 10 km or more, no significant clouds or weather",
+                "  Not decoded: XYZ",
+            ]
+        );
+    }
+}
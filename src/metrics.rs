@@ -0,0 +1,136 @@
+//! Prometheus metrics with current weather at configured locations
+//!
+//! Metrics are exposed by `serve` command at `/metrics`, in Prometheus text format,
+//! and are refreshed periodically rather than on each scrape, to save provider's quota
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::condition_mapping::MappedProvider;
+use crate::provider::WeatherInfo;
+
+/// Gets gauge's value out of weather info, if it's present
+type GaugeValue = fn(&WeatherInfo) -> Option<f32>;
+
+/// Exported gauges: name, help text and value getter
+const GAUGES: &[(&str, &str, GaugeValue)] = &[
+    (
+        "weather_temperature_celsius",
+        "Temperature, in Celsius degrees",
+        |info| Some(info.temperature),
+    ),
+    (
+        "weather_humidity_percent",
+        "Relative humidity, in percents",
+        |info| Some(info.humidity),
+    ),
+    (
+        "weather_wind_speed_meters_per_second",
+        "Wind speed, in m/s",
+        |info| Some(info.wind_speed),
+    ),
+    (
+        "weather_precipitation_millimeters",
+        "Precipitation amount, in millimeters",
+        |info| info.precipitation,
+    ),
+];
+
+/// Current weather at set of locations, periodically refreshed
+pub struct Metrics {
+    /// Name of provider used to obtain weather
+    provider: String,
+    /// Locations for which weather is obtained
+    locations: Vec<String>,
+    /// How often weather is refreshed
+    interval: Duration,
+    /// Latest weather per location, `None` if last refresh failed
+    samples: Mutex<BTreeMap<String, Option<WeatherInfo>>>,
+}
+
+impl Metrics {
+    /// Creates metrics without samples; they appear after first refresh
+    ///
+    /// # Parameters
+    /// * `provider` - name of provider used to obtain weather
+    /// * `locations` - locations for which weather is obtained
+    /// * `interval` - how often weather is refreshed
+    pub fn new(provider: String, locations: Vec<String>, interval: Duration) -> Self {
+        Self {
+            provider,
+            locations,
+            interval,
+            samples: Mutex::default(),
+        }
+    }
+    /// Name of provider used to obtain weather
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+    /// How often weather is refreshed
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+    /// Obtains current weather for all locations; failures are reported and exported as `weather_up` 0
+    pub async fn refresh(&self, provider: &MappedProvider) {
+        for location in &self.locations {
+            let info = match provider.get_weather(location.clone().into(), None).await {
+                Ok(info) => Some(info),
+                Err(error) => {
+                    tracing::warn!("Could not refresh metrics for '{location}': {error:#}");
+                    None
+                }
+            };
+            self.samples().insert(location.clone(), info);
+        }
+    }
+    /// Latest samples; they stay usable even if some refresh panicked while holding them
+    fn samples(&self) -> MutexGuard<'_, BTreeMap<String, Option<WeatherInfo>>> {
+        self.samples
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+    /// Renders latest samples in Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let samples = self.samples();
+        let mut text = String::new();
+        let labels = |location: &str| {
+            format!(
+                "{{location=\"{}\",provider=\"{}\"}}",
+                escape_label(location),
+                escape_label(&self.provider)
+            )
+        };
+
+        let _ = writeln!(
+            text,
+            "# HELP weather_up Whether last weather refresh succeeded\n# TYPE weather_up gauge"
+        );
+        for (location, info) in samples.iter() {
+            let _ = writeln!(
+                text,
+                "weather_up{} {}",
+                labels(location),
+                u8::from(info.is_some())
+            );
+        }
+
+        for (name, help, value) in GAUGES {
+            let _ = writeln!(text, "# HELP {name} {help}\n# TYPE {name} gauge");
+            for (location, info) in samples.iter() {
+                if let Some(value) = info.as_ref().and_then(value) {
+                    let _ = writeln!(text, "{name}{} {value}", labels(location));
+                }
+            }
+        }
+        text
+    }
+}
+/// Escapes label value, as required by Prometheus text format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,557 @@
+//! Normalization of providers' responses: condition codes into weather kinds
+//! and units into metric ones used by `WeatherInfo`
+//!
+//! Mappings are shared by built-in providers and are available to external ones,
+//! which may report `condition_code` along with its `condition_scheme` instead of weather kind.
+//! Every mapping is checked against common set of semantic test vectors, so same weather
+//! is classified same way whichever provider reports it
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::provider::WeatherKind;
+
+/// Converts speed from km/h into m/s
+pub fn kmh_to_ms(speed: f32) -> f32 {
+    speed / 3.6
+}
+
+/// Vocabulary of condition codes, named after provider which defines it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodeScheme {
+    /// Condition ids, see https://openweathermap.org/weather-conditions
+    OpenWeather,
+    /// Condition codes, see https://www.weatherapi.com/docs/weather_conditions.json
+    WeatherApi,
+    /// Weather icon numbers, see https://developer.accuweather.com/weather-icons
+    AccuWeather,
+    /// Weather symbols, see https://www.meteomatics.com/en/api/available-parameters/weather-parameter/general-weather-state/
+    Meteomatics,
+    /// Coded weather, "[coverage]:[intensity]:[weather]",
+    /// see https://www.xweather.com/docs/weather-api/reference/weather-codes
+    Xweather,
+    /// Dark Sky icon names, extended by Pirate Weather,
+    /// see https://pirateweather.net/en/latest/API/#icon
+    PirateWeather,
+    /// Weather condition codes of station observations,
+    /// see https://dev.meteostat.net/formats.html#weather-condition-codes
+    Meteostat,
+    /// WMO present weather codes of SYNOP reports, 00-99, used by national weather services
+    /// like Deutscher Wetterdienst, see WMO code table 4677
+    Synop,
+    /// Icon codes of Environment Canada's citypage weather, 00-48; night icons are 30 and above,
+    /// see https://eccc-msc.github.io/open-data/msc-data/citypage-weather/readme_citypageweather-datamart_en/
+    EnvCanada,
+}
+
+impl CodeScheme {
+    /// All schemes
+    pub const ALL: [CodeScheme; 9] = [
+        CodeScheme::OpenWeather,
+        CodeScheme::WeatherApi,
+        CodeScheme::AccuWeather,
+        CodeScheme::Meteomatics,
+        CodeScheme::Xweather,
+        CodeScheme::PirateWeather,
+        CodeScheme::Meteostat,
+        CodeScheme::Synop,
+        CodeScheme::EnvCanada,
+    ];
+
+    /// Scheme's identifier, as used in external providers' replies
+    pub fn id(&self) -> &'static str {
+        match self {
+            CodeScheme::OpenWeather => "openweather",
+            CodeScheme::WeatherApi => "weatherapi",
+            CodeScheme::AccuWeather => "accuweather",
+            CodeScheme::Meteomatics => "meteomatics",
+            CodeScheme::Xweather => "xweather",
+            CodeScheme::PirateWeather => "pirateweather",
+            CodeScheme::Meteostat => "meteostat",
+            CodeScheme::Synop => "synop",
+            CodeScheme::EnvCanada => "envcanada",
+        }
+    }
+    /// Maps condition code to weather kind; codes which aren't valid in scheme are unknown weather
+    ///
+    /// # Parameters
+    /// * `code` - condition code, as text
+    pub fn kind(&self, code: &str) -> WeatherKind {
+        let numeric = || code.trim().parse::<u32>().ok();
+        let kind = match self {
+            CodeScheme::OpenWeather => numeric().map(openweather_kind),
+            CodeScheme::WeatherApi => numeric().map(weatherapi_kind),
+            CodeScheme::AccuWeather => numeric().map(accuweather_kind),
+            CodeScheme::Meteomatics => numeric().map(meteomatics_kind),
+            CodeScheme::Xweather => Some(xweather_kind(code.trim())),
+            CodeScheme::PirateWeather => Some(pirateweather_kind(code.trim())),
+            CodeScheme::Meteostat => numeric().map(meteostat_kind),
+            CodeScheme::Synop => numeric().map(synop_kind),
+            CodeScheme::EnvCanada => numeric().map(envcanada_kind),
+        };
+        kind.unwrap_or(WeatherKind::Unknown)
+    }
+}
+
+impl FromStr for CodeScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        CodeScheme::ALL
+            .into_iter()
+            .find(|scheme| scheme.id().eq_ignore_ascii_case(s))
+            .ok_or_else(|| anyhow!("Unknown condition code scheme '{s}'"))
+    }
+}
+
+impl Display for CodeScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+/// Maps OpenWeather condition id to weather kind
+pub fn openweather_kind(id: u32) -> WeatherKind {
+    match id {
+        200..=299 => WeatherKind::Thunderstorm,
+        300..=399 => WeatherKind::Drizzle,
+        511 | 611..=616 => WeatherKind::Sleet,
+        500..=599 => WeatherKind::Rain,
+        600..=699 => WeatherKind::Snow,
+        800 => WeatherKind::Clear,
+        801..=809 => WeatherKind::Clouds,
+        771 | 781 => WeatherKind::Windy,
+        700..=799 => WeatherKind::Fog,
+        _ => WeatherKind::Unknown,
+    }
+}
+/// Maps WeatherAPI condition code to weather kind
+pub fn weatherapi_kind(code: u32) -> WeatherKind {
+    match code {
+        1000 => WeatherKind::Clear,
+        1003 | 1006 | 1009 => WeatherKind::Clouds,
+        1030 | 1135 | 1147 => WeatherKind::Fog,
+        1087 | 1273 | 1276 | 1279 | 1282 => WeatherKind::Thunderstorm,
+        1072 | 1150 | 1153 | 1168 | 1171 => WeatherKind::Drizzle,
+        1063 | 1180 | 1183 | 1186 | 1189 | 1192 | 1195 | 1240 | 1243 | 1246 => WeatherKind::Rain,
+        1069 | 1198 | 1201 | 1204 | 1207 | 1249 | 1252 => WeatherKind::Sleet,
+        1237 | 1261 | 1264 => WeatherKind::Hail,
+        1066 | 1114 | 1117 | 1210 | 1213 | 1216 | 1219 | 1222 | 1225 | 1255 | 1258 => {
+            WeatherKind::Snow
+        }
+        _ => WeatherKind::Unknown,
+    }
+}
+/// Maps AccuWeather weather icon number to weather kind
+pub fn accuweather_kind(icon: u32) -> WeatherKind {
+    match icon {
+        1 | 2 | 30 | 31 | 33 | 34 => WeatherKind::Clear,
+        3..=8 | 35..=38 => WeatherKind::Clouds,
+        11 => WeatherKind::Fog,
+        15..=17 | 41 | 42 => WeatherKind::Thunderstorm,
+        12..=14 | 18 | 39 | 40 => WeatherKind::Rain,
+        24..=26 | 29 => WeatherKind::Sleet,
+        19..=23 | 43 | 44 => WeatherKind::Snow,
+        32 => WeatherKind::Windy,
+        _ => WeatherKind::Unknown,
+    }
+}
+/// Maps Meteomatics weather symbol to weather kind; night symbols are day ones plus 100
+pub fn meteomatics_kind(symbol: u32) -> WeatherKind {
+    match symbol % 100 {
+        1 => WeatherKind::Clear,
+        2..=4 => WeatherKind::Clouds,
+        5 | 8 => WeatherKind::Rain,
+        6 | 10 | 13 => WeatherKind::Sleet,
+        7 | 9 => WeatherKind::Snow,
+        11 | 12 => WeatherKind::Fog,
+        14 => WeatherKind::Thunderstorm,
+        15 => WeatherKind::Drizzle,
+        16 => WeatherKind::Windy,
+        _ => WeatherKind::Unknown,
+    }
+}
+/// Maps Xweather coded weather to weather kind, by its weather part
+pub fn xweather_kind(coded: &str) -> WeatherKind {
+    match coded.rsplit(':').next().unwrap_or_default() {
+        "CL" | "FW" => WeatherKind::Clear,
+        "SC" | "BK" | "OV" => WeatherKind::Clouds,
+        "F" | "BR" | "H" | "IF" | "ZF" | "K" => WeatherKind::Fog,
+        "R" | "RW" => WeatherKind::Rain,
+        "L" | "ZL" => WeatherKind::Drizzle,
+        "T" => WeatherKind::Thunderstorm,
+        "ZR" | "SI" | "WM" | "RS" => WeatherKind::Sleet,
+        "A" | "IP" => WeatherKind::Hail,
+        "BD" | "BN" | "BY" => WeatherKind::Windy,
+        "S" | "SW" | "BS" => WeatherKind::Snow,
+        _ => WeatherKind::Unknown,
+    }
+}
+/// Maps Pirate Weather icon name to weather kind; intensity and time of day variants,
+/// like "light-rain" or "partly-cloudy-night", are same kinds as base icons
+pub fn pirateweather_kind(icon: &str) -> WeatherKind {
+    let icon = icon.trim_end_matches("-day").trim_end_matches("-night");
+    let icon = ["very-light-", "light-", "heavy-", "possible-"]
+        .into_iter()
+        .find_map(|prefix| icon.strip_prefix(prefix))
+        .unwrap_or(icon);
+    match icon {
+        "clear" | "mostly-clear" => WeatherKind::Clear,
+        "partly-cloudy" | "mostly-cloudy" | "cloudy" => WeatherKind::Clouds,
+        "fog" | "mist" | "haze" | "smoke" => WeatherKind::Fog,
+        "rain" | "precipitation" => WeatherKind::Rain,
+        "drizzle" => WeatherKind::Drizzle,
+        "thunderstorm" => WeatherKind::Thunderstorm,
+        "sleet" | "freezing-rain" | "freezing-drizzle" | "mixed" => WeatherKind::Sleet,
+        "hail" => WeatherKind::Hail,
+        "snow" | "flurries" => WeatherKind::Snow,
+        "wind" | "breezy" | "dangerous-wind" => WeatherKind::Windy,
+        _ => WeatherKind::Unknown,
+    }
+}
+/// Maps Meteostat weather condition code to weather kind
+pub fn meteostat_kind(code: u32) -> WeatherKind {
+    match code {
+        1 | 2 => WeatherKind::Clear,
+        3 | 4 => WeatherKind::Clouds,
+        5 | 6 => WeatherKind::Fog,
+        7..=9 | 17 | 18 => WeatherKind::Rain,
+        10..=13 | 19 | 20 => WeatherKind::Sleet,
+        14..=16 | 21 | 22 => WeatherKind::Snow,
+        23 | 25 | 26 => WeatherKind::Thunderstorm,
+        24 => WeatherKind::Hail,
+        27 => WeatherKind::Windy,
+        _ => WeatherKind::Unknown,
+    }
+}
+/// Maps WMO present weather code of SYNOP report to weather kind
+pub fn synop_kind(ww: u32) -> WeatherKind {
+    match ww {
+        0 => WeatherKind::Clear,
+        1..=3 => WeatherKind::Clouds,
+        13 | 17 | 29 | 91..=99 => WeatherKind::Thunderstorm,
+        7..=9 | 18 | 19 | 30..=35 => WeatherKind::Windy,
+        4..=6 | 10..=12 | 28 | 40..=49 => WeatherKind::Fog,
+        20 | 50..=55 => WeatherKind::Drizzle,
+        14..=16 | 21 | 25 | 58..=65 | 80..=82 => WeatherKind::Rain,
+        23 | 24 | 56 | 57 | 66..=69 | 83 | 84 => WeatherKind::Sleet,
+        27 | 79 | 87..=90 => WeatherKind::Hail,
+        22 | 26 | 36..=39 | 70..=78 | 85 | 86 => WeatherKind::Snow,
+        _ => WeatherKind::Unknown,
+    }
+}
+/// Maps Environment Canada icon code to weather kind
+pub fn envcanada_kind(icon: u32) -> WeatherKind {
+    match icon {
+        0 | 1 | 30 | 31 => WeatherKind::Clear,
+        2..=5 | 10 | 22 | 32..=35 => WeatherKind::Clouds,
+        6 | 11..=13 | 36 => WeatherKind::Rain,
+        28 => WeatherKind::Drizzle,
+        9 | 19 | 39 | 46 | 47 => WeatherKind::Thunderstorm,
+        7 | 14 | 15 | 37 => WeatherKind::Sleet,
+        8 | 16..=18 | 25 | 26 | 38 | 40 => WeatherKind::Snow,
+        27 => WeatherKind::Hail,
+        23 | 24 | 44 => WeatherKind::Fog,
+        41..=43 | 45 | 48 => WeatherKind::Windy,
+        _ => WeatherKind::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Weather phenomenon, with its code in each scheme which has one
+    struct Vector {
+        phenomenon: &'static str,
+        kind: WeatherKind,
+        codes: &'static [(CodeScheme, &'static str)],
+    }
+
+    use CodeScheme::{
+        AccuWeather, EnvCanada, Meteomatics, Meteostat, OpenWeather, PirateWeather, Synop,
+        WeatherApi, Xweather,
+    };
+
+    const VECTORS: &[Vector] = &[
+        Vector {
+            phenomenon: "clear sky by day",
+            kind: WeatherKind::Clear,
+            codes: &[
+                (OpenWeather, "800"),
+                (WeatherApi, "1000"),
+                (AccuWeather, "1"),
+                (Meteomatics, "1"),
+                (Xweather, "::CL"),
+                (PirateWeather, "clear-day"),
+                (Meteostat, "1"),
+                (Synop, "0"),
+                (EnvCanada, "0"),
+            ],
+        },
+        Vector {
+            phenomenon: "clear sky at night",
+            kind: WeatherKind::Clear,
+            codes: &[
+                (AccuWeather, "33"),
+                (Meteomatics, "101"),
+                (PirateWeather, "clear-night"),
+                (EnvCanada, "30"),
+            ],
+        },
+        Vector {
+            phenomenon: "partly cloudy",
+            kind: WeatherKind::Clouds,
+            codes: &[
+                (OpenWeather, "802"),
+                (WeatherApi, "1003"),
+                (AccuWeather, "3"),
+                (Meteomatics, "2"),
+                (Xweather, "::SC"),
+                (PirateWeather, "partly-cloudy-day"),
+                (Meteostat, "3"),
+                (Synop, "3"),
+                (EnvCanada, "2"),
+            ],
+        },
+        Vector {
+            phenomenon: "overcast",
+            kind: WeatherKind::Clouds,
+            codes: &[
+                (OpenWeather, "804"),
+                (WeatherApi, "1009"),
+                (AccuWeather, "7"),
+                (Meteomatics, "4"),
+                (Xweather, "::OV"),
+                (PirateWeather, "cloudy"),
+                (Meteostat, "4"),
+                (EnvCanada, "10"),
+            ],
+        },
+        Vector {
+            phenomenon: "fog",
+            kind: WeatherKind::Fog,
+            codes: &[
+                (OpenWeather, "741"),
+                (WeatherApi, "1135"),
+                (AccuWeather, "11"),
+                (Meteomatics, "11"),
+                (Xweather, "::F"),
+                (PirateWeather, "fog"),
+                (Meteostat, "5"),
+                (Synop, "45"),
+                (EnvCanada, "24"),
+            ],
+        },
+        Vector {
+            phenomenon: "light rain",
+            kind: WeatherKind::Rain,
+            codes: &[
+                (OpenWeather, "500"),
+                (WeatherApi, "1183"),
+                (AccuWeather, "12"),
+                (Meteomatics, "5"),
+                (Xweather, ":L:R"),
+                (PirateWeather, "light-rain"),
+                (Meteostat, "7"),
+                (Synop, "61"),
+                (EnvCanada, "12"),
+            ],
+        },
+        Vector {
+            phenomenon: "drizzle",
+            kind: WeatherKind::Drizzle,
+            codes: &[
+                (OpenWeather, "300"),
+                (WeatherApi, "1153"),
+                (Meteomatics, "15"),
+                (Xweather, "::L"),
+                (PirateWeather, "drizzle"),
+                (Synop, "51"),
+                (EnvCanada, "28"),
+            ],
+        },
+        Vector {
+            phenomenon: "thunderstorm",
+            kind: WeatherKind::Thunderstorm,
+            codes: &[
+                (OpenWeather, "211"),
+                (WeatherApi, "1276"),
+                (AccuWeather, "15"),
+                (Meteomatics, "14"),
+                (Xweather, "::T"),
+                (PirateWeather, "thunderstorm"),
+                (Meteostat, "25"),
+                (Synop, "95"),
+                (EnvCanada, "19"),
+            ],
+        },
+        Vector {
+            phenomenon: "snow",
+            kind: WeatherKind::Snow,
+            codes: &[
+                (OpenWeather, "601"),
+                (WeatherApi, "1219"),
+                (AccuWeather, "22"),
+                (Meteomatics, "7"),
+                (Xweather, "::S"),
+                (PirateWeather, "snow"),
+                (Meteostat, "15"),
+                (Synop, "73"),
+                (EnvCanada, "17"),
+            ],
+        },
+        Vector {
+            phenomenon: "sleet",
+            kind: WeatherKind::Sleet,
+            codes: &[
+                (OpenWeather, "611"),
+                (WeatherApi, "1069"),
+                (AccuWeather, "29"),
+                (Meteomatics, "6"),
+                (Xweather, "::RS"),
+                (PirateWeather, "sleet"),
+                (Meteostat, "12"),
+                (Synop, "68"),
+                (EnvCanada, "15"),
+            ],
+        },
+        Vector {
+            phenomenon: "freezing rain",
+            kind: WeatherKind::Sleet,
+            codes: &[
+                (OpenWeather, "511"),
+                (WeatherApi, "1201"),
+                (AccuWeather, "26"),
+                (Meteomatics, "13"),
+                (Xweather, "::ZR"),
+                (PirateWeather, "freezing-rain"),
+                (Meteostat, "10"),
+                (Synop, "66"),
+                (EnvCanada, "14"),
+            ],
+        },
+        Vector {
+            phenomenon: "hail",
+            kind: WeatherKind::Hail,
+            codes: &[
+                (WeatherApi, "1237"),
+                (Xweather, "::A"),
+                (PirateWeather, "hail"),
+                (Meteostat, "24"),
+                (Synop, "89"),
+                (EnvCanada, "27"),
+            ],
+        },
+        Vector {
+            phenomenon: "strong wind",
+            kind: WeatherKind::Windy,
+            codes: &[
+                (OpenWeather, "771"),
+                (AccuWeather, "32"),
+                (Meteomatics, "16"),
+                (Xweather, "::BD"),
+                (PirateWeather, "wind"),
+                (Meteostat, "27"),
+                (Synop, "18"),
+                (EnvCanada, "43"),
+            ],
+        },
+        Vector {
+            phenomenon: "code scheme doesn't define",
+            kind: WeatherKind::Unknown,
+            codes: &[
+                (OpenWeather, "999"),
+                (WeatherApi, "42"),
+                (AccuWeather, "0"),
+                (Meteomatics, "0"),
+                (Xweather, "::XX"),
+                (PirateWeather, "tornado"),
+                (Meteostat, "28"),
+                (Synop, "100"),
+                (EnvCanada, "29"),
+            ],
+        },
+        Vector {
+            phenomenon: "malformed code",
+            kind: WeatherKind::Unknown,
+            codes: &[
+                (OpenWeather, "rain"),
+                (WeatherApi, ""),
+                (AccuWeather, "-1"),
+                (Meteomatics, "1.5"),
+                (PirateWeather, ""),
+                (Meteostat, "fair"),
+                (Synop, "-5"),
+                (EnvCanada, "sunny"),
+            ],
+        },
+    ];
+
+    #[test]
+    fn condition_codes_match_vectors() {
+        for vector in VECTORS {
+            for (scheme, code) in vector.codes {
+                assert_eq!(
+                    scheme.kind(code),
+                    vector.kind,
+                    "{scheme} code '{code}' should mean {} ({})",
+                    vector.kind,
+                    vector.phenomenon
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_scheme_is_covered_by_common_kinds() {
+        let kinds = [
+            WeatherKind::Clear,
+            WeatherKind::Clouds,
+            WeatherKind::Fog,
+            WeatherKind::Rain,
+            WeatherKind::Snow,
+            WeatherKind::Thunderstorm,
+            WeatherKind::Sleet,
+            WeatherKind::Unknown,
+        ];
+        for scheme in CodeScheme::ALL {
+            for kind in kinds {
+                assert!(
+                    VECTORS.iter().any(|vector| vector.kind == kind
+                        && vector.codes.iter().any(|(other, _)| *other == scheme)),
+                    "No vector checks {kind} in {scheme} scheme"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn schemes_are_parsed_by_id() {
+        for scheme in CodeScheme::ALL {
+            assert_eq!(scheme.id().parse::<CodeScheme>().ok(), Some(scheme));
+            assert_eq!(
+                scheme.id().to_uppercase().parse::<CodeScheme>().ok(),
+                Some(scheme)
+            );
+        }
+        assert!("wmo".parse::<CodeScheme>().is_err());
+    }
+
+    #[test]
+    fn speeds_are_converted_to_metric() {
+        for (kmh, ms) in [
+            (0.0, 0.0),
+            (3.6, 1.0),
+            (18.0, 5.0),
+            (36.0, 10.0),
+            (100.0, 27.78),
+        ] {
+            assert!(
+                (kmh_to_ms(kmh) - ms).abs() < 0.01,
+                "{kmh} km/h should be {ms} m/s"
+            );
+        }
+    }
+}
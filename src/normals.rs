@@ -0,0 +1,149 @@
+//! Climate normals: weather typical for calendar day, averaged over same day of previous years,
+//! which forecasts are compared with, like "3°C above normal"
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use anyhow::bail;
+use chrono::NaiveDate;
+
+use crate::aggregate::{self, Aggregate, Statistic};
+use crate::condition_mapping::MappedProvider;
+use crate::date::Date;
+use crate::provider::WeatherInfo;
+use crate::redact;
+use crate::units::Units;
+
+/// Temperature difference, in shown units, within which weather is considered normal
+const NEAR_NORMAL: f32 = 0.5;
+
+/// Same calendar day, with same hour if any, in each of previous years;
+/// February 29 becomes February 28 in non-leap years
+///
+/// # Parameters
+/// * `date` - day to look back from
+/// * `years` - number of previous years
+///
+/// # Returns
+/// Days, from most recent one
+pub fn past_days(date: Date, years: u32) -> Vec<Date> {
+    (1..=years)
+        .filter_map(|back| {
+            let year = i32::from(date.year).checked_sub(back as i32)?;
+            let day = NaiveDate::from_ymd_opt(year, date.month.into(), date.day.into())
+                .or_else(|| NaiveDate::from_ymd_opt(year, date.month.into(), 28))?;
+            Some(Date::from(day).with_hour(date.hour))
+        })
+        .collect()
+}
+/// Obtains weather of same day in previous years, concurrently, and averages it;
+/// days which fail are skipped
+///
+/// # Parameters
+/// * `provider` - provider with historical data
+/// * `location` - location of requests
+/// * `date` - day whose normal is obtained
+/// * `years` - number of previous years
+///
+/// # Returns
+/// Averaged weather, or `None` if no year was obtained
+pub async fn normal(
+    provider: Arc<MappedProvider>,
+    location: &str,
+    date: Date,
+    years: u32,
+) -> anyhow::Result<Option<Aggregate>> {
+    // Providers tell upfront which past days they can serve
+    let days: Vec<_> = past_days(date, years)
+        .into_iter()
+        .filter(|day| provider.plan_weather(location, Some(*day)).is_ok())
+        .collect();
+    if days.is_empty() {
+        bail!("Provider doesn't provide historical data for days of {date} in previous years");
+    }
+    let tasks: Vec<_> = days
+        .into_iter()
+        .map(|day| {
+            let (provider, location) = (provider.clone(), location.to_string());
+            (
+                day,
+                tokio::spawn(async move { provider.get_weather(location.into(), Some(day)).await }),
+            )
+        })
+        .collect();
+    let mut history = Vec::new();
+    for (day, task) in tasks {
+        match task.await? {
+            Ok(info) => history.push((day, info)),
+            Err(error) => tracing::warn!(
+                "Skipping {day} in climate normal: {}",
+                redact::redact(&format!("{error:#}"))
+            ),
+        }
+    }
+    Ok(aggregate::aggregate(&history, Statistic::Mean))
+}
+/// Describes how forecast's temperature differs from normal, like "3°C above normal"
+///
+/// # Parameters
+/// * `info` - forecast
+/// * `normal` - averaged weather of same day in previous years
+/// * `units` - units in which difference is shown
+pub fn describe(info: &WeatherInfo, normal: &Aggregate, units: Units) -> String {
+    let difference = units.temperature(info.temperature) - units.temperature(normal.temperature);
+    let unit = units.temperature_unit();
+    if difference.abs() < NEAR_NORMAL {
+        "near normal".to_string()
+    } else if difference > 0.0 {
+        format!("{difference:.0}{unit} above normal")
+    } else {
+        format!("{:.0}{unit} below normal", -difference)
+    }
+}
+/// Renders comparison of forecasts with their normals, one line per day
+///
+/// # Parameters
+/// * `forecasts` - forecasts along with dates they're for
+/// * `normals` - normal of each forecast's day, if it was obtained
+/// * `units` - units in which values are shown
+pub fn render(
+    forecasts: &[(Date, WeatherInfo)],
+    normals: &[Option<Aggregate>],
+    units: Units,
+) -> String {
+    let mut text = String::from("Compared to normal:\n");
+    for ((date, info), normal) in forecasts.iter().zip(normals) {
+        let Some(normal) = normal else {
+            continue;
+        };
+        let _ = writeln!(
+            text,
+            "  {}: {} ({:.1}{} on average over {} years)",
+            date.formatted(),
+            describe(info, normal, units),
+            units.temperature(normal.temperature),
+            units.temperature_unit(),
+            normal.days
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::past_days;
+    use crate::date::Date;
+
+    #[test]
+    fn leap_day_falls_back_to_february_28() {
+        let days: Vec<_> = past_days(Date::from_str("2024-02-29").unwrap(), 4)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            days,
+            ["2023-02-28", "2022-02-28", "2021-02-28", "2020-02-29"]
+        );
+    }
+}
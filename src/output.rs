@@ -0,0 +1,31 @@
+//! Output formats supported by commands which print forecasts or listings
+
+/// How forecast is presented to user
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Plain text lines; multi-day forecasts are followed by charts
+    #[default]
+    Text,
+    /// Boxed summary card with condition art, one per day
+    Card,
+    /// iCalendar file with one all-day event per day, for importing into calendar apps
+    Ics,
+    /// Newline-delimited JSON, one object per day, written as soon as day's forecast is obtained
+    Ndjson,
+    /// GeoJSON feature collection with one point feature per day at location's coordinates,
+    /// for mapping tools
+    Geojson,
+    /// Single line with temperature, trend of following hours as sparkline and weather kind,
+    /// for status bars
+    Oneline,
+}
+
+/// How listings, like one of providers, are presented
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum ListFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON object, for wrapper UIs and completion scripts
+    Json,
+}
@@ -0,0 +1,309 @@
+//! Dashboard of current weather at all profiles' locations, the "home screen" for multi-location users
+//!
+//! Weather of all places is fetched concurrently; results are cached on disk
+//! for a short time, so repeated runs don't spend provider's quota.
+//! Cache is also filled in advance by 'prefetch' command, and read by 'get --max-age'
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+#[cfg(not(feature = "sqlite"))]
+use crate::app_dirs;
+use crate::clock;
+use crate::condition::Condition;
+use crate::condition_mapping::MappedProvider;
+use crate::provider::{WeatherInfo, WeatherKind};
+use crate::redact;
+#[cfg(feature = "sqlite")]
+use crate::store;
+use crate::units::Units;
+
+/// How long cached weather is kept; older one is dropped when cache is written
+const RETENTION_DAYS: i64 = 1;
+
+/// Place shown on dashboard
+pub struct Place {
+    /// Place's name, which is name of profile
+    pub name: String,
+    /// Place's location
+    pub location: String,
+    /// Name of provider used for place
+    pub provider: String,
+    /// Condition which marks place with alert badge
+    pub alert: Option<Condition>,
+}
+
+/// Cached weather
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    fetched: DateTime<Utc>,
+    info: WeatherInfo,
+}
+
+/// Weather obtained recently, keyed by provider and location
+#[derive(Default, Serialize, Deserialize)]
+struct Cache(BTreeMap<String, CacheEntry>);
+
+impl Cache {
+    /// Path of cache file, if OS has notion of cache directory
+    #[cfg(not(feature = "sqlite"))]
+    fn path() -> Option<PathBuf> {
+        app_dirs::cache_dir().map(|path| path.join("places.json"))
+    }
+    /// Path of database which keeps cache
+    #[cfg(feature = "sqlite")]
+    fn path() -> Option<PathBuf> {
+        store::path()
+    }
+    /// Reads cache; missing or broken cache is just empty one
+    #[cfg(not(feature = "sqlite"))]
+    fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+    /// Reads cache from database; broken database is reported and treated as empty cache
+    #[cfg(feature = "sqlite")]
+    fn load() -> Self {
+        let rows = store::cache_rows().unwrap_or_else(|error| {
+            tracing::warn!("Could not read places cache: {error:#}");
+            Vec::new()
+        });
+        Self(
+            rows.into_iter()
+                .map(|row| {
+                    let entry = CacheEntry {
+                        fetched: row.fetched,
+                        info: row.info,
+                    };
+                    (Self::key(&row.provider, &row.location), entry)
+                })
+                .collect(),
+        )
+    }
+    /// Writes cache, dropping entries older than `RETENTION_DAYS`
+    fn save(mut self) -> anyhow::Result<()> {
+        let now = clock::now().with_timezone(&Utc);
+        self.0
+            .retain(|_, entry| now - entry.fetched < Duration::days(RETENTION_DAYS));
+        self.write()
+    }
+    /// Writes cache file
+    #[cfg(not(feature = "sqlite"))]
+    fn write(self) -> anyhow::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string(&self)?)
+            .with_context(|| anyhow!("When writing places cache to {}", path.display()))
+    }
+    /// Replaces cache in database
+    #[cfg(feature = "sqlite")]
+    fn write(self) -> anyhow::Result<()> {
+        let rows: Vec<_> = self
+            .0
+            .into_iter()
+            // Keys are made by `key`, so they always have both parts
+            .filter_map(|(key, entry)| {
+                let (provider, location) = key.split_once('\t')?;
+                Some(store::CacheRow {
+                    provider: provider.to_string(),
+                    location: location.to_string(),
+                    fetched: entry.fetched,
+                    info: entry.info,
+                })
+            })
+            .collect();
+        store::replace_cache(&rows).with_context(|| anyhow!("When writing places cache"))
+    }
+
+    fn key(provider: &str, location: &str) -> String {
+        format!("{provider}\t{location}")
+    }
+}
+/// Statistics of cached weather
+pub struct CacheStats {
+    /// Cache file, or database which keeps cache
+    pub path: PathBuf,
+    /// Number of cached entries
+    pub entries: usize,
+    /// Size of cache file or database, in bytes
+    pub bytes: u64,
+    /// When most recent entry was fetched
+    pub newest: Option<DateTime<Utc>>,
+}
+/// Collects statistics of places cache
+///
+/// # Returns
+/// Statistics, or `None` if OS has no notion of cache directory
+pub fn cache_stats() -> Option<CacheStats> {
+    let path = Cache::path()?;
+    let bytes = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+    let cache = Cache::load();
+    Some(CacheStats {
+        entries: cache.0.len(),
+        newest: cache.0.values().map(|entry| entry.fetched).max(),
+        path,
+        bytes,
+    })
+}
+/// Current weather at location obtained recently, by 'places' or 'prefetch' command
+///
+/// # Parameters
+/// * `provider` - name of provider which obtained weather
+/// * `location` - location, with alias expanded
+/// * `max_age` - how old weather may be
+///
+/// # Returns
+/// Weather along with time it was obtained, or `None` if there's no such fresh weather
+pub fn cached(
+    provider: &str,
+    location: &str,
+    max_age: Duration,
+) -> Option<(DateTime<Utc>, WeatherInfo)> {
+    let entry = Cache::load().0.remove(&Cache::key(provider, location))?;
+    let now = clock::now().with_timezone(&Utc);
+    (now - entry.fetched < max_age).then_some((entry.fetched, entry.info))
+}
+/// Obtains current weather at each place, concurrently, reusing cached weather
+///
+/// # Parameters
+/// * `places` - places to obtain weather for
+/// * `providers` - instantiated providers, by name; each place's provider should be there
+/// * `max_age` - how long cached weather is reused; zero refreshes all places
+/// * `jobs` - how many places are fetched at once
+///
+/// # Returns
+/// Weather or error description for each place, in same order as places
+pub async fn fetch(
+    places: &[Place],
+    providers: &BTreeMap<String, Arc<MappedProvider>>,
+    max_age: Duration,
+    jobs: usize,
+) -> anyhow::Result<Vec<Result<WeatherInfo, String>>> {
+    anyhow::ensure!(jobs > 0, "Number of concurrent fetches should be positive");
+    let mut cache = Cache::load();
+    let now = clock::now().with_timezone(&Utc);
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(jobs));
+
+    let tasks: Vec<_> = places
+        .iter()
+        .map(|place| {
+            let key = Cache::key(&place.provider, &place.location);
+            // Stale entry stays cached, in case refreshing it fails
+            match cache.0.get(&key) {
+                Some(entry) if now - entry.fetched < max_age => {
+                    tracing::info_span!("place", name = place.name, cache_hit = true)
+                        .in_scope(|| tracing::debug!("Cache hit for place '{}'", place.name));
+                    Err(Box::new(entry.clone()))
+                }
+                _ => {
+                    let provider = providers.get(&place.provider).cloned();
+                    let location = place.location.clone();
+                    let semaphore = semaphore.clone();
+                    let span = tracing::info_span!("place", name = place.name, cache_hit = false);
+                    Ok(tokio::spawn(
+                        async move {
+                            let provider =
+                                provider.ok_or_else(|| anyhow!("Provider isn't configured"))?;
+                            let _permit = semaphore.acquire().await?;
+                            provider.get_weather(location.into(), None).await
+                        }
+                        .instrument(span),
+                    ))
+                }
+            }
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for (place, task) in places.iter().zip(tasks) {
+        let key = Cache::key(&place.provider, &place.location);
+        let (fetched, info) = match task {
+            Err(entry) => (entry.fetched, entry.info),
+            Ok(task) => match task.await? {
+                Ok(info) => (clock::now().with_timezone(&Utc), info),
+                Err(error) => {
+                    results.push(Err(redact::redact(&format!("{error:#}"))));
+                    continue;
+                }
+            },
+        };
+        cache.0.insert(
+            key,
+            CacheEntry {
+                fetched,
+                info: info.clone(),
+            },
+        );
+        results.push(Ok(info));
+    }
+
+    cache.save()?;
+    Ok(results)
+}
+/// Renders dashboard as table, one place per line
+///
+/// # Parameters
+/// * `places` - places shown
+/// * `results` - weather or error for each place
+/// * `units` - units in which temperature is shown
+pub fn render(places: &[Place], results: &[Result<WeatherInfo, String>], units: Units) -> String {
+    let name_width = places.iter().map(|place| place.name.chars().count()).max();
+    let location_width = places
+        .iter()
+        .map(|place| place.location.chars().count())
+        .max();
+    let (name_width, location_width) = (name_width.unwrap_or(0), location_width.unwrap_or(0));
+
+    let mut text = String::new();
+    for (place, result) in places.iter().zip(results) {
+        let row = match result {
+            Ok(info) => {
+                let badge = match &place.alert {
+                    Some(alert) if alert.evaluate(info) => " (!)",
+                    _ => "",
+                };
+                format!(
+                    "{} {:>6.1}{} {}{badge}",
+                    glyph(&info.weather),
+                    units.temperature(info.temperature),
+                    units.temperature_unit(),
+                    info.weather,
+                )
+            }
+            Err(error) => format!("? error: {error}"),
+        };
+        text.push_str(&format!(
+            "{:<name_width$}  {:<location_width$}  {row}\n",
+            place.name, place.location
+        ));
+    }
+    text
+}
+/// Single-character glyph of weather kind
+fn glyph(kind: &WeatherKind) -> char {
+    match kind {
+        WeatherKind::Unknown => '?',
+        WeatherKind::Clear => '☀',
+        WeatherKind::Clouds => '☁',
+        WeatherKind::Fog => '≡',
+        WeatherKind::Rain => '☂',
+        WeatherKind::Snow => '❄',
+        WeatherKind::Drizzle => '⸪',
+        WeatherKind::Thunderstorm => 'ϟ',
+        WeatherKind::Sleet => '❅',
+        WeatherKind::Hail => '⁘',
+        WeatherKind::Windy => '≋',
+    }
+}
@@ -0,0 +1,55 @@
+//! Machine-readable progress events for GUI front-ends wrapping the CLI
+//!
+//! When enabled with `--progress-json`, each event is written to stderr as single-line JSON object,
+//! like `{"event":"fetched","location":"London","date":"2024-05-01","step":1,"total":3}`.
+//! Whole line is written at once, so events from concurrent tasks don't interleave
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+/// Whether events are emitted
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Progress event
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event<'a> {
+    /// Command started
+    Started { command: &'a str },
+    /// Address was resolved into coordinates
+    Geocoded {
+        location: &'a str,
+        lat: f64,
+        lon: f64,
+    },
+    /// Weather data for location was obtained; `step` counts from 1 up to `total`
+    Fetched {
+        location: &'a str,
+        date: Option<String>,
+        step: usize,
+        total: usize,
+    },
+    /// Requests to host are paused for `seconds` because it rejected them by rate limit;
+    /// work resumes by itself afterwards
+    Paused { host: &'a str, seconds: u64 },
+    /// Command output was rendered
+    Rendered,
+    /// Command failed
+    Error { message: String },
+}
+/// Turns emission of progress events on or off
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+/// Writes event to stderr, if events are enabled
+pub fn emit(event: Event) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    // Serializing plain enum never fails
+    if let Ok(mut line) = serde_json::to_string(&event) {
+        line.push('\n');
+        let _ = std::io::stderr().lock().write_all(line.as_bytes());
+    }
+}
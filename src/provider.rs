@@ -1,12 +1,39 @@
 use std::fmt::Display;
+use std::str::FromStr;
 
-use crate::{config::Section, date::Date, BoxFuture, CowString};
+use anyhow::{anyhow, bail, ensure, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
 
+use crate::api_url::ApiUrl;
+use crate::redact;
+use crate::units::Units;
+use crate::{config::Section, date::Date, CowString};
+
+#[cfg(feature = "accuweather")]
 pub mod accuweather;
+#[cfg(feature = "brightsky")]
+pub mod brightsky;
+#[cfg(feature = "envcanada")]
+pub mod envcanada;
+pub mod external;
+#[cfg(feature = "meteomatics")]
+pub mod meteomatics;
+#[cfg(feature = "meteostat")]
+pub mod meteostat;
+#[cfg(feature = "openweather")]
 pub mod openweather;
+#[cfg(feature = "pirateweather")]
+pub mod pirateweather;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
+#[cfg(feature = "weatherapi")]
 pub mod weatherapi;
+#[cfg(feature = "xweather")]
+pub mod xweather;
 /// Describes kind of weather - clear sky, clouds, raining etc.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum WeatherKind {
     Unknown,
     Clear,
@@ -14,6 +41,35 @@ pub enum WeatherKind {
     Fog,
     Rain,
     Snow,
+    Drizzle,
+    Thunderstorm,
+    /// Rain and snow mix, or freezing rain
+    Sleet,
+    /// Hail or ice pellets
+    Hail,
+    /// Strong wind, squalls or sand and dust storms
+    Windy,
+}
+
+impl FromStr for WeatherKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "unknown" => Ok(WeatherKind::Unknown),
+            "clear" => Ok(WeatherKind::Clear),
+            "clouds" => Ok(WeatherKind::Clouds),
+            "fog" => Ok(WeatherKind::Fog),
+            "rain" | "raining" => Ok(WeatherKind::Rain),
+            "snow" => Ok(WeatherKind::Snow),
+            "drizzle" => Ok(WeatherKind::Drizzle),
+            "thunderstorm" => Ok(WeatherKind::Thunderstorm),
+            "sleet" => Ok(WeatherKind::Sleet),
+            "hail" => Ok(WeatherKind::Hail),
+            "windy" => Ok(WeatherKind::Windy),
+            _ => Err(anyhow!("Unknown weather kind '{s}'")),
+        }
+    }
 }
 
 impl Display for WeatherKind {
@@ -25,29 +81,293 @@ impl Display for WeatherKind {
             WeatherKind::Fog => "fog",
             WeatherKind::Rain => "raining",
             WeatherKind::Snow => "snow",
+            WeatherKind::Drizzle => "drizzle",
+            WeatherKind::Thunderstorm => "thunderstorm",
+            WeatherKind::Sleet => "sleet",
+            WeatherKind::Hail => "hail",
+            WeatherKind::Windy => "windy",
         };
         f.write_str(desc)
     }
 }
+/// Serialized same way as displayed
+impl Serialize for WeatherKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+/// Deserialized from displayed form
+impl<'de> Deserialize<'de> for WeatherKind {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let kind = String::deserialize(deserializer)?;
+        kind.parse().map_err(serde::de::Error::custom)
+    }
+}
 /// Weather information
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WeatherInfo {
     /// What kind of weather
     pub weather: WeatherKind,
     /// Temperature, in Celsius degrees
     pub temperature: f32,
+    /// Day's minimal temperature, in Celsius degrees, for daily forecasts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_min: Option<f32>,
+    /// Day's maximal temperature, in Celsius degrees, for daily forecasts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_max: Option<f32>,
     /// Wind speed, in m/s
     pub wind_speed: f32,
     /// Humidity, in percents, 0..=100
     pub humidity: f32,
+    /// Precipitation amount, in millimeters, if provider reports it
+    pub precipitation: Option<f32>,
+    /// Atmospheric pressure at sea level, in hectopascals, if provider reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<f32>,
+    /// Provider-specific condition code from which `weather` was derived, if any
+    pub condition_code: Option<String>,
+    /// Where data comes from and how fresh it is
+    #[serde(default)]
+    pub meta: Meta,
+}
+
+impl WeatherInfo {
+    /// Day's minimal and maximal temperatures, if provider reports both
+    pub fn temperature_range(&self) -> Option<(f32, f32)> {
+        self.temperature_min.zip(self.temperature_max)
+    }
+    /// Weather of specified kind and temperature, with light wind, 60% humidity and no other data;
+    /// tests set fields they're interested in with struct update syntax
+    #[cfg(test)]
+    pub fn sample(weather: WeatherKind, temperature: f32) -> Self {
+        Self {
+            weather,
+            temperature,
+            temperature_min: None,
+            temperature_max: None,
+            wind_speed: 3.0,
+            humidity: 60.0,
+            precipitation: None,
+            pressure: None,
+            condition_code: None,
+            meta: Default::default(),
+        }
+    }
 }
 
 impl Display for WeatherInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "Weather: {}\nTemperature: {}°C\nWind speed: {} m/s\nHumidity: {}%",
-            self.weather, self.temperature, self.wind_speed, self.humidity
-        ))
+            "Weather: {}\nTemperature: {}°C",
+            self.weather, self.temperature
+        ))?;
+        if let Some((min, max)) = self.temperature_range() {
+            f.write_fmt(format_args!(" ({min}…{max}°C)"))?;
+        }
+        f.write_fmt(format_args!(
+            "\nWind speed: {} m/s\nHumidity: {}%",
+            self.wind_speed, self.humidity
+        ))?;
+        if let Some(precipitation) = self.precipitation {
+            f.write_fmt(format_args!("\nPrecipitation: {precipitation} mm"))?;
+        }
+        if let Some(pressure) = self.pressure {
+            f.write_fmt(format_args!("\nPressure: {pressure} hPa"))?;
+        }
+        Ok(())
+    }
+}
+/// Whether weather data is measured or predicted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataKind {
+    /// Current or past conditions, measured by station or analyzed by model
+    Observation,
+    /// Predicted conditions
+    Forecast,
+}
+
+impl Display for DataKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DataKind::Observation => "observation",
+            DataKind::Forecast => "forecast",
+        })
+    }
+}
+/// Origin of weather data; fields provider doesn't report are `None`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Meta {
+    /// Provider which supplied data; set by application, not by provider itself
+    #[serde(default)]
+    pub provider: String,
+    /// Whether data is measured or predicted
+    #[serde(default)]
+    pub kind: Option<DataKind>,
+    /// Time data refers to, i.e. time of observation or start of forecast period
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Weather station or other data source
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+impl Meta {
+    /// Metadata of specified kind of data, without timestamp and source
+    pub fn new(kind: DataKind) -> Self {
+        Self {
+            kind: Some(kind),
+            ..Default::default()
+        }
+    }
+    /// Sets time data refers to, as UNIX timestamp; invalid timestamps are ignored
+    pub fn with_timestamp(mut self, timestamp: Option<i64>) -> Self {
+        self.timestamp = timestamp.and_then(|secs| DateTime::from_timestamp(secs, 0));
+        self
+    }
+    /// Sets data source
+    pub fn with_source(mut self, source: Option<String>) -> Self {
+        self.source = source.filter(|source| !source.is_empty());
+        self
+    }
+}
+
+impl Display for Meta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Provider: {}", self.provider))?;
+        if let Some(kind) = self.kind {
+            f.write_fmt(format_args!("\nData: {kind}"))?;
+        }
+        if let Some(timestamp) = self.timestamp {
+            let local = timestamp.with_timezone(&chrono::Local);
+            f.write_fmt(format_args!(
+                "\nData time: {} {}",
+                crate::date::format_time(local.naive_local()),
+                local.format("%Z")
+            ))?;
+        }
+        if let Some(source) = &self.source {
+            f.write_fmt(format_args!("\nSource: {source}"))?;
+        }
+        Ok(())
+    }
+}
+/// Recent lightning activity near location
+#[derive(Debug)]
+pub struct LightningInfo {
+    /// Number of strikes registered within search radius during search period
+    pub strikes: usize,
+    /// Distance to nearest registered strike, in kilometers
+    pub nearest_km: Option<f32>,
+    /// Search radius, in kilometers
+    pub radius_km: f32,
+    /// Search period, in minutes
+    pub period_minutes: u32,
+}
+
+impl LightningInfo {
+    /// Describes lightning activity
+    ///
+    /// # Parameters
+    /// * `units` - units in which distances are shown
+    pub fn render(&self, units: Units) -> String {
+        let distance_unit = units.distance_unit();
+        let mut text = format!(
+            "Lightning strikes within {:.0} {distance_unit} during last {} minutes: {}",
+            units.distance(self.radius_km.into()),
+            self.period_minutes,
+            self.strikes
+        );
+        if let Some(nearest) = self.nearest_km {
+            text += &format!(
+                "\nNearest strike: {:.1} {distance_unit}",
+                units.distance(nearest.into())
+            );
+        }
+        text
+    }
+}
+/// HTTP request which provider would perform, shown by dry run
+pub struct PlannedRequest {
+    /// What request obtains, like "location's coordinates"
+    pub purpose: &'static str,
+    /// HTTP method, or "RUN" for external programs
+    pub method: &'static str,
+    /// Request URL, or command line for external programs; values known only from earlier responses are shown as placeholders like "{lat}"
+    pub url: String,
+    /// Additional request headers
+    pub headers: Vec<(&'static str, String)>,
+    /// Secret values, like API keys, which are masked when request is shown
+    secrets: Vec<String>,
+}
+
+impl PlannedRequest {
+    /// Creates GET request
+    ///
+    /// # Parameters
+    /// * `purpose` - what request obtains
+    /// * `url` - request URL; its secrets are masked when request is shown
+    pub fn get(purpose: &'static str, url: ApiUrl) -> Self {
+        Self {
+            purpose,
+            method: "GET",
+            secrets: url.secrets().to_vec(),
+            url: url.as_str().to_string(),
+            headers: Vec::new(),
+        }
+    }
+    /// Creates request which runs external program instead of HTTP request
+    ///
+    /// # Parameters
+    /// * `purpose` - what request obtains
+    /// * `command` - program and its input, as shown to user
+    pub fn run(purpose: &'static str, command: impl Into<String>) -> Self {
+        Self {
+            purpose,
+            method: "RUN",
+            url: command.into(),
+            headers: Vec::new(),
+            secrets: Vec::new(),
+        }
+    }
+    /// Adds request header
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+    /// Marks value as secret, so it's masked wherever it appears in request
+    pub fn secret(mut self, value: impl Into<String>) -> Self {
+        let value = value.into();
+        redact::register(&value);
+        self.secrets.push(value);
+        self
+    }
+    /// Replaces secrets in text with asterisks
+    fn redact(&self, text: &str) -> String {
+        self.secrets
+            .iter()
+            .filter(|secret| !secret.is_empty())
+            .fold(text.to_string(), |text, secret| {
+                text.replace(secret.as_str(), "***")
+            })
+    }
+}
+
+impl Display for PlannedRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Placeholders like "{lat}" are shown as is, rather than percent-encoded
+        let url = self.url.replace("%7B", "{").replace("%7D", "}");
+        f.write_fmt(format_args!(
+            "{}: {} {}",
+            self.purpose,
+            self.method,
+            self.redact(&url)
+        ))?;
+        for (name, value) in &self.headers {
+            f.write_fmt(format_args!("\n    {name}: {}", self.redact(value)))?;
+        }
+        Ok(())
     }
 }
 /// Additional information about provider, used to show extended help or validate
@@ -55,8 +375,29 @@ impl Display for WeatherInfo {
 pub struct ProviderInfo {
     /// Detailed description, used when listing providers
     pub description: &'static str,
+    /// Credit which should accompany provider's data
+    pub attribution: Option<Attribution>,
     /// Parameters this provider requires as its configuration
     pub params: &'static [ParamDesc],
+    /// Whether provider returns conditions at requested hour of day, rather than for whole day
+    pub hourly: bool,
+    /// Whether provider reports lightning strikes, see `Provider::get_lightning`
+    pub lightning: bool,
+}
+/// Credit to data source, shown along with its data
+pub struct Attribution {
+    /// Credit text, like "Weather data provided by OpenWeather"
+    pub text: &'static str,
+    /// Link to data source
+    pub url: &'static str,
+    /// Whether provider's terms require attribution, so it can't be turned off
+    pub required: bool,
+}
+
+impl Display for Attribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.text, self.url)
+    }
 }
 /// Parameter description
 pub struct ParamDesc {
@@ -66,11 +407,168 @@ pub struct ParamDesc {
     pub name: &'static str,
     /// Parameter description, used when listing providers
     pub description: &'static str,
+    /// Whether parameter should be specified when configuring provider;
+    /// optional ones aren't asked for in interactive mode
+    pub required: bool,
+    /// Value used when parameter isn't specified
+    pub default: Option<&'static str>,
+    /// Kind of value, which is checked when configuring provider
+    pub kind: ParamKind,
+}
+/// Identifier of parameter which overrides provider's API root
+pub const BASE_URL_PARAM: &str = "base_url";
+/// Describes optional parameter which overrides provider's API root,
+/// e.g. to use proxy, regional endpoint or mock server
+///
+/// # Parameters
+/// * `default` - provider's own API root
+pub const fn base_url_param(default: &'static str) -> ParamDesc {
+    ParamDesc {
+        id: BASE_URL_PARAM,
+        name: "API root URL",
+        description: "used instead of provider's own API endpoint, like proxy or mock server",
+        required: false,
+        default: Some(default),
+        kind: ParamKind::Url,
+    }
+}
+/// Local times of consecutive hours, as requested from `Provider::get_hourly`
+///
+/// # Parameters
+/// * `start` - day and hour of first entry; midnight if hour isn't specified
+/// * `hours` - number of entries
+///
+/// # Returns
+/// Times, or error if start date doesn't exist
+pub fn hours_from(start: Date, hours: u32) -> anyhow::Result<Vec<chrono::NaiveDateTime>> {
+    let first = start
+        .to_naive()
+        .and_then(|day| day.and_hms_opt(start.hour.unwrap_or_default().into(), 0, 0))
+        .ok_or_else(|| anyhow!("Forecast date {start} doesn't exist"))?;
+    Ok((0..hours)
+        .map(|step| first + chrono::Duration::hours(step.into()))
+        .collect())
+}
+/// Picks entry of each requested hour out of hourly data obtained at once
+///
+/// # Parameters
+/// * `times` - requested hours, in location's local time
+/// * `entries` - entries along with their local times
+///
+/// # Returns
+/// Entry of each hour, in order of hours, or error if some hour is missing
+pub fn pick_hours<T>(
+    times: &[chrono::NaiveDateTime],
+    entries: Vec<(chrono::NaiveDateTime, T)>,
+) -> anyhow::Result<Vec<T>> {
+    let mut entries: std::collections::BTreeMap<_, _> = entries.into_iter().collect();
+    times
+        .iter()
+        .map(|time| {
+            entries
+                .remove(time)
+                .ok_or_else(|| anyhow!("No hourly data for {}", time.format("%Y-%m-%d %H:%M")))
+        })
+        .collect()
+}
+/// Reads API root from provider's config
+///
+/// # Parameters
+/// * `config` - provider's config section
+/// * `default` - provider's own API root
+///
+/// # Returns
+/// Configured API root or default one, or error if configured one isn't valid URL
+pub fn base_url(config: &Section, default: &str) -> anyhow::Result<String> {
+    let Some(url) = config.get(BASE_URL_PARAM) else {
+        return Ok(default.to_string());
+    };
+    ParamKind::Url
+        .validate(url)
+        .with_context(|| anyhow!("Invalid parameter '{BASE_URL_PARAM}'"))?;
+    Ok(url.clone())
+}
+/// Kind of parameter's value
+#[derive(Clone, Copy)]
+pub enum ParamKind {
+    /// Any non-empty text
+    String,
+    /// Whole number
+    Integer,
+    /// Absolute HTTP or HTTPS URL
+    Url,
+    /// One of listed values
+    Choice(&'static [&'static str]),
+    /// Credential, like API key or password
+    Secret,
+}
+
+impl ParamKind {
+    /// Kind's identifier, like "integer"
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Url => "url",
+            Self::Choice(_) => "choice",
+            Self::Secret => "secret",
+        }
+    }
+    /// Allowed values, empty unless kind is choice
+    pub fn choices(&self) -> &'static [&'static str] {
+        match self {
+            Self::Choice(choices) => choices,
+            _ => &[],
+        }
+    }
+    /// Checks that value is of this kind
+    ///
+    /// # Parameters
+    /// * `value` - value entered by user
+    ///
+    /// # Returns
+    /// Error describing what's expected, if value doesn't fit
+    pub fn validate(&self, value: &str) -> anyhow::Result<()> {
+        ensure!(!value.trim().is_empty(), "Value cannot be empty");
+        match self {
+            Self::String | Self::Secret => {}
+            Self::Integer => {
+                value
+                    .parse::<i64>()
+                    .map_err(|_| anyhow!("Expected whole number, got '{value}'"))?;
+            }
+            Self::Url => {
+                let url = url::Url::parse(value)
+                    .map_err(|error| anyhow!("Expected URL, got '{value}': {error}"))?;
+                ensure!(
+                    matches!(url.scheme(), "http" | "https"),
+                    "Expected HTTP or HTTPS URL, got '{value}'"
+                );
+            }
+            Self::Choice(choices) => {
+                if !choices.contains(&value) {
+                    bail!("Expected one of {}, got '{value}'", choices.join(", "));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for ParamKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Choice(choices) => write!(f, "one of {}", choices.join(", ")),
+            _ => f.write_str(self.id()),
+        }
+    }
 }
 /// Defines any provider of weather data
 ///
-/// NB: Futures can be unboxed when async traits arrive
-pub trait Provider {
+/// Requests are async methods whose futures are `Send`, so providers can be driven
+/// by multi-threaded runtimes
+#[async_trait]
+pub trait Provider: Send + Sync {
     /// Creates new instance of provider, using provided TOML config to configure it
     ///
     /// # Parameters
@@ -92,15 +590,76 @@ pub trait Provider {
     ///
     /// # Parameters
     /// * `location` - name of location for which forecast is required;
-    ///     provider would usually use some geolocation service
-    /// * `date` - day when weather forecast is needed;
-    ///     limitations on future forecasting depend on concrete provider
+    ///   provider would usually use some geolocation service
+    /// * `date` - day when weather forecast is needed, possibly with hour of day in location's
+    ///   local time, which only hourly providers take into account;
+    ///   limitations on future forecasting depend on concrete provider
     ///
     /// # Returns
-    /// Boxed future which completes with forecast data or error
-    fn get_weather(
+    /// Forecast data or error
+    async fn get_weather(
         &self,
         location: CowString,
         date: Option<Date>,
-    ) -> BoxFuture<anyhow::Result<WeatherInfo>>;
+    ) -> anyhow::Result<WeatherInfo>;
+    /// Fetches weather for consecutive hours, starting from specified one
+    ///
+    /// By default, each hour is requested with `get_weather`; providers with hourly data
+    /// override it to obtain all hours with single request
+    ///
+    /// # Parameters
+    /// * `location` - name of location for which forecast is required
+    /// * `start` - day and hour, in location's local time, of first entry
+    /// * `hours` - number of entries
+    ///
+    /// # Returns
+    /// Forecast for each hour or error
+    async fn get_hourly(
+        &self,
+        location: CowString,
+        start: Date,
+        hours: u32,
+    ) -> anyhow::Result<Vec<WeatherInfo>> {
+        let mut result = Vec::new();
+        for time in hours_from(start, hours)? {
+            let date = Date::from(time.date()).with_hour(Some(time.hour() as u8));
+            result.push(self.get_weather(location.clone(), Some(date)).await?);
+        }
+        Ok(result)
+    }
+    /// Describes requests which `get_weather` would perform, without performing them
+    ///
+    /// # Parameters
+    /// * `location` - name of location for which forecast is required
+    /// * `date` - day when weather forecast is needed
+    ///
+    /// # Returns
+    /// Requests in order they're performed, or error if such forecast can't be requested
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>>;
+    /// Fetches recent lightning activity near specified location
+    ///
+    /// Optional capability; by default fails as unsupported
+    ///
+    /// # Parameters
+    /// * `location` - name of location around which strikes are searched
+    /// * `radius_km` - search radius, in kilometers
+    /// * `period_minutes` - how far into past strikes are searched, in minutes
+    ///
+    /// # Returns
+    /// Lightning data or error
+    async fn get_lightning(
+        &self,
+        location: CowString,
+        radius_km: f32,
+        period_minutes: u32,
+    ) -> anyhow::Result<LightningInfo> {
+        let _ = (location, radius_km, period_minutes);
+        Err(anyhow!(
+            "Sorry, lightning data isn't supported by this provider"
+        ))
+    }
 }
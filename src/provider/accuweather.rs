@@ -1,19 +1,32 @@
 use std::fmt::Display;
 use std::str::FromStr;
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
 use serde::Deserialize;
 
+use crate::api_url::ApiUrl;
 use crate::config::Section;
+use crate::geocode;
+use crate::normalize;
+use crate::schema;
 use crate::utils::restful_get;
-use crate::{BoxFuture, CowString};
+use crate::CowString;
+
+use super::{
+    base_url_param, Attribution, DataKind, Date, Meta, ParamDesc, ParamKind, PlannedRequest,
+    ProviderInfo, WeatherInfo, WeatherKind,
+};
+/// How many days daily forecast covers, including today
+const FORECAST_DAYS: i64 = 5;
+/// API root
+const API_URL: &str = "https://dataservice.accuweather.com";
 
-use super::{Date, ParamDesc, ProviderInfo, WeatherInfo, WeatherKind};
-// Convert km/h to m/s
-const KM_H_M_S: f32 = 1.0 / 3.6;
 /// Implementation of AccuWeather forecast provider
 pub struct AccuWeather {
     apikey: String,
+    /// API root, own one unless overridden
+    base_url: String,
 }
 
 //
@@ -54,7 +67,17 @@ impl FromStr for LocationData {
     type Err = serde_json::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(serde_json::from_str(s)?))
+        // City search responds with list, while geoposition search - with single location
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Found {
+            Many(Vec<Location>),
+            One(Location),
+        }
+        Ok(Self(match schema::parse(s)? {
+            Found::Many(locations) => locations,
+            Found::One(location) => vec![location],
+        }))
     }
 }
 
@@ -68,16 +91,23 @@ struct Location {
 // Weather API structures
 //
 
-/// Weather response root
+/// Response of request which serves requested date
+enum WeatherData {
+    Current(CurrentData),
+    /// Daily forecast, along with requested date
+    Daily(DailyData, Date),
+}
+
+/// Current conditions response root
 #[derive(Deserialize)]
 #[serde(rename_all = "PascalCase")]
-struct WeatherData(Vec<Condition>);
+struct CurrentData(Vec<Condition>);
 
-impl FromStr for WeatherData {
+impl FromStr for CurrentData {
     type Err = serde_json::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(serde_json::from_str(s)?))
+        Ok(Self(schema::parse(s)?))
     }
 }
 
@@ -89,6 +119,16 @@ struct Condition {
     wind: Wind,
     cloud_cover: f32,
     precipitation_type: Option<PrecipitationType>,
+    precipitation_summary: Option<PrecipitationSummary>,
+    weather_icon: Option<u32>,
+    /// UNIX timestamp of observation
+    epoch_time: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PrecipitationSummary {
+    past_hour: ValueEntry,
 }
 
 #[derive(Deserialize)]
@@ -118,6 +158,61 @@ struct Wind {
     speed: ValueEntry,
 }
 
+/// Daily forecast response root
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DailyData {
+    daily_forecasts: Vec<DailyForecast>,
+}
+
+impl FromStr for DailyData {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        schema::parse(s)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DailyForecast {
+    /// Local date and time, like "2023-10-16T07:00:00+01:00"
+    date: String,
+    /// UNIX timestamp of forecast's day
+    epoch_date: Option<i64>,
+    temperature: TemperatureRange,
+    day: HalfDay,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TemperatureRange {
+    minimum: Value,
+    maximum: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HalfDay {
+    icon: u32,
+    relative_humidity: Option<HumidityRange>,
+    wind: DailyWind,
+    total_liquid: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HumidityRange {
+    average: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DailyWind {
+    speed: Value,
+}
+
+#[async_trait]
 impl super::Provider for AccuWeather {
     fn new(config: &Section) -> anyhow::Result<Self>
     where
@@ -128,6 +223,7 @@ impl super::Provider for AccuWeather {
                 .get("apikey")
                 .ok_or_else(|| anyhow!("Missing parameter 'apikey'"))?
                 .clone(),
+            base_url: super::base_url(config, API_URL)?,
         })
     }
 
@@ -136,86 +232,359 @@ impl super::Provider for AccuWeather {
         Self: Sized,
     {
         const INFO: ProviderInfo = ProviderInfo {
-            description: "AccuWeather (https://www.accuweather.com/); doesn't support specific dates, only current conditions",
-            params: &[ParamDesc {
+            description: "AccuWeather (https://www.accuweather.com/); provides current conditions and daily forecasts up to 5 days",
+            attribution: Some(Attribution {
+                text: "Weather data by AccuWeather",
+                url: "https://www.accuweather.com/",
+                required: true,
+            }),
+            params: &[
+                ParamDesc {
                 id: "apikey",
                 name: "User's API key",
                 description: "used to authenticate user requests",
-            }],
+                required: true,
+                default: None,
+                kind: ParamKind::Secret,
+                },
+                base_url_param(API_URL),
+            ],
+            hourly: false,
+            lightning: false,
         };
         &INFO
     }
 
-    fn get_weather(
+    async fn get_weather(
         &self,
         location: CowString,
         date: Option<Date>,
-    ) -> BoxFuture<anyhow::Result<WeatherInfo>> {
-        let apikey = &self.apikey;
-        if date.is_some() {
-            return Box::pin(async {
-                Err(anyhow!(
-                    "Sorry, requesting weather for specific date isn't supported"
-                ))
-            });
+    ) -> anyhow::Result<WeatherInfo> {
+        let request = Request::select(&location, date)?;
+        let location_url = location_url(&self.base_url, &self.apikey, &location);
+        // Convert location lookup to location key
+        let locations = restful_get::<LocationData, ApiError>(location_url)
+            .await
+            .with_context(|| anyhow!("Could not obtain location key for {location}"))?
+            .0;
+
+        let location_key = locations
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Could not obtain location key for {location}"))?
+            .key;
+
+        let data_url = request.url(&self.base_url, &self.apikey, &location_key);
+
+        let data = match request {
+            Request::Current => WeatherData::Current(
+                restful_get::<CurrentData, ApiError>(data_url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain forecast data"))?,
+            ),
+            Request::Daily(date) => WeatherData::Daily(
+                restful_get::<DailyData, ApiError>(data_url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain daily forecast"))?,
+                date,
+            ),
+        };
+        map_response(data)
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        let request = Request::select(location, date)?;
+        Ok(vec![
+            PlannedRequest::get(
+                "location key",
+                location_url(&self.base_url, &self.apikey, location),
+            ),
+            PlannedRequest::get(
+                request.purpose(),
+                request.url(&self.base_url, &self.apikey, "{location_key}"),
+            ),
+        ])
+    }
+}
+/// URL of request which resolves location into AccuWeather's location key;
+/// locations specified with coordinates are resolved with geoposition search
+fn location_url(base_url: &str, apikey: &str, location: &str) -> ApiUrl {
+    let url = ApiUrl::new(base_url)
+        .segment("locations")
+        .segment("v1")
+        .segment("cities");
+    let (url, query) = match geocode::coordinates_text(location) {
+        Some((lat, lon)) => (
+            url.segment("geoposition").segment("search"),
+            format!("{lat},{lon}"),
+        ),
+        None => (url.segment("search"), location.to_string()),
+    };
+    url.secret_query("apikey", apikey).query("q", query)
+}
+/// Request which serves requested date
+#[derive(Clone, Copy)]
+enum Request {
+    /// Current conditions
+    Current,
+    /// Daily forecast, for today and `FORECAST_DAYS - 1` following days
+    Daily(Date),
+}
+
+impl Request {
+    /// Selects request by comparing date with today's date at location
+    ///
+    /// # Parameters
+    /// * `location` - location, whose timezone defines today's date if it's specified with coordinates
+    /// * `date` - requested date; `None` means current conditions
+    ///
+    /// # Returns
+    /// Request, or error if date is in past, too far in future or doesn't exist
+    fn select(location: &str, date: Option<Date>) -> anyhow::Result<Self> {
+        let Some(date) = date else {
+            return Ok(Self::Current);
+        };
+        let day = date
+            .to_naive()
+            .ok_or_else(|| anyhow!("Date {date} doesn't exist"))?;
+        let today = Date::today_at(location)
+            .to_naive()
+            .ok_or_else(|| anyhow!("Could not determine today's date"))?;
+        Ok(match (day - today).num_days() {
+            ..=-1 => bail!("Sorry, AccuWeather doesn't provide historical data"),
+            0..FORECAST_DAYS => Self::Daily(date),
+            _ => bail!("Sorry, AccuWeather forecasts only {FORECAST_DAYS} days, including today"),
+        })
+    }
+    /// Describes data request provides
+    fn purpose(&self) -> &'static str {
+        match self {
+            Self::Current => "current conditions",
+            Self::Daily(_) => "daily forecast",
         }
-        let location_url = format!(
-            "https://dataservice.accuweather.com/locations/v1/cities/search?apikey={apikey}&q={location}"
-        );
-        let data_url_head = "http://dataservice.accuweather.com/currentconditions/v1/".to_string();
-        let data_url_tail = format!("?apikey={apikey}&details=true");
-        let fut = async move {
-            // Convert location lookup to location key
-            let locations = restful_get::<LocationData, ApiError>(location_url)
-                .await
-                .with_context(|| anyhow!("Could not obtain location key for {location}"))?
-                .0;
-
-            let location_key = locations
+    }
+    /// URL of request at location with specified key
+    ///
+    /// # Parameters
+    /// * `base_url` - API root
+    /// * `apikey` - user's API key
+    /// * `location_key` - AccuWeather's location key
+    fn url(&self, base_url: &str, apikey: &str, location_key: &str) -> ApiUrl {
+        let url = ApiUrl::new(base_url);
+        let url = match self {
+            Self::Current => url.segment("currentconditions").segment("v1"),
+            Self::Daily(_) => url
+                .segment("forecasts")
+                .segment("v1")
+                .segment("daily")
+                .segment(format_args!("{FORECAST_DAYS}day"))
+                .query("metric", true),
+        };
+        url.segment(location_key)
+            .secret_query("apikey", apikey)
+            .query("details", true)
+    }
+}
+/// Converts response into weather information; daily forecast is picked by its local date
+///
+/// # Returns
+/// Weather information, or error if response lacks requested data
+fn map_response(data: WeatherData) -> anyhow::Result<WeatherInfo> {
+    match data {
+        WeatherData::Current(data) => data
+            .0
+            .into_iter()
+            .next()
+            .map(current_info)
+            .ok_or_else(|| anyhow!("No current condition entries")),
+        WeatherData::Daily(data, date) => {
+            let day = date.without_hour().to_string();
+            data.daily_forecasts
                 .into_iter()
-                .next()
-                .ok_or_else(|| anyhow!("Could not obtain location key for {location}"))?
-                .key;
+                .find(|forecast| forecast.date.starts_with(&day))
+                .map(daily_info)
+                .ok_or_else(|| anyhow!("No forecast for {day}"))
+        }
+    }
+}
+/// Converts current conditions into weather information
+fn current_info(condition: Condition) -> WeatherInfo {
+    if condition.precipitation_summary.is_none() {
+        schema::missing("PrecipitationSummary", "unknown precipitation");
+    }
+    if condition.weather_icon.is_none() {
+        schema::missing("WeatherIcon", "no condition code");
+    }
+    // Only icon tells thunderstorms and wind apart
+    let icon_kind = condition.weather_icon.map(normalize::accuweather_kind);
+    let weather = match (icon_kind, &condition.precipitation_type) {
+        (Some(kind @ (WeatherKind::Thunderstorm | WeatherKind::Windy)), _) => kind,
+        (_, Some(precip)) => match precip {
+            PrecipitationType::Snow => WeatherKind::Snow,
+            PrecipitationType::Ice => WeatherKind::Hail,
+            PrecipitationType::Mixed => WeatherKind::Sleet,
+            PrecipitationType::Rain => WeatherKind::Rain,
+        },
+        (_, None) => {
+            if condition.cloud_cover > 5.0 {
+                WeatherKind::Clouds
+            } else {
+                WeatherKind::Clear
+            }
+        }
+    };
+
+    WeatherInfo {
+        weather,
+        temperature: condition.temperature.metric.value,
+        temperature_min: None,
+        temperature_max: None,
+        wind_speed: normalize::kmh_to_ms(condition.wind.speed.metric.value),
+        humidity: condition.relative_humidity,
+        precipitation: condition
+            .precipitation_summary
+            .map(|summary| summary.past_hour.metric.value),
+        pressure: None,
+        condition_code: condition.weather_icon.map(|icon| icon.to_string()),
+        meta: Meta::new(DataKind::Observation).with_timestamp(condition.epoch_time),
+    }
+}
+/// Converts daily forecast into weather information; its daytime part describes weather
+fn daily_info(forecast: DailyForecast) -> WeatherInfo {
+    let (min, max) = (
+        forecast.temperature.minimum.value,
+        forecast.temperature.maximum.value,
+    );
+    let day = forecast.day;
+    if day.relative_humidity.is_none() {
+        schema::missing("DailyForecasts.Day.RelativeHumidity", "zero humidity");
+    }
+    if day.total_liquid.is_none() {
+        schema::missing("DailyForecasts.Day.TotalLiquid", "unknown precipitation");
+    }
+    WeatherInfo {
+        weather: normalize::accuweather_kind(day.icon),
+        temperature: (min + max) / 2.0,
+        temperature_min: Some(min),
+        temperature_max: Some(max),
+        wind_speed: normalize::kmh_to_ms(day.wind.speed.value),
+        humidity: day
+            .relative_humidity
+            .map(|humidity| humidity.average)
+            .unwrap_or_default(),
+        precipitation: day.total_liquid.map(|liquid| liquid.value),
+        pressure: None,
+        condition_code: Some(day.icon.to_string()),
+        meta: Meta::new(DataKind::Forecast).with_timestamp(forecast.epoch_date),
+    }
+}
 
-            let data_url = format!("{data_url_head}{location_key}{data_url_tail}");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            let data = restful_get::<WeatherData, ApiError>(data_url)
-                .await
-                .with_context(|| anyhow!("Could not obtain forecast data"))?;
+    fn date(day: u8) -> Date {
+        Date {
+            year: 2024,
+            month: 7,
+            day,
+            hour: None,
+        }
+    }
 
-            let condition = data
-                .0
-                .into_iter()
-                .next()
-                .ok_or_else(|| anyhow!("No current condition entries"))?;
-
-            let temperature = condition.temperature.metric.value;
-            let wind_speed = condition.wind.speed.metric.value * KM_H_M_S;
-            let humidity = condition.relative_humidity;
-
-            let weather = match condition.precipitation_type {
-                Some(precip) => match precip {
-                    PrecipitationType::Snow | PrecipitationType::Ice | PrecipitationType::Mixed => {
-                        WeatherKind::Snow
-                    }
-                    PrecipitationType::Rain => WeatherKind::Rain,
-                },
-                None => {
-                    if condition.cloud_cover > 5.0 {
-                        WeatherKind::Clouds
-                    } else {
-                        WeatherKind::Clear
-                    }
-                }
-            };
-
-            Ok(WeatherInfo {
-                weather,
-                temperature,
-                wind_speed,
-                humidity,
-            })
-        };
-        Box::pin(fut)
+    #[test]
+    fn locations_are_listed_or_single() {
+        let found =
+            LocationData::from_str(include_str!("../../fixtures/accuweather/locations.json"));
+        let keys: Vec<_> = found
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|location| location.key)
+            .collect();
+        assert_eq!(keys, ["328328", "56186"]);
+
+        let found =
+            LocationData::from_str(include_str!("../../fixtures/accuweather/geoposition.json"));
+        let keys: Vec<_> = found
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|location| location.key)
+            .collect();
+        assert_eq!(keys, ["712327"]);
+    }
+
+    fn map_current(text: &str) -> anyhow::Result<WeatherInfo> {
+        map_response(WeatherData::Current(CurrentData::from_str(text).unwrap()))
+    }
+
+    #[test]
+    fn current_conditions() {
+        let info = map_current(include_str!(
+            "../../fixtures/accuweather/currentconditions.json"
+        ))
+        .unwrap();
+        // Icon tells thunderstorm apart from rain which precipitation type reports
+        assert_eq!(info.weather, WeatherKind::Thunderstorm);
+        assert_eq!(info.temperature, 21.1);
+        assert_eq!(info.wind_speed, 5.0);
+        assert_eq!(info.humidity, 78.0);
+        assert_eq!(info.precipitation, Some(3.6));
+        assert_eq!(info.condition_code.as_deref(), Some("15"));
+        assert_eq!(info.meta.kind, Some(DataKind::Observation));
+    }
+
+    #[test]
+    fn dry_conditions_without_icon() {
+        let info =
+            map_current(include_str!("../../fixtures/accuweather/current_dry.json")).unwrap();
+        assert_eq!(info.weather, WeatherKind::Clear);
+        assert_eq!(info.wind_speed, 0.0);
+        assert_eq!(info.precipitation, None);
+        assert_eq!(info.condition_code, None);
+    }
+
+    fn map_daily(text: &str, day: u8) -> anyhow::Result<WeatherInfo> {
+        map_response(WeatherData::Daily(
+            DailyData::from_str(text).unwrap(),
+            date(day),
+        ))
+    }
+
+    #[test]
+    fn daily_forecast() {
+        let text = include_str!("../../fixtures/accuweather/daily.json");
+        let info = map_daily(text, 1).unwrap();
+        assert_eq!(info.weather, WeatherKind::Clouds);
+        assert_eq!(info.temperature_range(), Some((11.8, 21.9)));
+        assert_eq!(info.humidity, 61.0);
+        assert_eq!(info.precipitation, Some(0.0));
+        assert_eq!(info.meta.kind, Some(DataKind::Forecast));
+
+        // Day without humidity and liquid totals
+        let info = map_daily(text, 2).unwrap();
+        assert_eq!(info.weather, WeatherKind::Rain);
+        assert_eq!(info.temperature, 16.0);
+        assert_eq!(info.wind_speed, 10.0);
+        assert_eq!(info.humidity, 0.0);
+        assert_eq!(info.precipitation, None);
+
+        let error = map_daily(text, 9).unwrap_err();
+        assert_eq!(error.to_string(), "No forecast for 2024-07-09");
+    }
+
+    #[test]
+    fn error_payload() {
+        let error =
+            ApiError::from_str(include_str!("../../fixtures/accuweather/error.json")).unwrap();
+        assert_eq!(
+            error.to_string(),
+            "API error 'Unauthorized': Api Authorization failed"
+        );
     }
 }
@@ -0,0 +1,480 @@
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDate, Timelike};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::config::Section;
+use crate::geocode;
+use crate::normalize;
+use crate::schema;
+use crate::timezone;
+use crate::utils::{restful_get_as, Json};
+use crate::CowString;
+
+use super::{
+    base_url_param, Attribution, DataKind, Date, Meta, PlannedRequest, ProviderInfo, WeatherInfo,
+    WeatherKind,
+};
+
+/// API root
+const API_URL: &str = "https://api.brightsky.dev";
+
+/// Bright Sky provider, which serves Deutscher Wetterdienst's open data
+pub struct BrightSky {
+    /// API root, own one unless overridden
+    base_url: String,
+}
+
+//
+// Error handling structures
+//
+
+/// API failure
+#[derive(Debug)]
+struct ApiError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorJson {
+    title: Option<String>,
+    description: Option<String>,
+}
+
+impl FromStr for ApiError {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let message = match serde_json::from_str::<ApiErrorJson>(s) {
+            Ok(error) => error.description.or(error.title).unwrap_or_default(),
+            Err(_) => s.trim().to_string(),
+        };
+        Ok(Self { message })
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("API error: {}", self.message))
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+//
+// Weather response structures
+//
+
+/// Response root; current weather endpoint reports single record, weather endpoint - hourly ones
+#[derive(Deserialize)]
+struct Response<T> {
+    weather: T,
+    #[serde(default)]
+    sources: Vec<Source>,
+}
+
+/// Response of request which serves requested date
+enum WeatherData {
+    Current(Response<CurrentRecord>),
+    /// Day's hourly records, along with requested date
+    Day(Response<Vec<Record>>, Date),
+}
+
+/// Station or forecast point which records come from
+#[derive(Deserialize)]
+struct Source {
+    id: i64,
+    station_name: Option<String>,
+    /// Like "historical", "current", "synop" or "forecast"
+    observation_type: Option<String>,
+}
+
+/// Hourly record
+#[derive(Deserialize)]
+struct Record {
+    /// Time, in ISO 8601 format with offset
+    timestamp: String,
+    source_id: Option<i64>,
+    temperature: Option<f32>,
+    relative_humidity: Option<f32>,
+    /// Wind speed, in km/h
+    wind_speed: Option<f32>,
+    /// Precipitation over hour, in millimeters
+    precipitation: Option<f32>,
+    /// Pressure at sea level, in hPa
+    pressure_msl: Option<f32>,
+    /// Significant weather derived from SYNOP present weather code,
+    /// like "dry", "fog", "rain", "sleet", "snow", "hail" or "thunderstorm"
+    condition: Option<String>,
+    /// Icon, like "partly-cloudy-day", which also tells cloudiness of dry weather
+    icon: Option<String>,
+}
+
+/// Current weather record, which has values aggregated over several periods
+#[derive(Deserialize)]
+struct CurrentRecord {
+    timestamp: String,
+    source_id: Option<i64>,
+    temperature: Option<f32>,
+    relative_humidity: Option<f32>,
+    /// Mean wind speed over last 10 minutes, in km/h
+    wind_speed_10: Option<f32>,
+    /// Precipitation over last hour, in millimeters
+    precipitation_60: Option<f32>,
+    pressure_msl: Option<f32>,
+    condition: Option<String>,
+    icon: Option<String>,
+}
+
+impl From<CurrentRecord> for Record {
+    fn from(current: CurrentRecord) -> Self {
+        Self {
+            timestamp: current.timestamp,
+            source_id: current.source_id,
+            temperature: current.temperature,
+            relative_humidity: current.relative_humidity,
+            wind_speed: current.wind_speed_10,
+            precipitation: current.precipitation_60,
+            pressure_msl: current.pressure_msl,
+            condition: current.condition,
+            icon: current.icon,
+        }
+    }
+}
+
+impl Record {
+    /// Record's time, if it's well-formed
+    fn time(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(&self.timestamp).ok()
+    }
+    /// Weather kind; dry weather is told by icon, since condition only tells precipitation and fog
+    fn kind(&self) -> WeatherKind {
+        match self.condition.as_deref() {
+            Some("fog") => WeatherKind::Fog,
+            Some("rain") => WeatherKind::Rain,
+            Some("sleet") => WeatherKind::Sleet,
+            Some("snow") => WeatherKind::Snow,
+            Some("hail") => WeatherKind::Hail,
+            Some("thunderstorm") => WeatherKind::Thunderstorm,
+            _ => match self
+                .icon
+                .as_deref()
+                .map(|icon| icon.trim_end_matches("-day").trim_end_matches("-night"))
+            {
+                Some("clear") => WeatherKind::Clear,
+                Some("partly-cloudy" | "cloudy") => WeatherKind::Clouds,
+                Some("fog") => WeatherKind::Fog,
+                Some("wind") => WeatherKind::Windy,
+                Some("rain") => WeatherKind::Rain,
+                Some("sleet") => WeatherKind::Sleet,
+                Some("snow") => WeatherKind::Snow,
+                Some("hail") => WeatherKind::Hail,
+                Some("thunderstorm") => WeatherKind::Thunderstorm,
+                _ => WeatherKind::Unknown,
+            },
+        }
+    }
+}
+/// Summarizes records, either single hour or whole day
+///
+/// # Parameters
+/// * `records` - records which have temperature, in chronological order; shouldn't be empty
+/// * `sources` - sources of records
+///
+/// # Returns
+/// Weather, with temperature range if there are several records
+fn summarize(records: &[Record], sources: &[Source]) -> WeatherInfo {
+    let average = |values: Vec<f32>| {
+        (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+    };
+    let temperatures: Vec<_> = records
+        .iter()
+        .filter_map(|record| record.temperature)
+        .collect();
+    let humidity = average(
+        records
+            .iter()
+            .filter_map(|record| record.relative_humidity)
+            .collect(),
+    );
+    if humidity.is_none() {
+        schema::missing("weather.relative_humidity", "0");
+    }
+    // Most frequent weather stands for day; ties are resolved towards more severe one
+    let kinds: Vec<_> = records.iter().map(Record::kind).collect();
+    let kind = SEVERITY
+        .iter()
+        .max_by_key(|kind| kinds.iter().filter(|other| other == kind).count())
+        .cloned()
+        .unwrap_or(WeatherKind::Unknown);
+    let first = records.first();
+    let source = first
+        .and_then(|record| record.source_id)
+        .and_then(|id| sources.iter().find(|source| source.id == id));
+    // Forecasts come from model output at station's location, others are observations
+    let data_kind = match source.and_then(|source| source.observation_type.as_deref()) {
+        Some("forecast") => DataKind::Forecast,
+        _ => DataKind::Observation,
+    };
+    let range = (temperatures.len() > 1).then(|| {
+        temperatures
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), temp| {
+                (min.min(*temp), max.max(*temp))
+            })
+    });
+    WeatherInfo {
+        weather: kind,
+        temperature: average(temperatures).unwrap_or_default(),
+        temperature_min: range.map(|(min, _)| min),
+        temperature_max: range.map(|(_, max)| max),
+        wind_speed: normalize::kmh_to_ms(
+            records
+                .iter()
+                .filter_map(|record| record.wind_speed)
+                .fold(0.0, f32::max),
+        ),
+        humidity: humidity.unwrap_or(0.0),
+        precipitation: Some(
+            records
+                .iter()
+                .filter_map(|record| record.precipitation)
+                .sum(),
+        ),
+        pressure: average(
+            records
+                .iter()
+                .filter_map(|record| record.pressure_msl)
+                .collect(),
+        ),
+        condition_code: records
+            .iter()
+            .find(|record| record.kind() == kind)
+            .and_then(|record| record.condition.clone().or(record.icon.clone())),
+        meta: Meta::new(data_kind)
+            .with_timestamp(first.and_then(Record::time).map(|time| time.timestamp()))
+            .with_source(
+                source
+                    .and_then(|source| source.station_name.as_ref())
+                    .map(|name| format!("station {name}")),
+            ),
+    }
+}
+/// Weather kinds from least to most severe, to pick day's weather when several are equally frequent
+const SEVERITY: [WeatherKind; 11] = [
+    WeatherKind::Unknown,
+    WeatherKind::Clear,
+    WeatherKind::Clouds,
+    WeatherKind::Windy,
+    WeatherKind::Fog,
+    WeatherKind::Drizzle,
+    WeatherKind::Rain,
+    WeatherKind::Snow,
+    WeatherKind::Sleet,
+    WeatherKind::Hail,
+    WeatherKind::Thunderstorm,
+];
+
+#[async_trait]
+impl super::Provider for BrightSky {
+    fn new(config: &Section) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            base_url: super::base_url(config, API_URL)?,
+        })
+    }
+
+    fn info() -> &'static ProviderInfo
+    where
+        Self: Sized,
+    {
+        const INFO: ProviderInfo = ProviderInfo {
+            description: "Bright Sky (https://brightsky.dev/), open data of Deutscher Wetterdienst; free, no key needed; covers Germany with station observations, historical data and forecasts up to 10 days",
+            attribution: Some(Attribution {
+                text: "Weather data from Deutscher Wetterdienst, via Bright Sky",
+                url: "https://www.dwd.de/",
+                required: true,
+            }),
+            params: &[base_url_param(API_URL)],
+            hourly: true,
+            lightning: false,
+        };
+        &INFO
+    }
+
+    async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let day = requested_day(date)?;
+        let (lat, lon) = geocode::coordinates(&location).await?;
+        let tz = timezone::at(lat, lon);
+        let url = weather_url(&self.base_url, &format!("{lat:.4},{lon:.4}"), day, tz);
+        let data = match date {
+            None => WeatherData::Current(
+                restful_get_as::<Response<CurrentRecord>, ApiError, Json>(url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain current weather"))?,
+            ),
+            Some(date) => WeatherData::Day(
+                restful_get_as::<Response<Vec<Record>>, ApiError, Json>(url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain weather data"))?,
+                date,
+            ),
+        };
+        map_response(data)
+    }
+
+    async fn get_hourly(
+        &self,
+        location: CowString,
+        start: Date,
+        hours: u32,
+    ) -> anyhow::Result<Vec<WeatherInfo>> {
+        let times = super::hours_from(start, hours)?;
+        let (Some(first), Some(last)) = (times.first(), times.last()) else {
+            return Ok(Vec::new());
+        };
+        let (lat, lon) = geocode::coordinates(&location).await?;
+        let tz = timezone::at(lat, lon);
+        let end = last
+            .date()
+            .succ_opt()
+            .ok_or_else(|| anyhow!("Date {} has no following day", last.date()))?;
+        // Range's last date is exclusive
+        let url = weather_url(
+            &self.base_url,
+            &format!("{lat:.4},{lon:.4}"),
+            Some(first.date()),
+            tz,
+        )
+        .query("last_date", end);
+        let data = restful_get_as::<Response<Vec<Record>>, ApiError, Json>(url)
+            .await
+            .with_context(|| anyhow!("Could not obtain hourly weather data"))?;
+        map_hours(&times, data)
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        let coordinates = match geocode::coordinates_text(location) {
+            Some((lat, lon)) => format!("{lat},{lon}"),
+            None => geocode::PLACEHOLDER.to_string(),
+        };
+        let day = requested_day(date)?;
+        let purpose = match date {
+            None => "current weather",
+            Some(_) => "hourly weather data",
+        };
+        let data = PlannedRequest::get(
+            purpose,
+            weather_url(
+                &self.base_url,
+                &coordinates,
+                day,
+                timezone::of_location(location),
+            ),
+        );
+        Ok(geocode::plan(location).into_iter().chain([data]).collect())
+    }
+}
+/// Converts response into weather information; records are picked by requested day and hour
+///
+/// # Returns
+/// Weather information, or error if response has no records for requested date
+fn map_response(data: WeatherData) -> anyhow::Result<WeatherInfo> {
+    let (data, date) = match data {
+        WeatherData::Current(data) => return Ok(summarize(&[data.weather.into()], &data.sources)),
+        WeatherData::Day(data, date) => (data, date),
+    };
+    let day = date.to_naive();
+    let records: Vec<_> = data
+        .weather
+        .into_iter()
+        .filter(|record| record.temperature.is_some())
+        .filter(|record| {
+            record.time().is_some_and(|time| {
+                Some(time.date_naive()) == day
+                    && date.hour.is_none_or(|hour| time.hour() == u32::from(hour))
+            })
+        })
+        .collect();
+    if records.is_empty() {
+        return Err(anyhow!("No weather data for {date}"));
+    }
+    Ok(summarize(&records, &data.sources))
+}
+/// Picks requested hours out of response with records of several days
+///
+/// # Parameters
+/// * `times` - requested hours, in location's local time
+/// * `data` - response
+///
+/// # Returns
+/// Weather information of each hour, or error if response misses some hour
+fn map_hours(
+    times: &[chrono::NaiveDateTime],
+    data: Response<Vec<Record>>,
+) -> anyhow::Result<Vec<WeatherInfo>> {
+    let entries = data
+        .weather
+        .into_iter()
+        .filter(|record| record.temperature.is_some())
+        .filter_map(|record| Some((record.time()?.naive_local(), record)))
+        .collect();
+    Ok(super::pick_hours(times, entries)?
+        .into_iter()
+        .map(|record| summarize(&[record], &data.sources))
+        .collect())
+}
+/// Day of requested date
+///
+/// # Returns
+/// Day, `None` for current weather, or error if date doesn't exist
+fn requested_day(date: Option<Date>) -> anyhow::Result<Option<NaiveDate>> {
+    date.map(|date| {
+        date.to_naive()
+            .ok_or_else(|| anyhow!("Date {date} doesn't exist"))
+    })
+    .transpose()
+}
+/// URL of current weather or of day's hourly records, which are observations
+/// for past and forecasts for future
+///
+/// # Parameters
+/// * `base_url` - API root
+/// * `coordinates` - location's coordinates, as "lat,lon"
+/// * `day` - requested day; `None` means current weather
+/// * `tz` - location's timezone, in which day is taken and time is reported; UTC if unknown
+fn weather_url(
+    base_url: &str,
+    coordinates: &str,
+    day: Option<NaiveDate>,
+    tz: Option<Tz>,
+) -> ApiUrl {
+    let (lat, lon) = coordinates.split_once(',').unwrap_or((coordinates, ""));
+    let url = ApiUrl::new(base_url);
+    let url = match day {
+        None => url.segment("current_weather"),
+        Some(day) => url.segment("weather").query("date", day),
+    }
+    .query("lat", lat)
+    .query("lon", lon);
+    match tz {
+        Some(tz) => url.query("tz", tz.name()),
+        None => url,
+    }
+}
@@ -0,0 +1,648 @@
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use chrono::{Days, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Deserializer};
+
+use crate::api_url::ApiUrl;
+use crate::config::Section;
+use crate::geocode;
+use crate::normalize;
+use crate::schema;
+use crate::timezone;
+use crate::utils::{distance_km, restful_get, restful_get_as, Xml};
+use crate::CowString;
+
+use super::{
+    base_url_param, Attribution, DataKind, Date, Meta, ParamDesc, ParamKind, PlannedRequest,
+    ProviderInfo, WeatherInfo, WeatherKind,
+};
+
+/// Root of MSC Datamart, which publishes citypage weather
+const API_URL: &str = "https://dd.weather.gc.ca";
+/// Format of timestamps in citypage weather
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// Environment Canada provider, using Meteorological Service of Canada's citypage weather
+pub struct EnvCanada {
+    /// Citypage site, as "<province>/<code>", like "ON/s0000458"; nearest to location unless specified
+    site: Option<String>,
+    /// Datamart root, own one unless overridden
+    base_url: String,
+}
+
+//
+// Error handling structures
+//
+
+/// Datamart failure, which is reported as plain text
+#[derive(Debug)]
+struct ApiError {
+    message: String,
+}
+
+impl FromStr for ApiError {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            message: s.trim().to_string(),
+        })
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Datamart error: {}", self.message))
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+//
+// Site list structures
+//
+
+/// Citypage site, as listed in sites' CSV
+struct Site {
+    code: String,
+    province: String,
+    lat: f64,
+    lon: f64,
+}
+
+/// Sites' CSV, like "s0000458,Toronto,ON,43.74N,79.37W"; lines which aren't sites are skipped
+struct SiteList(Vec<Site>);
+
+impl FromStr for SiteList {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Site names may contain commas, so fields are taken from line's ends
+        let site = |line: &str| {
+            let (code, rest) = line.trim().split_once(',')?;
+            let mut fields = rest.rsplitn(4, ',');
+            let lon = coordinate(fields.next()?, 'E', 'W')?;
+            let lat = coordinate(fields.next()?, 'N', 'S')?;
+            let province = fields.next()?.trim();
+            code.starts_with('s').then(|| Site {
+                code: code.to_string(),
+                province: province.to_string(),
+                lat,
+                lon,
+            })
+        };
+        Ok(Self(s.lines().filter_map(site).collect()))
+    }
+}
+/// Parses coordinate with hemisphere suffix, like "79.37W"
+///
+/// # Parameters
+/// * `text` - coordinate
+/// * `positive` - suffix of positive hemisphere
+/// * `negative` - suffix of negative hemisphere
+fn coordinate(text: &str, positive: char, negative: char) -> Option<f64> {
+    let text = text.trim();
+    if let Some(value) = text.strip_suffix(positive) {
+        value.parse().ok()
+    } else {
+        text.strip_suffix(negative)?
+            .parse::<f64>()
+            .ok()
+            .map(|value| -value)
+    }
+}
+
+//
+// Citypage weather structures
+//
+
+/// Weather conditions, either observed or forecast for part of day
+#[derive(Default)]
+struct Conditions {
+    icon: Option<u32>,
+    temperature: Option<f32>,
+    /// Forecast period's high or low temperature
+    high: Option<f32>,
+    low: Option<f32>,
+    humidity: Option<f32>,
+    /// Wind speed, in km/h
+    wind_speed: Option<f32>,
+    /// Precipitation, in millimeters; only reported for forecasts with significant accumulation
+    precipitation: Option<f32>,
+    /// Pressure at sea level, in hPa; only reported for current conditions
+    pressure: Option<f32>,
+}
+
+/// Citypage weather of site
+#[derive(Deserialize)]
+#[serde(from = "SiteData")]
+struct CityPage {
+    /// Observing station's name
+    station: Option<String>,
+    /// UTC time of observation
+    observed: Option<NaiveDateTime>,
+    current: Conditions,
+    /// Local date when forecast was issued
+    issued: Option<NaiveDate>,
+    /// Forecast periods, each day or night, in order
+    forecasts: Vec<Conditions>,
+}
+
+/// Citypage weather document, as it's published
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SiteData {
+    current_conditions: Option<CurrentConditions>,
+    forecast_group: Option<ForecastGroup>,
+}
+
+/// Time of observation or forecast issue, in UTC and local zone
+#[derive(Deserialize)]
+struct DateTime {
+    #[serde(rename = "@zone")]
+    zone: Option<String>,
+    #[serde(rename = "timeStamp")]
+    timestamp: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurrentConditions {
+    station: Option<String>,
+    #[serde(default)]
+    date_time: Vec<DateTime>,
+    #[serde(default, deserialize_with = "number")]
+    icon_code: Option<u32>,
+    #[serde(default, deserialize_with = "number")]
+    temperature: Option<f32>,
+    #[serde(default, deserialize_with = "number")]
+    relative_humidity: Option<f32>,
+    /// Pressure at sea level, in kPa
+    #[serde(default, deserialize_with = "number")]
+    pressure: Option<f32>,
+    wind: Option<Wind>,
+}
+
+/// Wind speed, in km/h; calm wind is reported as text
+#[derive(Deserialize)]
+struct Wind {
+    #[serde(default, deserialize_with = "number")]
+    speed: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ForecastGroup {
+    #[serde(default)]
+    date_time: Vec<DateTime>,
+    #[serde(default)]
+    forecast: Vec<Forecast>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Forecast {
+    abbreviated_forecast: Option<AbbreviatedForecast>,
+    temperatures: Option<Temperatures>,
+    winds: Option<Winds>,
+    precipitation: Option<Precipitation>,
+    #[serde(default, deserialize_with = "number")]
+    relative_humidity: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AbbreviatedForecast {
+    #[serde(default, deserialize_with = "number")]
+    icon_code: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct Temperatures {
+    #[serde(default)]
+    temperature: Vec<Temperature>,
+}
+
+/// Period's temperature, either "high" or "low"
+#[derive(Deserialize)]
+struct Temperature {
+    #[serde(rename = "@class")]
+    class: Option<String>,
+    #[serde(rename = "$text", default, deserialize_with = "number")]
+    value: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct Winds {
+    #[serde(default)]
+    wind: Vec<Wind>,
+}
+
+#[derive(Deserialize)]
+struct Precipitation {
+    #[serde(default)]
+    accumulation: Vec<Accumulation>,
+}
+
+/// Significant accumulation of rain or snow
+#[derive(Deserialize)]
+struct Accumulation {
+    amount: Option<Amount>,
+}
+
+#[derive(Deserialize)]
+struct Amount {
+    #[serde(rename = "@units")]
+    units: Option<String>,
+    #[serde(rename = "$text", default, deserialize_with = "number")]
+    value: Option<f32>,
+}
+
+impl From<SiteData> for CityPage {
+    fn from(data: SiteData) -> Self {
+        let current = data.current_conditions;
+        let group = data.forecast_group;
+        let observed = current
+            .as_ref()
+            .and_then(|current| timestamp(&current.date_time, |zone| zone == "UTC"))
+            .and_then(|time| NaiveDateTime::parse_from_str(time, TIMESTAMP_FORMAT).ok());
+        let issued = group
+            .as_ref()
+            .and_then(|group| timestamp(&group.date_time, |zone| zone != "UTC"))
+            .and_then(|time| time.get(..8))
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok());
+        Self {
+            station: current.as_ref().and_then(|current| current.station.clone()),
+            observed,
+            current: current.map(Conditions::from).unwrap_or_default(),
+            issued,
+            forecasts: group
+                .map(|group| group.forecast.into_iter().map(Conditions::from).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<CurrentConditions> for Conditions {
+    fn from(current: CurrentConditions) -> Self {
+        Self {
+            icon: current.icon_code,
+            temperature: current.temperature,
+            humidity: current.relative_humidity,
+            wind_speed: current.wind.map(|wind| wind.speed()),
+            pressure: current.pressure.map(|kpa| kpa * 10.0),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Forecast> for Conditions {
+    fn from(forecast: Forecast) -> Self {
+        let icon = forecast
+            .abbreviated_forecast
+            .and_then(|abbreviated| abbreviated.icon_code);
+        let temperatures = forecast
+            .temperatures
+            .map(|temperatures| temperatures.temperature)
+            .unwrap_or_default();
+        let temperature = |class: &str| {
+            temperatures
+                .iter()
+                .find(|temperature| temperature.class.as_deref() == Some(class))
+                .and_then(|temperature| temperature.value)
+        };
+        let precipitation = forecast.precipitation.and_then(|precipitation| {
+            precipitation
+                .accumulation
+                .into_iter()
+                .filter_map(|accumulation| accumulation.amount)
+                .map(|amount| {
+                    let scale = match amount.units.as_deref() {
+                        Some("cm") => 10.0,
+                        _ => 1.0,
+                    };
+                    amount.value.unwrap_or(0.0) * scale
+                })
+                .reduce(|total, amount| total + amount)
+        });
+        Self {
+            icon,
+            temperature: None,
+            high: temperature("high"),
+            low: temperature("low"),
+            humidity: forecast.relative_humidity,
+            wind_speed: forecast
+                .winds
+                .and_then(|winds| winds.wind.iter().map(Wind::speed).reduce(f32::max)),
+            precipitation,
+            pressure: None,
+        }
+    }
+}
+
+impl Wind {
+    fn speed(&self) -> f32 {
+        self.speed.unwrap_or(0.0)
+    }
+}
+/// Deserializes number from element's text, which is empty or textual when number is unavailable
+fn number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    let text = Option::<String>::deserialize(deserializer)?;
+    Ok(text.and_then(|text| text.trim().parse().ok()))
+}
+/// Timestamp of time in zone of interest
+///
+/// # Parameters
+/// * `times` - same time in different zones
+/// * `zone` - tells whether zone is of interest
+fn timestamp(times: &[DateTime], zone: impl Fn(&str) -> bool) -> Option<&str> {
+    times
+        .iter()
+        .find(|time| time.zone.as_deref().is_some_and(&zone))
+        .and_then(|time| time.timestamp.as_deref())
+}
+
+impl CityPage {
+    /// Local dates of forecast periods
+    ///
+    /// Periods go in pairs of day and night, starting from issue date; forecast issued
+    /// in the evening starts with night, so each day period starts next date
+    fn forecast_dates(&self) -> Vec<Option<NaiveDate>> {
+        let mut date = self.issued;
+        let mut started = false;
+        self.forecasts
+            .iter()
+            .map(|forecast| {
+                if forecast.high.is_some() && started {
+                    date = date.and_then(|date| date.checked_add_days(Days::new(1)));
+                }
+                started = true;
+                date
+            })
+            .collect()
+    }
+}
+
+impl Conditions {
+    fn kind(&self) -> WeatherKind {
+        if self.icon.is_none() {
+            schema::missing("iconCode", "unknown weather");
+        }
+        self.icon
+            .map_or(WeatherKind::Unknown, normalize::envcanada_kind)
+    }
+}
+/// Combines day's forecast periods, day one and night one, into weather
+///
+/// # Parameters
+/// * `periods` - periods of same date, in order
+fn day_weather(periods: &[&Conditions]) -> WeatherInfo {
+    // Day period tells day's weather, if forecast still has it
+    let main = periods
+        .iter()
+        .find(|period| period.high.is_some())
+        .or(periods.first())
+        .copied();
+    let high = periods.iter().find_map(|period| period.high);
+    let low = periods.iter().find_map(|period| period.low);
+    let temperature = match (high, low) {
+        (Some(high), Some(low)) => (high + low) / 2.0,
+        (Some(temperature), None) | (None, Some(temperature)) => temperature,
+        (None, None) => 0.0,
+    };
+    let humidity = periods.iter().find_map(|period| period.humidity);
+    if humidity.is_none() {
+        schema::missing("forecast.relativeHumidity", "0");
+    }
+    let precipitation = periods
+        .iter()
+        .filter_map(|period| period.precipitation)
+        .reduce(|total, amount| total + amount);
+    WeatherInfo {
+        weather: main.map_or(WeatherKind::Unknown, Conditions::kind),
+        temperature,
+        temperature_min: high.and(low),
+        temperature_max: low.and(high),
+        wind_speed: normalize::kmh_to_ms(
+            periods
+                .iter()
+                .filter_map(|period| period.wind_speed)
+                .fold(0.0, f32::max),
+        ),
+        humidity: humidity.unwrap_or(0.0),
+        precipitation,
+        pressure: None,
+        condition_code: main
+            .and_then(|period| period.icon)
+            .map(|icon| icon.to_string()),
+        meta: Meta::new(DataKind::Forecast),
+    }
+}
+
+#[async_trait]
+impl super::Provider for EnvCanada {
+    fn new(config: &Section) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let site = match config.get("site").map(|site| site.trim()) {
+            None | Some("") => None,
+            Some(site) => {
+                let valid = site
+                    .split_once('/')
+                    .is_some_and(|(province, code)| !province.is_empty() && code.starts_with('s'));
+                if !valid {
+                    bail!("Parameter 'site' should look like 'ON/s0000458', got '{site}'");
+                }
+                Some(site.to_string())
+            }
+        };
+        Ok(Self {
+            site,
+            base_url: super::base_url(config, API_URL)?,
+        })
+    }
+
+    fn info() -> &'static ProviderInfo
+    where
+        Self: Sized,
+    {
+        const INFO: ProviderInfo = ProviderInfo {
+            description: "Environment Canada (https://weather.gc.ca/), official citypage weather of Meteorological Service of Canada; free, no key needed; covers Canada with current conditions and forecasts up to 6 days",
+            attribution: Some(Attribution {
+                text: "Data Source: Environment and Climate Change Canada",
+                url: "https://weather.gc.ca/",
+                required: true,
+            }),
+            params: &[
+                ParamDesc {
+                    id: "site",
+                    name: "Citypage site",
+                    description: "province and site code, like ON/s0000458 for Toronto; nearest site to location is used if not specified",
+                    required: false,
+                    default: None,
+                    kind: ParamKind::String,
+                },
+                base_url_param(API_URL),
+            ],
+            hourly: false,
+            lightning: false,
+        };
+        &INFO
+    }
+
+    async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let site = match &self.site {
+            Some(site) => site.clone(),
+            None => {
+                let (lat, lon) = geocode::coordinates(&location).await?;
+                let sites = restful_get::<SiteList, ApiError>(site_list_url(&self.base_url))
+                    .await
+                    .with_context(|| anyhow!("Could not obtain list of citypage sites"))?;
+                nearest_site(sites, (lat, lon))?
+            }
+        };
+        let page = restful_get_as::<CityPage, ApiError, Xml>(citypage_url(&self.base_url, &site))
+            .await
+            .with_context(|| anyhow!("Could not obtain citypage weather of site {site}"))?;
+        map_response(&site, page, date)
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        check_date(date, location)?;
+        let page = |site: &str| {
+            PlannedRequest::get("citypage weather", citypage_url(&self.base_url, site))
+        };
+        Ok(match &self.site {
+            Some(site) => vec![page(site)],
+            None => geocode::plan(location)
+                .into_iter()
+                .chain([
+                    PlannedRequest::get("nearest citypage site", site_list_url(&self.base_url)),
+                    page("{province}/{site}"),
+                ])
+                .collect(),
+        })
+    }
+}
+/// Site nearest to location
+///
+/// # Parameters
+/// * `sites` - list of citypage sites
+/// * `coordinates` - location's latitude and longitude
+///
+/// # Returns
+/// Site, as "<province>/<code>", or error if list is empty
+fn nearest_site(sites: SiteList, coordinates: (f64, f64)) -> anyhow::Result<String> {
+    sites
+        .0
+        .into_iter()
+        .min_by(|one, other| {
+            let distance = |site: &Site| distance_km(coordinates, (site.lat, site.lon));
+            distance(one).total_cmp(&distance(other))
+        })
+        .map(|site| format!("{}/{}", site.province, site.code))
+        .ok_or_else(|| anyhow!("No citypage sites found"))
+}
+/// Converts site's citypage weather into weather information of requested date
+///
+/// # Parameters
+/// * `site` - site, as "<province>/<code>"
+/// * `page` - citypage weather
+/// * `date` - requested date; `None` means current conditions
+///
+/// # Returns
+/// Weather information, or error if page lacks requested data
+fn map_response(site: &str, page: CityPage, date: Option<Date>) -> anyhow::Result<WeatherInfo> {
+    let Some(date) = date else {
+        let current = &page.current;
+        let temperature = current
+            .temperature
+            .ok_or_else(|| anyhow!("Site {site} reports no current conditions"))?;
+        return Ok(WeatherInfo {
+            weather: current.kind(),
+            temperature,
+            temperature_min: None,
+            temperature_max: None,
+            wind_speed: normalize::kmh_to_ms(current.wind_speed.unwrap_or(0.0)),
+            humidity: current.humidity.unwrap_or(0.0),
+            precipitation: None,
+            pressure: current.pressure,
+            condition_code: current.icon.map(|icon| icon.to_string()),
+            meta: Meta::new(DataKind::Observation)
+                .with_timestamp(page.observed.map(|time| time.and_utc().timestamp()))
+                .with_source(
+                    page.station
+                        .clone()
+                        .map(|station| format!("station {station}")),
+                ),
+        });
+    };
+    let day = date
+        .to_naive()
+        .ok_or_else(|| anyhow!("Date {date} doesn't exist"))?;
+    let periods: Vec<_> = page
+        .forecast_dates()
+        .into_iter()
+        .zip(&page.forecasts)
+        .filter(|(period_date, _)| *period_date == Some(day))
+        .map(|(_, period)| period)
+        .collect();
+    if periods.is_empty() {
+        bail!("No forecast for {date}");
+    }
+    Ok(day_weather(&periods))
+}
+/// Checks that date isn't in past, since citypage weather has only current conditions and forecasts
+///
+/// # Parameters
+/// * `date` - requested date; `None` means current conditions
+/// * `location` - location, whose timezone defines today's date if it's specified with coordinates
+fn check_date(date: Option<Date>, location: &str) -> anyhow::Result<()> {
+    let Some(date) = date else {
+        return Ok(());
+    };
+    let day = date
+        .to_naive()
+        .ok_or_else(|| anyhow!("Date {date} doesn't exist"))?;
+    if day < timezone::today(timezone::of_location(location)) {
+        bail!("Sorry, Environment Canada provides only current conditions and forecasts");
+    }
+    Ok(())
+}
+/// URL of sites' list, with their coordinates
+fn site_list_url(base_url: &str) -> ApiUrl {
+    ApiUrl::new(base_url)
+        .segment("citypage_weather")
+        .segment("docs")
+        .segment("site_list_en.csv")
+}
+/// URL of site's citypage weather, in English
+///
+/// # Parameters
+/// * `base_url` - Datamart root
+/// * `site` - site, as "<province>/<code>"
+fn citypage_url(base_url: &str, site: &str) -> ApiUrl {
+    let (province, code) = site.split_once('/').unwrap_or(("", site));
+    ApiUrl::new(base_url)
+        .segment("citypage_weather")
+        .segment("xml")
+        .segment(province)
+        .segment(format_args!("{code}_e.xml"))
+}
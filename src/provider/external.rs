@@ -0,0 +1,350 @@
+//! Providers implemented by external executables, using JSON-over-stdio protocol
+//!
+//! Every executable in `<config dir>/weather-cli/providers/` is registered as provider
+//! named after its file name without extension. Executable is run with single argument:
+//!
+//! * `info` - should print provider description as JSON object, like
+//!   `{"description": "...", "params": [{"id": "apikey", "name": "API key", "description": "..."}],
+//!   "attribution": {"text": "...", "url": "...", "required": true}, "hourly": true}`;
+//!   `params`, `attribution` and `hourly` are optional. Parameter may also have `"required": false`
+//!   along with `default` value, and `kind` of value, one of `string` (default), `integer`, `url`,
+//!   `secret` or `choice`, the latter with list of `choices`
+//! * `weather` - receives `{"config": {...}, "location": "...", "date": "YYYY-MM-DD", "hour": 18}`
+//!   on stdin, where `date` is `null` for current conditions and `hour` is local hour of day,
+//!   passed only to providers which declared themselves `hourly`, and should print weather as JSON object,
+//!   like `{"weather": "rain", "temperature": 12.5, "wind_speed": 3.1, "humidity": 80,
+//!   "precipitation": 1.2, "pressure": 1013, "condition_code": "..."}`, in metric units,
+//!   with optional `precipitation` and `pressure`, the latter in hPa. Instead of `weather`,
+//!   reply may have `condition_scheme`, one of `normalize::CodeScheme` identifiers,
+//!   so weather kind is derived from `condition_code` same way as by built-in providers.
+//!   On failure, it should exit with non-zero status, printing either `{"error": "..."}` or plain message
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::app_dirs;
+use crate::config::Section;
+use crate::normalize::CodeScheme;
+use crate::provider_registry::ProviderFactory;
+use crate::CowString;
+
+use super::{
+    Attribution, Date, ParamDesc, ParamKind, PlannedRequest, Provider, ProviderInfo, WeatherInfo,
+};
+
+/// How long external provider may run
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Provider description printed by `info` command
+#[derive(Deserialize)]
+struct InfoReply {
+    description: String,
+    #[serde(default)]
+    params: Vec<ParamReply>,
+    attribution: Option<AttributionReply>,
+    #[serde(default)]
+    hourly: bool,
+}
+
+#[derive(Deserialize)]
+struct ParamReply {
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// Parameters are required unless stated otherwise
+    #[serde(default = "required_by_default")]
+    required: bool,
+    #[serde(default)]
+    default: Option<String>,
+    #[serde(default)]
+    kind: Option<String>,
+    /// Allowed values of `choice` parameter
+    #[serde(default)]
+    choices: Vec<String>,
+}
+
+impl ParamReply {
+    /// Converts declared kind of parameter
+    ///
+    /// # Parameters
+    /// * `leak` - converts owned string into static one
+    fn kind(&self, leak: impl Fn(String) -> &'static str) -> anyhow::Result<ParamKind> {
+        Ok(match self.kind.as_deref().unwrap_or("string") {
+            "string" => ParamKind::String,
+            "integer" => ParamKind::Integer,
+            "url" => ParamKind::Url,
+            "secret" => ParamKind::Secret,
+            "choice" => {
+                let choices: Vec<_> = self.choices.iter().cloned().map(leak).collect();
+                ParamKind::Choice(Box::leak(choices.into_boxed_slice()))
+            }
+            other => bail!("Unknown kind '{other}' of parameter '{}'", self.id),
+        })
+    }
+}
+
+fn required_by_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct AttributionReply {
+    text: String,
+    url: String,
+    #[serde(default)]
+    required: bool,
+}
+
+/// Failure printed by `weather` command
+#[derive(Deserialize)]
+struct ErrorReply {
+    error: String,
+}
+
+/// Directory where external providers are looked for
+fn providers_dir() -> Option<PathBuf> {
+    app_dirs::config_dir().map(|path| path.join("providers"))
+}
+/// Finds external providers and describes them
+///
+/// Executables which fail to describe themselves are skipped with warning
+///
+/// # Returns
+/// Provider name and its factory, for each found executable
+pub async fn discover() -> Vec<(String, ExternalFactory)> {
+    let Some(entries) = providers_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_executable(path))
+        .collect();
+    paths.sort();
+
+    let mut factories = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        match ExternalFactory::describe(&path).await {
+            Ok(factory) => factories.push((name.to_string(), factory)),
+            Err(error) => tracing::warn!("Skipping external provider '{name}': {error:#}"),
+        }
+    }
+    factories
+}
+/// Whether file can be executed
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+/// Runs executable with single argument, passing input on stdin
+///
+/// # Returns
+/// Whether executable succeeded, and its stdout
+async fn run(path: &Path, command: &str, input: &[u8]) -> anyhow::Result<(bool, String)> {
+    let mut child = Command::new(path)
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| anyhow!("Could not run '{}'", path.display()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Executable may not read its input at all
+        let _ = stdin.write_all(input).await;
+    }
+    let output = tokio::time::timeout(TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow!("'{}' didn't finish in {TIMEOUT:?}", path.display()))??;
+    Ok((
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    ))
+}
+
+/// Factory of external provider, which knows its executable and description
+pub struct ExternalFactory {
+    path: PathBuf,
+    info: &'static ProviderInfo,
+}
+
+impl ExternalFactory {
+    /// Obtains provider's description by running its `info` command
+    async fn describe(path: &Path) -> anyhow::Result<Self> {
+        let (success, output) = run(path, "info", &[]).await?;
+        if !success {
+            bail!("'info' command failed");
+        }
+        let reply: InfoReply = serde_json::from_str(&output)
+            .with_context(|| anyhow!("Could not parse 'info' reply"))?;
+        // Provider infos are static, like built-in ones; external providers are described once per run
+        let leak = |text: String| -> &'static str { Box::leak(text.into_boxed_str()) };
+        let params: Vec<_> = reply
+            .params
+            .into_iter()
+            .map(|param| {
+                Ok(ParamDesc {
+                    kind: param.kind(leak)?,
+                    id: leak(param.id),
+                    name: leak(param.name),
+                    description: leak(param.description),
+                    required: param.required,
+                    default: param.default.map(leak),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let info = ProviderInfo {
+            description: leak(reply.description),
+            attribution: reply.attribution.map(|attribution| Attribution {
+                text: leak(attribution.text),
+                url: leak(attribution.url),
+                required: attribution.required,
+            }),
+            params: Box::leak(params.into_boxed_slice()),
+            hourly: reply.hourly,
+            // Protocol has no lightning command
+            lightning: false,
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            info: Box::leak(Box::new(info)),
+        })
+    }
+}
+
+impl ProviderFactory for ExternalFactory {
+    fn create(&self, config: &Section) -> anyhow::Result<Box<dyn Provider>> {
+        for param in self.info.params.iter().filter(|param| param.required) {
+            if !config.contains_key(param.id) {
+                bail!("Missing parameter '{}'", param.id);
+            }
+        }
+        Ok(Box::new(ExternalProvider {
+            path: self.path.clone(),
+            config: config.clone(),
+            hourly: self.info.hourly,
+        }))
+    }
+
+    fn info(&self) -> &'static ProviderInfo {
+        self.info
+    }
+}
+
+/// Provider implemented by external executable
+///
+/// Created only by `ExternalFactory`, since it needs executable's path
+pub struct ExternalProvider {
+    path: PathBuf,
+    config: Section,
+    /// Whether executable accepts hour of day
+    hourly: bool,
+}
+
+#[async_trait]
+impl Provider for ExternalProvider {
+    fn new(_config: &Section) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        bail!("External provider can only be created from its executable")
+    }
+
+    fn info() -> &'static ProviderInfo
+    where
+        Self: Sized,
+    {
+        const INFO: ProviderInfo = ProviderInfo {
+            description: "External provider",
+            attribution: None,
+            params: &[],
+            hourly: false,
+            lightning: false,
+        };
+        &INFO
+    }
+
+    async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let request = json!({
+            "config": self.config,
+            "location": location,
+            "date": date.map(|date| date.without_hour().to_string()),
+            "hour": date.and_then(|date| date.hour).filter(|_| self.hourly),
+        });
+        let (success, output) = run(&self.path, "weather", request.to_string().as_bytes()).await?;
+        if !success {
+            let message = serde_json::from_str::<ErrorReply>(&output)
+                .map(|reply| reply.error)
+                .unwrap_or_else(|_| output.trim().to_string());
+            bail!("External provider failed: {message}");
+        }
+        weather_reply(&output).with_context(|| anyhow!("Could not parse external provider's reply"))
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        // Config isn't shown, since it usually holds secrets
+        let input = json!({
+            "location": location,
+            "date": date.map(|date| date.without_hour().to_string()),
+            "hour": date.and_then(|date| date.hour).filter(|_| self.hourly),
+        });
+        Ok(vec![PlannedRequest::run(
+            "weather",
+            format!("{} weather < {input}", self.path.display()),
+        )])
+    }
+}
+/// Parses weather reply; if it has no weather kind, kind is derived from condition code
+/// by its scheme
+///
+/// # Parameters
+/// * `output` - reply printed by provider
+fn weather_reply(output: &str) -> anyhow::Result<WeatherInfo> {
+    let mut reply: serde_json::Value = serde_json::from_str(output)?;
+    if let Some(fields) = reply.as_object_mut() {
+        if let Some(scheme) = fields.remove("condition_scheme") {
+            let scheme: CodeScheme = scheme
+                .as_str()
+                .ok_or_else(|| anyhow!("'condition_scheme' should be string"))?
+                .parse()?;
+            if !fields.contains_key("weather") {
+                let code = fields
+                    .get("condition_code")
+                    .and_then(|code| code.as_str())
+                    .ok_or_else(|| {
+                        anyhow!("'condition_scheme' requires 'condition_code' string")
+                    })?;
+                fields.insert("weather".to_string(), json!(scheme.kind(code)));
+            }
+        }
+    }
+    Ok(serde_json::from_value(reply)?)
+}
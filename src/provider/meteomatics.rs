@@ -0,0 +1,458 @@
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::config::Section;
+use crate::geocode;
+use crate::http_backend::Request;
+use crate::normalize;
+use crate::redact;
+use crate::schema;
+use crate::timezone;
+use crate::utils::{distance_km, restful_request};
+use crate::CowString;
+
+use super::{
+    base_url_param, Attribution, Date, LightningInfo, Meta, ParamDesc, ParamKind, PlannedRequest,
+    ProviderInfo, WeatherInfo,
+};
+/// API root
+const API_URL: &str = "https://api.meteomatics.com";
+/// Kilometers per degree of latitude, used to build lightning search box
+const KM_PER_DEGREE: f64 = 111.0;
+/// Meteomatics provider
+pub struct Meteomatics {
+    username: String,
+    password: String,
+    /// API root, own one unless overridden
+    base_url: String,
+}
+
+//
+// Error handling structures
+//
+
+/// API failure; Meteomatics may respond with either JSON or plain text
+#[derive(Debug)]
+struct ApiError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorJson {
+    message: String,
+}
+
+impl FromStr for ApiError {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let message = match serde_json::from_str::<ApiErrorJson>(s) {
+            Ok(error) => error.message,
+            Err(_) => s.trim().to_string(),
+        };
+        Ok(Self { message })
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("API error: {}", self.message))
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+//
+// Weather response structures
+//
+
+/// Weather response root
+#[derive(Deserialize)]
+struct WeatherData {
+    data: Vec<ParameterData>,
+}
+
+impl FromStr for WeatherData {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        schema::parse(s)
+    }
+}
+
+impl WeatherData {
+    /// Get first value of specified parameter
+    fn value(&self, parameter: &str) -> anyhow::Result<f32> {
+        self.first(parameter)
+            .map(|date| date.value)
+            .ok_or_else(|| anyhow!("Response misses parameter '{parameter}'"))
+    }
+    /// Get value of specified parameter at specified time
+    fn value_at(&self, parameter: &str, time: DateTime<Utc>) -> anyhow::Result<f32> {
+        self.data
+            .iter()
+            .find(|data| data.parameter == parameter)
+            .and_then(|data| data.coordinates.first())
+            .and_then(|coords| coords.dates.iter().find(|date| date.date == Some(time)))
+            .map(|date| date.value)
+            .ok_or_else(|| anyhow!("Response misses parameter '{parameter}' at {time}"))
+    }
+    /// Get first dated value of specified parameter, if any
+    fn first(&self, parameter: &str) -> Option<&DateValue> {
+        self.data
+            .iter()
+            .find(|data| data.parameter == parameter)
+            .and_then(|data| data.coordinates.first())
+            .and_then(|coords| coords.dates.first())
+    }
+}
+
+#[derive(Deserialize)]
+struct ParameterData {
+    parameter: String,
+    coordinates: Vec<CoordinateData>,
+}
+
+#[derive(Deserialize)]
+struct CoordinateData {
+    dates: Vec<DateValue>,
+}
+
+#[derive(Deserialize)]
+struct DateValue {
+    /// Time value refers to
+    date: Option<DateTime<Utc>>,
+    value: f32,
+}
+
+//
+// Lightning response structures
+//
+
+/// Lightning list, returned as semicolon-separated CSV
+struct LightningList(Vec<(f64, f64)>);
+
+impl FromStr for LightningList {
+    type Err = std::num::ParseFloatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut strikes = Vec::new();
+        // First line is header, "stroke_time:sql;stroke_lat:d;stroke_lon:d;stroke_current:kA"
+        for line in s.lines().skip(1).filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.split(';').skip(1);
+            let lat = fields.next().unwrap_or_default().trim().parse()?;
+            let lon = fields.next().unwrap_or_default().trim().parse()?;
+            strikes.push((lat, lon));
+        }
+        Ok(Self(strikes))
+    }
+}
+
+#[async_trait]
+impl super::Provider for Meteomatics {
+    fn new(config: &Section) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let param = |name: &str| {
+            config
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Missing parameter '{name}'"))
+        };
+        let password = param("password")?;
+        // Password is sent in header, yet it may still surface in errors
+        redact::register(&password);
+        Ok(Self {
+            username: param("username")?,
+            password,
+            base_url: super::base_url(config, API_URL)?,
+        })
+    }
+
+    fn info() -> &'static ProviderInfo
+    where
+        Self: Sized,
+    {
+        const INFO: ProviderInfo = ProviderInfo {
+            description: "Meteomatics (https://www.meteomatics.com/); supports lightning data",
+            attribution: Some(Attribution {
+                text: "Weather data by Meteomatics",
+                url: "https://www.meteomatics.com/",
+                required: false,
+            }),
+            params: &[
+                ParamDesc {
+                    id: "username",
+                    name: "API username",
+                    description: "used to authenticate user requests",
+                    required: true,
+                    default: None,
+                    kind: ParamKind::String,
+                },
+                ParamDesc {
+                    id: "password",
+                    name: "API password",
+                    description: "used to authenticate user requests",
+                    required: true,
+                    default: None,
+                    kind: ParamKind::Secret,
+                },
+                base_url_param(API_URL),
+            ],
+            hourly: true,
+            lightning: true,
+        };
+        &INFO
+    }
+
+    async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let (lat, lon) = geocode::coordinates(&location).await?;
+        let (time, period) = time_and_period(date, timezone::at(lat, lon));
+        let url = weather_url(&self.base_url, &time, period, &format!("{lat:.4},{lon:.4}"));
+        let data = restful_request::<WeatherData, ApiError>(authorized(
+            &url,
+            &self.username,
+            &self.password,
+        ))
+        .await
+        .with_context(|| anyhow!("Could not obtain weather forecast"))?;
+        map_response(period, data)
+    }
+
+    async fn get_hourly(
+        &self,
+        location: CowString,
+        start: Date,
+        hours: u32,
+    ) -> anyhow::Result<Vec<WeatherInfo>> {
+        let (lat, lon) = geocode::coordinates(&location).await?;
+        let tz = timezone::at(lat, lon);
+        let times: Vec<_> = super::hours_from(start, hours)?
+            .into_iter()
+            .map(|time| utc_time(time, tz))
+            .collect();
+        let (Some(first), Some(last)) = (times.first(), times.last()) else {
+            return Ok(Vec::new());
+        };
+        // All hours are requested at once, as time series with hourly step
+        let format = "%Y-%m-%dT%H:%M:%SZ";
+        let range = format!("{}--{}:PT1H", first.format(format), last.format(format));
+        let url = weather_url(&self.base_url, &range, "1h", &format!("{lat:.4},{lon:.4}"));
+        let data = restful_request::<WeatherData, ApiError>(authorized(
+            &url,
+            &self.username,
+            &self.password,
+        ))
+        .await
+        .with_context(|| anyhow!("Could not obtain hourly weather forecast"))?;
+        times
+            .iter()
+            .map(|time| {
+                weather_info(
+                    "1h",
+                    |parameter| data.value_at(parameter, *time),
+                    Some(*time),
+                )
+            })
+            .collect()
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        let (time, period) = time_and_period(date, timezone::of_location(location));
+        let coordinates = match geocode::coordinates_text(location) {
+            Some((lat, lon)) => format!("{lat},{lon}"),
+            None => geocode::PLACEHOLDER.to_string(),
+        };
+        let weather = PlannedRequest::get(
+            "weather data",
+            weather_url(&self.base_url, &time, period, &coordinates),
+        )
+        .header(
+            "Authorization",
+            format!("Basic {}:{}", self.username, self.password),
+        )
+        .secret(&self.password);
+        Ok(geocode::plan(location)
+            .into_iter()
+            .chain([weather])
+            .collect())
+    }
+
+    async fn get_lightning(
+        &self,
+        location: CowString,
+        radius_km: f32,
+        period_minutes: u32,
+    ) -> anyhow::Result<LightningInfo> {
+        let (lat, lon) = geocode::coordinates(&location).await?;
+        // Search strikes within bounding box, then filter them by actual distance
+        let dlat = f64::from(radius_km) / KM_PER_DEGREE;
+        let dlon = dlat / lat.to_radians().cos().max(0.01);
+        let end = Utc::now();
+        let start = end - Duration::minutes(period_minutes.into());
+        let time_format = "%Y-%m-%dT%H:%M:%SZ";
+        let url = ApiUrl::new(&self.base_url)
+            .segment("get_lightning_list")
+            .query(
+                "time_range",
+                format_args!("{}--{}", start.format(time_format), end.format(time_format)),
+            )
+            .query(
+                "bounding_box",
+                format_args!(
+                    "{:.4},{:.4}_{:.4},{:.4}",
+                    lat + dlat,
+                    lon - dlon,
+                    lat - dlat,
+                    lon + dlon
+                ),
+            )
+            .query("format", "csv");
+        let strikes = restful_request::<LightningList, ApiError>(authorized(
+            &url,
+            &self.username,
+            &self.password,
+        ))
+        .await
+        .with_context(|| anyhow!("Could not obtain lightning data"))?;
+
+        let distances: Vec<f32> = strikes
+            .0
+            .into_iter()
+            .map(|strike| distance_km((lat, lon), strike) as f32)
+            .filter(|distance| *distance <= radius_km)
+            .collect();
+
+        Ok(LightningInfo {
+            strikes: distances.len(),
+            nearest_km: distances.iter().copied().reduce(f32::min),
+            radius_km,
+            period_minutes,
+        })
+    }
+}
+/// Converts weather response into weather information
+///
+/// # Parameters
+/// * `period` - aggregation period which response's precipitation and weather symbol have
+/// * `data` - response
+///
+/// # Returns
+/// Weather information, or error if response lacks some parameter
+fn map_response(period: &str, data: WeatherData) -> anyhow::Result<WeatherInfo> {
+    let timestamp = data
+        .first(&format!("weather_symbol_{period}:idx"))
+        .and_then(|value| value.date);
+    weather_info(period, |parameter| data.value(parameter), timestamp)
+}
+/// Builds weather information out of values of weather parameters
+///
+/// # Parameters
+/// * `period` - aggregation period which precipitation and weather symbol have
+/// * `value` - obtains value of parameter
+/// * `timestamp` - time which values refer to
+///
+/// # Returns
+/// Weather information, or error if some parameter has no value
+fn weather_info(
+    period: &str,
+    value: impl Fn(&str) -> anyhow::Result<f32>,
+    timestamp: Option<DateTime<Utc>>,
+) -> anyhow::Result<WeatherInfo> {
+    let symbol = value(&format!("weather_symbol_{period}:idx"))? as u32;
+    // Data comes from model, so whether it's observation is decided by requested date
+    let meta = Meta {
+        timestamp,
+        ..Default::default()
+    };
+
+    Ok(WeatherInfo {
+        weather: normalize::meteomatics_kind(symbol),
+        temperature: value("t_2m:C")?,
+        temperature_min: None,
+        temperature_max: None,
+        wind_speed: value("wind_speed_10m:ms")?,
+        humidity: value("relative_humidity_2m:p")?,
+        precipitation: Some(value(&format!("precip_{period}:mm"))?),
+        pressure: None,
+        condition_code: Some(symbol.to_string()),
+        meta,
+    })
+}
+/// Converts local time into UTC one
+///
+/// # Parameters
+/// * `local` - time in location's timezone
+/// * `tz` - location's timezone; time is taken as UTC one if it's unknown
+fn utc_time(local: NaiveDateTime, tz: Option<Tz>) -> DateTime<Utc> {
+    tz.and_then(|tz| tz.from_local_datetime(&local).earliest())
+        .map_or_else(|| local.and_utc(), |time| time.with_timezone(&Utc))
+}
+/// Request authorized with account's credentials
+///
+/// # Parameters
+/// * `url` - request's URL
+/// * `username` - account's username
+/// * `password` - account's password
+fn authorized(url: &ApiUrl, username: &str, password: &str) -> Request {
+    Request::get(url.as_str()).basic_auth(username, password)
+}
+/// Time and aggregation period of weather parameters;
+/// current conditions and specific hours use hourly values, specific dates - daily ones
+///
+/// # Parameters
+/// * `date` - requested date, possibly with hour of day
+/// * `tz` - location's timezone, in which hour of day is specified; UTC is used if it's unknown
+fn time_and_period(date: Option<Date>, tz: Option<Tz>) -> (String, &'static str) {
+    let Some(date) = date else {
+        return ("now".to_string(), "1h");
+    };
+    let Some(hour) = date.hour else {
+        return (format!("{date}T12:00:00Z"), "24h");
+    };
+    let local = date
+        .to_naive()
+        .and_then(|day| day.and_hms_opt(hour.into(), 0, 0));
+    let time = match (local, tz) {
+        (Some(local), Some(tz)) => tz
+            .from_local_datetime(&local)
+            .earliest()
+            .map(|time| time.format("%Y-%m-%dT%H:%M:%S%:z").to_string()),
+        _ => None,
+    };
+    let time = time.unwrap_or_else(|| format!("{}T{hour:02}:00:00Z", date.without_hour()));
+    (time, "1h")
+}
+/// URL of weather request
+///
+/// # Parameters
+/// * `base_url` - API root
+/// * `time` - point in time, "now" or ISO 8601 timestamp
+/// * `period` - aggregation period of precipitation and weather symbol
+/// * `coordinates` - "<lat>,<lon>" location
+fn weather_url(base_url: &str, time: &str, period: &str, coordinates: &str) -> ApiUrl {
+    ApiUrl::new(base_url)
+        .segment(time)
+        .segment(format_args!(
+            "t_2m:C,wind_speed_10m:ms,relative_humidity_2m:p,precip_{period}:mm,weather_symbol_{period}:idx"
+        ))
+        .segment(coordinates)
+        .segment("json")
+}
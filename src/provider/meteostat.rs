@@ -0,0 +1,532 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::config::Section;
+use crate::geocode;
+use crate::http_backend::Request;
+use crate::normalize;
+use crate::schema;
+use crate::timezone;
+use crate::utils::restful_request;
+use crate::CowString;
+
+use super::{
+    base_url_param, Attribution, DataKind, Date, Meta, ParamDesc, ParamKind, PlannedRequest,
+    ProviderInfo, WeatherInfo, WeatherKind,
+};
+
+/// API root, at RapidAPI
+const API_URL: &str = "https://meteostat.p.rapidapi.com";
+/// Host which RapidAPI routes requests to
+const RAPIDAPI_HOST: &str = "meteostat.p.rapidapi.com";
+/// Format of observations' local time
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Meteostat provider, which reports observations of weather stations
+pub struct Meteostat {
+    apikey: String,
+    /// Station whose observations are reported; nearest to location unless specified
+    station: Option<String>,
+    /// API root, own one unless overridden
+    base_url: String,
+}
+
+//
+// Error handling structures
+//
+
+/// API failure; RapidAPI reports rejected keys with `message`, Meteostat - with `error`
+#[derive(Debug)]
+struct ApiError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorJson {
+    error: Option<String>,
+    message: Option<String>,
+}
+
+impl FromStr for ApiError {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let message = match serde_json::from_str::<ApiErrorJson>(s) {
+            Ok(error) => error.error.or(error.message).unwrap_or_default(),
+            Err(_) => s.trim().to_string(),
+        };
+        Ok(Self { message })
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("API error: {}", self.message))
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+//
+// Response structures
+//
+
+/// Response root, common to all endpoints
+#[derive(Deserialize)]
+struct Response<T> {
+    data: Option<T>,
+}
+
+impl<T: serde::de::DeserializeOwned> FromStr for Response<T> {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        schema::parse(s)
+    }
+}
+
+/// Observations of station, along with what's needed to pick and summarize them
+struct WeatherData {
+    station: Station,
+    /// Timezone of station or location, if known
+    tz: Option<Tz>,
+    /// Day of observations
+    day: chrono::NaiveDate,
+    observations: Vec<Observation>,
+}
+
+/// Weather station, as listed by nearby stations and station metadata endpoints
+#[derive(Deserialize)]
+struct Station {
+    id: String,
+    name: Option<StationName>,
+    /// Station's timezone; only metadata endpoint reports it
+    timezone: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StationName {
+    en: Option<String>,
+}
+
+impl Station {
+    /// Describes station as data source
+    fn source(&self) -> String {
+        match self.name.as_ref().and_then(|name| name.en.as_deref()) {
+            Some(name) => format!("station {} ({name})", self.id),
+            None => format!("station {}", self.id),
+        }
+    }
+}
+
+/// Hourly observation; any value may be missing when station didn't measure it
+#[derive(Deserialize)]
+struct Observation {
+    /// Local time, like "2024-07-01 18:00:00"
+    time: String,
+    temp: Option<f32>,
+    /// Relative humidity, in percents
+    rhum: Option<f32>,
+    /// Precipitation over hour, in millimeters
+    prcp: Option<f32>,
+    /// Wind speed, in km/h
+    wspd: Option<f32>,
+    /// Pressure at sea level, in hPa
+    pres: Option<f32>,
+    /// Weather condition code
+    coco: Option<u32>,
+}
+
+impl Observation {
+    /// Observation's local time, if it's well-formed
+    fn local_time(&self) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(&self.time, TIME_FORMAT).ok()
+    }
+}
+/// Summarizes observations, either single hour or whole day
+///
+/// # Parameters
+/// * `observations` - observations which have temperature measured, in chronological order
+/// * `tz` - station's timezone, if known
+/// * `source` - description of station
+///
+/// # Returns
+/// Weather, with temperature range if there are several observations
+fn summarize(observations: &[Observation], tz: Option<Tz>, source: String) -> WeatherInfo {
+    let count = observations.len() as f32;
+    let temperatures = observations.iter().filter_map(|obs| obs.temp);
+    let average = |values: Vec<f32>| {
+        (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+    };
+    let humidity = average(observations.iter().filter_map(|obs| obs.rhum).collect());
+    if humidity.is_none() {
+        schema::missing("data.rhum", "0");
+    }
+    // Most frequent condition stands for day; ties are resolved towards more severe one,
+    // which has greater code
+    let mut conditions = BTreeMap::new();
+    for code in observations.iter().filter_map(|obs| obs.coco) {
+        *conditions.entry(code).or_insert(0) += 1;
+    }
+    let code = conditions
+        .into_iter()
+        .max_by_key(|(code, count)| (*count, *code))
+        .map(|(code, _)| code);
+    let timestamp = observations
+        .first()
+        .and_then(Observation::local_time)
+        .and_then(|time| match tz {
+            Some(tz) => tz
+                .from_local_datetime(&time)
+                .earliest()
+                .map(|time| time.timestamp()),
+            None => Some(time.and_utc().timestamp()),
+        });
+    let range = (observations.len() > 1).then(|| {
+        temperatures
+            .clone()
+            .fold((f32::MAX, f32::MIN), |(min, max), temp| {
+                (min.min(temp), max.max(temp))
+            })
+    });
+    WeatherInfo {
+        weather: code.map_or(WeatherKind::Unknown, normalize::meteostat_kind),
+        temperature: temperatures.sum::<f32>() / count,
+        temperature_min: range.map(|(min, _)| min),
+        temperature_max: range.map(|(_, max)| max),
+        wind_speed: normalize::kmh_to_ms(
+            observations
+                .iter()
+                .filter_map(|obs| obs.wspd)
+                .fold(0.0, f32::max),
+        ),
+        humidity: humidity.unwrap_or(0.0),
+        precipitation: Some(observations.iter().filter_map(|obs| obs.prcp).sum()),
+        pressure: average(observations.iter().filter_map(|obs| obs.pres).collect()),
+        condition_code: code.map(|code| code.to_string()),
+        meta: Meta::new(DataKind::Observation)
+            .with_timestamp(timestamp)
+            .with_source(Some(source)),
+    }
+}
+
+#[async_trait]
+impl super::Provider for Meteostat {
+    fn new(config: &Section) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            apikey: config
+                .get("apikey")
+                .ok_or_else(|| anyhow!("Missing parameter 'apikey'"))?
+                .clone(),
+            station: config
+                .get("station")
+                .map(|station| station.trim().to_string())
+                .filter(|station| !station.is_empty()),
+            base_url: super::base_url(config, API_URL)?,
+        })
+    }
+
+    fn info() -> &'static ProviderInfo
+    where
+        Self: Sized,
+    {
+        const INFO: ProviderInfo = ProviderInfo {
+            description: "Meteostat (https://meteostat.net/), via RapidAPI; provides observations of weather stations for today and past dates, no forecasts",
+            attribution: Some(Attribution {
+                text: "Weather data provided by Meteostat",
+                url: "https://meteostat.net/",
+                required: true,
+            }),
+            params: &[
+                ParamDesc {
+                    id: "apikey",
+                    name: "User's RapidAPI key",
+                    description: "used to authenticate user requests",
+                    required: true,
+                    default: None,
+                    kind: ParamKind::Secret,
+                },
+                ParamDesc {
+                    id: "station",
+                    name: "Weather station",
+                    description: "Meteostat station ID, like 03772, whose observations are reported; nearest station to location is used if not specified",
+                    required: false,
+                    default: None,
+                    kind: ParamKind::String,
+                },
+                base_url_param(API_URL),
+            ],
+            hourly: true,
+            lightning: false,
+        };
+        &INFO
+    }
+
+    async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let (station, tz) = self.station(&location).await?;
+        let day = observed_day(date, tz)?;
+        let observations = restful_request::<Response<Vec<Observation>>, ApiError>(rapidapi(
+            &hourly_url(&self.base_url, &station.id, (day, day), tz),
+            &self.apikey,
+        ))
+        .await
+        .with_context(|| anyhow!("Could not obtain observations"))?
+        .data
+        .unwrap_or_default();
+        map_response(
+            date,
+            WeatherData {
+                station,
+                tz,
+                day,
+                observations,
+            },
+        )
+    }
+
+    async fn get_hourly(
+        &self,
+        location: CowString,
+        start: Date,
+        hours: u32,
+    ) -> anyhow::Result<Vec<WeatherInfo>> {
+        let times = super::hours_from(start, hours)?;
+        let (Some(first), Some(last)) = (times.first(), times.last()) else {
+            return Ok(Vec::new());
+        };
+        let (station, tz) = self.station(&location).await?;
+        let days = (
+            first.date(),
+            observed_day(Some(Date::from(last.date())), tz)?,
+        );
+        let observations = restful_request::<Response<Vec<Observation>>, ApiError>(rapidapi(
+            &hourly_url(&self.base_url, &station.id, days, tz),
+            &self.apikey,
+        ))
+        .await
+        .with_context(|| anyhow!("Could not obtain observations"))?
+        .data
+        .unwrap_or_default();
+        let source = station.source();
+        let entries = observations
+            .into_iter()
+            .filter(|obs| obs.temp.is_some())
+            .filter_map(|obs| {
+                let time = obs.local_time()?;
+                Some((time, summarize(&[obs], tz, source.clone())))
+            })
+            .collect();
+        super::pick_hours(&times, entries)
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        let tz = timezone::of_location(location);
+        let day = observed_day(date, tz)?;
+        let planned = |purpose, url| {
+            PlannedRequest::get(purpose, url)
+                .header("X-RapidAPI-Key", self.apikey.clone())
+                .header("X-RapidAPI-Host", RAPIDAPI_HOST)
+                .secret(&self.apikey)
+        };
+        let (lookup, station) = match &self.station {
+            Some(id) => (
+                vec![planned("weather station", station_url(&self.base_url, id))],
+                id.clone(),
+            ),
+            None => {
+                let coordinates = match geocode::coordinates_text(location) {
+                    Some((lat, lon)) => format!("{lat},{lon}"),
+                    None => geocode::PLACEHOLDER.to_string(),
+                };
+                let nearby = planned(
+                    "nearest weather station",
+                    nearby_url(&self.base_url, &coordinates),
+                );
+                (
+                    geocode::plan(location)
+                        .into_iter()
+                        .chain([nearby])
+                        .collect(),
+                    "{station}".to_string(),
+                )
+            }
+        };
+        let observations = planned(
+            "observations",
+            hourly_url(&self.base_url, &station, (day, day), tz),
+        );
+        Ok(lookup.into_iter().chain([observations]).collect())
+    }
+}
+impl Meteostat {
+    /// Finds station whose observations are reported
+    ///
+    /// # Parameters
+    /// * `location` - location whose nearest station is used, unless station is configured
+    ///
+    /// # Returns
+    /// Station along with its timezone, if known
+    async fn station(&self, location: &str) -> anyhow::Result<(Station, Option<Tz>)> {
+        let get = |url: ApiUrl| rapidapi(&url, &self.apikey);
+        // Station's own timezone is preferred, since observations are taken in it
+        Ok(match &self.station {
+            Some(id) => {
+                let station = restful_request::<Response<Station>, ApiError>(get(station_url(
+                    &self.base_url,
+                    id,
+                )))
+                .await
+                .with_context(|| anyhow!("Could not obtain weather station {id}"))?
+                .data
+                .ok_or_else(|| anyhow!("No such weather station: {id}"))?;
+                let tz = station.timezone.as_deref().and_then(|tz| tz.parse().ok());
+                (station, tz)
+            }
+            None => {
+                let (lat, lon) = geocode::coordinates(location).await?;
+                let coordinates = format!("{lat:.4},{lon:.4}");
+                let station = restful_request::<Response<Vec<Station>>, ApiError>(get(nearby_url(
+                    &self.base_url,
+                    &coordinates,
+                )))
+                .await
+                .with_context(|| anyhow!("Could not obtain nearest weather station"))?
+                .data
+                .and_then(|stations| stations.into_iter().next())
+                .ok_or_else(|| anyhow!("No weather stations near '{location}'"))?;
+                (station, timezone::at(lat, lon))
+            }
+        })
+    }
+}
+/// Converts station's observations into weather information of requested date
+///
+/// # Parameters
+/// * `date` - requested date; `None` means current conditions, which are day's latest observation
+/// * `data` - observations
+///
+/// # Returns
+/// Weather information, or error if there are no observations for requested date
+fn map_response(date: Option<Date>, data: WeatherData) -> anyhow::Result<WeatherInfo> {
+    let mut measured = data
+        .observations
+        .into_iter()
+        .filter(|obs| obs.temp.is_some());
+    let selected: Vec<_> = match date.and_then(|date| date.hour) {
+        Some(hour) => measured
+            .filter(|obs| {
+                obs.local_time()
+                    .is_some_and(|time| time.hour() == u32::from(hour))
+            })
+            .collect(),
+        None if date.is_some() => measured.collect(),
+        // Latest observation stands for current conditions
+        None => measured.next_back().into_iter().collect(),
+    };
+    if selected.is_empty() {
+        bail!(
+            "Station {} has no observations for {}",
+            data.station.id,
+            data.day
+        );
+    }
+    Ok(summarize(&selected, data.tz, data.station.source()))
+}
+/// Request with RapidAPI credentials
+///
+/// # Parameters
+/// * `url` - request's URL
+/// * `apikey` - RapidAPI key
+fn rapidapi(url: &ApiUrl, apikey: &str) -> Request {
+    Request::get(url.as_str())
+        .header("X-RapidAPI-Key", apikey)
+        .header("X-RapidAPI-Host", RAPIDAPI_HOST)
+}
+/// Day whose observations are requested
+///
+/// # Parameters
+/// * `date` - requested date; `None` means current conditions, which are today's latest observation
+/// * `tz` - timezone of station or location, if known
+///
+/// # Returns
+/// Day, or error if it's in future, since stations only observe past
+fn observed_day(date: Option<Date>, tz: Option<Tz>) -> anyhow::Result<chrono::NaiveDate> {
+    let today = timezone::today(tz);
+    let Some(date) = date else {
+        return Ok(today);
+    };
+    let day = date
+        .to_naive()
+        .ok_or_else(|| anyhow!("Date {date} doesn't exist"))?;
+    if day > today {
+        bail!("Sorry, Meteostat provides only observations, so date can't be in future");
+    }
+    Ok(day)
+}
+/// URL of station's metadata
+fn station_url(base_url: &str, id: &str) -> ApiUrl {
+    ApiUrl::new(base_url)
+        .segment("stations")
+        .segment("meta")
+        .query("id", id)
+}
+/// URL of station nearest to coordinates
+///
+/// # Parameters
+/// * `base_url` - API root
+/// * `coordinates` - coordinates, as "lat,lon"
+fn nearby_url(base_url: &str, coordinates: &str) -> ApiUrl {
+    let (lat, lon) = coordinates.split_once(',').unwrap_or((coordinates, ""));
+    ApiUrl::new(base_url)
+        .segment("stations")
+        .segment("nearby")
+        .query("lat", lat)
+        .query("lon", lon)
+        .query("limit", 1)
+}
+/// URL of station's hourly observations over range of days
+///
+/// # Parameters
+/// * `base_url` - API root
+/// * `station` - station ID
+/// * `days` - first and last day of observations, inclusive
+/// * `tz` - timezone in which days are taken and observations' time is reported; UTC if unknown
+fn hourly_url(
+    base_url: &str,
+    station: &str,
+    (start, end): (chrono::NaiveDate, chrono::NaiveDate),
+    tz: Option<Tz>,
+) -> ApiUrl {
+    let url = ApiUrl::new(base_url)
+        .segment("stations")
+        .segment("hourly")
+        .query("station", station)
+        .query("start", start)
+        .query("end", end)
+        // Gaps shouldn't be filled with model data, which is the point of station observations
+        .query("model", 0);
+    match tz {
+        Some(tz) => url.query("tz", tz.name()),
+        None => url,
+    }
+}
@@ -1,26 +1,45 @@
 use std::fmt::Display;
 use std::str::FromStr;
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone};
+use chrono_tz::Tz;
 use serde::Deserialize;
 
+use crate::api_url::ApiUrl;
 use crate::config::Section;
-use crate::utils::restful_get;
-use crate::{BoxFuture, CowString};
+use crate::geocode::{self, openweather::OpenWeatherGeocoder};
+use crate::normalize;
+use crate::schema;
+use crate::timezone;
+use crate::utils::{restful_get_as, Json};
+use crate::CowString;
 
-use super::{Date, ParamDesc, ProviderInfo, WeatherInfo, WeatherKind};
+use super::{
+    base_url_param, Attribution, DataKind, Date, Meta, ParamDesc, ParamKind, PlannedRequest,
+    ProviderInfo, WeatherInfo, WeatherKind,
+};
+
+/// API root, under which One Call API endpoint is
+const API_URL: &str = "https://api.openweathermap.org/data/3.0";
+/// How many days daily forecast covers, including today
+const FORECAST_DAYS: i64 = 8;
 
 /// OpenWeather provider
 pub struct OpenWeather {
     apikey: String,
+    /// API root, own one unless overridden
+    base_url: String,
 }
 
 //
 // Error handling structures
 //
 
+/// API failure, also reported by OpenWeather's geocoding API
 #[derive(Debug, Deserialize)]
-struct ApiError {
+pub struct ApiError {
     cod: i32,
     message: String,
 }
@@ -42,55 +61,69 @@ impl Display for ApiError {
 impl std::error::Error for ApiError {}
 
 //
-// Location response structures
+// Weather response structures
 //
 
-/// Location response root
-struct CoordsVec(Vec<Coords>);
-
-impl FromStr for CoordsVec {
-    type Err = serde_json::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(serde_json::from_str(s)?))
-    }
+/// One Call response root; which sections are present depends on excluded ones
+#[derive(Deserialize)]
+struct OneCallData {
+    /// Shift of location's local time from UTC, in seconds
+    timezone_offset: i64,
+    current: Option<Conditions>,
+    daily: Option<Vec<DailyConditions>>,
 }
 
+/// Time machine response root
 #[derive(Deserialize)]
-struct Coords {
-    lat: f64,
-    lon: f64,
+struct TimeMachineData {
+    data: Vec<Conditions>,
 }
 
-//
-// Weather response structures
-//
-
-/// Weather response root
+/// Conditions at single moment
 #[derive(Deserialize)]
-struct WeatherData {
-    main: MainSection,
-    wind: WindSection,
+struct Conditions {
+    /// UNIX timestamp of conditions
+    dt: Option<i64>,
+    temp: f32,
+    humidity: f32,
+    wind_speed: f32,
+    /// Pressure at sea level, in hPa
+    pressure: Option<f32>,
+    rain: Option<PrecipitationSection>,
+    snow: Option<PrecipitationSection>,
+    #[serde(default)]
     weather: Vec<WeatherSection>,
 }
 
-impl FromStr for WeatherData {
-    type Err = serde_json::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        serde_json::from_str(s)
-    }
+/// Conditions aggregated over day
+#[derive(Deserialize)]
+struct DailyConditions {
+    /// UNIX timestamp of day's noon
+    dt: i64,
+    temp: DailyTemperature,
+    humidity: f32,
+    wind_speed: f32,
+    /// Pressure at sea level, in hPa
+    pressure: Option<f32>,
+    /// Day's rain, in millimeters
+    rain: Option<f32>,
+    /// Day's snow, in millimeters
+    snow: Option<f32>,
+    #[serde(default)]
+    weather: Vec<WeatherSection>,
 }
 
 #[derive(Deserialize)]
-struct MainSection {
-    temp: f32,
-    humidity: f32,
+struct DailyTemperature {
+    day: f32,
+    min: f32,
+    max: f32,
 }
 
 #[derive(Deserialize)]
-struct WindSection {
-    speed: f32,
+struct PrecipitationSection {
+    #[serde(rename = "1h")]
+    one_hour: Option<f32>,
 }
 
 #[derive(Deserialize)]
@@ -98,6 +131,55 @@ struct WeatherSection {
     id: u32,
 }
 
+impl From<Conditions> for WeatherInfo {
+    fn from(conditions: Conditions) -> Self {
+        let id = conditions.weather.first().map(|weather| weather.id);
+        if id.is_none() {
+            schema::missing("weather", "unknown weather");
+        }
+        WeatherInfo {
+            weather: id.map_or(WeatherKind::Unknown, normalize::openweather_kind),
+            temperature: conditions.temp,
+            temperature_min: None,
+            temperature_max: None,
+            wind_speed: conditions.wind_speed,
+            humidity: conditions.humidity,
+            precipitation: Some(
+                [conditions.rain, conditions.snow]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|section| section.one_hour)
+                    .fold(0.0, |total, amount| total + amount),
+            ),
+            pressure: conditions.pressure,
+            condition_code: id.map(|id| id.to_string()),
+            meta: Meta::new(DataKind::Observation).with_timestamp(conditions.dt),
+        }
+    }
+}
+
+impl From<DailyConditions> for WeatherInfo {
+    fn from(daily: DailyConditions) -> Self {
+        let id = daily.weather.first().map(|weather| weather.id);
+        if id.is_none() {
+            schema::missing("daily.weather", "unknown weather");
+        }
+        WeatherInfo {
+            weather: id.map_or(WeatherKind::Unknown, normalize::openweather_kind),
+            temperature: daily.temp.day,
+            temperature_min: Some(daily.temp.min),
+            temperature_max: Some(daily.temp.max),
+            wind_speed: daily.wind_speed,
+            humidity: daily.humidity,
+            precipitation: Some(daily.rain.unwrap_or(0.0) + daily.snow.unwrap_or(0.0)),
+            pressure: daily.pressure,
+            condition_code: id.map(|id| id.to_string()),
+            meta: Meta::new(DataKind::Forecast).with_timestamp(Some(daily.dt)),
+        }
+    }
+}
+
+#[async_trait]
 impl super::Provider for OpenWeather {
     fn new(config: &Section) -> anyhow::Result<Self>
     where
@@ -108,6 +190,7 @@ impl super::Provider for OpenWeather {
                 .get("apikey")
                 .ok_or_else(|| anyhow!("Missing parameter 'apikey'"))?
                 .clone(),
+            base_url: super::base_url(config, API_URL)?,
         })
     }
 
@@ -116,74 +199,282 @@ impl super::Provider for OpenWeather {
         Self: Sized,
     {
         const INFO: ProviderInfo = ProviderInfo {
-            description: "OpenWeather (https://openweathermap.org/); doesn't support specific dates, only current conditions",
-            params: &[ParamDesc {
+            description: "OpenWeather (https://openweathermap.org/), using One Call API 3.0 subscription; provides current conditions, historical data and forecasts up to 8 days",
+            attribution: Some(Attribution {
+                text: "Weather data provided by OpenWeather",
+                url: "https://openweathermap.org/",
+                required: true,
+            }),
+            params: &[
+                ParamDesc {
                 id: "apikey",
                 name: "User's API key",
                 description: "used to authenticate user requests",
-            }],
+                required: true,
+                default: None,
+                kind: ParamKind::Secret,
+                },
+                base_url_param(API_URL),
+            ],
+            hourly: false,
+            lightning: false,
         };
         &INFO
     }
 
-    fn get_weather(
+    async fn get_weather(
         &self,
         location: CowString,
         date: Option<Date>,
-    ) -> BoxFuture<anyhow::Result<WeatherInfo>> {
-        let apikey = &self.apikey;
-        if date.is_some() {
-            return Box::pin(async {
-                Err(anyhow!(
-                    "Sorry, requesting weather for specific date isn't supported"
-                ))
-            });
-        }
-        let location_url = format!(
-            "https://api.openweathermap.org/geo/1.0/direct?q={location}&limit=1&appid={apikey}"
+    ) -> anyhow::Result<WeatherInfo> {
+        let geocoder = OpenWeatherGeocoder::new(self.apikey.clone());
+        // Transform location into coordinates, unless it's already specified with them
+        let (lat, lon) = geocode::coordinates_with(&geocoder, &location).await?;
+        let tz = timezone::at(lat, lon);
+        let request = Request::select(date, tz)?;
+        let url = request
+            .url(&self.base_url, &self.apikey, tz)
+            .query("lat", format_args!("{lat:.4}"))
+            .query("lon", format_args!("{lon:.4}"));
+        let data = match request {
+            Request::Current => WeatherData::Current(
+                restful_get_as::<OneCallData, ApiError, Json>(url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain current conditions"))?,
+            ),
+            Request::History(date) => WeatherData::History(
+                restful_get_as::<TimeMachineData, ApiError, Json>(url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain historical weather data"))?,
+                date,
+            ),
+            Request::Daily(date) => WeatherData::Daily(
+                restful_get_as::<OneCallData, ApiError, Json>(url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain weather forecast"))?,
+                date,
+            ),
+        };
+        map_response(data)
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        let tz = timezone::of_location(location);
+        let request = Request::select(date, tz)?;
+        let geocoder = OpenWeatherGeocoder::new(self.apikey.clone());
+        let lookup = geocode::plan_with(&geocoder, location, 1);
+        let (lat, lon) = geocode::coordinates_text(location)
+            .unwrap_or_else(|| ("{lat}".to_string(), "{lon}".to_string()));
+        let data = PlannedRequest::get(
+            request.purpose(),
+            request
+                .url(&self.base_url, &self.apikey, tz)
+                .query("lat", lat)
+                .query("lon", lon),
         );
+        Ok(lookup.into_iter().chain([data]).collect())
+    }
+}
+/// Response of request which serves requested date
+enum WeatherData {
+    Current(OneCallData),
+    /// Time machine response, along with requested date
+    History(TimeMachineData, Date),
+    /// Daily forecast, along with requested date
+    Daily(OneCallData, Date),
+}
+/// Converts response into weather information; daily forecast is picked by its local date
+///
+/// # Returns
+/// Weather information, or error if response lacks requested data
+fn map_response(data: WeatherData) -> anyhow::Result<WeatherInfo> {
+    match data {
+        WeatherData::Current(data) => data
+            .current
+            .map(WeatherInfo::from)
+            .ok_or_else(|| anyhow!("Could not parse response: missing current conditions")),
+        WeatherData::History(data, date) => data
+            .data
+            .into_iter()
+            .next()
+            .map(WeatherInfo::from)
+            .ok_or_else(|| anyhow!("No historical weather data for {date}")),
+        WeatherData::Daily(data, date) => {
+            let day = date.to_naive();
+            data.daily
+                .unwrap_or_default()
+                .into_iter()
+                .find(|daily| {
+                    DateTime::from_timestamp(daily.dt + data.timezone_offset, 0)
+                        .map(|time| time.date_naive())
+                        == day
+                })
+                .map(WeatherInfo::from)
+                .ok_or_else(|| anyhow!("No forecast for {date}"))
+        }
+    }
+}
+/// One Call API request which serves requested date
+#[derive(Clone, Copy)]
+enum Request {
+    /// Current conditions
+    Current,
+    /// Conditions at noon of past day
+    History(Date),
+    /// Daily forecast, for today and `FORECAST_DAYS - 1` following days
+    Daily(Date),
+}
 
-        let data_url =
-            format!("https://api.openweathermap.org/data/2.5/weather?appid={apikey}&units=metric");
-        let fut = async move {
-            // Transform location into coordinates
-            let locs = restful_get::<CoordsVec, ApiError>(location_url)
-                .await
-                .with_context(|| anyhow!("Could not obtain location's coordinates"))?
-                .0;
-
-            let Coords { lat, lon } = locs
-                .first()
-                .ok_or_else(|| anyhow!("Could not obtain coordinates of location '{location}'"))?;
-            // Perform actual weather request
-            let data_url = format!("{data_url}&lat={lat:.4}&lon={lon:.4}");
-
-            let resp = restful_get::<WeatherData, ApiError>(data_url)
-                .await
-                .with_context(|| anyhow!("Could not obtain weather forecast"))?;
-
-            // Primitive weather resolver = fetch first entry, otherwise unknown
-            let weather = if let Some(weather) = resp.weather.first() {
-                // Use weather condition codes form https://openweathermap.org/weather-conditions
-                match weather.id {
-                    200..=299 | 300..=399 | 500..=599 => WeatherKind::Rain,
-                    600..=699 => WeatherKind::Snow,
-                    800 => WeatherKind::Clear,
-                    801..=809 => WeatherKind::Clouds,
-                    700..=799 => WeatherKind::Fog,
-                    _ => WeatherKind::Unknown,
-                }
-            } else {
-                WeatherKind::Unknown
-            };
-
-            Ok(WeatherInfo {
-                weather,
-                temperature: resp.main.temp,
-                wind_speed: resp.wind.speed,
-                humidity: resp.main.humidity,
-            })
+impl Request {
+    /// Selects request by comparing date with today's date at location
+    ///
+    /// # Parameters
+    /// * `date` - requested date; `None` means current conditions
+    /// * `tz` - location's timezone, if known
+    ///
+    /// # Returns
+    /// Request, or error if date is too far in future or doesn't exist
+    fn select(date: Option<Date>, tz: Option<Tz>) -> anyhow::Result<Self> {
+        let Some(date) = date else {
+            return Ok(Self::Current);
         };
-        Box::pin(fut)
+        let day = date
+            .to_naive()
+            .ok_or_else(|| anyhow!("Date {date} doesn't exist"))?;
+        Ok(match (day - timezone::today(tz)).num_days() {
+            ..=-1 => Self::History(date),
+            0..FORECAST_DAYS => Self::Daily(date),
+            _ => bail!("Sorry, OpenWeather forecasts only {FORECAST_DAYS} days, including today"),
+        })
+    }
+    /// Describes data request provides
+    fn purpose(&self) -> &'static str {
+        match self {
+            Self::Current => "current conditions",
+            Self::History(_) => "historical weather data",
+            Self::Daily(_) => "daily forecast",
+        }
+    }
+    /// URL of request, without coordinates
+    ///
+    /// # Parameters
+    /// * `base_url` - API root
+    /// * `apikey` - user's API key
+    /// * `tz` - location's timezone, in which historical conditions are taken at noon;
+    ///   UTC is used if it's unknown
+    fn url(&self, base_url: &str, apikey: &str, tz: Option<Tz>) -> ApiUrl {
+        let url = ApiUrl::new(base_url).segment("onecall");
+        let url = match self {
+            Self::Current => url.query("exclude", "minutely,hourly,daily,alerts"),
+            Self::Daily(_) => url.query("exclude", "current,minutely,hourly,alerts"),
+            Self::History(date) => {
+                let noon = date
+                    .to_naive()
+                    .and_then(|day| day.and_hms_opt(12, 0, 0))
+                    .unwrap_or_default();
+                let time = match tz {
+                    Some(tz) => tz
+                        .from_local_datetime(&noon)
+                        .earliest()
+                        .map(|time| time.timestamp()),
+                    None => None,
+                };
+                url.segment("timemachine")
+                    .query("dt", time.unwrap_or_else(|| noon.and_utc().timestamp()))
+            }
+        };
+        url.secret_query("appid", apikey).query("units", "metric")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u8) -> Date {
+        Date {
+            year: 2024,
+            month: 7,
+            day,
+            hour: None,
+        }
+    }
+
+    #[test]
+    fn current_conditions() {
+        let data = schema::parse(include_str!(
+            "../../fixtures/openweather/onecall_current.json"
+        ));
+        let info = map_response(WeatherData::Current(data.unwrap())).unwrap();
+        // First of reported conditions is primary one
+        assert_eq!(info.weather, WeatherKind::Rain);
+        assert_eq!(info.condition_code.as_deref(), Some("500"));
+        assert_eq!(info.temperature, 18.6);
+        assert_eq!(info.wind_speed, 5.14);
+        assert_eq!(info.humidity, 61.0);
+        assert_eq!(info.precipitation, Some(0.32));
+        assert_eq!(info.pressure, Some(1017.0));
+        assert_eq!(info.meta.kind, Some(DataKind::Observation));
+    }
+
+    #[test]
+    fn missing_condition_array_is_unknown_weather() {
+        let data = schema::parse(include_str!(
+            "../../fixtures/openweather/current_no_weather.json"
+        ));
+        let info = map_response(WeatherData::Current(data.unwrap())).unwrap();
+        assert_eq!(info.weather, WeatherKind::Unknown);
+        assert_eq!(info.condition_code, None);
+        assert_eq!(info.precipitation, Some(0.0));
+    }
+
+    fn map_daily(text: &str, date: Date) -> anyhow::Result<WeatherInfo> {
+        map_response(WeatherData::Daily(schema::parse(text).unwrap(), date))
+    }
+
+    #[test]
+    fn daily_forecast_is_matched_by_local_date() {
+        let text = include_str!("../../fixtures/openweather/onecall_daily.json");
+        let info = map_daily(text, date(2)).unwrap();
+        assert_eq!(info.weather, WeatherKind::Clear);
+        assert_eq!(info.temperature, 22.0);
+        assert_eq!(info.temperature_range(), Some((13.0, 23.5)));
+        assert_eq!(info.precipitation, Some(0.0));
+        assert_eq!(info.meta.kind, Some(DataKind::Forecast));
+
+        let info = map_daily(text, date(1)).unwrap();
+        assert_eq!(info.weather, WeatherKind::Rain);
+        assert_eq!(info.precipitation, Some(3.4));
+
+        let error = map_daily(text, date(5)).unwrap_err();
+        assert_eq!(error.to_string(), "No forecast for 2024-07-05");
+    }
+
+    #[test]
+    fn history_sums_rain_and_snow() {
+        let data = schema::parse(include_str!("../../fixtures/openweather/timemachine.json"));
+        let info = map_response(WeatherData::History(data.unwrap(), date(1))).unwrap();
+        assert_eq!(info.weather, WeatherKind::Snow);
+        assert_eq!(info.precipitation, Some(1.0));
+        assert_eq!(
+            info.meta.timestamp.map(|time| time.timestamp()),
+            Some(1704110400)
+        );
+    }
+
+    #[test]
+    fn error_payload() {
+        let error =
+            ApiError::from_str(include_str!("../../fixtures/openweather/error.json")).unwrap();
+        assert!(
+            error
+                .to_string()
+                .starts_with("API error 401: Invalid API key."),
+            "{error}"
+        );
     }
 }
@@ -0,0 +1,469 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::config::Section;
+use crate::geocode;
+use crate::normalize;
+use crate::schema;
+use crate::timezone;
+use crate::utils::{restful_get_as, Json};
+use crate::CowString;
+
+use super::{
+    base_url_param, Attribution, DataKind, Date, Meta, ParamDesc, ParamKind, PlannedRequest,
+    ProviderInfo, WeatherInfo, WeatherKind,
+};
+
+/// API root
+const API_URL: &str = "https://api.pirateweather.net";
+/// How many days daily forecast covers, including today
+const FORECAST_DAYS: i64 = 8;
+/// Millimeters per centimeter; accumulations are reported in centimeters in SI units
+const MM_PER_CM: f32 = 10.0;
+
+/// Pirate Weather provider, Dark Sky-compatible API
+pub struct PirateWeather {
+    apikey: String,
+    /// API root, own one unless overridden
+    base_url: String,
+}
+
+//
+// Error handling structures
+//
+
+/// API failure; API gateway reports rejected keys with `message`, API itself - with `error`
+#[derive(Debug)]
+struct ApiError {
+    code: Option<i32>,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorJson {
+    code: Option<i32>,
+    error: Option<String>,
+    message: Option<String>,
+}
+
+impl FromStr for ApiError {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match serde_json::from_str::<ApiErrorJson>(s) {
+            Ok(error) => Self {
+                code: error.code,
+                message: error.error.or(error.message).unwrap_or_default(),
+            },
+            Err(_) => Self {
+                code: None,
+                message: s.trim().to_string(),
+            },
+        })
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => f.write_fmt(format_args!("API error {code}: {}", self.message)),
+            None => f.write_fmt(format_args!("API error: {}", self.message)),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+//
+// Weather response structures
+//
+
+/// Forecast response root; which blocks are present depends on excluded ones
+#[derive(Deserialize)]
+struct ForecastData {
+    /// Shift of location's local time from UTC, in hours
+    offset: f64,
+    currently: Option<DataPoint>,
+    hourly: Option<DataBlock>,
+    daily: Option<DataBlock>,
+}
+
+#[derive(Deserialize)]
+struct DataBlock {
+    data: Vec<DataPoint>,
+}
+
+/// Conditions at single moment, over hour or over day; daily points lack momentary temperature
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataPoint {
+    /// UNIX timestamp of moment, or of hour's or day's start
+    time: i64,
+    icon: Option<String>,
+    summary: Option<String>,
+    temperature: Option<f32>,
+    temperature_min: Option<f32>,
+    temperature_max: Option<f32>,
+    /// Relative humidity, 0-1
+    humidity: f32,
+    wind_speed: f32,
+    /// Precipitation intensity, in mm/h
+    precip_intensity: Option<f32>,
+    /// Precipitation over hour or day, in centimeters
+    precip_accumulation: Option<f32>,
+    /// Pressure at sea level, in hPa
+    pressure: Option<f32>,
+}
+
+impl DataPoint {
+    /// Converts data point into weather info
+    ///
+    /// # Parameters
+    /// * `kind` - whether data point is observed or forecast
+    fn into_info(self, kind: DataKind) -> WeatherInfo {
+        // Daily points have temperature range instead of momentary temperature
+        let (temperature, range) = match (
+            self.temperature,
+            self.temperature_min.zip(self.temperature_max),
+        ) {
+            (Some(temperature), _) => (temperature, None),
+            (None, Some((min, max))) => ((min + max) / 2.0, Some((min, max))),
+            (None, None) => {
+                schema::missing("temperature", "0");
+                (0.0, None)
+            }
+        };
+        let precipitation = self
+            .precip_accumulation
+            .map(|amount| amount * MM_PER_CM)
+            .or(self.precip_intensity);
+        WeatherInfo {
+            weather: weather_kind(self.icon.as_deref(), self.summary.as_deref()),
+            temperature,
+            temperature_min: range.map(|(min, _)| min),
+            temperature_max: range.map(|(_, max)| max),
+            wind_speed: self.wind_speed,
+            humidity: self.humidity * 100.0,
+            precipitation,
+            pressure: self.pressure,
+            condition_code: self.icon,
+            meta: Meta::new(kind).with_timestamp(Some(self.time)),
+        }
+    }
+}
+/// Weather kind of data point, by its icon refined with its summary
+///
+/// Classic Dark Sky icons don't tell drizzle, thunderstorms or hail from rain, while summaries do
+fn weather_kind(icon: Option<&str>, summary: Option<&str>) -> WeatherKind {
+    let kind = icon.map_or(WeatherKind::Unknown, normalize::pirateweather_kind);
+    let summary = summary.unwrap_or_default().to_lowercase();
+    let refined = [
+        ("thunder", WeatherKind::Thunderstorm),
+        ("drizzle", WeatherKind::Drizzle),
+        ("hail", WeatherKind::Hail),
+    ]
+    .into_iter()
+    .find(|(word, _)| summary.contains(word));
+    match (kind, refined) {
+        (WeatherKind::Rain | WeatherKind::Clouds | WeatherKind::Unknown, Some((_, refined))) => {
+            refined
+        }
+        (WeatherKind::Unknown, None) => {
+            if icon.is_none() {
+                schema::missing("icon", "weather kind from summary");
+            }
+            [
+                ("sleet", WeatherKind::Sleet),
+                ("snow", WeatherKind::Snow),
+                ("flurries", WeatherKind::Snow),
+                ("rain", WeatherKind::Rain),
+                ("fog", WeatherKind::Fog),
+                ("wind", WeatherKind::Windy),
+                ("breezy", WeatherKind::Windy),
+                ("cloud", WeatherKind::Clouds),
+                ("overcast", WeatherKind::Clouds),
+                ("clear", WeatherKind::Clear),
+            ]
+            .into_iter()
+            .find(|(word, _)| summary.contains(word))
+            .map_or(WeatherKind::Unknown, |(_, kind)| kind)
+        }
+        _ => kind,
+    }
+}
+
+#[async_trait]
+impl super::Provider for PirateWeather {
+    fn new(config: &Section) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            apikey: config
+                .get("apikey")
+                .ok_or_else(|| anyhow!("Missing parameter 'apikey'"))?
+                .clone(),
+            base_url: super::base_url(config, API_URL)?,
+        })
+    }
+
+    fn info() -> &'static ProviderInfo
+    where
+        Self: Sized,
+    {
+        const INFO: ProviderInfo = ProviderInfo {
+            description: "Pirate Weather (https://pirateweather.net/), Dark Sky-compatible API; provides current conditions, historical data and forecasts up to 8 days",
+            attribution: Some(Attribution {
+                text: "Powered by Pirate Weather",
+                url: "https://pirateweather.net/",
+                required: false,
+            }),
+            params: &[
+                ParamDesc {
+                    id: "apikey",
+                    name: "User's API key",
+                    description: "used to authenticate user requests",
+                    required: true,
+                    default: None,
+                    kind: ParamKind::Secret,
+                },
+                base_url_param(API_URL),
+            ],
+            hourly: true,
+            lightning: false,
+        };
+        &INFO
+    }
+
+    async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let (lat, lon) = geocode::coordinates(&location).await?;
+        let tz = timezone::at(lat, lon);
+        let request = Request::select(date, tz)?;
+        let url = request.url(
+            &self.base_url,
+            &self.apikey,
+            &format!("{lat:.4},{lon:.4}"),
+            tz,
+        );
+        let data = restful_get_as::<ForecastData, ApiError, Json>(url)
+            .await
+            .with_context(|| anyhow!("Could not obtain {}", request.purpose()))?;
+        map_response(request, data)
+    }
+
+    async fn get_hourly(
+        &self,
+        location: CowString,
+        start: Date,
+        hours: u32,
+    ) -> anyhow::Result<Vec<WeatherInfo>> {
+        let (lat, lon) = geocode::coordinates(&location).await?;
+        let tz = timezone::at(lat, lon);
+        let times = super::hours_from(start, hours)?;
+        // Forecast's hourly block covers all forecast hours, while time machine serves single day
+        let mut requests = BTreeMap::new();
+        for time in &times {
+            let date = Date::from(time.date()).with_hour(Some(time.hour() as u8));
+            let request = Request::select(Some(date), tz)?;
+            let day = matches!(request, Request::History(_)).then(|| time.date());
+            requests.entry(day).or_insert(request);
+        }
+        let mut entries = Vec::new();
+        for request in requests.into_values() {
+            let url = request.url(
+                &self.base_url,
+                &self.apikey,
+                &format!("{lat:.4},{lon:.4}"),
+                tz,
+            );
+            let data = restful_get_as::<ForecastData, ApiError, Json>(url)
+                .await
+                .with_context(|| anyhow!("Could not obtain hourly {}", request.purpose()))?;
+            entries.extend(hourly_points(request, data));
+        }
+        super::pick_hours(&times, entries)
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        let tz = timezone::of_location(location);
+        let request = Request::select(date, tz)?;
+        let coordinates = match geocode::coordinates_text(location) {
+            Some((lat, lon)) => format!("{lat},{lon}"),
+            None => geocode::PLACEHOLDER.to_string(),
+        };
+        let data = PlannedRequest::get(
+            request.purpose(),
+            request.url(&self.base_url, &self.apikey, &coordinates, tz),
+        );
+        Ok(geocode::plan(location).into_iter().chain([data]).collect())
+    }
+}
+/// Converts response of request into weather information;
+/// data points are matched by their local time
+///
+/// # Parameters
+/// * `request` - request which served response
+/// * `data` - response
+///
+/// # Returns
+/// Weather information, or error if response lacks data request should provide
+fn map_response(request: Request, data: ForecastData) -> anyhow::Result<WeatherInfo> {
+    let offset = (data.offset * 3600.0).round() as i64;
+    let local = |point: &DataPoint| DateTime::from_timestamp(point.time + offset, 0);
+    let (date, kind) = match request {
+        Request::Current => {
+            return data
+                .currently
+                .map(|point| point.into_info(DataKind::Observation))
+                .ok_or_else(|| anyhow!("Could not parse response: missing current conditions"))
+        }
+        Request::History(date) => (date, DataKind::Observation),
+        Request::Forecast(date) => (date, DataKind::Forecast),
+    };
+    let day = date.to_naive();
+    match date.hour {
+        Some(hour) => data
+            .hourly
+            .map(|block| block.data)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|point| {
+                local(point).is_some_and(|time| {
+                    Some(time.date_naive()) == day && time.hour() == u32::from(hour)
+                })
+            })
+            .map(|point| point.into_info(kind))
+            .ok_or_else(|| anyhow!("No hourly data for {date}")),
+        None => data
+            .daily
+            .map(|block| block.data)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|point| local(point).map(|time| time.date_naive()) == day)
+            .map(|point| point.into_info(kind))
+            .ok_or_else(|| anyhow!("No daily data for {date}")),
+    }
+}
+/// Converts hourly data points of response into weather information
+///
+/// # Parameters
+/// * `request` - request which served response
+/// * `data` - response
+///
+/// # Returns
+/// Weather information of each hour, along with hour's local time
+fn hourly_points(request: Request, data: ForecastData) -> Vec<(NaiveDateTime, WeatherInfo)> {
+    let offset = (data.offset * 3600.0).round() as i64;
+    let kind = match request {
+        Request::History(_) => DataKind::Observation,
+        _ => DataKind::Forecast,
+    };
+    data.hourly
+        .map(|block| block.data)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|point| {
+            let time = DateTime::from_timestamp(point.time + offset, 0)?.naive_utc();
+            Some((time, point.into_info(kind)))
+        })
+        .collect()
+}
+/// Forecast request which serves requested date
+#[derive(Clone, Copy)]
+enum Request {
+    /// Current conditions
+    Current,
+    /// Past day, as time machine request
+    History(Date),
+    /// Today and `FORECAST_DAYS - 1` following days
+    Forecast(Date),
+}
+
+impl Request {
+    /// Selects request by comparing date with today's date at location
+    ///
+    /// # Parameters
+    /// * `date` - requested date; `None` means current conditions
+    /// * `tz` - location's timezone, if known
+    ///
+    /// # Returns
+    /// Request, or error if date is too far in future or doesn't exist
+    fn select(date: Option<Date>, tz: Option<Tz>) -> anyhow::Result<Self> {
+        let Some(date) = date else {
+            return Ok(Self::Current);
+        };
+        let day = date
+            .to_naive()
+            .ok_or_else(|| anyhow!("Date {date} doesn't exist"))?;
+        Ok(match (day - timezone::today(tz)).num_days() {
+            ..=-1 => Self::History(date),
+            0..FORECAST_DAYS => Self::Forecast(date),
+            _ => {
+                bail!("Sorry, Pirate Weather forecasts only {FORECAST_DAYS} days, including today")
+            }
+        })
+    }
+    /// Describes data request provides
+    fn purpose(&self) -> &'static str {
+        match self {
+            Self::Current => "current conditions",
+            Self::History(_) => "historical weather data",
+            Self::Forecast(_) => "weather forecast",
+        }
+    }
+    /// URL of request
+    ///
+    /// # Parameters
+    /// * `base_url` - API root
+    /// * `apikey` - user's API key
+    /// * `coordinates` - location's coordinates, as "lat,lon"
+    /// * `tz` - location's timezone, in which historical day is taken; UTC is used if it's unknown
+    fn url(&self, base_url: &str, apikey: &str, coordinates: &str, tz: Option<Tz>) -> ApiUrl {
+        let url = ApiUrl::new(base_url)
+            .segment("forecast")
+            .secret_segment(apikey);
+        let (url, exclude) = match self {
+            Self::Current => (url.segment(coordinates), "minutely,hourly,daily,alerts"),
+            Self::Forecast(date) => (
+                url.segment(coordinates),
+                if date.hour.is_some() {
+                    "minutely,currently,daily,alerts"
+                } else {
+                    "minutely,currently,hourly,alerts"
+                },
+            ),
+            // Time machine responds with whole day which contains specified moment
+            Self::History(date) => {
+                let noon = date
+                    .to_naive()
+                    .and_then(|day| day.and_hms_opt(12, 0, 0))
+                    .unwrap_or_default();
+                let time = tz
+                    .and_then(|tz| tz.from_local_datetime(&noon).earliest())
+                    .map_or_else(|| noon.and_utc().timestamp(), |time| time.timestamp());
+                (
+                    url.segment(format_args!("{coordinates},{time}")),
+                    "minutely,alerts",
+                )
+            }
+        };
+        url.query("units", "si").query("exclude", exclude)
+    }
+}
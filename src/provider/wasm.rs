@@ -0,0 +1,341 @@
+//! Provider plugins compiled to WebAssembly components
+//!
+//! Every `*.wasm` component in `<config dir>/weather-cli/plugins/` which implements `provider` world
+//! from `wit/provider.wit` is registered as provider named after its file name without extension.
+//! Plugins run sandboxed: they get no filesystem, network or environment access, and reach
+//! provider's API only through host's `http` interface. Each request runs in fresh instance
+//! with limited memory and fuel
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use wasmtime::component::{Component, HasSelf, Linker, ResourceTable};
+use wasmtime::{Engine, Store, StoreLimits, StoreLimitsBuilder};
+use wasmtime_wasi::{WasiCtx, WasiCtxView, WasiView};
+
+use crate::app_dirs;
+use crate::config::Section;
+use crate::http_backend::{self, Request};
+use crate::provider_registry::ProviderFactory;
+use crate::CowString;
+
+use super::{
+    Attribution, Date, Meta, ParamDesc, ParamKind, PlannedRequest, Provider, ProviderInfo,
+    WeatherInfo, WeatherKind,
+};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        world: "provider",
+        path: "wit",
+        imports: { default: async },
+        exports: { default: async },
+    });
+}
+
+use bindings::weather_cli::provider::{http, types};
+use bindings::Provider as Plugin;
+
+/// How long plugin may run single request
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Memory limit of plugin instance
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+/// Amount of fuel, roughly number of executed instructions, plugin instance gets
+const FUEL: u64 = 10_000_000_000;
+/// How often, in fuel units, plugin yields to let timeout fire
+const FUEL_YIELD_INTERVAL: u64 = 10_000_000;
+
+/// Store data of plugin instance
+struct HostState {
+    wasi: WasiCtx,
+    table: ResourceTable,
+    limits: StoreLimits,
+}
+
+impl WasiView for HostState {
+    fn ctx(&mut self) -> WasiCtxView<'_> {
+        WasiCtxView {
+            ctx: &mut self.wasi,
+            table: &mut self.table,
+        }
+    }
+}
+
+impl types::Host for HostState {}
+
+impl http::Host for HostState {
+    async fn get(
+        &mut self,
+        url: String,
+        headers: Vec<(String, String)>,
+    ) -> Result<http::Response, String> {
+        let request = headers
+            .into_iter()
+            .fold(Request::get(url), |request, (name, value)| {
+                request.header(name, value)
+            });
+        let response = http_backend::fetch_text(request)
+            .await
+            .map(|(status, body)| http::Response { status, body })
+            .map_err(|error| error.to_string());
+        if let Ok(response) = &response {
+            tracing::debug!("Plugin's GET request responded with {}", response.status);
+        }
+        response
+    }
+}
+
+/// Creates store for single plugin instance, with no access to host's resources
+fn new_store(engine: &Engine) -> anyhow::Result<Store<HostState>> {
+    let state = HostState {
+        wasi: WasiCtx::builder().build(),
+        table: ResourceTable::new(),
+        limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+    };
+    let mut store = Store::new(engine, state);
+    store.limiter(|state| &mut state.limits);
+    store.set_fuel(FUEL)?;
+    store.fuel_async_yield_interval(Some(FUEL_YIELD_INTERVAL))?;
+    Ok(store)
+}
+
+/// Directory where plugins are looked for
+fn plugins_dir() -> Option<PathBuf> {
+    app_dirs::config_dir().map(|path| path.join("plugins"))
+}
+/// Finds plugins, compiles and describes them
+///
+/// Plugins which fail to load are skipped with warning
+///
+/// # Returns
+/// Provider name and its factory, for each found plugin
+pub async fn discover() -> Vec<(String, WasmFactory)> {
+    let Some(entries) = plugins_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .collect();
+    if paths.is_empty() {
+        return Vec::new();
+    }
+    paths.sort();
+
+    let engine = match new_engine() {
+        Ok(engine) => engine,
+        Err(error) => {
+            tracing::warn!("Could not initialize WebAssembly engine: {error:#}");
+            return Vec::new();
+        }
+    };
+    let mut factories = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        match WasmFactory::load(&engine, &path).await {
+            Ok(factory) => factories.push((name.to_string(), factory)),
+            Err(error) => tracing::warn!("Skipping plugin '{name}': {error:#}"),
+        }
+    }
+    factories
+}
+/// Creates engine which limits plugins' resources
+fn new_engine() -> anyhow::Result<Engine> {
+    let mut config = wasmtime::Config::new();
+    config.consume_fuel(true);
+    Ok(Engine::new(&config)?)
+}
+
+/// Compiled plugin, ready to be instantiated
+#[derive(Clone)]
+struct Instantiable {
+    engine: Engine,
+    component: Component,
+    linker: Linker<HostState>,
+}
+
+impl Instantiable {
+    /// Instantiates plugin in fresh store
+    async fn instantiate(&self) -> anyhow::Result<(Store<HostState>, Plugin)> {
+        let mut store = new_store(&self.engine)?;
+        let plugin = Plugin::instantiate_async(&mut store, &self.component, &self.linker).await?;
+        Ok((store, plugin))
+    }
+}
+
+/// Factory of plugin provider, which knows compiled plugin and its description
+pub struct WasmFactory {
+    path: PathBuf,
+    plugin: Instantiable,
+    info: &'static ProviderInfo,
+}
+
+impl WasmFactory {
+    /// Compiles plugin and obtains its description
+    async fn load(engine: &Engine, path: &Path) -> anyhow::Result<Self> {
+        let component = Component::from_file(engine, path)
+            .map_err(anyhow::Error::from)
+            .with_context(|| anyhow!("Could not compile '{}'", path.display()))?;
+        let mut linker = Linker::new(engine);
+        wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+        Plugin::add_to_linker::<_, HasSelf<_>>(&mut linker, |state| state)?;
+        let plugin = Instantiable {
+            engine: engine.clone(),
+            component,
+            linker,
+        };
+
+        let (mut store, instance) = plugin.instantiate().await?;
+        let info = tokio::time::timeout(TIMEOUT, instance.call_info(&mut store))
+            .await
+            .map_err(|_| anyhow!("Plugin didn't describe itself in {TIMEOUT:?}"))??;
+        // Provider infos are static, like built-in ones; plugins are described once per run
+        let leak = |text: String| -> &'static str { Box::leak(text.into_boxed_str()) };
+        let params: Vec<_> = info
+            .params
+            .into_iter()
+            .map(|param| ParamDesc {
+                id: leak(param.id),
+                name: leak(param.name),
+                description: leak(param.description),
+                // Plugin interface has no optional or typed parameters
+                required: true,
+                default: None,
+                kind: ParamKind::String,
+            })
+            .collect();
+        let info = ProviderInfo {
+            description: leak(info.description),
+            attribution: info.attribution.map(|attribution| Attribution {
+                text: leak(attribution.text),
+                url: leak(attribution.url),
+                required: attribution.required,
+            }),
+            params: Box::leak(params.into_boxed_slice()),
+            // Plugin interface passes dates without time of day, and has no lightning function
+            hourly: false,
+            lightning: false,
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            plugin,
+            info: Box::leak(Box::new(info)),
+        })
+    }
+}
+
+impl ProviderFactory for WasmFactory {
+    fn create(&self, config: &Section) -> anyhow::Result<Box<dyn Provider>> {
+        for param in self.info.params {
+            if !config.contains_key(param.id) {
+                anyhow::bail!("Missing parameter '{}'", param.id);
+            }
+        }
+        Ok(Box::new(WasmProvider {
+            path: self.path.clone(),
+            plugin: self.plugin.clone(),
+            config: config.clone(),
+        }))
+    }
+
+    fn info(&self) -> &'static ProviderInfo {
+        self.info
+    }
+}
+
+/// Provider implemented by WebAssembly plugin
+///
+/// Created only by `WasmFactory`, since it needs compiled plugin
+pub struct WasmProvider {
+    path: PathBuf,
+    plugin: Instantiable,
+    config: Section,
+}
+
+#[async_trait]
+impl Provider for WasmProvider {
+    fn new(_config: &Section) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        anyhow::bail!("Plugin provider can only be created from its component")
+    }
+
+    fn info() -> &'static ProviderInfo
+    where
+        Self: Sized,
+    {
+        const INFO: ProviderInfo = ProviderInfo {
+            description: "WebAssembly plugin provider",
+            attribution: None,
+            params: &[],
+            hourly: false,
+            lightning: false,
+        };
+        &INFO
+    }
+
+    async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let config: Vec<_> = self.config.clone().into_iter().collect();
+        let date = date.map(|date| types::Date {
+            year: date.year,
+            month: date.month,
+            day: date.day,
+        });
+        let (mut store, instance) = self.plugin.instantiate().await?;
+        let call = instance.call_get_weather(&mut store, &config, &location, date);
+        let info = tokio::time::timeout(TIMEOUT, call)
+            .await
+            .map_err(|_| anyhow!("Plugin didn't respond in {TIMEOUT:?}"))??
+            .map_err(|error| anyhow!("Plugin failed: {error}"))?;
+        Ok(WeatherInfo {
+            weather: match info.weather {
+                types::WeatherKind::Unknown => WeatherKind::Unknown,
+                types::WeatherKind::Clear => WeatherKind::Clear,
+                types::WeatherKind::Clouds => WeatherKind::Clouds,
+                types::WeatherKind::Fog => WeatherKind::Fog,
+                types::WeatherKind::Rain => WeatherKind::Rain,
+                types::WeatherKind::Snow => WeatherKind::Snow,
+                types::WeatherKind::Drizzle => WeatherKind::Drizzle,
+                types::WeatherKind::Thunderstorm => WeatherKind::Thunderstorm,
+                types::WeatherKind::Sleet => WeatherKind::Sleet,
+                types::WeatherKind::Hail => WeatherKind::Hail,
+                types::WeatherKind::Windy => WeatherKind::Windy,
+            },
+            temperature: info.temperature,
+            temperature_min: None,
+            temperature_max: None,
+            wind_speed: info.wind_speed,
+            humidity: info.humidity,
+            precipitation: info.precipitation,
+            pressure: None,
+            condition_code: info.condition_code,
+            meta: Meta::default(),
+        })
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        let date = date.map_or_else(
+            || "none".to_string(),
+            |date| date.without_hour().to_string(),
+        );
+        Ok(vec![PlannedRequest::run(
+            "weather",
+            format!(
+                "{} get-weather(location: '{location}', date: {date})",
+                self.path.display()
+            ),
+        )])
+    }
+}
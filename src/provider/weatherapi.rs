@@ -1,18 +1,35 @@
 use std::fmt::Display;
 use std::str::FromStr;
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, ensure, Context};
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::Deserialize;
 
+use crate::api_url::ApiUrl;
 use crate::config::Section;
-use crate::utils::restful_get;
-use crate::{BoxFuture, CowString};
+use crate::normalize;
+use crate::schema;
+use crate::utils::{restful_get_as, Json};
+use crate::CowString;
 
-use super::{Date, ParamDesc, ProviderInfo, WeatherInfo, WeatherKind};
+use super::{
+    base_url_param, Attribution, DataKind, Date, Meta, ParamDesc, ParamKind, PlannedRequest,
+    ProviderInfo, WeatherInfo,
+};
+
+/// API root
+const API_URL: &str = "https://api.weatherapi.com/v1";
+/// How many days forecast endpoint covers, including today
+const FORECAST_DAYS: i64 = 14;
+/// Format of hours' local time
+const HOUR_FORMAT: &str = "%Y-%m-%d %H:%M";
 
 /// WeatherAPI provider implementation
 pub struct WeatherApi {
     apikey: String,
+    /// API root, own one unless overridden
+    base_url: String,
 }
 
 //
@@ -53,18 +70,24 @@ struct ApiErrorInner {
 // Weather response structures
 //
 
-/// Weather response root
+/// Weather response root; current conditions endpoint fills `current`, others - `forecast`
 #[derive(Deserialize)]
 struct WeatherData {
-    forecast: Forecast,
+    current: Option<Current>,
+    forecast: Option<Forecast>,
 }
 
-impl FromStr for WeatherData {
-    type Err = serde_json::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        serde_json::from_str(s)
-    }
+#[derive(Deserialize)]
+struct Current {
+    temp_c: f32,
+    humidity: f32,
+    /// Reported as null when station's wind sensor is out of order
+    wind_kph: Option<f32>,
+    precip_mm: f32,
+    pressure_mb: Option<f32>,
+    condition: Condition,
+    /// UNIX timestamp of conditions' last update
+    last_updated_epoch: Option<i64>,
 }
 
 #[derive(Deserialize)]
@@ -74,14 +97,36 @@ struct Forecast {
 
 #[derive(Deserialize)]
 struct ForecastDay {
+    /// UNIX timestamp of day's start
+    date_epoch: Option<i64>,
     day: ForecastDayAvg,
+    /// Hourly conditions; only requested hour, if request specifies one
+    #[serde(default)]
+    hour: Vec<ForecastHour>,
 }
 
 #[derive(Deserialize)]
 struct ForecastDayAvg {
     avghumidity: f32,
     avgtemp_c: f32,
+    mintemp_c: f32,
+    maxtemp_c: f32,
     maxwind_kph: f32,
+    totalprecip_mm: f32,
+    condition: Condition,
+}
+
+#[derive(Deserialize)]
+struct ForecastHour {
+    /// Local time, like "2024-07-01 18:00"
+    time: String,
+    /// UNIX timestamp of hour's start
+    time_epoch: Option<i64>,
+    temp_c: f32,
+    humidity: f32,
+    wind_kph: Option<f32>,
+    precip_mm: f32,
+    pressure_mb: Option<f32>,
     condition: Condition,
 }
 
@@ -90,6 +135,7 @@ struct Condition {
     code: u32,
 }
 
+#[async_trait]
 impl super::Provider for WeatherApi {
     fn new(config: &Section) -> anyhow::Result<Self>
     where
@@ -100,6 +146,7 @@ impl super::Provider for WeatherApi {
                 .get("apikey")
                 .ok_or_else(|| anyhow!("Missing parameter 'apikey'"))?
                 .clone(),
+            base_url: super::base_url(config, API_URL)?,
         })
     }
 
@@ -109,58 +156,429 @@ impl super::Provider for WeatherApi {
     {
         const INFO: ProviderInfo = ProviderInfo {
             description: "WeatherAPI (https://www.weatherapi.com/)",
-            params: &[ParamDesc {
-                id: "apikey",
-                name: "User's API key",
-                description: "used to authenticate user requests",
-            }],
+            attribution: Some(Attribution {
+                text: "Powered by WeatherAPI.com",
+                url: "https://www.weatherapi.com/",
+                required: true,
+            }),
+            params: &[
+                ParamDesc {
+                    id: "apikey",
+                    name: "User's API key",
+                    description: "used to authenticate user requests",
+                    required: true,
+                    default: None,
+                    kind: ParamKind::Secret,
+                },
+                base_url_param(API_URL),
+            ],
+            hourly: true,
+            lightning: false,
         };
         &INFO
     }
 
-    fn get_weather(
+    async fn get_weather(
         &self,
         location: CowString,
         date: Option<Date>,
-    ) -> BoxFuture<anyhow::Result<WeatherInfo>> {
-        let apikey = &self.apikey;
-        let date = date.unwrap_or_else(Date::today);
-        let url = format!(
-            "https://api.weatherapi.com/v1/history.json?key={apikey}&q={location}&dt={}-{}-{}",
-            date.year, date.month, date.day
-        );
-        let fut = async {
-            let resp = restful_get::<WeatherData, ApiError>(url)
-                .await
-                .with_context(|| anyhow!("Request to historical weather data failed"))?;
-
-            let day = &resp
-                .forecast
-                .forecastday
-                .first()
-                .ok_or_else(|| anyhow!("Could not parse response: missing forecast day data"))?
-                .day;
-            // Use codes from https://www.weatherapi.com/docs/weather_conditions.json
-            let weather = match day.condition.code {
-                1000 => WeatherKind::Clear,
-                1003 | 1006 | 1009 | 1087 => WeatherKind::Clouds,
-                1030 | 1135 | 1147 => WeatherKind::Fog,
-                1063 | 1072 | 1150 | 1153 | 1168 | 1171 | 1180 | 1183 | 1186 | 1189 | 1192
-                | 1195 | 1198 | 1201 | 1240 | 1243 | 1246 | 1273 | 1276 => WeatherKind::Rain,
-                1066 | 1069 | 1114 | 1117 | 1204 | 1207 | 1210 | 1213 | 1216 | 1219 | 1222
-                | 1225 | 1237 | 1249 | 1252 | 1255 | 1258 | 1261 | 1264 | 1279 | 1282 => {
-                    WeatherKind::Snow
+    ) -> anyhow::Result<WeatherInfo> {
+        let endpoint = Endpoint::select(&location, date)?;
+        let url = endpoint.url(&self.base_url, &self.apikey, &location);
+        let data = restful_get_as::<WeatherData, ApiError, Json>(url)
+            .await
+            .with_context(|| anyhow!("Request to {} failed", endpoint.purpose()))?;
+        map_response(endpoint, data)
+    }
+
+    async fn get_hourly(
+        &self,
+        location: CowString,
+        start: Date,
+        hours: u32,
+    ) -> anyhow::Result<Vec<WeatherInfo>> {
+        let times = super::hours_from(start, hours)?;
+        let (Some(first), Some(last)) = (times.first(), times.last()) else {
+            return Ok(Vec::new());
+        };
+        let (kind, url) = hours_url(
+            &self.base_url,
+            &self.apikey,
+            &location,
+            first.date(),
+            last.date(),
+        )?;
+        let data = restful_get_as::<WeatherData, ApiError, Json>(url)
+            .await
+            .with_context(|| anyhow!("Request to hourly weather data failed"))?;
+        map_hours(&times, kind, data)
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        let endpoint = Endpoint::select(location, date)?;
+        Ok(vec![PlannedRequest::get(
+            endpoint.purpose(),
+            endpoint.url(&self.base_url, &self.apikey, location),
+        )])
+    }
+}
+/// Converts response of endpoint into weather information
+///
+/// # Parameters
+/// * `endpoint` - endpoint which served response
+/// * `data` - response
+///
+/// # Returns
+/// Weather information, or error if response lacks data endpoint should provide
+fn map_response(endpoint: Endpoint, data: WeatherData) -> anyhow::Result<WeatherInfo> {
+    let kind = match endpoint {
+        Endpoint::Forecast(_) => DataKind::Forecast,
+        Endpoint::Current | Endpoint::History(_) => DataKind::Observation,
+    };
+    // Day's summary has no pressure
+    let (temperature, range, wind_speed, humidity, precipitation, pressure, code, timestamp) =
+        match endpoint {
+            Endpoint::Current => {
+                let current = data.current.ok_or_else(|| {
+                    anyhow!("Could not parse response: missing current conditions")
+                })?;
+                (
+                    current.temp_c,
+                    None,
+                    wind(current.wind_kph, "current.wind_kph"),
+                    current.humidity,
+                    current.precip_mm,
+                    current.pressure_mb,
+                    current.condition.code,
+                    current.last_updated_epoch,
+                )
+            }
+            Endpoint::History(date) | Endpoint::Forecast(date) => {
+                let day = data
+                    .forecast
+                    .and_then(|forecast| forecast.forecastday.into_iter().next())
+                    .ok_or_else(|| {
+                        anyhow!("Could not parse response: missing forecast day data")
+                    })?;
+                // Requested hour's conditions, if any, or whole day's summary
+                match date.hour.map(|hour| format!(" {hour:02}:00")) {
+                    Some(suffix) => {
+                        let hour = day
+                            .hour
+                            .into_iter()
+                            .find(|hour| hour.time.ends_with(&suffix))
+                            .ok_or_else(|| {
+                                anyhow!("Could not parse response: missing hourly data")
+                            })?;
+                        return Ok(hour_info(hour, kind));
+                    }
+                    None => (
+                        day.day.avgtemp_c,
+                        Some((day.day.mintemp_c, day.day.maxtemp_c)),
+                        day.day.maxwind_kph,
+                        day.day.avghumidity,
+                        day.day.totalprecip_mm,
+                        None,
+                        day.day.condition.code,
+                        day.date_epoch,
+                    ),
                 }
-                _ => WeatherKind::Unknown,
-            };
-
-            Ok(WeatherInfo {
-                weather,
-                temperature: day.avgtemp_c,
-                wind_speed: day.maxwind_kph,
-                humidity: day.avghumidity,
-            })
+            }
+        };
+
+    Ok(WeatherInfo {
+        weather: normalize::weatherapi_kind(code),
+        temperature,
+        temperature_min: range.map(|(min, _)| min),
+        temperature_max: range.map(|(_, max)| max),
+        wind_speed: normalize::kmh_to_ms(wind_speed),
+        humidity,
+        precipitation: Some(precipitation),
+        pressure,
+        condition_code: Some(code.to_string()),
+        meta: Meta::new(kind).with_timestamp(timestamp),
+    })
+}
+/// Picks requested hours out of response with hourly data of whole days
+///
+/// # Parameters
+/// * `times` - requested hours, in location's local time
+/// * `kind` - whether response has observations or forecast
+/// * `data` - response
+///
+/// # Returns
+/// Weather information of each hour, or error if response misses some hour
+fn map_hours(
+    times: &[NaiveDateTime],
+    kind: DataKind,
+    data: WeatherData,
+) -> anyhow::Result<Vec<WeatherInfo>> {
+    let entries = data
+        .forecast
+        .map(|forecast| forecast.forecastday)
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|day| day.hour)
+        .filter_map(|hour| {
+            let time = NaiveDateTime::parse_from_str(&hour.time, HOUR_FORMAT).ok()?;
+            Some((time, hour_info(hour, kind)))
+        })
+        .collect();
+    super::pick_hours(times, entries)
+}
+/// Converts hour's conditions into weather information
+///
+/// # Parameters
+/// * `hour` - hour's conditions
+/// * `kind` - whether conditions are observed or forecast
+fn hour_info(hour: ForecastHour, kind: DataKind) -> WeatherInfo {
+    let code = hour.condition.code;
+    WeatherInfo {
+        weather: normalize::weatherapi_kind(code),
+        temperature: hour.temp_c,
+        temperature_min: None,
+        temperature_max: None,
+        wind_speed: normalize::kmh_to_ms(wind(hour.wind_kph, "hour.wind_kph")),
+        humidity: hour.humidity,
+        precipitation: Some(hour.precip_mm),
+        pressure: hour.pressure_mb,
+        condition_code: Some(code.to_string()),
+        meta: Meta::new(kind).with_timestamp(hour.time_epoch),
+    }
+}
+/// URL of request which serves hourly data of consecutive days at once:
+/// history for ranges starting in the past, forecast otherwise
+///
+/// # Parameters
+/// * `base_url` - API root
+/// * `apikey` - user's API key
+/// * `location` - location of forecast
+/// * `first` - first day, in location's local time
+/// * `last` - last day, in location's local time
+///
+/// # Returns
+/// Kind of data request serves along with its URL, or error if range ends too far in future
+fn hours_url(
+    base_url: &str,
+    apikey: &str,
+    location: &str,
+    first: NaiveDate,
+    last: NaiveDate,
+) -> anyhow::Result<(DataKind, ApiUrl)> {
+    let today = Date::today_at(location)
+        .to_naive()
+        .ok_or_else(|| anyhow!("Could not determine today's date"))?;
+    let url = |name: &str| {
+        ApiUrl::new(base_url)
+            .segment(name)
+            .secret_query("key", apikey)
+            .query("q", location)
+    };
+    if first < today {
+        return Ok((
+            DataKind::Observation,
+            url("history.json").query("dt", first).query("end_dt", last),
+        ));
+    }
+    // Forecast starts today, so days before first one are included too
+    let days = (last - today).num_days() + 1;
+    ensure!(
+        days <= FORECAST_DAYS,
+        "Sorry, WeatherAPI forecasts only {FORECAST_DAYS} days, including today"
+    );
+    Ok((DataKind::Forecast, url("forecast.json").query("days", days)))
+}
+/// Wind speed, in km/h, or calm if it isn't reported
+///
+/// # Parameters
+/// * `speed` - reported speed
+/// * `field` - response field which reports speed
+fn wind(speed: Option<f32>, field: &str) -> f32 {
+    speed.unwrap_or_else(|| {
+        schema::missing(field, "calm wind");
+        0.0
+    })
+}
+/// API endpoint which serves requested date
+#[derive(Clone, Copy)]
+enum Endpoint {
+    /// Current conditions, for today's date without hour
+    Current,
+    /// Past days
+    History(Date),
+    /// Today's hours and following days, `FORECAST_DAYS` days in total
+    Forecast(Date),
+}
+
+impl Endpoint {
+    /// Selects endpoint by comparing date with today's date at location
+    ///
+    /// # Parameters
+    /// * `location` - location, whose timezone defines today's date if it's specified with coordinates
+    /// * `date` - requested date; `None` means current conditions
+    ///
+    /// # Returns
+    /// Endpoint, or error if date is too far in future or doesn't exist
+    fn select(location: &str, date: Option<Date>) -> anyhow::Result<Self> {
+        let Some(date) = date else {
+            return Ok(Self::Current);
         };
-        Box::pin(fut)
+        let day = date
+            .to_naive()
+            .ok_or_else(|| anyhow!("Date {date} doesn't exist"))?;
+        let today = Date::today_at(location)
+            .to_naive()
+            .ok_or_else(|| anyhow!("Could not determine today's date"))?;
+        let ahead = (day - today).num_days();
+        Ok(match ahead {
+            ..=-1 => Self::History(date),
+            0 if date.hour.is_none() => Self::Current,
+            0..FORECAST_DAYS => Self::Forecast(date),
+            _ => bail!("Sorry, WeatherAPI forecasts only {FORECAST_DAYS} days, including today"),
+        })
+    }
+    /// Describes data endpoint provides
+    fn purpose(&self) -> &'static str {
+        match self {
+            Self::Current => "current conditions",
+            Self::History(_) => "historical weather data",
+            Self::Forecast(_) => "weather forecast",
+        }
+    }
+    /// URL of endpoint's request
+    ///
+    /// # Parameters
+    /// * `base_url` - API root
+    /// * `apikey` - user's API key
+    /// * `location` - location of forecast
+    fn url(&self, base_url: &str, apikey: &str, location: &str) -> ApiUrl {
+        let (name, date) = match self {
+            Self::Current => ("current.json", None),
+            Self::History(date) => ("history.json", Some(date)),
+            Self::Forecast(date) => ("forecast.json", Some(date)),
+        };
+        let url = ApiUrl::new(base_url)
+            .segment(name)
+            .secret_query("key", apikey)
+            .query("q", location);
+        let Some(date) = date else {
+            return url;
+        };
+        let url = url.query(
+            "dt",
+            format_args!("{}-{}-{}", date.year, date.month, date.day),
+        );
+        // Hourly data is limited to requested hour
+        match date.hour {
+            Some(hour) => url.query("hour", hour),
+            None => url,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::WeatherKind;
+
+    fn parse(text: &str) -> WeatherData {
+        schema::parse(text).expect("Fixture should be parsed")
+    }
+
+    fn date(hour: Option<u8>) -> Date {
+        Date {
+            year: 2024,
+            month: 7,
+            day: 2,
+            hour,
+        }
+    }
+
+    #[test]
+    fn current_conditions() {
+        let data = parse(include_str!("../../fixtures/weatherapi/current.json"));
+        let info = map_response(Endpoint::Current, data).unwrap();
+        assert_eq!(info.weather, WeatherKind::Clouds);
+        assert_eq!(info.temperature, 18.3);
+        assert_eq!(info.wind_speed, 5.0);
+        assert_eq!(info.humidity, 59.0);
+        assert_eq!(info.precipitation, Some(0.1));
+        assert_eq!(info.pressure, Some(1017.0));
+        assert_eq!(info.condition_code.as_deref(), Some("1003"));
+        assert_eq!(info.meta.kind, Some(DataKind::Observation));
+        assert_eq!(
+            info.meta.timestamp.map(|time| time.timestamp()),
+            Some(1719849600)
+        );
+    }
+
+    #[test]
+    fn null_wind_is_calm() {
+        let data = parse(include_str!(
+            "../../fixtures/weatherapi/current_null_wind.json"
+        ));
+        let info = map_response(Endpoint::Current, data).unwrap();
+        assert_eq!(info.weather, WeatherKind::Snow);
+        assert_eq!(info.wind_speed, 0.0);
+    }
+
+    #[test]
+    fn forecast_day_is_summarized() {
+        let data = parse(include_str!("../../fixtures/weatherapi/forecast.json"));
+        let info = map_response(Endpoint::Forecast(date(None)), data).unwrap();
+        assert_eq!(info.weather, WeatherKind::Rain);
+        assert_eq!(info.temperature, 16.8);
+        assert_eq!(info.temperature_range(), Some((12.6, 21.4)));
+        assert_eq!(info.wind_speed, 10.0);
+        assert_eq!(info.precipitation, Some(4.2));
+        assert_eq!(info.meta.kind, Some(DataKind::Forecast));
+    }
+
+    #[test]
+    fn forecast_hour_is_picked() {
+        let data = parse(include_str!("../../fixtures/weatherapi/forecast.json"));
+        let info = map_response(Endpoint::Forecast(date(Some(18))), data).unwrap();
+        assert_eq!(info.weather, WeatherKind::Clouds);
+        assert_eq!(info.temperature, 18.7);
+        assert_eq!(info.temperature_range(), None);
+        assert_eq!(info.wind_speed, 0.0);
+
+        let data = parse(include_str!("../../fixtures/weatherapi/forecast.json"));
+        assert!(map_response(Endpoint::Forecast(date(Some(3))), data).is_err());
+    }
+
+    #[test]
+    fn hours_are_picked_from_days() {
+        let times = crate::provider::hours_from(date(Some(17)), 2).unwrap();
+        let data = parse(include_str!("../../fixtures/weatherapi/forecast.json"));
+        let infos = map_hours(&times, DataKind::Forecast, data).unwrap();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[1].temperature, 18.7);
+
+        let times = crate::provider::hours_from(date(Some(17)), 3).unwrap();
+        let data = parse(include_str!("../../fixtures/weatherapi/forecast.json"));
+        let error = map_hours(&times, DataKind::Forecast, data).unwrap_err();
+        assert!(error.to_string().contains("2024-07-02 19:00"), "{error}");
+    }
+
+    #[test]
+    fn missing_section_is_error() {
+        let data = parse(include_str!("../../fixtures/weatherapi/forecast.json"));
+        let error = map_response(Endpoint::Current, data).unwrap_err();
+        assert!(
+            error.to_string().contains("missing current conditions"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn error_payload() {
+        let error =
+            ApiError::from_str(include_str!("../../fixtures/weatherapi/error.json")).unwrap();
+        assert_eq!(error.to_string(), "API error 2006: API key is invalid.");
     }
 }
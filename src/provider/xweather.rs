@@ -0,0 +1,463 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, ensure, Context};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::config::Section;
+use crate::normalize;
+use crate::schema;
+use crate::utils::restful_get;
+use crate::CowString;
+
+use super::{
+    base_url_param, Attribution, DataKind, Date, LightningInfo, Meta, ParamDesc, ParamKind,
+    PlannedRequest, ProviderInfo, WeatherInfo, WeatherKind,
+};
+/// API root
+const API_URL: &str = "https://data.api.xweather.com";
+/// Xweather (formerly AerisWeather) provider
+pub struct Xweather {
+    client_id: String,
+    client_secret: String,
+    /// API root, own one unless overridden
+    base_url: String,
+}
+
+//
+// Error handling structures
+//
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    error: Option<ApiErrorInner>,
+}
+
+impl FromStr for ApiError {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.error {
+            Some(error) => f.write_fmt(format_args!(
+                "API error '{}': {}",
+                error.code, error.description
+            )),
+            None => f.write_str("API error without description"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorInner {
+    code: String,
+    description: String,
+}
+
+//
+// Response envelope
+//
+
+/// Every response is wrapped into envelope, which may report failure even with HTTP 200
+struct Envelope<T> {
+    success: bool,
+    error: Option<ApiErrorInner>,
+    response: Option<T>,
+}
+
+/// Envelope as sent; failed responses carry empty list as payload, whatever its actual type
+#[derive(Deserialize)]
+struct RawEnvelope {
+    success: bool,
+    error: Option<ApiErrorInner>,
+    response: Option<serde_json::Value>,
+}
+
+impl<T: DeserializeOwned> FromStr for Envelope<T> {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw: RawEnvelope = serde_json::from_str(s)?;
+        let response = match raw.response {
+            Some(response) if raw.success => schema::parse_value(response)?,
+            _ => None,
+        };
+        Ok(Self {
+            success: raw.success,
+            error: raw.error,
+            response,
+        })
+    }
+}
+
+impl<T> Envelope<T> {
+    /// Extract response payload or convert envelope's failure into error
+    fn into_response(self) -> anyhow::Result<Option<T>> {
+        if self.success {
+            Ok(self.response)
+        } else {
+            Err(ApiError { error: self.error }.into())
+        }
+    }
+}
+
+//
+// Weather response structures
+//
+
+#[derive(Deserialize)]
+struct Observation {
+    /// Station identifier
+    id: Option<String>,
+    ob: ObservationData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ObservationData {
+    /// UNIX timestamp of observation
+    timestamp: Option<i64>,
+    #[serde(rename = "tempC")]
+    temp_c: f32,
+    humidity: f32,
+    #[serde(rename = "windSpeedKPH")]
+    wind_speed_kph: f32,
+    #[serde(rename = "precipMM")]
+    precip_mm: Option<f32>,
+    /// Pressure at sea level, in millibars, same as hPa
+    #[serde(rename = "pressureMB")]
+    pressure_mb: Option<f32>,
+    weather_primary_coded: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Forecast {
+    periods: Vec<ForecastPeriod>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ForecastPeriod {
+    /// UNIX timestamp of period's start
+    timestamp: Option<i64>,
+    #[serde(rename = "avgTempC")]
+    avg_temp_c: f32,
+    humidity: f32,
+    #[serde(rename = "windSpeedKPH")]
+    wind_speed_kph: f32,
+    #[serde(rename = "precipMM")]
+    precip_mm: Option<f32>,
+    /// Pressure at sea level, in millibars, same as hPa
+    #[serde(rename = "pressureMB")]
+    pressure_mb: Option<f32>,
+    weather_primary_coded: Option<String>,
+}
+
+/// Response of request which serves requested date
+enum WeatherData {
+    Observation(Option<Observation>),
+    /// Forecast, along with requested date
+    Forecast(Option<Vec<Forecast>>, Date),
+}
+
+//
+// Lightning response structures
+//
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Strike {
+    relative_to: RelativeTo,
+}
+
+#[derive(Deserialize)]
+struct RelativeTo {
+    #[serde(rename = "distanceKM")]
+    distance_km: f32,
+}
+
+#[async_trait]
+impl super::Provider for Xweather {
+    fn new(config: &Section) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let param = |name: &str| {
+            config
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Missing parameter '{name}'"))
+        };
+        Ok(Self {
+            client_id: param("client_id")?,
+            client_secret: param("client_secret")?,
+            base_url: super::base_url(config, API_URL)?,
+        })
+    }
+
+    fn info() -> &'static ProviderInfo
+    where
+        Self: Sized,
+    {
+        const INFO: ProviderInfo = ProviderInfo {
+            description: "Xweather (https://www.xweather.com/), formerly AerisWeather; supports lightning data",
+            attribution: Some(Attribution {
+                text: "Weather data by Xweather",
+                url: "https://www.xweather.com/",
+                required: false,
+            }),
+            params: &[
+                ParamDesc {
+                    id: "client_id",
+                    name: "Application's client ID",
+                    description: "used to authenticate user requests",
+                    required: true,
+                    default: None,
+                    kind: ParamKind::String,
+                },
+                ParamDesc {
+                    id: "client_secret",
+                    name: "Application's client secret",
+                    description: "used to authenticate user requests",
+                    required: true,
+                    default: None,
+                    kind: ParamKind::Secret,
+                },
+                base_url_param(API_URL),
+            ],
+            hourly: true,
+            lightning: true,
+        };
+        &INFO
+    }
+
+    async fn get_weather(
+        &self,
+        location: CowString,
+        date: Option<Date>,
+    ) -> anyhow::Result<WeatherInfo> {
+        let url = self.authorize(match date {
+            None => observations_url(&self.base_url, &location),
+            Some(date) => forecast_url(&self.base_url, &location, date, 1),
+        });
+        let data = match date {
+            None => WeatherData::Observation(
+                restful_get::<Envelope<Observation>, ApiError>(url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain current conditions"))?
+                    .into_response()?,
+            ),
+            Some(date) => WeatherData::Forecast(
+                restful_get::<Envelope<Vec<Forecast>>, ApiError>(url)
+                    .await
+                    .with_context(|| anyhow!("Could not obtain weather forecast"))?
+                    .into_response()?,
+                date,
+            ),
+        };
+        map_response(&location, data)
+    }
+
+    async fn get_hourly(
+        &self,
+        location: CowString,
+        start: Date,
+        hours: u32,
+    ) -> anyhow::Result<Vec<WeatherInfo>> {
+        let start = start.with_hour(Some(start.hour.unwrap_or(0)));
+        let url = self.authorize(forecast_url(&self.base_url, &location, start, hours));
+        let periods = restful_get::<Envelope<Vec<Forecast>>, ApiError>(url)
+            .await
+            .with_context(|| anyhow!("Could not obtain hourly weather forecast"))?
+            .into_response()?
+            .and_then(|forecasts| forecasts.into_iter().next())
+            .map(|forecast| forecast.periods)
+            .unwrap_or_default();
+        // Periods go hour by hour from requested one
+        ensure!(
+            periods.len() >= hours as usize,
+            "Hourly forecast for '{location}' from {start} has only {} hours",
+            periods.len()
+        );
+        periods
+            .into_iter()
+            .take(hours as usize)
+            .map(|period| {
+                map_response(
+                    &location,
+                    WeatherData::Forecast(
+                        Some(vec![Forecast {
+                            periods: vec![period],
+                        }]),
+                        start,
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn plan_weather(
+        &self,
+        location: &str,
+        date: Option<Date>,
+    ) -> anyhow::Result<Vec<PlannedRequest>> {
+        Ok(vec![match date {
+            None => PlannedRequest::get(
+                "current conditions",
+                self.authorize(observations_url(&self.base_url, location)),
+            ),
+            Some(date) => PlannedRequest::get(
+                if date.hour.is_some() {
+                    "hourly forecast"
+                } else {
+                    "daily forecast"
+                },
+                self.authorize(forecast_url(&self.base_url, location, date, 1)),
+            ),
+        }])
+    }
+
+    async fn get_lightning(
+        &self,
+        location: CowString,
+        radius_km: f32,
+        period_minutes: u32,
+    ) -> anyhow::Result<LightningInfo> {
+        let url = self.authorize(
+            ApiUrl::new(&self.base_url)
+                .segment("lightning")
+                .segment(&location)
+                .query("radius", format_args!("{radius_km}km"))
+                .query("from", format_args!("-{period_minutes}minutes"))
+                .query("limit", 1000),
+        );
+        // Absence of strikes is reported as response without payload
+        let strikes = restful_get::<Envelope<Vec<Strike>>, ApiError>(url)
+            .await
+            .with_context(|| anyhow!("Could not obtain lightning data"))?
+            .into_response()?
+            .unwrap_or_default();
+
+        Ok(LightningInfo {
+            strikes: strikes.len(),
+            nearest_km: strikes
+                .iter()
+                .map(|strike| strike.relative_to.distance_km)
+                .reduce(f32::min),
+            radius_km,
+            period_minutes,
+        })
+    }
+}
+
+impl Xweather {
+    /// Adds authentication query parameters to URL
+    fn authorize(&self, url: ApiUrl) -> ApiUrl {
+        url.secret_query("client_id", &self.client_id)
+            .secret_query("client_secret", &self.client_secret)
+    }
+}
+/// Converts response into weather information
+///
+/// # Parameters
+/// * `location` - requested location, for error messages
+/// * `data` - response
+///
+/// # Returns
+/// Weather information, or error if response has no observation or forecast period
+fn map_response(location: &str, data: WeatherData) -> anyhow::Result<WeatherInfo> {
+    let (temperature, humidity, wind_speed_kph, precipitation, pressure, coded, meta) = match data {
+        WeatherData::Observation(observation) => {
+            let observation =
+                observation.ok_or_else(|| anyhow!("No observations for '{location}'"))?;
+            let ob = observation.ob;
+            (
+                ob.temp_c,
+                ob.humidity,
+                ob.wind_speed_kph,
+                ob.precip_mm,
+                ob.pressure_mb,
+                ob.weather_primary_coded,
+                Meta::new(DataKind::Observation)
+                    .with_timestamp(ob.timestamp)
+                    .with_source(observation.id.map(|id| format!("station {id}"))),
+            )
+        }
+        WeatherData::Forecast(forecasts, date) => {
+            let period = forecasts
+                .and_then(|forecasts| forecasts.into_iter().next())
+                .and_then(|forecast| forecast.periods.into_iter().next())
+                .ok_or_else(|| anyhow!("No forecast for '{location}' at {date}"))?;
+            (
+                period.avg_temp_c,
+                period.humidity,
+                period.wind_speed_kph,
+                period.precip_mm,
+                period.pressure_mb,
+                period.weather_primary_coded,
+                Meta::new(DataKind::Forecast).with_timestamp(period.timestamp),
+            )
+        }
+    };
+
+    if precipitation.is_none() {
+        schema::missing("precipMM", "unknown precipitation");
+    }
+    if coded.is_none() {
+        schema::missing("weatherPrimaryCoded", "unknown weather");
+    }
+
+    Ok(WeatherInfo {
+        weather: coded
+            .as_deref()
+            .map_or(WeatherKind::Unknown, normalize::xweather_kind),
+        condition_code: coded,
+        temperature,
+        temperature_min: None,
+        temperature_max: None,
+        wind_speed: normalize::kmh_to_ms(wind_speed_kph),
+        humidity,
+        precipitation,
+        pressure,
+        meta,
+    })
+}
+/// URL of current conditions request, without authentication
+///
+/// # Parameters
+/// * `base_url` - API root
+/// * `location` - location of conditions
+fn observations_url(base_url: &str, location: &str) -> ApiUrl {
+    ApiUrl::new(base_url)
+        .segment("observations")
+        .segment(location)
+}
+/// URL of daily or, if date has hour of day, hourly forecast request, without authentication
+///
+/// # Parameters
+/// * `base_url` - API root
+/// * `location` - location of forecast
+/// * `date` - date of forecast, possibly with hour of day
+/// * `limit` - number of periods, starting from requested one
+fn forecast_url(base_url: &str, location: &str, date: Date, limit: u32) -> ApiUrl {
+    let url = ApiUrl::new(base_url).segment("forecasts").segment(location);
+    // Times without offset are taken by API as location's local ones
+    let url = match date.hour {
+        Some(hour) => url.query("filter", "1hr").query(
+            "from",
+            format_args!("{}T{hour:02}:00:00", date.without_hour()),
+        ),
+        None => url.query("filter", "day").query("from", date),
+    };
+    url.query("limit", limit)
+}
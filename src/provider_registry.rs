@@ -46,12 +46,33 @@ impl ProviderRegistry {
     /// If provider with such name is already registered.
     /// Registering multiple providers under same name is programmer's error.
     pub fn add_provider<T: Provider + 'static>(&mut self, name: impl Into<CowString>) {
+        if let Err(error) = self.add_factory(name, Box::new(ProviderFactoryT::<T>::new())) {
+            panic!("{error}");
+        }
+    }
+    /// Adds new named provider to registry, specified by its factory;
+    /// used for providers discovered at runtime
+    ///
+    /// # Parameters
+    /// * `name` - provider's name
+    /// * `factory` - provider's factory
+    ///
+    /// # Returns
+    /// Error if provider with such name is already registered
+    pub fn add_factory(
+        &mut self,
+        name: impl Into<CowString>,
+        factory: Box<dyn ProviderFactory>,
+    ) -> anyhow::Result<()> {
         let name: CowString = name.into();
         match self.providers.entry(name) {
             BTreeEntry::Vacant(e) => {
-                e.insert(Box::new(ProviderFactoryT::<T>::new()));
+                e.insert(factory);
+                Ok(())
+            }
+            BTreeEntry::Occupied(e) => {
+                Err(anyhow::anyhow!("Provider {} already registered", e.key()))
             }
-            BTreeEntry::Occupied(e) => panic!("Provider {} already registered", e.key()),
         }
     }
 }
@@ -64,7 +85,7 @@ pub trait ProviderFactory {
     /// * `config` - TOML config for this provider, concrete format depends on implementor
     ///
     /// # Returns
-    /// Boxed future which completes with boxed provider instance or error
+    /// Boxed provider instance or error
     fn create(&self, config: &Section) -> anyhow::Result<Box<dyn Provider>>;
     /// Get additional information about provider
     ///
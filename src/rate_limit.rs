@@ -0,0 +1,239 @@
+//! Throttling of hosts which rejected request with "429 Too Many Requests"
+//!
+//! Host is paused for time its `Retry-After` header tells, and further requests to it wait
+//! until pause ends. Bulk commands, like batch and export, retry rejected requests with
+//! `retrying`, so they resume by themselves instead of failing.
+//!
+//! Hosts whose usage policy limits request rate, like Nominatim, are also spaced proactively
+//! with `space`, so concurrent jobs don't exceed the limit in the first place
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::progress::{self, Event};
+
+/// Pause when host doesn't tell how long to wait
+const DEFAULT_PAUSE: Duration = Duration::from_secs(60);
+/// Longest pause waited out; longer ones usually mean quota for whole day is spent
+const MAX_PAUSE: Duration = Duration::from_secs(15 * 60);
+/// How many times request is retried after being rejected
+const MAX_RETRIES: u32 = 5;
+
+/// Pauses of hosts, by host name
+static PAUSES: LazyLock<Mutex<HashMap<String, Pause>>> = LazyLock::new(Default::default);
+/// Earliest time of next request to spaced hosts, by host name
+static NEXT_SLOTS: LazyLock<Mutex<HashMap<String, Instant>>> = LazyLock::new(Default::default);
+
+/// Pause of requests to single host
+struct Pause {
+    /// When requests may be sent again
+    until: Instant,
+    /// Whether pause was already reported to user
+    reported: bool,
+}
+/// Error of request which host rejected because of rate limit
+#[derive(Debug, thiserror::Error)]
+#[error("Rate limit of {host} exceeded, retry after {} s", retry_after.as_secs())]
+pub struct RateLimited {
+    /// Host which rejected request
+    pub host: String,
+    /// How long host asked to wait
+    pub retry_after: Duration,
+}
+/// Parses `Retry-After` header, which is either number of seconds or HTTP date
+///
+/// # Parameters
+/// * `value` - header's value
+/// * `now` - current time by server's clock, which HTTP date is compared with
+///
+/// # Returns
+/// Time to wait, or `None` if value is malformed
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    // Date in the past means requests are allowed already
+    Some((at.with_timezone(&Utc) - now).to_std().unwrap_or_default())
+}
+/// Pauses requests to host after it rejected one
+///
+/// # Parameters
+/// * `host` - host name
+/// * `retry_after` - value of response's `Retry-After` header, if any
+/// * `now` - current time by server's clock, from response's `Date` header if any
+///
+/// # Returns
+/// Error to fail request with
+pub fn reject(host: &str, retry_after: Option<&str>, now: DateTime<Utc>) -> RateLimited {
+    let retry_after = retry_after
+        .and_then(|value| parse_retry_after(value, now))
+        .unwrap_or(DEFAULT_PAUSE);
+    let until = Instant::now() + retry_after;
+    let mut pauses = PAUSES.lock().unwrap_or_else(|error| error.into_inner());
+    match pauses.get_mut(host) {
+        Some(pause) if pause.until >= until => {}
+        Some(pause) if pause.until > Instant::now() => pause.until = until,
+        _ => {
+            pauses.insert(
+                host.to_string(),
+                Pause {
+                    until,
+                    reported: false,
+                },
+            );
+        }
+    }
+    RateLimited {
+        host: host.to_string(),
+        retry_after,
+    }
+}
+/// Waits until host's pause, if any, ends
+///
+/// # Parameters
+/// * `host` - host name
+///
+/// # Returns
+/// Error if pause is too long to wait it out
+pub async fn wait(host: &str) -> Result<(), RateLimited> {
+    let until = {
+        let pauses = PAUSES.lock().unwrap_or_else(|error| error.into_inner());
+        pauses.get(host).map(|pause| pause.until)
+    };
+    let Some(remaining) = until.and_then(|until| until.checked_duration_since(Instant::now()))
+    else {
+        return Ok(());
+    };
+    if remaining > MAX_PAUSE {
+        return Err(RateLimited {
+            host: host.to_string(),
+            retry_after: remaining,
+        });
+    }
+    tracing::debug!("Waiting for rate limit of {host}");
+    tokio::time::sleep(remaining).await;
+    Ok(())
+}
+/// Waits for host's next request slot, so requests to host are at least interval apart,
+/// whichever task sends them
+///
+/// # Parameters
+/// * `host` - host name
+/// * `interval` - minimal time between requests
+pub async fn space(host: &str, interval: Duration) {
+    let slot = {
+        let mut slots = NEXT_SLOTS.lock().unwrap_or_else(|error| error.into_inner());
+        let now = Instant::now();
+        let next = slots.entry(host.to_string()).or_insert(now);
+        let slot = (*next).max(now);
+        *next = slot + interval;
+        slot
+    };
+    if slot > Instant::now() {
+        tracing::debug!("Spacing requests to {host}");
+        tokio::time::sleep_until(slot.into()).await;
+    }
+}
+/// Performs request, repeating it while host rejects it because of rate limit;
+/// each pause is reported once, along with its duration
+///
+/// # Parameters
+/// * `call` - makes request
+///
+/// # Returns
+/// Request's result, or its error if pause is too long or retries are exhausted
+pub async fn retrying<T, F, Fut>(mut call: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        let error = match call().await {
+            Ok(result) => return Ok(result),
+            Err(error) => error,
+        };
+        let Some(limited) = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<RateLimited>())
+        else {
+            return Err(error);
+        };
+        if retries == MAX_RETRIES || limited.retry_after > MAX_PAUSE {
+            return Err(error);
+        }
+        retries += 1;
+        if first_report(&limited.host) {
+            let seconds = limited.retry_after.as_secs();
+            tracing::warn!("Paused {seconds}s due to rate limit of {}", limited.host);
+            progress::emit(Event::Paused {
+                host: &limited.host,
+                seconds,
+            });
+        }
+        wait(&limited.host).await?;
+    }
+}
+/// Whether host's current pause isn't reported yet; marks it reported
+fn first_report(host: &str) -> bool {
+    let mut pauses = PAUSES.lock().unwrap_or_else(|error| error.into_inner());
+    match pauses.get_mut(host) {
+        Some(pause) if !pause.reported => {
+            pause.reported = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use chrono::{TimeZone, Utc};
+
+    use super::{parse_retry_after, space};
+
+    #[test]
+    fn retry_after_is_seconds_or_date() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 0).unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_are_spaced() {
+        let interval = Duration::from_millis(100);
+        let started = Instant::now();
+        let request = || async {
+            space("spaced.example.com", interval).await;
+            started.elapsed()
+        };
+        let (first, second, third) = tokio::join!(request(), request(), request());
+        let mut slots = [first, second, third];
+        slots.sort();
+        assert!(slots[0] < interval, "{slots:?}");
+        assert!(slots[1] >= interval, "{slots:?}");
+        assert!(slots[2] >= interval * 2, "{slots:?}");
+        // Other hosts aren't affected
+        let started = Instant::now();
+        space("other.example.com", interval).await;
+        assert!(started.elapsed() < interval);
+    }
+}
@@ -0,0 +1,180 @@
+//! Keeping secrets, like API keys, out of errors, logs and dry-run output
+//!
+//! Providers build request URLs with `ApiUrl`, marking credentials as secret query parameters.
+//! Every secret is also remembered globally, so any text shown to user can be passed through `redact`
+//! as last line of defense, e.g. messages of third-party errors which embed request URL
+use std::io::Write;
+use std::sync::RwLock;
+
+/// Text which replaces secrets
+const MASK: &str = "***";
+
+/// Secrets seen during this run
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Remembers secret, so it's masked by `redact` from now on
+///
+/// # Parameters
+/// * `secret` - secret value; empty values are ignored, since they can't leak anything
+pub fn register(secret: &str) {
+    if secret.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap_or_else(|error| error.into_inner());
+    if !secrets.iter().any(|known| known == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+/// Masks all known secrets in text
+///
+/// # Parameters
+/// * `text` - text which may contain secrets
+///
+/// # Returns
+/// Text with secrets replaced by asterisks
+pub fn redact(text: &str) -> String {
+    let secrets = SECRETS.read().unwrap_or_else(|error| error.into_inner());
+    mask(text, secrets.iter())
+}
+/// Replaces specified secrets in text with asterisks
+pub fn mask<'a>(text: &str, secrets: impl Iterator<Item = &'a String>) -> String {
+    secrets
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), MASK)
+        })
+}
+/// Masks secret value for display, keeping its last characters if it's long enough
+/// for them to be of no use, so user can still tell which of keys is configured
+///
+/// # Parameters
+/// * `value` - secret value, like API key
+pub fn mask_value(value: &str) -> String {
+    const SHOWN: usize = 4;
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < SHOWN * 3 {
+        return MASK.to_string();
+    }
+    let tail: String = chars[chars.len() - SHOWN..].iter().collect();
+    format!("{MASK}{tail}")
+}
+
+/// Writer for logger, which masks known secrets in every record before passing it on
+///
+/// Text is passed on by whole lines, so secret split between several writes is still masked;
+/// incomplete last line is passed on when writer is flushed or dropped
+pub struct RedactingWriter<W: Write> {
+    inner: W,
+    /// Written text which doesn't end with line break yet
+    pending: Vec<u8>,
+}
+
+impl RedactingWriter<std::io::Stderr> {
+    /// Writer to stderr
+    pub fn stderr() -> Self {
+        Self::new(std::io::stderr())
+    }
+}
+
+impl<W: Write> RedactingWriter<W> {
+    /// Wraps writer
+    ///
+    /// # Parameters
+    /// * `inner` - writer which receives text with secrets masked
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+        }
+    }
+    /// Passes on specified number of pending bytes, with secrets masked
+    fn pass_on(&mut self, len: usize) -> std::io::Result<()> {
+        let text: Vec<u8> = self.pending.drain(..len).collect();
+        self.inner
+            .write_all(redact(&String::from_utf8_lossy(&text)).as_bytes())
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        if let Some(end) = self.pending.iter().rposition(|byte| *byte == b'\n') {
+            self.pass_on(end + 1)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.pass_on(self.pending.len())?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for RedactingWriter<W> {
+    fn drop(&mut self) {
+        // There's nowhere to report failure of writing log
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{mask, mask_value, redact, register, RedactingWriter};
+
+    #[test]
+    fn secrets_are_masked_in_url_and_error_text() {
+        let secrets = ["k3y".to_string(), "p@ss".to_string()];
+        assert_eq!(
+            mask(
+                "https://api.example.com/v1?key=k3y&q=London",
+                secrets.iter()
+            ),
+            "https://api.example.com/v1?key=***&q=London"
+        );
+        assert_eq!(
+            mask("Login as user:p@ss failed: k3y expired", secrets.iter()),
+            "Login as user:*** failed: *** expired"
+        );
+        // Empty secret would otherwise mask between every character
+        assert_eq!(mask("text", [String::new()].iter()), "text");
+    }
+
+    #[test]
+    fn registered_secrets_are_redacted() {
+        register("registered-secret-b7f1");
+        register("");
+        assert_eq!(
+            redact("error sending request for url (https://host/?appid=registered-secret-b7f1)"),
+            "error sending request for url (https://host/?appid=***)"
+        );
+    }
+
+    #[test]
+    fn masked_value_keeps_tail_of_long_secrets_only() {
+        assert_eq!(mask_value("0123456789abcdef"), "***cdef");
+        assert_eq!(mask_value("short"), "***");
+        assert_eq!(mask_value(""), "***");
+    }
+
+    #[test]
+    fn secret_split_between_writes_is_masked() {
+        register("split-secret-4c2e");
+        let mut output = Vec::new();
+        {
+            let mut writer = RedactingWriter::new(&mut output);
+            writer
+                .write_all(b"WARN request to https://host/?key=split-")
+                .unwrap();
+            writer
+                .write_all(b"secret-4c2e failed\nlast record with split-sec")
+                .unwrap();
+            writer.write_all(b"ret-4c2e").unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "WARN request to https://host/?key=*** failed\nlast record with ***"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Rules which map forecast conditions to messages, like advice on what to wear or alerts
+//!
+//! Rules files have one rule per line, `<condition> => <message>`, where condition uses
+//! same syntax as in 'check' command; empty lines and lines starting with '#' are skipped.
+//! Config sections, like `[alerts]`, have `<message> = <condition>` entries instead
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, ensure, Context};
+
+use crate::app_dirs;
+use crate::condition::Condition;
+use crate::config::Section;
+use crate::provider::WeatherInfo;
+
+/// Name of advice rules file in config directory
+const ADVICE_FILE: &str = "advice.rules";
+/// Advice rules used when config directory has no rules file
+const DEFAULT_ADVICE: &str = "\
+rain or drizzle or thunderstorm or precipitation > 1 => Take an umbrella
+snow or sleet or hail => Wear waterproof boots
+temperature < 0 => Wear warm layers, hat and gloves
+temperature >= 0 and temperature < 12 => Wear layers or jacket
+clear and temperature >= 20 => Use sunscreen
+temperature >= 28 => Drink plenty of water
+wind >= 10 or thunderstorm => Avoid cycling due to strong wind gusts
+fog => Drive carefully, visibility may be poor
+";
+
+/// Condition along with message shown when it holds
+pub struct Rule {
+    pub condition: Condition,
+    pub message: String,
+}
+/// Ordered list of rules
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    /// Loads advice rules from config directory, or default ones if there's no rules file
+    ///
+    /// # Returns
+    /// Rules or error if rules file can't be read or parsed
+    pub fn load_advice() -> anyhow::Result<Self> {
+        Self::load_advice_from(advice_path())
+    }
+    /// Loads advice rules from specified file, or default ones if it doesn't exist
+    ///
+    /// # Parameters
+    /// * `path` - path to rules file, if it's known
+    ///
+    /// # Returns
+    /// Rules or error if rules file can't be read or parsed
+    fn load_advice_from(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        match path.filter(|path| path.exists()) {
+            Some(path) => fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|text| text.parse())
+                .with_context(|| anyhow!("When reading rules file {}", path.display())),
+            None => DEFAULT_ADVICE.parse(),
+        }
+    }
+    /// Parses rules from config section
+    ///
+    /// # Parameters
+    /// * `section` - section with "<message> = <condition>" entries, like `[alerts]`
+    ///
+    /// # Returns
+    /// Rules, in order of messages, or error if some condition can't be parsed
+    pub fn from_section(section: &Section) -> anyhow::Result<Self> {
+        section
+            .iter()
+            .map(|(message, condition)| {
+                let condition = Condition::from_str(condition)
+                    .with_context(|| anyhow!("When parsing condition of '{message}'"))?;
+                Ok(Rule {
+                    condition,
+                    message: message.clone(),
+                })
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+    /// Messages of rules which forecast satisfies, in rules' order
+    ///
+    /// # Parameters
+    /// * `info` - forecast
+    pub fn matching<'a>(&'a self, info: &'a WeatherInfo) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(|rule| rule.condition.evaluate(info))
+            .map(|rule| rule.message.as_str())
+    }
+}
+
+impl FromStr for Rules {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (condition, message) = line
+                .split_once("=>")
+                .ok_or_else(|| anyhow!("Line {line_no}: expected '<condition> => <message>'"))?;
+            let condition = Condition::from_str(condition)
+                .with_context(|| anyhow!("Line {line_no}: invalid condition"))?;
+            let message = message.trim();
+            ensure!(!message.is_empty(), "Line {line_no}: message is missing");
+            rules.push(Rule {
+                condition,
+                message: message.to_string(),
+            });
+        }
+        Ok(Self(rules))
+    }
+}
+/// Path to advice rules file
+fn advice_path() -> Option<PathBuf> {
+    app_dirs::config_dir().map(|path| path.join(ADVICE_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rules;
+    use crate::config::Section;
+    use crate::provider::{WeatherInfo, WeatherKind};
+
+    fn matching(rules: &Rules, info: &WeatherInfo) -> Vec<String> {
+        rules.matching(info).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn rules_are_parsed_skipping_comments() {
+        let rules: Rules = "\
+            # Outdoor activities\n\
+            \n\
+            rain => Take an umbrella\n  \
+            temperature >= 20 and clear =>  Use sunscreen  \n"
+            .parse()
+            .unwrap();
+        let sunny = WeatherInfo::sample(WeatherKind::Clear, 25.0);
+        assert_eq!(matching(&rules, &sunny), ["Use sunscreen"]);
+        let rainy = WeatherInfo::sample(WeatherKind::Rain, 25.0);
+        assert_eq!(matching(&rules, &rainy), ["Take an umbrella"]);
+    }
+
+    #[test]
+    fn invalid_lines_are_reported_with_their_numbers() {
+        let error = |text: &str| format!("{:#}", text.parse::<Rules>().err().unwrap());
+        assert_eq!(
+            error("rain => Umbrella\nTake an umbrella"),
+            "Line 2: expected '<condition> => <message>'"
+        );
+        assert!(error("# Comment\nrain and => Umbrella").starts_with("Line 2: invalid condition: "));
+        assert_eq!(error("rain =>  "), "Line 1: message is missing");
+    }
+
+    #[test]
+    fn default_advice_is_valid() {
+        let rules: Rules = super::DEFAULT_ADVICE.parse().unwrap();
+        let freezing = WeatherInfo::sample(WeatherKind::Snow, -5.0);
+        assert_eq!(
+            matching(&rules, &freezing),
+            ["Wear waterproof boots", "Wear warm layers, hat and gloves"]
+        );
+    }
+
+    #[test]
+    fn advice_is_loaded_from_file_or_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("advice.rules");
+        let rainy = WeatherInfo::sample(WeatherKind::Rain, 15.0);
+
+        let defaults = Rules::load_advice_from(Some(path.clone())).unwrap();
+        assert_eq!(matching(&defaults, &rainy), ["Take an umbrella"]);
+        let defaults = Rules::load_advice_from(None).unwrap();
+        assert_eq!(matching(&defaults, &rainy), ["Take an umbrella"]);
+
+        std::fs::write(&path, "rain => Stay home\n").unwrap();
+        let custom = Rules::load_advice_from(Some(path.clone())).unwrap();
+        assert_eq!(matching(&custom, &rainy), ["Stay home"]);
+
+        std::fs::write(&path, "rain => Stay home\nfog\n").unwrap();
+        let error = Rules::load_advice_from(Some(path.clone())).err().unwrap();
+        assert_eq!(
+            format!("{error:#}"),
+            format!(
+                "When reading rules file {}: Line 2: expected '<condition> => <message>'",
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn section_rules_keep_message_order() {
+        let section: Section = [
+            ("Frost", "temperature < 0"),
+            ("Cold", "temperature < 5"),
+            ("Gale", "wind > 15"),
+        ]
+        .into_iter()
+        .map(|(message, condition)| (message.to_string(), condition.to_string()))
+        .collect();
+        let rules = Rules::from_section(&section).unwrap();
+        let freezing = WeatherInfo::sample(WeatherKind::Clear, -3.0);
+        assert_eq!(matching(&rules, &freezing), ["Cold", "Frost"]);
+        let stormy = WeatherInfo {
+            wind_speed: 20.0,
+            ..WeatherInfo::sample(WeatherKind::Rain, 10.0)
+        };
+        assert_eq!(matching(&rules, &stormy), ["Gale"]);
+
+        let section: Section = [("Frost".to_string(), "temperature <".to_string())].into();
+        let error = Rules::from_section(&section).err().unwrap();
+        assert!(format!("{error:#}").starts_with("When parsing condition of 'Frost': "));
+    }
+}
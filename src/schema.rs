@@ -0,0 +1,121 @@
+//! Detection of providers changing their response schemas
+//!
+//! Providers' responses are parsed leniently: fields application doesn't use are ignored,
+//! and absent optional fields fall back to unknown or zero values. So schema change
+//! rather produces odd weather than error. In strict mode, any field response structures
+//! don't describe fails parsing, like `#[serde(deny_unknown_fields)]` on each of them,
+//! and expected optional fields which are missing are warned about
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::de::{DeserializeOwned, Error};
+
+/// Whether responses are parsed strictly
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Turns strict parsing on or off
+///
+/// # Parameters
+/// * `strict` - whether unknown fields fail parsing and missing optional ones are warned about
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+/// Parses response structure from JSON text, failing on unknown fields in strict mode
+///
+/// # Parameters
+/// * `text` - JSON text
+///
+/// # Returns
+/// Parsed structure or error
+pub fn parse<T: DeserializeOwned>(text: &str) -> serde_json::Result<T> {
+    if !STRICT.load(Ordering::Relaxed) {
+        return serde_json::from_str(text);
+    }
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    let value = deserialize_known(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+/// Same as `parse`, but for XML text; attributes are fields named like `@id`,
+/// and element's text is field named `$text`
+///
+/// # Parameters
+/// * `text` - XML text
+///
+/// # Returns
+/// Parsed structure or error
+pub fn parse_xml<T: DeserializeOwned>(text: &str) -> Result<T, quick_xml::DeError> {
+    let mut deserializer = quick_xml::de::Deserializer::from_str(text);
+    if STRICT.load(Ordering::Relaxed) {
+        deserialize_known(&mut deserializer)
+    } else {
+        serde::Deserialize::deserialize(&mut deserializer)
+    }
+}
+/// Same as `parse`, but for already parsed JSON value,
+/// e.g. payload of envelope whose type depends on its status
+///
+/// # Parameters
+/// * `value` - JSON value
+///
+/// # Returns
+/// Parsed structure or error
+pub fn parse_value<T: DeserializeOwned>(value: serde_json::Value) -> serde_json::Result<T> {
+    if STRICT.load(Ordering::Relaxed) {
+        deserialize_known(value)
+    } else {
+        serde_json::from_value(value)
+    }
+}
+/// Deserializes structure, collecting paths of all fields it ignores
+fn deserialize_known<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: DeserializeOwned,
+    D: serde::Deserializer<'de>,
+{
+    let mut unknown = Vec::new();
+    let value = serde_ignored::deserialize(deserializer, |path| {
+        let mut text = String::new();
+        write_path(&path, &mut text);
+        unknown.push(text);
+    })?;
+    if unknown.is_empty() {
+        Ok(value)
+    } else {
+        Err(D::Error::custom(format_args!(
+            "Unknown fields in response: {}",
+            unknown.join(", ")
+        )))
+    }
+}
+/// Writes field's path like "current.condition" or "daily[0].weather",
+/// skipping levels which aren't present in JSON itself, like optional values
+fn write_path(path: &serde_ignored::Path, text: &mut String) {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => {}
+        Path::Seq { parent, index } => {
+            write_path(parent, text);
+            text.push_str(&format!("[{index}]"));
+        }
+        Path::Map { parent, key } => {
+            write_path(parent, text);
+            if !text.is_empty() {
+                text.push('.');
+            }
+            text.push_str(key);
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => write_path(parent, text),
+    }
+}
+/// Reports expected optional field which response misses, in strict mode
+///
+/// # Parameters
+/// * `field` - path of field in response, like "current.condition"
+/// * `fallback` - what's used instead
+pub fn missing(field: &str, fallback: &str) {
+    if STRICT.load(Ordering::Relaxed) {
+        tracing::warn!("Response misses field '{field}', {fallback} is used instead");
+    }
+}
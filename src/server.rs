@@ -0,0 +1,281 @@
+//! Small HTTP API which lets other local tools, like status bars and dashboards,
+//! query forecasts without spawning processes
+//!
+//! Single endpoint is served:
+//! `GET /weather?location=<address>&date=<YYYY-MM-DD|YYYY-MM-DD HH:MM|now>&provider=<name>`,
+//! where only `location` is required. Responses are JSON objects; failures are reported
+//! as `{"error": "..."}` with appropriate HTTP status
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::condition_mapping::MappedProvider;
+use crate::date::Date;
+use crate::metrics::Metrics;
+use crate::redact;
+
+/// Maximal accepted size of request line and headers, in bytes
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// Cached response key: provider, location and date, `None` for current conditions
+type CacheKey = (String, String, Option<String>);
+
+/// Server state, shared by all connections
+pub struct Server {
+    /// Instantiated providers, by name
+    providers: BTreeMap<String, MappedProvider>,
+    /// Providers' attributions, included into responses, by provider name
+    attributions: BTreeMap<String, Value>,
+    /// Provider used when request doesn't specify one
+    default_provider: Option<String>,
+    /// How long successful responses are reused
+    cache_ttl: Duration,
+    /// Recent successful responses along with time they were obtained
+    cache: Mutex<HashMap<CacheKey, (Instant, Value)>>,
+    /// Weather metrics exposed at `/metrics`, if enabled
+    metrics: Option<Metrics>,
+}
+
+/// HTTP response status along with body
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: impl std::fmt::Display) -> Self {
+        Self::json(status, &json!({ "error": message.to_string() }))
+    }
+}
+
+impl Server {
+    /// Creates server state
+    ///
+    /// # Parameters
+    /// * `providers` - configured providers which can be queried, by name
+    /// * `attributions` - JSON attributions of providers, by name
+    /// * `default_provider` - provider used when request doesn't specify one
+    /// * `cache_ttl` - how long successful responses are reused
+    /// * `metrics` - weather metrics to expose at `/metrics`; their provider should be among `providers`
+    pub fn new(
+        providers: BTreeMap<String, MappedProvider>,
+        attributions: BTreeMap<String, Value>,
+        default_provider: Option<String>,
+        cache_ttl: Duration,
+        metrics: Option<Metrics>,
+    ) -> Self {
+        Self {
+            providers,
+            attributions,
+            default_provider,
+            cache_ttl,
+            cache: Mutex::default(),
+            metrics,
+        }
+    }
+    /// Listens for connections until process is terminated
+    ///
+    /// Each connection is served by its own task, which shares server state,
+    /// so providers are created once rather than per request
+    ///
+    /// # Parameters
+    /// * `listen` - address to listen on
+    pub async fn serve(self, listen: SocketAddr) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(listen)
+            .await
+            .with_context(|| anyhow!("Could not listen on {listen}"))?;
+        println!("Serving forecasts on http://{listen}/weather");
+
+        let server = Arc::new(self);
+        // Metrics are refreshed in background, independently from requests
+        if server.metrics.is_some() {
+            let server = server.clone();
+            tokio::spawn(async move { server.refresh_metrics().await });
+        }
+        loop {
+            let (stream, peer) = listener
+                .accept()
+                .await
+                .with_context(|| anyhow!("When accepting connection"))?;
+            let server = server.clone();
+            tokio::spawn(async move {
+                if let Err(error) = server.handle(stream).await {
+                    tracing::warn!("Connection from {peer} failed: {error:#}");
+                }
+            });
+        }
+    }
+    /// Recent responses; they stay usable even if some request panicked while holding them
+    fn cache(&self) -> MutexGuard<'_, HashMap<CacheKey, (Instant, Value)>> {
+        self.cache.lock().unwrap_or_else(|error| error.into_inner())
+    }
+    /// Refreshes metrics periodically, forever
+    async fn refresh_metrics(&self) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        let Some(provider) = self.providers.get(metrics.provider()) else {
+            return;
+        };
+        let mut interval = tokio::time::interval(metrics.interval());
+        loop {
+            interval.tick().await;
+            metrics.refresh(provider).await;
+        }
+    }
+    /// Reads single request from connection and writes response to it
+    async fn handle(&self, stream: TcpStream) -> anyhow::Result<()> {
+        let mut stream = BufReader::new(stream);
+        // Read request line, then skip headers up to empty line
+        let mut request_line = String::new();
+        stream.read_line(&mut request_line).await?;
+        let mut head_size = request_line.len();
+        loop {
+            let mut header = String::new();
+            let read = stream.read_line(&mut header).await?;
+            head_size += read;
+            if read == 0 || header.trim().is_empty() || head_size > MAX_REQUEST_HEAD {
+                break;
+            }
+        }
+
+        let Response {
+            status,
+            content_type,
+            body,
+        } = if head_size > MAX_REQUEST_HEAD {
+            Response::error(431, "Request head is too large")
+        } else {
+            self.respond(request_line.trim()).await
+        };
+        let reason = match status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            431 => "Request Header Fields Too Large",
+            _ => "Bad Gateway",
+        };
+        let response = format!(
+            "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.get_mut().write_all(response.as_bytes()).await?;
+        stream.get_mut().shutdown().await?;
+        Ok(())
+    }
+    /// Produces response for request line, like "GET /weather?location=London HTTP/1.1"
+    async fn respond(&self, request_line: &str) -> Response {
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Response::error(400, "Malformed request line");
+        };
+        let Ok(url) = url::Url::parse(&format!("http://localhost{target}")) else {
+            return Response::error(400, format!("Malformed request target '{target}'"));
+        };
+        let metrics_requested = match (url.path(), &self.metrics) {
+            ("/weather", _) => false,
+            ("/metrics", Some(_)) => true,
+            (path, _) => return Response::error(404, format!("No such endpoint '{path}'")),
+        };
+        if method != "GET" {
+            return Response::error(405, format!("Method '{method}' isn't supported"));
+        }
+
+        match &self.metrics {
+            Some(metrics) if metrics_requested => Response {
+                status: 200,
+                content_type: "text/plain; version=0.0.4",
+                body: metrics.render(),
+            },
+            _ => self.weather(&url).await,
+        }
+    }
+    /// Produces response for weather request
+    async fn weather(&self, url: &url::Url) -> Response {
+        let query: HashMap<_, _> = url.query_pairs().collect();
+        let Some(location) = query.get("location") else {
+            return Response::error(400, "Parameter 'location' is required");
+        };
+        let date = match query.get("date").map(|date| date.as_ref()) {
+            None | Some("now") => None,
+            Some(date) => match Date::parse_in(date, crate::timezone::of_location(location)) {
+                Ok(date) => Some(date),
+                Err(error) => return Response::error(400, format!("Invalid date: {error}")),
+            },
+        };
+        let Some(provider_name) = query
+            .get("provider")
+            .map(|provider| provider.to_string())
+            .or_else(|| self.default_provider.clone())
+        else {
+            return Response::error(
+                400,
+                "Parameter 'provider' is required, as there's no default one",
+            );
+        };
+        let Some(provider) = self.providers.get(&provider_name) else {
+            return Response::error(
+                404,
+                format!("Provider '{provider_name}' doesn't exist or isn't configured"),
+            );
+        };
+
+        let key = (
+            provider_name.clone(),
+            location.to_string(),
+            date.as_ref().map(Date::to_string),
+        );
+        if let Some((obtained, body)) = self.cache().get(&key) {
+            if obtained.elapsed() < self.cache_ttl {
+                tracing::debug!("Cache hit for '{location}' from '{provider_name}'");
+                return Response::json(200, body);
+            }
+        }
+
+        let response_date = key
+            .2
+            .clone()
+            .unwrap_or_else(|| Date::today_at(location).to_string());
+        match provider
+            .get_weather(location.to_string().into(), date)
+            .await
+        {
+            Ok(info) => {
+                let body = json!({
+                    "provider": provider_name,
+                    "location": location,
+                    "date": response_date,
+                    "weather": info.weather.to_string(),
+                    "temperature": info.temperature,
+                    "wind_speed": info.wind_speed,
+                    "humidity": info.humidity,
+                    "precipitation": info.precipitation,
+                    "condition_code": info.condition_code,
+                    "attribution": self.attributions.get(&provider_name),
+                });
+                let mut cache = self.cache();
+                cache.retain(|_, (obtained, _)| obtained.elapsed() < self.cache_ttl);
+                let response = Response::json(200, &body);
+                cache.insert(key, (Instant::now(), body));
+                response
+            }
+            Err(error) => Response::error(502, redact::redact(&format!("{error:#}"))),
+        }
+    }
+}
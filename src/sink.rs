@@ -0,0 +1,41 @@
+//! Destinations where obtained forecasts are published, besides stdout
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+pub mod mqtt;
+pub mod webhook;
+
+/// Boxed future which may borrow sink
+pub type SinkFuture<'a> = Pin<Box<dyn Future<Output = anyhow::Result<()>> + 'a>>;
+
+/// Destination of published forecasts
+pub trait Sink {
+    /// Publishes single payload
+    ///
+    /// # Parameters
+    /// * `payload` - JSON text to publish
+    fn publish<'a>(&'a mut self, payload: &'a str) -> SinkFuture<'a>;
+    /// Waits for specified period between publications, keeping connection alive if there's one
+    ///
+    /// # Parameters
+    /// * `period` - how long to wait
+    fn idle(&mut self, period: Duration) -> SinkFuture<'_>;
+}
+
+/// Prints each payload to stdout, as single line
+pub struct Stdout;
+
+impl Sink for Stdout {
+    fn publish<'a>(&'a mut self, payload: &'a str) -> SinkFuture<'a> {
+        println!("{payload}");
+        Box::pin(async { Ok(()) })
+    }
+
+    fn idle(&mut self, period: Duration) -> SinkFuture<'_> {
+        Box::pin(async move {
+            tokio::time::sleep(period).await;
+            Ok(())
+        })
+    }
+}
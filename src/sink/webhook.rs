@@ -0,0 +1,84 @@
+//! Webhook sink, for posting forecasts into team chats or custom endpoints
+use std::time::Duration;
+
+use anyhow::{anyhow, ensure, Context};
+use serde_json::{json, Value};
+
+use crate::http_backend::{self, Request};
+
+use super::{Sink, SinkFuture};
+
+/// Message format expected by webhook
+#[derive(Clone, Copy)]
+enum Format {
+    /// Payload is posted as is
+    Json,
+    /// Slack incoming webhook, `{"text": ...}`
+    Slack,
+    /// Discord webhook, `{"content": ...}`
+    Discord,
+}
+
+/// Posts payloads to webhook URL
+///
+/// Payloads are JSON objects; for chat webhooks, their `text` field is posted as message
+pub struct Webhook {
+    url: url::Url,
+    format: Format,
+}
+
+impl Webhook {
+    /// Creates sink, detecting message format by webhook's host
+    ///
+    /// # Parameters
+    /// * `url` - webhook URL
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        let url = url::Url::parse(url).with_context(|| anyhow!("Invalid webhook URL '{url}'"))?;
+        ensure!(
+            matches!(url.scheme(), "http" | "https"),
+            "Webhook URL should be HTTP(S) one"
+        );
+        let format = match url.host_str().unwrap_or_default() {
+            "hooks.slack.com" => Format::Slack,
+            "discord.com" | "discordapp.com" if url.path().starts_with("/api/webhooks/") => {
+                Format::Discord
+            }
+            _ => Format::Json,
+        };
+        Ok(Self { url, format })
+    }
+}
+
+impl Sink for Webhook {
+    fn publish<'a>(&'a mut self, payload: &'a str) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let payload: Value = serde_json::from_str(payload)?;
+            let text = || payload.get("text").cloned().unwrap_or_default();
+            let body = match self.format {
+                Format::Json => payload.clone(),
+                Format::Slack => json!({ "text": text() }),
+                Format::Discord => json!({ "content": text() }),
+            };
+            let request = Request::post(self.url.as_str())
+                .header("Content-Type", "application/json")
+                .body(body.to_string());
+            let (status, text) = http_backend::fetch_text(request)
+                .await
+                .with_context(|| anyhow!("HTTP POST request failed"))?;
+            ensure!(
+                http_backend::is_success(status),
+                "Webhook responded with {}: {}",
+                http_backend::status_text(status),
+                text.trim()
+            );
+            Ok(())
+        })
+    }
+
+    fn idle(&mut self, period: Duration) -> SinkFuture<'_> {
+        Box::pin(async move {
+            tokio::time::sleep(period).await;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,291 @@
+//! Single SQLite database in data directory, which keeps application's growing state consistent
+//! and queryable: cached weather, history of forecast queries and daily request counters of hosts
+//!
+//! Schema is created and upgraded by migrations, whose number is kept in `user_version` pragma.
+//! Connection is opened on first use and shared by whole process
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, ensure, Context};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection};
+
+use crate::app_dirs;
+use crate::clock;
+use crate::provider::WeatherInfo;
+
+/// Name of database file in data directory
+const FILE: &str = "weather.db";
+/// How long writer waits for database locked by another process
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Schema changes, applied in order; new ones are only appended
+const MIGRATIONS: [&str; 1] = ["
+    CREATE TABLE cache (
+        provider TEXT NOT NULL,
+        location TEXT NOT NULL,
+        fetched TEXT NOT NULL,
+        info TEXT NOT NULL,
+        PRIMARY KEY (provider, location)
+    );
+    CREATE TABLE history (
+        id INTEGER PRIMARY KEY,
+        at TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        location TEXT NOT NULL,
+        date TEXT NOT NULL,
+        days INTEGER NOT NULL
+    );
+    CREATE TABLE quota (
+        host TEXT NOT NULL,
+        day TEXT NOT NULL,
+        requests INTEGER NOT NULL,
+        PRIMARY KEY (host, day)
+    );
+"];
+/// Tables reported by `info`
+const TABLES: [&str; 3] = ["cache", "history", "quota"];
+
+/// Connection shared by whole process, opened on first use
+static DATABASE: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// Cached weather at location
+pub struct CacheRow {
+    /// Provider which obtained weather
+    pub provider: String,
+    /// Location, with alias expanded
+    pub location: String,
+    /// When weather was obtained
+    pub fetched: DateTime<Utc>,
+    /// Weather itself
+    pub info: WeatherInfo,
+}
+/// Statistics of database
+pub struct Info {
+    /// Database file
+    pub path: PathBuf,
+    /// Number of applied migrations
+    pub version: i64,
+    /// Size of database file, in bytes
+    pub bytes: u64,
+    /// Number of rows in each table
+    pub rows: Vec<(&'static str, i64)>,
+    /// Requests sent today to each host, most requested first
+    pub requests_today: Vec<(String, i64)>,
+}
+/// Path of database file, if OS has notion of data directory
+pub fn path() -> Option<PathBuf> {
+    app_dirs::data_dir().map(|path| path.join(FILE))
+}
+/// Opens database, creating it if needed, and brings its schema up to date
+fn open() -> anyhow::Result<Connection> {
+    let path = path().ok_or_else(|| anyhow!("OS has no notion of data directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut connection = Connection::open(&path)
+        .with_context(|| anyhow!("When opening database {}", path.display()))?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    migrate(&mut connection)
+        .with_context(|| anyhow!("When migrating database {}", path.display()))?;
+    Ok(connection)
+}
+/// Applies migrations which database doesn't have yet, all in one transaction
+fn migrate(connection: &mut Connection) -> anyhow::Result<()> {
+    let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let applied = usize::try_from(version)?;
+    ensure!(
+        applied <= MIGRATIONS.len(),
+        "Database has schema version {version}, which is newer than this application supports"
+    );
+    if applied == MIGRATIONS.len() {
+        return Ok(());
+    }
+    let transaction = connection.transaction()?;
+    for migration in &MIGRATIONS[applied..] {
+        transaction.execute_batch(migration)?;
+    }
+    // Pragma doesn't accept bound parameters
+    transaction.execute_batch(&format!("PRAGMA user_version = {}", MIGRATIONS.len()))?;
+    transaction.commit()?;
+    Ok(())
+}
+/// Runs operation on shared connection, opening it first if needed
+fn with<T>(operation: impl FnOnce(&mut Connection) -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let mut database = DATABASE.lock().unwrap_or_else(|error| error.into_inner());
+    let connection = match database.take() {
+        Some(connection) => connection,
+        None => open()?,
+    };
+    operation(database.insert(connection))
+}
+/// Formats time so timestamps of same table compare as text
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+/// Reads all cached weather
+pub fn cache_rows() -> anyhow::Result<Vec<CacheRow>> {
+    with(|connection| {
+        let mut statement =
+            connection.prepare("SELECT provider, location, fetched, info FROM cache")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        let mut result = Vec::new();
+        for row in rows {
+            let (provider, location, fetched, info) = row?;
+            result.push(CacheRow {
+                provider,
+                location,
+                fetched: DateTime::parse_from_rfc3339(&fetched)?.with_timezone(&Utc),
+                info: serde_json::from_str(&info)?,
+            });
+        }
+        Ok(result)
+    })
+}
+/// Replaces cached weather with specified one
+///
+/// # Parameters
+/// * `rows` - weather to keep cached
+pub fn replace_cache(rows: &[CacheRow]) -> anyhow::Result<()> {
+    with(|connection| {
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM cache", [])?;
+        for row in rows {
+            transaction.execute(
+                "INSERT INTO cache (provider, location, fetched, info) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    row.provider,
+                    row.location,
+                    timestamp(row.fetched),
+                    serde_json::to_string(&row.info)?
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    })
+}
+/// Appends forecast query to history
+///
+/// # Parameters
+/// * `provider` - provider which served query
+/// * `location` - location of forecast
+/// * `date` - first day, as specified by user
+/// * `days` - number of days
+pub fn record_query(provider: &str, location: &str, date: &str, days: u32) -> anyhow::Result<()> {
+    with(|connection| {
+        connection.execute(
+            "INSERT INTO history (at, provider, location, date, days) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                timestamp(clock::now().with_timezone(&Utc)),
+                provider,
+                location,
+                date,
+                days
+            ],
+        )?;
+        Ok(())
+    })
+}
+/// Counts request sent to host today, by local calendar
+///
+/// # Parameters
+/// * `host` - host name
+pub fn count_request(host: &str) -> anyhow::Result<()> {
+    with(|connection| {
+        connection.execute(
+            "INSERT INTO quota (host, day, requests) VALUES (?1, ?2, 1)
+             ON CONFLICT (host, day) DO UPDATE SET requests = requests + 1",
+            params![host, clock::now().date_naive().to_string()],
+        )?;
+        Ok(())
+    })
+}
+/// Collects statistics of database
+pub fn info() -> anyhow::Result<Info> {
+    let path = path().ok_or_else(|| anyhow!("OS has no notion of data directory"))?;
+    let (version, rows, requests_today) = with(|connection| {
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let mut rows = Vec::new();
+        for table in TABLES {
+            let count: i64 =
+                connection.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                    row.get(0)
+                })?;
+            rows.push((table, count));
+        }
+        let mut statement = connection.prepare(
+            "SELECT host, requests FROM quota WHERE day = ?1 ORDER BY requests DESC, host",
+        )?;
+        let requests_today = statement
+            .query_map([clock::now().date_naive().to_string()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((version, rows, requests_today))
+    })?;
+    Ok(Info {
+        bytes: fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0),
+        path,
+        version,
+        rows,
+        requests_today,
+    })
+}
+/// Drops history and request counters older than specified number of days, then compacts
+/// database file
+///
+/// # Parameters
+/// * `keep_days` - how many recent days of history and counters are kept
+///
+/// # Returns
+/// Number of dropped rows, and size of database file before and after compaction, in bytes
+pub fn vacuum(keep_days: u32) -> anyhow::Result<(usize, u64, u64)> {
+    let path = path().ok_or_else(|| anyhow!("OS has no notion of data directory"))?;
+    let size = || fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+    let before = size();
+    let dropped = with(|connection| {
+        let since = clock::now() - chrono::Duration::days(keep_days.into());
+        let dropped = connection.execute(
+            "DELETE FROM history WHERE at < ?1",
+            [timestamp(since.with_timezone(&Utc))],
+        )? + connection.execute(
+            "DELETE FROM quota WHERE day < ?1",
+            [since.date_naive().to_string()],
+        )?;
+        connection.execute_batch("VACUUM")?;
+        Ok(dropped)
+    })?;
+    Ok((dropped, before, size()))
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::{migrate, MIGRATIONS};
+
+    #[test]
+    fn migrations_are_applied_once() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        migrate(&mut connection).unwrap();
+        // Second run finds schema up to date
+        migrate(&mut connection).unwrap();
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+        let cached: i64 = connection
+            .query_row("SELECT COUNT(*) FROM cache", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cached, 0);
+    }
+}
@@ -0,0 +1,209 @@
+//! Active tropical cyclones, as reported by US National Hurricane Center
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+use crate::api_url::ApiUrl;
+use crate::units::Units;
+use crate::utils::{distance_km, restful_get};
+
+/// NHC feed with all currently active storms
+const STORMS_URL: &str = "https://www.nhc.noaa.gov/CurrentStorms.json";
+
+/// Ocean basin where storm is located
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Basin {
+    /// North Atlantic, Caribbean Sea and Gulf of Mexico
+    Atlantic,
+    /// Eastern North Pacific
+    EastPacific,
+    /// Central North Pacific
+    CentralPacific,
+}
+
+impl Basin {
+    /// Detects basin by NHC storm identifier, like "al092023"
+    fn from_id(id: &str) -> Option<Self> {
+        match id.get(..2)?.to_ascii_lowercase().as_str() {
+            "al" => Some(Basin::Atlantic),
+            "ep" => Some(Basin::EastPacific),
+            "cp" => Some(Basin::CentralPacific),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Basin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Basin::Atlantic => "Atlantic",
+            Basin::EastPacific => "Eastern Pacific",
+            Basin::CentralPacific => "Central Pacific",
+        })
+    }
+}
+
+/// Storm's strength, by Saffir-Simpson scale
+#[derive(Clone, Copy, PartialEq)]
+pub enum Category {
+    TropicalDepression,
+    TropicalStorm,
+    /// Hurricane of category 1 to 5
+    Hurricane(u8),
+}
+
+impl Category {
+    /// Classifies storm by its maximal sustained wind, in knots
+    pub fn from_wind_knots(knots: u32) -> Self {
+        match knots {
+            0..=33 => Category::TropicalDepression,
+            34..=63 => Category::TropicalStorm,
+            64..=82 => Category::Hurricane(1),
+            83..=95 => Category::Hurricane(2),
+            96..=112 => Category::Hurricane(3),
+            113..=136 => Category::Hurricane(4),
+            _ => Category::Hurricane(5),
+        }
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Category::TropicalDepression => f.write_str("tropical depression"),
+            Category::TropicalStorm => f.write_str("tropical storm"),
+            Category::Hurricane(category) => write!(f, "category {category} hurricane"),
+        }
+    }
+}
+
+/// Active tropical system
+pub struct Storm {
+    /// Storm's name
+    pub name: String,
+    /// Basin where storm is located, if known
+    pub basin: Option<Basin>,
+    /// Storm's strength
+    pub category: Category,
+    /// Maximal sustained wind, in knots
+    pub wind_knots: u32,
+    /// Latitude of storm's center, in degrees
+    pub lat: f64,
+    /// Longitude of storm's center, in degrees
+    pub lon: f64,
+    /// Movement direction, in degrees clockwise from north
+    pub movement_dir: Option<u32>,
+    /// Movement speed, in miles per hour
+    pub movement_mph: Option<u32>,
+}
+
+impl Storm {
+    /// Distance from storm's center to specified point
+    pub fn distance_km(&self, lat: f64, lon: f64) -> f64 {
+        distance_km((self.lat, self.lon), (lat, lon))
+    }
+}
+
+impl Storm {
+    /// Describes storm's strength, position and movement
+    ///
+    /// # Parameters
+    /// * `units` - units in which wind and movement speeds are shown
+    pub fn render(&self, units: Units) -> String {
+        let speed_unit = units.speed_unit();
+        let mut text = format!(
+            "{} ({}, {:.0} {speed_unit}), at {:.1}{} {:.1}{}",
+            self.name,
+            self.category,
+            units.speed_from_knots(self.wind_knots as f32),
+            self.lat.abs(),
+            if self.lat >= 0.0 { 'N' } else { 'S' },
+            self.lon.abs(),
+            if self.lon >= 0.0 { 'E' } else { 'W' },
+        );
+        if let Some(basin) = self.basin {
+            text += &format!(", {basin}");
+        }
+        if let (Some(dir), Some(speed)) = (self.movement_dir, self.movement_mph) {
+            text += &format!(
+                ", moving {dir}° at {:.0} {speed_unit}",
+                units.speed_from_mph(speed as f32)
+            );
+        }
+        text
+    }
+}
+
+//
+// NHC response structures
+//
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurrentStorms {
+    active_storms: Vec<ActiveStorm>,
+}
+
+impl FromStr for CurrentStorms {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActiveStorm {
+    id: String,
+    name: String,
+    intensity: String,
+    latitude_numeric: f64,
+    longitude_numeric: f64,
+    movement_dir: Option<u32>,
+    movement_speed: Option<u32>,
+}
+
+/// NHC serves feed as static file, so failure is just non-JSON text
+#[derive(Debug, thiserror::Error)]
+#[error("NHC feed error: {0}")]
+struct FeedError(String);
+
+impl FromStr for FeedError {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.trim().to_string()))
+    }
+}
+/// Fetches currently active tropical systems
+///
+/// # Returns
+/// List of storms, strongest first
+pub async fn active_storms() -> anyhow::Result<Vec<Storm>> {
+    let feed = restful_get::<CurrentStorms, FeedError>(ApiUrl::new(STORMS_URL))
+        .await
+        .with_context(|| anyhow!("Could not obtain active storms"))?;
+
+    let mut storms: Vec<Storm> = feed
+        .active_storms
+        .into_iter()
+        .map(|storm| {
+            let wind_knots = storm.intensity.trim().parse().unwrap_or_default();
+            Storm {
+                basin: Basin::from_id(&storm.id),
+                name: storm.name,
+                category: Category::from_wind_knots(wind_knots),
+                wind_knots,
+                lat: storm.latitude_numeric,
+                lon: storm.longitude_numeric,
+                movement_dir: storm.movement_dir,
+                movement_mph: storm.movement_speed,
+            }
+        })
+        .collect();
+    storms.sort_by_key(|storm| std::cmp::Reverse(storm.wind_knots));
+    Ok(storms)
+}
@@ -0,0 +1,303 @@
+//! Natural-language forecast summaries, like "Light rain in the evening, high of 14°C, breezy"
+use crate::date::Date;
+use crate::provider::{WeatherInfo, WeatherKind};
+use crate::units::Units;
+
+/// Hourly precipitation below which it's light, in millimeters
+const LIGHT_HOURLY: f32 = 2.5;
+/// Hourly precipitation from which it's heavy, in millimeters
+const HEAVY_HOURLY: f32 = 7.6;
+/// Daily precipitation below which it's light, in millimeters
+const LIGHT_DAILY: f32 = 10.0;
+/// Daily precipitation from which it's heavy, in millimeters
+const HEAVY_DAILY: f32 = 25.0;
+/// Temperature change across days, in Celsius degrees, from which it's reported as trend
+const TREND_THRESHOLD: f32 = 3.0;
+
+/// Renders summary of forecasts, one sentence per entry, followed by temperature trend
+/// if there are several entries
+///
+/// # Parameters
+/// * `forecasts` - forecasts along with dates they're for
+/// * `units` - units in which values are shown
+///
+/// # Returns
+/// Multi-line summary text
+pub fn render(forecasts: &[(Date, WeatherInfo)], units: Units) -> String {
+    if let [(date, info)] = forecasts {
+        return format!("{}\n", sentence(date, info, units));
+    }
+    let mut buf = String::new();
+    for (date, info) in forecasts {
+        buf.push_str(&format!(
+            "{}: {}\n",
+            date.formatted(),
+            sentence(date, info, units)
+        ));
+    }
+    if let Some(trend) = trend(forecasts, units) {
+        buf.push_str(&format!("{trend}\n"));
+    }
+    buf
+}
+/// Describes single forecast in one sentence
+///
+/// # Parameters
+/// * `date` - date forecast is for; its hour of day, if any, is mentioned as part of day
+/// * `info` - forecast
+/// * `units` - units in which values are shown
+pub fn sentence(date: &Date, info: &WeatherInfo, units: Units) -> String {
+    let mut phrase = condition(info, date.hour.is_some());
+    if let Some(hour) = date.hour {
+        phrase = format!("{phrase} {}", part_of_day(hour));
+    }
+    let mut parts = vec![phrase];
+    let temp_unit = units.temperature_unit();
+    match info.temperature_range() {
+        Some((min, max)) => parts.push(format!(
+            "high of {:.0}{temp_unit}, low of {:.0}{temp_unit}",
+            units.temperature(max),
+            units.temperature(min)
+        )),
+        None => parts.push(format!(
+            "around {:.0}{temp_unit}",
+            units.temperature(info.temperature)
+        )),
+    }
+    if let Some(wind) = wind(info) {
+        parts.push(wind.to_string());
+    }
+    if info.humidity >= 80.0 && info.temperature >= 25.0 {
+        parts.push("humid".to_string());
+    }
+    parts.join(", ")
+}
+/// Describes kind of weather, with precipitation intensity if it's known
+///
+/// # Parameters
+/// * `info` - forecast
+/// * `hourly` - whether forecast is for single hour rather than whole day
+fn condition(info: &WeatherInfo, hourly: bool) -> String {
+    let (light, heavy) = if hourly {
+        (LIGHT_HOURLY, HEAVY_HOURLY)
+    } else {
+        (LIGHT_DAILY, HEAVY_DAILY)
+    };
+    let intensity = match info.precipitation {
+        Some(amount) if amount < light => "Light ",
+        Some(amount) if amount >= heavy => "Heavy ",
+        _ => "",
+    };
+    match info.weather {
+        WeatherKind::Unknown => "Mixed conditions".to_string(),
+        WeatherKind::Clear => "Clear".to_string(),
+        WeatherKind::Clouds => "Cloudy".to_string(),
+        WeatherKind::Fog => "Foggy".to_string(),
+        WeatherKind::Rain if intensity.is_empty() => "Rain".to_string(),
+        WeatherKind::Rain => format!("{intensity}rain"),
+        WeatherKind::Snow if intensity.is_empty() => "Snow".to_string(),
+        WeatherKind::Snow => format!("{intensity}snow"),
+        WeatherKind::Drizzle => "Drizzle".to_string(),
+        WeatherKind::Thunderstorm => "Thunderstorms".to_string(),
+        WeatherKind::Sleet => "Sleet".to_string(),
+        WeatherKind::Hail => "Hail".to_string(),
+        WeatherKind::Windy => "Windy".to_string(),
+    }
+}
+/// Part of day hour belongs to, like "in the evening"
+///
+/// # Parameters
+/// * `hour` - hour of day, 0-23
+fn part_of_day(hour: u8) -> &'static str {
+    match hour {
+        5..=11 => "in the morning",
+        12..=16 => "in the afternoon",
+        17..=21 => "in the evening",
+        _ => "at night",
+    }
+}
+/// Describes wind by its speed, roughly along Beaufort scale; moderate wind isn't worth mentioning
+///
+/// # Parameters
+/// * `info` - forecast
+fn wind(info: &WeatherInfo) -> Option<&'static str> {
+    match info.wind_speed {
+        speed if speed < 1.5 => Some("calm"),
+        speed if speed < 5.5 => None,
+        // Windy weather already says so
+        _ if info.weather == WeatherKind::Windy && info.wind_speed < 14.0 => None,
+        speed if speed < 8.0 => Some("breezy"),
+        speed if speed < 14.0 => Some("windy"),
+        speed if speed < 21.0 => Some("strong winds"),
+        _ => Some("gale-force winds"),
+    }
+}
+/// Describes how temperature changes from first entry to last one
+///
+/// # Parameters
+/// * `forecasts` - forecasts along with dates they're for
+/// * `units` - units in which values are shown
+///
+/// # Returns
+/// Sentence, or `None` if there are less than two entries
+fn trend(forecasts: &[(Date, WeatherInfo)], units: Units) -> Option<String> {
+    let [(_, first), .., (last_date, last)] = forecasts else {
+        return None;
+    };
+    // Days' highs are compared when provider reports them, since they're more telling than averages
+    let temperature = |info: &WeatherInfo| info.temperature_max.unwrap_or(info.temperature);
+    let change = temperature(last) - temperature(first);
+    let target = format!(
+        "{:.0}{} by {}",
+        units.temperature(temperature(last)),
+        units.temperature_unit(),
+        last_date.formatted()
+    );
+    Some(if change >= TREND_THRESHOLD {
+        format!("Warming up to {target}")
+    } else if change <= -TREND_THRESHOLD {
+        format!("Cooling down to {target}")
+    } else {
+        "Temperatures staying steady".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{render, sentence};
+    use crate::date::Date;
+    use crate::provider::{WeatherInfo, WeatherKind};
+    use crate::units::{UnitSystem, Units};
+
+    fn day(day: u32) -> Date {
+        Date::from(NaiveDate::from_ymd_opt(2024, 5, day).unwrap())
+    }
+
+    #[test]
+    fn hourly_sentence_mentions_part_of_day() {
+        let info = WeatherInfo {
+            precipitation: Some(1.0),
+            wind_speed: 6.0,
+            ..WeatherInfo::sample(WeatherKind::Rain, 14.2)
+        };
+        let evening = day(10).with_hour(Some(19));
+        assert_eq!(
+            sentence(&evening, &info, Units::default()),
+            "Light rain in the evening, around 14°C, breezy"
+        );
+        let night = day(10).with_hour(Some(2));
+        assert_eq!(
+            sentence(&night, &info, Units::default()),
+            "Light rain at night, around 14°C, breezy"
+        );
+    }
+
+    #[test]
+    fn intensity_thresholds_depend_on_period() {
+        let rain = |amount| WeatherInfo {
+            precipitation: Some(amount),
+            ..WeatherInfo::sample(WeatherKind::Rain, 10.0)
+        };
+        let hour = day(10).with_hour(Some(9));
+        let sentence = |date: &Date, info| sentence(date, &info, Units::default());
+        // Hourly amount which is heavy is moderate for whole day
+        assert!(sentence(&hour, rain(8.0)).starts_with("Heavy rain in the morning"));
+        assert!(sentence(&day(10), rain(8.0)).starts_with("Light rain,"));
+        assert!(sentence(&day(10), rain(15.0)).starts_with("Rain,"));
+        assert!(sentence(&day(10), rain(25.0)).starts_with("Heavy rain,"));
+    }
+
+    #[test]
+    fn wind_and_humidity_are_mentioned_only_when_notable() {
+        let sentence = |weather, wind_speed, humidity, temperature| {
+            let info = WeatherInfo {
+                wind_speed,
+                humidity,
+                ..WeatherInfo::sample(weather, temperature)
+            };
+            sentence(&day(10), &info, Units::default())
+        };
+        assert_eq!(
+            sentence(WeatherKind::Clear, 1.0, 50.0, 20.0),
+            "Clear, around 20°C, calm"
+        );
+        assert_eq!(
+            sentence(WeatherKind::Clear, 3.0, 50.0, 20.0),
+            "Clear, around 20°C"
+        );
+        assert_eq!(
+            sentence(WeatherKind::Clear, 10.0, 50.0, 20.0),
+            "Clear, around 20°C, windy"
+        );
+        assert_eq!(
+            sentence(WeatherKind::Windy, 10.0, 50.0, 20.0),
+            "Windy, around 20°C"
+        );
+        assert_eq!(
+            sentence(WeatherKind::Windy, 25.0, 50.0, 20.0),
+            "Windy, around 20°C, gale-force winds"
+        );
+        assert_eq!(
+            sentence(WeatherKind::Clouds, 3.0, 85.0, 28.0),
+            "Cloudy, around 28°C, humid"
+        );
+        assert_eq!(
+            sentence(WeatherKind::Clouds, 3.0, 85.0, 20.0),
+            "Cloudy, around 20°C"
+        );
+    }
+
+    #[test]
+    fn daily_range_is_shown_in_selected_units() {
+        let info = WeatherInfo {
+            temperature_min: Some(10.0),
+            temperature_max: Some(20.0),
+            ..WeatherInfo::sample(WeatherKind::Clear, 15.0)
+        };
+        assert_eq!(
+            sentence(&day(10), &info, UnitSystem::Imperial.units()),
+            "Clear, high of 68°F, low of 50°F"
+        );
+    }
+
+    #[test]
+    fn several_days_end_with_trend() {
+        let days = |temperatures: &[f32]| {
+            temperatures
+                .iter()
+                .enumerate()
+                .map(|(index, &temperature)| {
+                    (
+                        day(10 + index as u32),
+                        WeatherInfo::sample(WeatherKind::Clear, temperature),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let last = |summary: String| summary.lines().last().unwrap().to_string();
+        let warming = days(&[10.0, 12.0, 13.0]);
+        assert_eq!(
+            warming.len() + 1,
+            render(&warming, Units::default()).lines().count()
+        );
+        assert_eq!(
+            last(render(&warming, Units::default())),
+            format!("Warming up to 13°C by {}", day(12).formatted())
+        );
+        assert_eq!(
+            last(render(&days(&[10.0, 7.0]), Units::default())),
+            format!("Cooling down to 7°C by {}", day(11).formatted())
+        );
+        assert_eq!(
+            last(render(&days(&[10.0, 12.0]), Units::default())),
+            "Temperatures staying steady"
+        );
+        // Single forecast has neither date prefix nor trend
+        assert_eq!(
+            render(&days(&[10.0]), Units::default()),
+            "Clear, around 10°C\n"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Terminal capabilities, so pretty output doesn't turn into garbage on terminals which can't show it
+//!
+//! On Windows, console is switched to UTF-8 code page and ANSI escape sequences are enabled,
+//! which is supported since Windows 10. Legacy consoles and non-UTF-8 locales get ASCII
+//! lookalikes of box drawing, weather symbols and sparkline blocks instead.
+//! `NO_COLOR` environment variable disables colors, and `WEATHER_CLI_ASCII` forces ASCII output
+use std::borrow::Cow;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+/// Environment variable which forces ASCII output when set to anything but empty string or "0"
+const ASCII_VAR: &str = "WEATHER_CLI_ASCII";
+
+/// Capabilities detected on startup
+static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+
+/// What terminal can show
+#[derive(Clone, Copy)]
+struct Capabilities {
+    /// ANSI escape sequences, like colors, are interpreted rather than printed
+    ansi: bool,
+    /// Characters beyond ASCII are shown properly
+    unicode: bool,
+    /// ASCII output is requested by user
+    forced_ascii: bool,
+}
+/// Detects terminal's capabilities, preparing Windows console if needed;
+/// should be called once, before any output
+pub fn init() {
+    CAPABILITIES.get_or_init(detect);
+}
+/// Whether colored diagnostics can be written to stderr
+pub fn ansi() -> bool {
+    capabilities().ansi
+        && std::io::stderr().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+/// Whether characters beyond ASCII, like box drawing and weather symbols, can be printed to stdout;
+/// redirected output is always UTF-8
+pub fn unicode() -> bool {
+    let capabilities = capabilities();
+    !capabilities.forced_ascii && (capabilities.unicode || !std::io::stdout().is_terminal())
+}
+/// Adapts text to terminal, replacing characters it can't show with ASCII lookalikes
+///
+/// # Parameters
+/// * `text` - rendered output
+///
+/// # Returns
+/// Same text if terminal shows Unicode, or its ASCII version
+pub fn fit(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() || unicode() {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(to_ascii(text))
+}
+/// Replaces non-ASCII characters with ASCII lookalikes, one for one, so boxes and columns stay aligned;
+/// unknown ones become '?'
+///
+/// # Parameters
+/// * `text` - text to convert
+fn to_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            c if c.is_ascii() => c,
+            // "12°C" reads well as "12 C"
+            '°' => ' ',
+            '…' => '~',
+            '―' | '─' | '‒' | '–' | '—' => '-',
+            '│' => '|',
+            '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' => '+',
+            '‘' | '’' | '‚' => '\'',
+            '•' | '⁘' => '*',
+            '▁' => '_',
+            '▂' => '.',
+            '▃' => ',',
+            '▄' => '-',
+            '▅' => '=',
+            '▆' => '+',
+            '▇' => '*',
+            '█' => '#',
+            '☀' => 'O',
+            '☁' => 'm',
+            '≡' | '≋' => '=',
+            '☂' => '/',
+            '❄' | '❅' => '*',
+            '⸪' => ',',
+            'ϟ' => 'z',
+            _ => '?',
+        })
+        .collect()
+}
+
+fn capabilities() -> Capabilities {
+    *CAPABILITIES.get_or_init(detect)
+}
+
+fn detect() -> Capabilities {
+    let forced_ascii =
+        std::env::var(ASCII_VAR).is_ok_and(|value| !value.is_empty() && value != "0");
+    #[cfg(windows)]
+    let (ansi, unicode) = {
+        let stdout = windows::enable_ansi(&std::io::stdout());
+        let stderr = windows::enable_ansi(&std::io::stderr());
+        let ansi = stdout && stderr;
+        // Consoles which can't interpret escape sequences are legacy ones, whose fonts lack symbols;
+        // Windows Terminal always has them
+        let unicode = windows::use_utf8() && (ansi || std::env::var_os("WT_SESSION").is_some());
+        (ansi, unicode)
+    };
+    #[cfg(not(windows))]
+    let (ansi, unicode) = (true, utf8_locale());
+    Capabilities {
+        ansi,
+        unicode,
+        forced_ascii,
+    }
+}
+/// Whether locale of `LC_ALL`, `LC_CTYPE` or `LANG` environment variables uses UTF-8;
+/// unset locale is assumed to, as most terminals do nowadays
+#[cfg(not(windows))]
+fn utf8_locale() -> bool {
+    let Some(locale) = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|locale| !locale.is_empty())
+    else {
+        return true;
+    };
+    let locale = locale.to_ascii_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::c_void;
+    use std::os::windows::io::AsRawHandle;
+
+    /// Console mode flag which enables ANSI escape sequences
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+    /// UTF-8 code page
+    const CP_UTF8: u32 = 65001;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetConsoleMode(console: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: *mut c_void, mode: u32) -> i32;
+        fn SetConsoleOutputCP(code_page: u32) -> i32;
+    }
+    /// Enables ANSI escape sequences in console
+    ///
+    /// # Parameters
+    /// * `stream` - console's output stream
+    ///
+    /// # Returns
+    /// Whether they're enabled; streams redirected away from console don't need them
+    pub fn enable_ansi(stream: &impl AsRawHandle) -> bool {
+        let handle = stream.as_raw_handle();
+        let mut mode = 0;
+        // SAFETY: handle belongs to standard stream, which lives as long as process
+        unsafe {
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                return true;
+            }
+            mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+        }
+    }
+    /// Switches console to UTF-8 code page, so degree sign and other symbols are shown properly
+    /// by programs which output goes through, like pagers
+    ///
+    /// # Returns
+    /// Whether console uses UTF-8 now
+    pub fn use_utf8() -> bool {
+        // SAFETY: plain call without pointers
+        unsafe { SetConsoleOutputCP(CP_UTF8) != 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_ascii;
+
+    #[test]
+    fn ascii_lookalikes_keep_layout() {
+        assert_eq!(
+            to_ascii("┌──┐\n│ ☀ 12.5°C (9…14°C) │"),
+            "+--+\n| O 12.5 C (9~14 C) |"
+        );
+        assert_eq!(to_ascii("▁▄█"), "_-#");
+    }
+}
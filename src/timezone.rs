@@ -0,0 +1,118 @@
+//! Timezones of locations
+//!
+//! Dates like "now" should be resolved in location's timezone rather than machine's one,
+//! otherwise querying city across date line yields wrong day. Timezone is looked up
+//! offline by coordinates, using bundled timezone boundaries
+use std::sync::OnceLock;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use tzf_rs::DefaultFinder;
+
+use crate::{clock, geocode};
+
+/// Lazily loaded timezone boundaries; loading takes a moment, so it's done once and only if needed
+fn finder() -> &'static DefaultFinder {
+    static FINDER: OnceLock<DefaultFinder> = OnceLock::new();
+    FINDER.get_or_init(DefaultFinder::new)
+}
+/// Looks up timezone at coordinates
+///
+/// # Parameters
+/// * `lat` - latitude
+/// * `lon` - longitude
+///
+/// # Returns
+/// Timezone, or `None` if it's unknown
+pub fn at(lat: f64, lon: f64) -> Option<Tz> {
+    let name = finder().get_tz_name(lon, lat);
+    match name.parse() {
+        Ok(tz) => Some(tz),
+        Err(_) => {
+            tracing::debug!("Unknown timezone '{name}' at {lat:.4},{lon:.4}");
+            None
+        }
+    }
+}
+/// Looks up timezone of location specified with coordinates, like "51.5237,-0.1585"
+///
+/// # Returns
+/// Timezone, or `None` if location isn't specified with coordinates or timezone is unknown
+pub fn of_location(location: &str) -> Option<Tz> {
+    let (lat, lon) = geocode::parse_coordinates(location)?;
+    at(lat, lon)
+}
+/// Current time in timezone, by local clock corrected for detected skew
+///
+/// # Parameters
+/// * `tz` - timezone; `None` means machine's local timezone
+pub fn now(tz: Option<Tz>) -> DateTime<FixedOffset> {
+    let now = clock::now();
+    match tz {
+        Some(tz) => now.with_timezone(&tz).fixed_offset(),
+        None => now.fixed_offset(),
+    }
+}
+/// Converts moment of machine's local time into timezone
+///
+/// # Parameters
+/// * `time` - local time, like one of track point or calendar event
+/// * `tz` - timezone; `None` means machine's local timezone
+///
+/// # Returns
+/// Time with timezone offset, or `None` if local time is skipped by DST transition
+pub fn from_local(time: NaiveDateTime, tz: Option<Tz>) -> Option<DateTime<FixedOffset>> {
+    let time = time.and_local_timezone(Local).earliest()?;
+    Some(match tz {
+        Some(tz) => time.with_timezone(&tz).fixed_offset(),
+        None => time.fixed_offset(),
+    })
+}
+/// Today's date in timezone
+///
+/// # Parameters
+/// * `tz` - timezone; `None` means machine's local timezone
+pub fn today(tz: Option<Tz>) -> NaiveDate {
+    now(tz).date_naive()
+}
+/// Describes current time in timezone, like "2023-10-16 09:12 NZDT (Pacific/Auckland)"
+///
+/// # Parameters
+/// * `tz` - timezone
+pub fn describe_now(tz: Tz) -> String {
+    let now = clock::now().with_timezone(&tz);
+    format!(
+        "{} {} ({tz})",
+        crate::date::format_time(now.naive_local()),
+        now.format("%Z")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use chrono_tz::Tz;
+
+    use super::{at, describe_now, of_location, today};
+
+    #[test]
+    fn timezone_is_found_by_coordinates() {
+        assert_eq!(at(51.5237, -0.1585), Some(Tz::Europe__London));
+        assert_eq!(at(-36.8485, 174.7633), Some(Tz::Pacific__Auckland));
+        assert_eq!(of_location("40.7128,-74.0060"), Some(Tz::America__New_York));
+        assert_eq!(of_location("New York"), None);
+        assert!(describe_now(Tz::Pacific__Auckland).ends_with("(Pacific/Auckland)"));
+    }
+
+    #[test]
+    fn today_depends_on_location() {
+        // Kiritimati is 14 hours ahead of UTC and Pago Pago is 11 hours behind,
+        // so they're always on different days, and at least one of them differs from UTC
+        let kiritimati = today(of_location("1.8721,-157.4278"));
+        let pago_pago = today(of_location("-14.2756,-170.7020"));
+        let utc = Utc::now().date_naive();
+        assert!(kiritimati > pago_pago);
+        assert!(kiritimati >= utc && pago_pago <= utc);
+        assert!(kiritimati != utc || pago_pago != utc);
+    }
+}
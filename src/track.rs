@@ -0,0 +1,476 @@
+//! GPX/KML track reading, sampling and export of weather-annotated tracks
+use std::path::Path;
+
+use anyhow::{anyhow, bail, ensure, Context};
+use chrono::{DateTime, Local, NaiveDateTime};
+use quick_xml::events::Event as XmlEvent;
+use quick_xml::Reader;
+
+use crate::provider::WeatherInfo;
+use crate::utils::distance_km;
+
+/// Single point of track
+#[derive(Clone)]
+pub struct TrackPoint {
+    /// Latitude, in degrees
+    pub lat: f64,
+    /// Longitude, in degrees
+    pub lon: f64,
+    /// Moment when point is passed, if track has timestamps
+    pub time: Option<NaiveDateTime>,
+}
+
+impl TrackPoint {
+    /// Represents point as location string, "<lat>,<lon>", which is accepted by providers
+    /// which support coordinates as location
+    pub fn location(&self) -> String {
+        format!("{:.4},{:.4}", self.lat, self.lon)
+    }
+    /// Great-circle distance to other point, in kilometers
+    pub fn distance_km(&self, other: &TrackPoint) -> f64 {
+        distance_km((self.lat, self.lon), (other.lat, other.lon))
+    }
+}
+
+/// How track points are sampled
+pub enum Sampling {
+    /// Take point every N kilometers
+    Distance(f64),
+    /// Take point every N minutes; requires track with timestamps
+    Time(chrono::Duration),
+}
+
+impl Sampling {
+    /// Sampling by time, if interval is specified, or by distance otherwise
+    ///
+    /// # Parameters
+    /// * `every_km` - distance between sampled points, in kilometers
+    /// * `every_minutes` - time between sampled points, in minutes
+    pub fn new(every_km: f64, every_minutes: Option<u32>) -> anyhow::Result<Self> {
+        match every_minutes {
+            Some(minutes) => Ok(Sampling::Time(chrono::Duration::minutes(minutes.into()))),
+            None => {
+                ensure!(every_km > 0.0, "Sampling distance should be positive");
+                Ok(Sampling::Distance(every_km))
+            }
+        }
+    }
+}
+
+/// Read track from GPX or KML file; format is detected by file extension
+///
+/// # Parameters
+/// * `path` - path to track file
+///
+/// # Returns
+/// List of track points, in order of appearance
+pub fn read_from_file(path: &Path) -> anyhow::Result<Vec<TrackPoint>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| anyhow!("When reading track file '{}'", path.display()))?;
+    let is_kml = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("kml"));
+
+    let points = if is_kml {
+        parse_kml(&text)
+    } else {
+        parse_gpx(&text)
+    }
+    .with_context(|| anyhow!("When parsing track file '{}'", path.display()))?;
+
+    if points.is_empty() {
+        bail!("Track file '{}' contains no points", path.display());
+    }
+    Ok(points)
+}
+/// Parse GPX track or route points
+///
+/// Only `time` elements of points themselves are taken, not ones of metadata or waypoints
+fn parse_gpx(text: &str) -> anyhow::Result<Vec<TrackPoint>> {
+    let mut reader = Reader::from_str(text);
+    reader.trim_text(true);
+
+    let mut points = Vec::new();
+    let mut in_point = false;
+    let mut in_time = false;
+
+    loop {
+        let event = reader.read_event()?;
+        match event {
+            XmlEvent::Start(ref tag) | XmlEvent::Empty(ref tag)
+                if matches!(tag.local_name().as_ref(), b"trkpt" | b"rtept") =>
+            {
+                let coord = |name: &str| -> anyhow::Result<f64> {
+                    let value = tag
+                        .try_get_attribute(name)?
+                        .ok_or_else(|| anyhow!("Track point misses '{name}' attribute"))?
+                        .unescape_value()?;
+                    Ok(value.parse()?)
+                };
+                points.push(TrackPoint {
+                    lat: coord("lat")?,
+                    lon: coord("lon")?,
+                    time: None,
+                });
+                in_point = matches!(event, XmlEvent::Start(_));
+            }
+            XmlEvent::End(tag) if matches!(tag.local_name().as_ref(), b"trkpt" | b"rtept") => {
+                in_point = false;
+            }
+            XmlEvent::Start(tag) if tag.local_name().as_ref() == b"time" => in_time = in_point,
+            XmlEvent::End(tag) if tag.local_name().as_ref() == b"time" => in_time = false,
+            XmlEvent::Text(text) if in_time => {
+                if let Some(point) = points.last_mut() {
+                    point.time = parse_time(&text.unescape()?);
+                }
+            }
+            XmlEvent::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(points)
+}
+/// Parse KML `LineString` coordinates and `gx:Track` timed coordinates
+fn parse_kml(text: &str) -> anyhow::Result<Vec<TrackPoint>> {
+    let mut reader = Reader::from_str(text);
+    reader.trim_text(true);
+
+    let mut points = Vec::new();
+    // `gx:Track` lists all `when` entries first, then all `gx:coord` entries
+    let mut whens = Vec::new();
+    let mut coord_count = 0;
+    let mut current_tag = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            XmlEvent::Start(tag) => current_tag = tag.local_name().as_ref().to_vec(),
+            XmlEvent::End(_) => current_tag.clear(),
+            XmlEvent::Text(text) => {
+                let text = text.unescape()?;
+                match current_tag.as_slice() {
+                    b"coordinates" => {
+                        for tuple in text.split_whitespace() {
+                            let mut parts = tuple.split(',');
+                            let (Some(lon), Some(lat)) = (parts.next(), parts.next()) else {
+                                bail!("Invalid KML coordinates tuple '{tuple}'");
+                            };
+                            points.push(TrackPoint {
+                                lat: lat.parse()?,
+                                lon: lon.parse()?,
+                                time: None,
+                            });
+                        }
+                    }
+                    b"when" => whens.push(parse_time(&text)),
+                    b"coord" => {
+                        let mut parts = text.split_whitespace();
+                        let (Some(lon), Some(lat)) = (parts.next(), parts.next()) else {
+                            bail!("Invalid KML coordinate '{text}'");
+                        };
+                        points.push(TrackPoint {
+                            lat: lat.parse()?,
+                            lon: lon.parse()?,
+                            time: whens.get(coord_count).copied().flatten(),
+                        });
+                        coord_count += 1;
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(points)
+}
+/// Parse RFC 3339 timestamp and convert it to local time
+fn parse_time(text: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(text.trim())
+        .ok()
+        .map(|time| time.with_timezone(&Local).naive_local())
+}
+/// Select points along track according to sampling mode
+///
+/// First and last points of track are always included
+///
+/// # Parameters
+/// * `points` - full track
+/// * `sampling` - sampling mode
+///
+/// # Returns
+/// Sampled points
+pub fn sample(points: &[TrackPoint], sampling: &Sampling) -> anyhow::Result<Vec<TrackPoint>> {
+    if points.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut indices = vec![0];
+
+    match sampling {
+        Sampling::Distance(step) => {
+            let mut travelled = 0.0;
+            for (index, pair) in points.windows(2).enumerate() {
+                travelled += pair[0].distance_km(&pair[1]);
+                if travelled >= *step {
+                    indices.push(index + 1);
+                    travelled = 0.0;
+                }
+            }
+        }
+        Sampling::Time(step) => {
+            let time_of = |point: &TrackPoint| {
+                point
+                    .time
+                    .ok_or_else(|| anyhow!("Track has no timestamps, cannot sample it by time"))
+            };
+            let mut last_time = time_of(&points[0])?;
+            for (index, point) in points.iter().enumerate().skip(1) {
+                let time = time_of(point)?;
+                if time - last_time >= *step {
+                    indices.push(index);
+                    last_time = time;
+                }
+            }
+        }
+    }
+    // Always finish at track's end, unless it's already sampled
+    if indices.last() != Some(&(points.len() - 1)) {
+        indices.push(points.len() - 1);
+    }
+    Ok(indices.into_iter().map(|i| points[i].clone()).collect())
+}
+/// Write weather-annotated track samples to file; format is selected by extension,
+/// `.gpx` for GPX waypoints, anything else for GeoJSON feature collection
+///
+/// # Parameters
+/// * `path` - output file path
+/// * `samples` - track points with forecast or error message
+pub fn export(
+    path: &Path,
+    samples: &[(TrackPoint, Result<WeatherInfo, String>)],
+) -> anyhow::Result<()> {
+    let is_gpx = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gpx"));
+    let text = if is_gpx {
+        to_gpx(samples)
+    } else {
+        to_geojson(samples)
+    };
+    std::fs::write(path, text)
+        .with_context(|| anyhow!("When writing annotated track to '{}'", path.display()))
+}
+/// Render samples as GPX waypoints, forecast is put into waypoint's description
+fn to_gpx(samples: &[(TrackPoint, Result<WeatherInfo, String>)]) -> String {
+    let mut buf = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\" creator=\"weather-cli\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+    for (point, forecast) in samples {
+        buf.push_str(&format!(
+            "  <wpt lat=\"{}\" lon=\"{}\">\n",
+            point.lat, point.lon
+        ));
+        if let Some(time) = point.time {
+            buf.push_str(&format!(
+                "    <time>{}</time>\n",
+                time.and_local_timezone(Local)
+                    .single()
+                    .map(|time| time.to_rfc3339())
+                    .unwrap_or_default()
+            ));
+        }
+        let (name, desc) = match forecast {
+            Ok(info) => (format!("{}°C", info.temperature), info.to_string()),
+            Err(error) => ("?".to_string(), error.clone()),
+        };
+        buf.push_str(&format!(
+            "    <name>{}</name>\n    <desc>{}</desc>\n  </wpt>\n",
+            quick_xml::escape::escape(&name),
+            quick_xml::escape::escape(&desc.replace('\n', "; "))
+        ));
+    }
+    buf.push_str("</gpx>\n");
+    buf
+}
+/// Render samples as GeoJSON point features with forecast properties
+fn to_geojson(samples: &[(TrackPoint, Result<WeatherInfo, String>)]) -> String {
+    let features: Vec<_> = samples
+        .iter()
+        .map(|(point, forecast)| {
+            let mut properties = serde_json::Map::new();
+            if let Some(time) = point.time {
+                properties.insert(
+                    "time".into(),
+                    time.format("%Y-%m-%dT%H:%M:%S").to_string().into(),
+                );
+            }
+            match forecast {
+                Ok(info) => {
+                    properties.insert("weather".into(), info.weather.to_string().into());
+                    properties.insert("temperature".into(), info.temperature.into());
+                    properties.insert("wind_speed".into(), info.wind_speed.into());
+                    properties.insert("humidity".into(), info.humidity.into());
+                }
+                Err(error) => {
+                    properties.insert("error".into(), error.clone().into());
+                }
+            }
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [point.lon, point.lat] },
+                "properties": properties,
+            })
+        })
+        .collect();
+
+    serde_json::json!({ "type": "FeatureCollection", "features": features }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Local, NaiveDateTime};
+
+    use super::{parse_gpx, parse_kml, sample, to_geojson, to_gpx, Sampling, TrackPoint};
+    use crate::provider::{WeatherInfo, WeatherKind};
+
+    const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+  <metadata><time>2024-07-01T06:00:00Z</time></metadata>
+  <trk><trkseg>
+    <trkpt lat="51.5" lon="-0.1"><ele>20</ele><time>2024-07-01T08:00:00Z</time></trkpt>
+    <trkpt lat="51.6" lon="-0.1"/>
+    <trkpt lat="51.7" lon="-0.1"><time>2024-07-01T09:00:00+01:00</time></trkpt>
+  </trkseg></trk>
+  <wpt lat="48.85" lon="2.35"><time>2024-07-02T12:00:00Z</time></wpt>
+</gpx>"#;
+
+    /// Local time of RFC 3339 timestamp, as track points keep it
+    fn local(time: &str) -> NaiveDateTime {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Local)
+            .naive_local()
+    }
+
+    fn point(lat: f64, lon: f64, time: Option<&str>) -> TrackPoint {
+        TrackPoint {
+            lat,
+            lon,
+            time: time.map(local),
+        }
+    }
+
+    #[test]
+    fn gpx_points_take_only_own_time() {
+        let points = parse_gpx(GPX).unwrap();
+        let parsed: Vec<_> = points
+            .iter()
+            .map(|point| (point.lat, point.lon, point.time))
+            .collect();
+        // Metadata's and waypoint's times don't leak into track points, and waypoint isn't one
+        assert_eq!(
+            parsed,
+            [
+                (51.5, -0.1, Some(local("2024-07-01T08:00:00Z"))),
+                (51.6, -0.1, None),
+                (51.7, -0.1, Some(local("2024-07-01T08:00:00Z"))),
+            ]
+        );
+        assert!(parse_gpx(r#"<gpx><trk><trkpt lat="1"/></trk></gpx>"#).is_err());
+    }
+
+    #[test]
+    fn kml_line_and_track_are_read() {
+        let points = parse_kml(
+            "<kml><Placemark><LineString><coordinates>\
+             -0.1,51.5,0 -0.2,51.6,0\
+             </coordinates></LineString></Placemark></kml>",
+        )
+        .unwrap();
+        let parsed: Vec<_> = points.iter().map(|point| (point.lat, point.lon)).collect();
+        assert_eq!(parsed, [(51.5, -0.1), (51.6, -0.2)]);
+
+        let points = parse_kml(
+            "<kml><gx:Track>\
+             <when>2024-07-01T08:00:00Z</when><when>2024-07-01T08:30:00Z</when>\
+             <gx:coord>-0.1 51.5 0</gx:coord><gx:coord>-0.2 51.6 0</gx:coord>\
+             </gx:Track></kml>",
+        )
+        .unwrap();
+        assert_eq!(points[1].lat, 51.6);
+        assert_eq!(points[1].time, Some(local("2024-07-01T08:30:00Z")));
+        assert!(parse_kml("<kml><coordinates>1.0</coordinates></kml>").is_err());
+    }
+
+    #[test]
+    fn distance_is_great_circle() {
+        let london = point(51.5074, -0.1278, None);
+        let paris = point(48.8566, 2.3522, None);
+        let distance = london.distance_km(&paris);
+        assert!((distance - 343.5).abs() < 1.0, "{distance}");
+        assert_eq!(london.distance_km(&london), 0.0);
+        assert_eq!(london.location(), "51.5074,-0.1278");
+    }
+
+    #[test]
+    fn points_are_sampled_by_distance_and_time() {
+        // Points are about 11 km apart, northwards
+        let points: Vec<_> = (0..6)
+            .map(|index| {
+                let time = format!("2024-07-01T08:{:02}:00Z", index * 10);
+                point(51.0 + f64::from(index) * 0.1, 0.0, Some(&time))
+            })
+            .collect();
+        let lats = |sampled: Vec<TrackPoint>| -> Vec<f64> {
+            sampled
+                .iter()
+                .map(|point| (point.lat * 10.0).round() / 10.0)
+                .collect()
+        };
+
+        let sampled = sample(&points, &Sampling::Distance(20.0)).unwrap();
+        // Track's end is always included
+        assert_eq!(lats(sampled), [51.0, 51.2, 51.4, 51.5]);
+        let sampled = sample(&points, &Sampling::Time(chrono::Duration::minutes(30))).unwrap();
+        assert_eq!(lats(sampled), [51.0, 51.3, 51.5]);
+
+        let untimed = [point(51.0, 0.0, None), point(51.1, 0.0, None)];
+        assert!(sample(&untimed, &Sampling::Time(chrono::Duration::minutes(5))).is_err());
+        assert!(sample(&[], &Sampling::Distance(1.0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn samples_are_exported() {
+        let info = WeatherInfo {
+            humidity: 90.0,
+            precipitation: Some(1.0),
+            ..WeatherInfo::sample(WeatherKind::Rain, 12.5)
+        };
+        let samples = [
+            (point(51.5, -0.1, Some("2024-07-01T08:00:00Z")), Ok(info)),
+            (
+                point(51.6, -0.2, None),
+                Err("Rate limit <exceeded>".to_string()),
+            ),
+        ];
+
+        let gpx = to_gpx(&samples);
+        assert!(gpx.contains(r#"<wpt lat="51.5" lon="-0.1">"#), "{gpx}");
+        assert!(gpx.contains("<name>12.5°C</name>"), "{gpx}");
+        assert!(
+            gpx.contains("<desc>Rate limit &lt;exceeded&gt;</desc>"),
+            "{gpx}"
+        );
+        assert_eq!(gpx.matches("<time>").count(), 1, "{gpx}");
+
+        let geojson: serde_json::Value = serde_json::from_str(&to_geojson(&samples)).unwrap();
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(
+            features[0]["geometry"]["coordinates"],
+            serde_json::json!([-0.1, 51.5])
+        );
+        assert_eq!(features[0]["properties"]["weather"], "raining");
+        assert_eq!(features[0]["properties"]["temperature"], 12.5);
+        assert_eq!(features[1]["properties"]["error"], "Rate limit <exceeded>");
+        assert!(features[1]["properties"].get("time").is_none());
+    }
+}
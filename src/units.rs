@@ -0,0 +1,450 @@
+//! Unit systems used to present forecasts
+//!
+//! Providers always report data in metric units, conversion happens only when printing.
+//! Unit system, like metric or imperial, selects unit of each field,
+//! and any field's unit can be overridden separately, e.g. to show wind in knots
+//! or distances in nautical miles
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use clap::ValueEnum;
+
+use crate::config::Section;
+use crate::i18n;
+use crate::provider::WeatherInfo;
+
+/// Name of profile entry with profile's unit system
+pub const PROFILE_UNITS: &str = "units";
+/// Name of profile entry with profile's temperature unit
+const PROFILE_TEMP_UNIT: &str = "temp_unit";
+/// Name of profile entry with profile's wind speed unit
+const PROFILE_WIND_UNIT: &str = "wind_unit";
+/// Name of profile entry with profile's precipitation unit
+const PROFILE_PRECIP_UNIT: &str = "precip_unit";
+/// Name of profile entry with profile's pressure unit
+const PROFILE_PRESSURE_UNIT: &str = "pressure_unit";
+/// Name of profile entry with profile's distance unit
+const PROFILE_DISTANCE_UNIT: &str = "distance_unit";
+/// Meters per second in knot
+const MS_PER_KNOT: f32 = 0.514_444;
+/// Meters per second in mile per hour
+const MS_PER_MPH: f32 = 0.447_04;
+
+/// Unit system, which defines units of all fields
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum UnitSystem {
+    /// Celsius degrees, meters per second, millimeters, hectopascals, kilometers
+    #[default]
+    Metric,
+    /// Fahrenheit degrees, miles per hour, inches, inches of mercury, miles
+    Imperial,
+}
+
+/// Temperature unit
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+/// Wind speed unit
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum SpeedUnit {
+    /// Meters per second
+    #[value(name = "ms")]
+    MetersPerSecond,
+    /// Kilometers per hour
+    #[value(name = "kmh")]
+    KilometersPerHour,
+    /// Miles per hour
+    #[value(name = "mph")]
+    MilesPerHour,
+    /// Nautical miles per hour
+    Knots,
+}
+
+/// Precipitation amount unit
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum PrecipitationUnit {
+    #[value(name = "mm")]
+    Millimeters,
+    #[value(name = "in")]
+    Inches,
+}
+
+/// Atmospheric pressure unit
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum PressureUnit {
+    /// Hectopascals, same as millibars
+    #[value(name = "hpa")]
+    Hectopascals,
+    /// Inches of mercury
+    #[value(name = "inhg")]
+    InchesOfMercury,
+}
+
+/// Distance unit
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum DistanceUnit {
+    #[value(name = "km")]
+    Kilometers,
+    #[value(name = "mi")]
+    Miles,
+    /// Nautical miles
+    #[value(name = "nmi")]
+    NauticalMiles,
+}
+/// Parses any unit by its CLI name, case-insensitively
+fn parse_unit<T: ValueEnum>(s: &str, what: &str) -> anyhow::Result<T> {
+    T::from_str(s.trim(), true).map_err(|_| anyhow!("Unknown {what} '{s}'"))
+}
+
+impl FromStr for UnitSystem {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_unit(s, "units")
+    }
+}
+
+impl UnitSystem {
+    /// Units of all fields in this system
+    pub fn units(&self) -> Units {
+        match self {
+            UnitSystem::Metric => Units {
+                temperature: TemperatureUnit::Celsius,
+                speed: SpeedUnit::MetersPerSecond,
+                precipitation: PrecipitationUnit::Millimeters,
+                pressure: PressureUnit::Hectopascals,
+                distance: DistanceUnit::Kilometers,
+            },
+            UnitSystem::Imperial => Units {
+                temperature: TemperatureUnit::Fahrenheit,
+                speed: SpeedUnit::MilesPerHour,
+                precipitation: PrecipitationUnit::Inches,
+                pressure: PressureUnit::InchesOfMercury,
+                distance: DistanceUnit::Miles,
+            },
+        }
+    }
+}
+
+/// Units of each forecast field; every renderer converts values through it
+#[derive(Clone, Copy)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub speed: SpeedUnit,
+    pub precipitation: PrecipitationUnit,
+    pub pressure: PressureUnit,
+    pub distance: DistanceUnit,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        UnitSystem::default().units()
+    }
+}
+
+impl Units {
+    /// Converts temperature from Celsius degrees
+    pub fn temperature(&self, celsius: f32) -> f32 {
+        match self.temperature {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        }
+    }
+    /// Temperature unit suffix
+    pub fn temperature_unit(&self) -> &'static str {
+        match self.temperature {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+            TemperatureUnit::Kelvin => " K",
+        }
+    }
+    /// Converts speed from meters per second
+    pub fn speed(&self, meters_per_second: f32) -> f32 {
+        match self.speed {
+            SpeedUnit::MetersPerSecond => meters_per_second,
+            SpeedUnit::KilometersPerHour => meters_per_second * 3.6,
+            SpeedUnit::MilesPerHour => meters_per_second * 2.236_936,
+            SpeedUnit::Knots => meters_per_second * 1.943_844,
+        }
+    }
+    /// Speed unit suffix
+    pub fn speed_unit(&self) -> &'static str {
+        match self.speed {
+            SpeedUnit::MetersPerSecond => "m/s",
+            SpeedUnit::KilometersPerHour => "km/h",
+            SpeedUnit::MilesPerHour => "mph",
+            SpeedUnit::Knots => "kn",
+        }
+    }
+    /// Converts precipitation amount from millimeters
+    pub fn precipitation(&self, millimeters: f32) -> f32 {
+        match self.precipitation {
+            PrecipitationUnit::Millimeters => millimeters,
+            PrecipitationUnit::Inches => millimeters / 25.4,
+        }
+    }
+    /// Precipitation unit suffix
+    pub fn precipitation_unit(&self) -> &'static str {
+        match self.precipitation {
+            PrecipitationUnit::Millimeters => "mm",
+            PrecipitationUnit::Inches => "in",
+        }
+    }
+    /// Converts speed from knots
+    pub fn speed_from_knots(&self, knots: f32) -> f32 {
+        self.speed(knots * MS_PER_KNOT)
+    }
+    /// Converts speed from miles per hour
+    pub fn speed_from_mph(&self, mph: f32) -> f32 {
+        self.speed(mph * MS_PER_MPH)
+    }
+    /// Converts pressure from hectopascals
+    pub fn pressure(&self, hectopascals: f32) -> f32 {
+        match self.pressure {
+            PressureUnit::Hectopascals => hectopascals,
+            PressureUnit::InchesOfMercury => hectopascals / 33.863_89,
+        }
+    }
+    /// Pressure unit suffix
+    pub fn pressure_unit(&self) -> &'static str {
+        match self.pressure {
+            PressureUnit::Hectopascals => "hPa",
+            PressureUnit::InchesOfMercury => "inHg",
+        }
+    }
+    /// Converts distance from kilometers
+    pub fn distance(&self, kilometers: f64) -> f64 {
+        kilometers / self.kilometers_per_unit()
+    }
+    /// Converts distance in these units into kilometers, e.g. search radius specified by user
+    pub fn distance_to_km(&self, distance: f64) -> f64 {
+        distance * self.kilometers_per_unit()
+    }
+    /// Distance unit suffix
+    pub fn distance_unit(&self) -> &'static str {
+        match self.distance {
+            DistanceUnit::Kilometers => "km",
+            DistanceUnit::Miles => "mi",
+            DistanceUnit::NauticalMiles => "nmi",
+        }
+    }
+
+    fn kilometers_per_unit(&self) -> f64 {
+        match self.distance {
+            DistanceUnit::Kilometers => 1.0,
+            DistanceUnit::Miles => 1.609_344,
+            DistanceUnit::NauticalMiles => 1.852,
+        }
+    }
+    /// Wraps forecast to display it in these units
+    pub fn display<'a>(&self, info: &'a WeatherInfo) -> InUnits<'a> {
+        InUnits { info, units: *self }
+    }
+}
+
+/// Command-line arguments which select units
+#[derive(clap::Args)]
+pub struct UnitArgs {
+    /// Units in which forecast is shown; default is profile's units or metric ones
+    #[arg(short, long, value_enum)]
+    units: Option<UnitSystem>,
+    /// Temperature unit, overrides one of unit system
+    #[arg(long, value_enum)]
+    temp_unit: Option<TemperatureUnit>,
+    /// Wind speed unit, overrides one of unit system
+    #[arg(long, value_enum)]
+    wind_unit: Option<SpeedUnit>,
+    /// Precipitation unit, overrides one of unit system
+    #[arg(long, value_enum)]
+    precip_unit: Option<PrecipitationUnit>,
+    /// Pressure unit, overrides one of unit system
+    #[arg(long, value_enum)]
+    pressure_unit: Option<PressureUnit>,
+    /// Distance unit, overrides one of unit system
+    #[arg(long, value_enum)]
+    distance_unit: Option<DistanceUnit>,
+}
+
+impl UnitArgs {
+    /// Resolves units of each field; command-line arguments take precedence over profile's entries,
+    /// and field units take precedence over unit system
+    ///
+    /// # Parameters
+    /// * `profile` - selected profile, which may have `units`, `temp_unit`, `wind_unit`,
+    ///   `precip_unit`, `pressure_unit` and `distance_unit` entries
+    pub fn resolve(self, profile: &Section) -> anyhow::Result<Units> {
+        let entry = |name: &str| profile.get(name).map(String::as_str);
+        let resolve = || -> anyhow::Result<Units> {
+            let system = match (self.units, entry(PROFILE_UNITS)) {
+                (Some(system), _) => system,
+                (None, Some(system)) => system.parse()?,
+                (None, None) => UnitSystem::default(),
+            };
+            let mut units = system.units();
+            if let Some(unit) = self.temp_unit.map(Ok).or_else(|| {
+                entry(PROFILE_TEMP_UNIT).map(|unit| parse_unit(unit, "temperature unit"))
+            }) {
+                units.temperature = unit?;
+            }
+            if let Some(unit) = self.wind_unit.map(Ok).or_else(|| {
+                entry(PROFILE_WIND_UNIT).map(|unit| parse_unit(unit, "wind speed unit"))
+            }) {
+                units.speed = unit?;
+            }
+            if let Some(unit) = self.precip_unit.map(Ok).or_else(|| {
+                entry(PROFILE_PRECIP_UNIT).map(|unit| parse_unit(unit, "precipitation unit"))
+            }) {
+                units.precipitation = unit?;
+            }
+            if let Some(unit) = self.pressure_unit.map(Ok).or_else(|| {
+                entry(PROFILE_PRESSURE_UNIT).map(|unit| parse_unit(unit, "pressure unit"))
+            }) {
+                units.pressure = unit?;
+            }
+            if let Some(unit) = self.distance_unit.map(Ok).or_else(|| {
+                entry(PROFILE_DISTANCE_UNIT).map(|unit| parse_unit(unit, "distance unit"))
+            }) {
+                units.distance = unit?;
+            }
+            Ok(units)
+        };
+        resolve().with_context(|| anyhow!("When reading profile's units"))
+    }
+}
+/// Forecast displayed in specific units; mirrors `WeatherInfo`'s own formatting
+pub struct InUnits<'a> {
+    info: &'a WeatherInfo,
+    units: Units,
+}
+
+impl Display for InUnits<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Self { info, units } = self;
+        let kind = info.weather.to_string();
+        f.write_fmt(format_args!(
+            "{}: {}\n{}: {}{}",
+            i18n::tr("label.weather", "Weather"),
+            i18n::tr(&format!("weather.{kind}"), &kind),
+            i18n::tr("label.temperature", "Temperature"),
+            units.temperature(info.temperature),
+            units.temperature_unit(),
+        ))?;
+        // Day's range tells more than its average temperature
+        if let Some((min, max)) = info.temperature_range() {
+            f.write_fmt(format_args!(
+                " ({}…{}{})",
+                units.temperature(min),
+                units.temperature(max),
+                units.temperature_unit()
+            ))?;
+        }
+        f.write_fmt(format_args!(
+            "\n{}: {} {}\n{}: {}%",
+            i18n::tr("label.wind_speed", "Wind speed"),
+            units.speed(info.wind_speed),
+            units.speed_unit(),
+            i18n::tr("label.humidity", "Humidity"),
+            info.humidity
+        ))?;
+        if let Some(precipitation) = info.precipitation {
+            f.write_fmt(format_args!(
+                "\n{}: {} {}",
+                i18n::tr("label.precipitation", "Precipitation"),
+                units.precipitation(precipitation),
+                units.precipitation_unit()
+            ))?;
+        }
+        if let Some(pressure) = info.pressure {
+            f.write_fmt(format_args!(
+                "\n{}: {:.1} {}",
+                i18n::tr("label.pressure", "Pressure"),
+                units.pressure(pressure),
+                units.pressure_unit()
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::{UnitArgs, Units};
+    use crate::config::Section;
+    use crate::provider::{WeatherInfo, WeatherKind};
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        units: UnitArgs,
+    }
+
+    fn resolve(args: &[&str], profile: &[(&str, &str)]) -> Units {
+        let profile: Section = profile
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let cli = Cli::try_parse_from([&["weather"], args].concat()).unwrap();
+        cli.units.resolve(&profile).unwrap()
+    }
+
+    #[test]
+    fn pressure_and_distance_follow_unit_system() {
+        let metric = Units::default();
+        assert_eq!(metric.pressure(1013.25), 1013.25);
+        assert_eq!(metric.distance(10.0), 10.0);
+        let imperial = resolve(&["--units", "imperial"], &[]);
+        assert!((imperial.pressure(1013.25) - 29.92).abs() < 0.01);
+        assert_eq!(imperial.pressure_unit(), "inHg");
+        assert!((imperial.distance(1.609_344) - 1.0).abs() < 1e-9);
+        assert_eq!(imperial.distance_unit(), "mi");
+    }
+
+    #[test]
+    fn field_units_override_system() {
+        let units = resolve(
+            &["--wind-unit", "knots"],
+            &[("units", "imperial"), ("distance_unit", "nmi")],
+        );
+        assert_eq!(units.speed_unit(), "kn");
+        assert_eq!(units.distance_unit(), "nmi");
+        assert!((units.distance(18.52) - 10.0).abs() < 1e-9);
+        assert!((units.distance_to_km(10.0) - 18.52).abs() < 1e-9);
+        assert!((units.speed_from_knots(10.0) - 10.0).abs() < 0.01);
+        // Command-line argument takes precedence over profile's entry
+        let units = resolve(&["--pressure-unit", "hpa"], &[("units", "imperial")]);
+        assert_eq!(units.pressure_unit(), "hPa");
+        assert_eq!(units.temperature_unit(), "°F");
+    }
+
+    #[test]
+    fn unknown_profile_unit_is_reported() {
+        let profile: Section = [("distance_unit".to_string(), "league".to_string())].into();
+        let cli = Cli::try_parse_from(["weather"]).unwrap();
+        let error = cli.units.resolve(&profile).err().unwrap();
+        assert!(format!("{error:#}").contains("Unknown distance unit 'league'"));
+    }
+
+    #[test]
+    fn pressure_is_shown_when_known() {
+        let info = WeatherInfo::sample(WeatherKind::Clear, 20.0);
+        assert!(!Units::default()
+            .display(&info)
+            .to_string()
+            .contains("Pressure"));
+        let info = WeatherInfo {
+            pressure: Some(1013.25),
+            ..info
+        };
+        let units = resolve(&["--pressure-unit", "inhg"], &[]);
+        let text = units.display(&info).to_string();
+        assert!(text.ends_with("\nPressure: 29.9 inHg"), "{text}");
+    }
+}
@@ -2,13 +2,30 @@ use std::error::Error as StdError;
 use std::str::FromStr;
 
 use anyhow::{anyhow, Context};
-use reqwest::IntoUrl;
+use serde::de::DeserializeOwned;
+use tracing::Instrument;
+
+use crate::api_url::ApiUrl;
+use crate::cancel;
+use crate::clock;
+use crate::fixtures;
+use crate::http_backend::{self, Request};
+use crate::http_limits;
+use crate::rate_limit;
+use crate::redact;
+use crate::schema;
+#[cfg(feature = "sqlite")]
+use crate::store;
+
+/// HTTP status of request rejected by rate limit
+const TOO_MANY_REQUESTS: u16 = 429;
 
 /// Perform HTTP GET request to REST API endpoint, handle its success or failure
 /// and parse result, either successful or failing, from text
 ///
 /// Please note that despite error type is specified, failure is returned as `anyhow::Error`.
 /// This is because there are many types of errors besides API error itself which may arise.
+/// URL's secrets are masked in returned errors
 ///
 /// # Generics
 /// * `R` - successful result type, should be parseable from response text
@@ -19,31 +36,341 @@ use reqwest::IntoUrl;
 ///
 /// # Returns
 /// Successful result or failure
-pub async fn restful_get<R, E>(url: impl IntoUrl) -> anyhow::Result<R>
+pub async fn restful_get<R, E>(url: ApiUrl) -> anyhow::Result<R>
 where
     R: FromStr,
     R::Err: StdError + Send + Sync + 'static,
     E: FromStr + StdError + Send + Sync + 'static,
     E::Err: StdError + Send + Sync + 'static,
 {
-    let response = reqwest::get(url)
-        .await
-        .with_context(|| anyhow!("HTTP GET request failed"))?;
+    let request = Request::get(url.as_str());
+    send::<R, E>(request, url.secrets()).await
+}
+/// Same as `restful_get`, but deserializes successful result from response text of specific format,
+/// like JSON or XML, instead of parsing it with `FromStr`
+///
+/// # Generics
+/// * `R` - successful result type, deserialized from response text
+/// * `E` - failure type, should be parseable from response text
+/// * `F` - format of successful response, `Json` or `Xml`
+///
+/// # Parameters
+/// * `url` - request URL
+///
+/// # Returns
+/// Successful result or failure
+pub async fn restful_get_as<R, E, F>(url: ApiUrl) -> anyhow::Result<R>
+where
+    R: DeserializeOwned,
+    E: FromStr + StdError + Send + Sync + 'static,
+    E::Err: StdError + Send + Sync + 'static,
+    F: ResponseFormat,
+{
+    let request = Request::get(url.as_str());
+    send_as::<R, E>(request, url.secrets(), F::parse).await
+}
+/// Format of API responses, which structures are deserialized from
+pub trait ResponseFormat {
+    /// Deserializes structure from response text
+    ///
+    /// # Parameters
+    /// * `text` - response text
+    ///
+    /// # Returns
+    /// Deserialized structure or error
+    fn parse<T: DeserializeOwned>(text: &str) -> anyhow::Result<T>;
+}
+/// JSON responses
+pub struct Json;
 
-    let is_ok = response.status().is_success();
-    let code = response.status().as_u16();
+impl ResponseFormat for Json {
+    fn parse<T: DeserializeOwned>(text: &str) -> anyhow::Result<T> {
+        Ok(schema::parse(text)?)
+    }
+}
+/// XML responses, for services which don't offer JSON, like government weather services
+pub struct Xml;
 
-    let text = response
-        .text()
-        .await
-        .with_context(|| anyhow!("Could not obtain response text"))?;
+impl ResponseFormat for Xml {
+    fn parse<T: DeserializeOwned>(text: &str) -> anyhow::Result<T> {
+        Ok(schema::parse_xml(text)?)
+    }
+}
+/// Same as `restful_get`, but sends arbitrary prepared request,
+/// e.g. one with authentication headers
+///
+/// # Generics
+/// * `R` - successful result type, should be parseable from response text
+/// * `E` - failure type, should be parseable from response text
+///
+/// # Parameters
+/// * `request` - prepared request; its URL shouldn't contain secrets,
+///   which should rather be passed in headers
+///
+/// # Returns
+/// Successful result or failure
+pub async fn restful_request<R, E>(request: Request) -> anyhow::Result<R>
+where
+    R: FromStr,
+    R::Err: StdError + Send + Sync + 'static,
+    E: FromStr + StdError + Send + Sync + 'static,
+    E::Err: StdError + Send + Sync + 'static,
+{
+    send::<R, E>(request, &[]).await
+}
+/// Sends request and parses its result
+///
+/// # Parameters
+/// * `request` - prepared request
+/// * `secrets` - secrets of request's URL, masked in logs and errors
+async fn send<R, E>(request: Request, secrets: &[String]) -> anyhow::Result<R>
+where
+    R: FromStr,
+    R::Err: StdError + Send + Sync + 'static,
+    E: FromStr + StdError + Send + Sync + 'static,
+    E::Err: StdError + Send + Sync + 'static,
+{
+    send_as::<R, E>(request, secrets, |text| Ok(R::from_str(text)?)).await
+}
+/// Sends request and parses its result, successful one with specified function
+///
+/// # Parameters
+/// * `request` - prepared request
+/// * `secrets` - secrets of request's URL, masked in logs and errors
+/// * `parse` - parses successful result from response text
+async fn send_as<R, E>(
+    request: Request,
+    secrets: &[String],
+    parse: impl FnOnce(&str) -> anyhow::Result<R>,
+) -> anyhow::Result<R>
+where
+    E: FromStr + StdError + Send + Sync + 'static,
+    E::Err: StdError + Send + Sync + 'static,
+{
+    // Parsing errors embed full URL, so it's left out of them
+    let url =
+        url::Url::parse(&request.url).map_err(|error| anyhow!("Invalid request URL: {error}"))?;
+    // Query is noisy, so it's logged only at most verbose level, with secrets masked
+    let endpoint = format!("{}{}", url.origin().ascii_serialization(), url.path());
+    let host = url.host_str().unwrap_or_default().to_string();
+    let method = request.method;
+    tracing::debug!("{method} {endpoint}");
+    let masked = redact::mask(url.as_str(), secrets.iter());
+    tracing::trace!("Full URL: {masked}");
+    // Status and whether response is replayed from fixture are known only later
+    let span = tracing::info_span!(
+        "http",
+        method,
+        endpoint,
+        status = tracing::field::Empty,
+        replayed = tracing::field::Empty,
+        duration_ms = tracing::field::Empty
+    );
+    let (code, content_type, text) = match fixtures::replayed(method, &masked)? {
+        Some(fixture) => {
+            span.record("replayed", true);
+            (fixture.status, fixture.content_type, fixture.body)
+        }
+        None => {
+            span.record("replayed", false);
+            let started = std::time::Instant::now();
+            // Host which rejected requests by rate limit is left alone until its pause ends
+            let (code, content_type, text) = cancel::interruptible(async {
+                rate_limit::wait(&host).await?;
+                // Counters only inform user, so failure to update them doesn't fail request
+                #[cfg(feature = "sqlite")]
+                if let Err(error) = store::count_request(&host) {
+                    tracing::warn!("Could not count request to {host}: {error:#}");
+                }
+                fetch(request, &host).await
+            })
+            .instrument(span.clone())
+            .await?;
+            span.record("duration_ms", started.elapsed().as_millis() as u64);
+            // Responses may echo secrets, so they're masked before being stored
+            let body = redact::redact(&redact::mask(&text, secrets.iter()));
+            fixtures::record(method, &masked, code, content_type.as_deref(), &body)?;
+            (code, content_type, text)
+        }
+    };
+    span.record("status", code);
+    let is_ok = http_backend::is_success(code);
+
+    // Proxies and misconfigured servers respond with pages or nothing, which aren't API's responses at all
+    let empty = text.trim().is_empty();
+    if (empty && !is_ok) || is_html(content_type.as_deref(), &text) {
+        return Err(anyhow!(
+            "Server responded with {} instead of API response (HTTP {}){}",
+            if empty { "empty body" } else { "web page" },
+            http_backend::status_text(code),
+            snippet(&text, secrets)
+        ));
+    }
 
     if is_ok {
-        Ok(R::from_str(&text)
-            .with_context(|| anyhow!("Could not parse response as successful result"))?)
+        parse(&text).with_context(|| anyhow!("Could not parse response as successful result"))
     } else {
-        Err(E::from_str(&text)
-            .with_context(|| anyhow!("Could not parse response as failure (HTTP {code})"))?
-            .into())
+        let error = E::from_str(&text).with_context(|| {
+            anyhow!(
+                "Could not parse response as failure (HTTP {code}){}",
+                snippet(&text, secrets)
+            )
+        })?;
+        // Some APIs echo request back in their error messages
+        let message = error.to_string();
+        let redacted = redact::mask(&message, secrets.iter());
+        if redacted == message {
+            Err(error.into())
+        } else {
+            Err(anyhow!(redacted))
+        }
+    }
+}
+/// Checks whether response is HTML page, by its media type or, if it's missing, by its text
+///
+/// # Parameters
+/// * `content_type` - media type of response, if it's specified
+/// * `text` - response text
+fn is_html(content_type: Option<&str>, text: &str) -> bool {
+    match content_type {
+        Some(content_type) => {
+            let media = content_type.split(';').next().unwrap_or_default().trim();
+            media.eq_ignore_ascii_case("text/html")
+                || media.eq_ignore_ascii_case("application/xhtml+xml")
+        }
+        None => {
+            let start = text.trim_start().get(..15).unwrap_or_default();
+            start.eq_ignore_ascii_case("<!doctype html>") || start.starts_with("<html")
+        }
+    }
+}
+/// Readable beginning of response text, to be appended to error message; markup is stripped
+/// and whitespace collapsed, so HTML page shows its title and first words
+///
+/// # Parameters
+/// * `text` - response text
+/// * `secrets` - secrets of request's URL, which response may echo
+///
+/// # Returns
+/// ": <snippet>" or empty string if there's no text
+fn snippet(text: &str, secrets: &[String]) -> String {
+    const MAX_CHARS: usize = 200;
+    let mut plain = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                plain.push(' ');
+            }
+            _ if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+    let words: Vec<&str> = plain.split_whitespace().collect();
+    let collapsed = words.join(" ");
+    if collapsed.is_empty() {
+        return String::new();
+    }
+    let mut snippet: String = collapsed.chars().take(MAX_CHARS).collect();
+    if snippet.len() < collapsed.len() {
+        snippet.push('…');
+    }
+    format!(": {}", redact::mask(&snippet, secrets.iter()))
+}
+/// Executes request over network
+///
+/// # Parameters
+/// * `request` - request to send
+/// * `host` - request's host, paused if it rejects request by rate limit
+///
+/// # Returns
+/// Response's HTTP status code, media type, if it's specified, and text;
+/// `RateLimited` error if host rejected request by rate limit
+async fn fetch(mut request: Request, host: &str) -> anyhow::Result<(u16, Option<String>, String)> {
+    let limits = http_limits::get();
+    // Covers connecting, waiting for response and reading its body
+    request.timeout = Some(limits.timeout);
+    let timed_out = |error: http_backend::Error| match error {
+        http_backend::Error::Timeout => anyhow!(
+            "Request took longer than {} s, see '{}' config entry",
+            limits.timeout.as_secs(),
+            http_limits::TIMEOUT_ENTRY
+        ),
+        http_backend::Error::Other(error) => error,
+    };
+    let started = std::time::Instant::now();
+    let mut response = request
+        .send()
+        .await
+        .map_err(timed_out)
+        .with_context(|| anyhow!("HTTP GET request failed"))?;
+    let code = response.status();
+    tracing::debug!(
+        "Responded with {} in {} ms",
+        http_backend::status_text(code),
+        started.elapsed().as_millis()
+    );
+
+    // Server's clock is a reference for detecting broken local clock
+    let date = response.header("Date");
+    if let Some(date) = &date {
+        clock::observe(date);
+    }
+    if code == TOO_MANY_REQUESTS {
+        let now = date
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(&date).ok())
+            .map_or_else(chrono::Utc::now, |date| date.with_timezone(&chrono::Utc));
+        let retry_after = response.header("Retry-After");
+        return Err(rate_limit::reject(host, retry_after.as_deref(), now).into());
+    }
+
+    let content_type = response.header("Content-Type");
+
+    // Declared size allows failing early, yet body is still checked as it arrives
+    if let Some(length) = response.content_length() {
+        limits.check_response_size(length)?;
+    }
+    let mut body = Vec::new();
+    loop {
+        let chunk = tokio::time::timeout(limits.read_timeout, response.chunk())
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "Server sent no data for {} s, see '{}' config entry",
+                    limits.read_timeout.as_secs(),
+                    http_limits::READ_TIMEOUT_ENTRY
+                )
+            })?
+            .map_err(timed_out)
+            .with_context(|| anyhow!("Could not obtain response text"))?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+        limits.check_response_size((body.len() + chunk.len()) as u64)?;
+        body.extend_from_slice(&chunk);
     }
+    let text = match String::from_utf8(body) {
+        Ok(text) => text,
+        Err(error) => String::from_utf8_lossy(error.as_bytes()).into_owned(),
+    };
+    Ok((code, content_type, text))
+}
+/// Mean Earth radius, in kilometers
+const EARTH_RADIUS_KM: f64 = 6371.0;
+/// Computes great-circle distance between two points
+///
+/// # Parameters
+/// * `from` - first point's latitude and longitude, in degrees
+/// * `to` - second point's latitude and longitude, in degrees
+///
+/// # Returns
+/// Distance in kilometers
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (from.0.to_radians(), to.0.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (to.1 - from.1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
 }
@@ -0,0 +1,1309 @@
+//! End-to-end tests of built-in providers against mocked HTTP endpoints
+//!
+//! Each test starts mock server, points provider at it with `base_url` parameter
+//! and runs application's binary in isolated home directory, checking its output.
+//! Tests of provider are compiled only along with provider's cargo feature
+#![cfg_attr(not(feature = "all-providers"), allow(unused_imports, dead_code))]
+use std::process::Output;
+
+use serde_json::json;
+use tempfile::TempDir;
+use wiremock::matchers::{header, method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Location specified with coordinates, so no geocoding requests are made
+const LOCATION: &str = "51.5,-0.1";
+
+/// Application run in its own home directory, with config file there
+struct App {
+    home: TempDir,
+}
+
+impl App {
+    /// Creates home directory with config file
+    ///
+    /// # Parameters
+    /// * `config` - contents of config file
+    fn new(config: &str) -> Self {
+        let home = TempDir::new().expect("Could not create home directory");
+        std::fs::write(home.path().join("config.ini"), config).expect("Could not write config");
+        Self { home }
+    }
+    /// Contents of config file
+    fn config(&self) -> String {
+        std::fs::read_to_string(self.home.path().join("config.ini")).unwrap_or_default()
+    }
+    /// Runs application with arguments, isolated from user's environment and directories
+    ///
+    /// # Returns
+    /// Exit status, stdout and stderr of application
+    async fn run(&self, args: &[&str]) -> Output {
+        let home = self.home.path();
+        tokio::process::Command::new(env!("CARGO_BIN_EXE_weather"))
+            .arg("--config")
+            .arg(home.join("config.ini"))
+            .args(args)
+            .env_clear()
+            .env("HOME", home)
+            .env("XDG_CONFIG_HOME", home.join("config"))
+            .env("XDG_CACHE_HOME", home.join("cache"))
+            .env("XDG_DATA_HOME", home.join("data"))
+            .env("XDG_STATE_HOME", home.join("state"))
+            .current_dir(home)
+            .output()
+            .await
+            .expect("Could not run application")
+    }
+}
+/// Stdout of successful run
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "Application failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+/// Stderr of failed run
+fn stderr(output: &Output) -> String {
+    assert!(
+        !output.status.success(),
+        "Application succeeded unexpectedly: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn weatherapi_current_conditions() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .and(query_param("key", "wa-secret-key"))
+        .and(query_param("q", LOCATION))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "current": {
+                "temp_c": 12.5,
+                "humidity": 80,
+                "wind_kph": 18,
+                "precip_mm": 1.2,
+                "condition": { "code": 1183 }
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+
+    let output = stdout(&app.run(&["get", "-p", "weatherapi", LOCATION]).await);
+    assert!(output.contains("Weather: raining"), "{output}");
+    assert!(output.contains("Temperature: 12.5°C"), "{output}");
+    assert!(output.contains("Wind speed: 5 m/s"), "{output}");
+    assert!(output.contains("Humidity: 80%"), "{output}");
+    assert!(output.contains("Precipitation: 1.2 mm"), "{output}");
+    assert!(output.contains("Powered by WeatherAPI.com"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn strict_mode_reports_unknown_fields() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "current": {
+                "temp_c": 12.5,
+                "humidity": 80,
+                "wind_kph": 18,
+                "precip_mm": 1.2,
+                "feelslike_c": 11,
+                "condition": { "code": 1183 }
+            }
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+
+    // Unknown fields are ignored unless strict mode is on
+    stdout(&app.run(&["get", "-p", "weatherapi", LOCATION]).await);
+    let error = stderr(
+        &app.run(&["get", "-p", "weatherapi", "--strict", LOCATION])
+            .await,
+    );
+    assert!(
+        error.contains("Unknown fields in response: current.feelslike_c"),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn forecast_is_streamed_as_ndjson() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "current": {
+                "temp_c": 12.5, "humidity": 80, "wind_kph": 18, "precip_mm": 1.2,
+                "condition": { "code": 1183 }
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+
+    let output = stdout(
+        &app.run(&["get", "-p", "weatherapi", "-o", "ndjson", LOCATION])
+            .await,
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 1, "{output}");
+    let line: serde_json::Value = serde_json::from_str(lines[0]).expect("Line should be JSON");
+    assert_eq!(line["location"], LOCATION);
+    assert_eq!(line["provider"], "weatherapi");
+    assert_eq!(line["temperature"], 12.5);
+    assert_eq!(line["attribution"][0]["text"], "Powered by WeatherAPI.com");
+}
+
+#[tokio::test]
+#[cfg(feature = "xweather")]
+async fn data_source_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/observations/{LOCATION}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "error": null,
+            "response": {
+                "id": "EGLL",
+                "ob": {
+                    "timestamp": 1720000000,
+                    "tempC": 18, "humidity": 40, "windSpeedKPH": 0,
+                    "precipMM": 0, "weatherPrimaryCoded": "::CL"
+                }
+            }
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[xweather]\nclient_id = xw-client\nclient_secret = xw-secret\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stdout(
+        &app.run(&["get", "-p", "xweather", "--detailed", LOCATION])
+            .await,
+    );
+    assert!(output.contains("Provider: xweather"), "{output}");
+    assert!(output.contains("Data: observation"), "{output}");
+    assert!(output.contains("Source: station EGLL"), "{output}");
+
+    let output = stdout(
+        &app.run(&["get", "-p", "xweather", "-o", "ndjson", LOCATION])
+            .await,
+    );
+    let line: serde_json::Value = serde_json::from_str(output.trim()).expect("Line should be JSON");
+    assert_eq!(line["meta"]["provider"], "xweather");
+    assert_eq!(line["meta"]["kind"], "observation");
+    assert_eq!(line["meta"]["timestamp"], "2024-07-03T09:46:40Z");
+    assert_eq!(line["meta"]["source"], "station EGLL");
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn weatherapi_historical_hour() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/history.json"))
+        .and(query_param("dt", "2024-7-1"))
+        .and(query_param("hour", "18"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "forecast": { "forecastday": [{
+                "day": {
+                    "avghumidity": 60, "avgtemp_c": 20, "mintemp_c": 15, "maxtemp_c": 25,
+                    "maxwind_kph": 36, "totalprecip_mm": 0, "condition": { "code": 1000 }
+                },
+                "hour": [
+                    {
+                        "time": "2024-07-01 17:00", "temp_c": 23, "humidity": 50,
+                        "wind_kph": 7.2, "precip_mm": 0, "condition": { "code": 1000 }
+                    },
+                    {
+                        "time": "2024-07-01 18:00", "temp_c": 21.5, "humidity": 55,
+                        "wind_kph": 3.6, "precip_mm": 0, "condition": { "code": 1003 }
+                    }
+                ]
+            }]}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+
+    let output = stdout(
+        &app.run(&[
+            "get",
+            "-p",
+            "weatherapi",
+            "-d",
+            "2024-07-01 18:00",
+            LOCATION,
+        ])
+        .await,
+    );
+    assert!(output.contains("Weather: clouds"), "{output}");
+    assert!(output.contains("Temperature: 21.5°C"), "{output}");
+    assert!(output.contains("Humidity: 55%"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn weatherapi_error_is_reported_without_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": { "code": 2006, "message": "API key wa-secret-key is invalid." }
+        })))
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+
+    let output = stderr(&app.run(&["get", "-p", "weatherapi", LOCATION]).await);
+    assert!(output.contains("API error 2006"), "{output}");
+    assert!(!output.contains("wa-secret-key"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn non_json_error_pages_are_summarized() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .respond_with(ResponseTemplate::new(503).set_body_raw(
+            "<!DOCTYPE html><html><head><title>503 Service Unavailable</title></head>\n\
+             <body><h1>Service Unavailable</h1></body></html>",
+            "text/html; charset=utf-8",
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/current.json"))
+        .respond_with(ResponseTemplate::new(502))
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+    let proxied = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v2\n",
+        server.uri()
+    ));
+
+    let output = stderr(&app.run(&["get", "-p", "weatherapi", LOCATION]).await);
+    assert!(
+        output.contains(
+            "Server responded with web page instead of API response \
+             (HTTP 503 Service Unavailable): 503 Service Unavailable Service Unavailable"
+        ),
+        "{output}"
+    );
+    let output = stderr(&proxied.run(&["get", "-p", "weatherapi", LOCATION]).await);
+    assert!(
+        output.contains(
+            "Server responded with empty body instead of API response (HTTP 502 Bad Gateway)"
+        ),
+        "{output}"
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn http_limits_are_enforced() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/large/current.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            format!("{{\"padding\": \"{}\"}}", "x".repeat(4096)),
+            "application/json",
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/slow/current.json"))
+        .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "http_max_response_kb = 2\n\n[weatherapi]\napikey = wa-secret-key\nbase_url = {}/large\n",
+        server.uri()
+    ));
+    let output = stderr(&app.run(&["get", "-p", "weatherapi", LOCATION]).await);
+    assert!(output.contains("Response is larger than 2 KB"), "{output}");
+
+    let app = App::new(&format!(
+        "http_timeout = 1\n\n[weatherapi]\napikey = wa-secret-key\nbase_url = {}/slow\n",
+        server.uri()
+    ));
+    let output = stderr(&app.run(&["get", "-p", "weatherapi", LOCATION]).await);
+    assert!(output.contains("Request took longer than 1 s"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn streamed_body_over_limit_is_rejected() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Mock server always declares body's size, so chunked response without it is served by hand
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).await;
+            let mut response = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                                Transfer-Encoding: chunked\r\n\r\n"
+                .to_string();
+            for _ in 0..4 {
+                response.push_str(&format!("400\r\n{}\r\n", " ".repeat(1024)));
+            }
+            response.push_str("0\r\n\r\n");
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    let app = App::new(&format!(
+        "http_max_response_kb = 2\n\n[weatherapi]\napikey = wa-secret-key\nbase_url = http://{address}\n"
+    ));
+    let output = stderr(&app.run(&["get", "-p", "weatherapi", LOCATION]).await);
+    assert!(output.contains("Response is larger than 2 KB"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn batch_queries_are_written_in_order() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .and(query_param("q", LOCATION))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "current": {
+                "temp_c": 12.5, "humidity": 80, "wind_kph": 18, "precip_mm": 1.2,
+                "condition": { "code": 1183 }
+            }
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+    std::fs::write(
+        app.home.path().join("queries.txt"),
+        format!("# Comment\n{LOCATION}\n{LOCATION};now;weatherapi\n{LOCATION};;openweather\n"),
+    )
+    .expect("Could not write queries");
+
+    let output = app
+        .run(&[
+            "batch",
+            "-p",
+            "weatherapi",
+            "--input",
+            "queries.txt",
+            "-o",
+            "ndjson",
+        ])
+        .await;
+    assert!(!output.status.success());
+    let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Line should be JSON"))
+        .collect();
+    assert_eq!(lines.len(), 3, "{lines:?}");
+    assert_eq!(lines[0]["line"], 2);
+    assert_eq!(lines[0]["temperature"], 12.5);
+    assert_eq!(lines[1]["line"], 3);
+    assert_eq!(lines[1]["provider"], "weatherapi");
+    assert_eq!(lines[2]["line"], 4);
+    assert!(lines[2]["error"].is_string(), "{:?}", lines[2]);
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn batch_resumes_after_rate_limit() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "current": {
+                "temp_c": 12.5, "humidity": 80, "wind_kph": 18, "precip_mm": 1.2,
+                "condition": { "code": 1183 }
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+    std::fs::write(app.home.path().join("queries.txt"), format!("{LOCATION}\n"))
+        .expect("Could not write queries");
+
+    let output = app
+        .run(&[
+            "batch",
+            "-p",
+            "weatherapi",
+            "--input",
+            "queries.txt",
+            "-o",
+            "ndjson",
+        ])
+        .await;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Paused 1s due to rate limit"), "{stderr}");
+    let line: serde_json::Value =
+        serde_json::from_str(stdout(&output).trim()).expect("Line should be JSON");
+    assert_eq!(line["temperature"], 12.5);
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn prefetched_weather_is_reused() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .and(query_param("q", LOCATION))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "current": {
+                "temp_c": 12.5, "humidity": 80, "wind_kph": 18, "precip_mm": 1.2,
+                "condition": { "code": 1183 }
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "current = weatherapi
+
+[weatherapi]
+apikey = wa-secret-key
+base_url = {}/v1
+
+\
+         [locations]
+home = {LOCATION}
+",
+        server.uri()
+    ));
+
+    let output = stdout(&app.run(&["prefetch"]).await);
+    assert!(output.contains("Refreshed 1 of 1 locations"), "{output}");
+    // Request isn't repeated, mock expects single one
+    let output = stdout(&app.run(&["get", "home", "--max-age", "600"]).await);
+    assert!(output.contains("Temperature: 12.5°C"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "openweather")]
+async fn openweather_current_conditions() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/data/3.0/onecall"))
+        .and(query_param("appid", "ow-secret-key"))
+        .and(query_param("lat", "51.5000"))
+        .and(query_param("lon", "-0.1000"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "timezone_offset": 3600,
+            "current": {
+                "temp": 8.5, "humidity": 90, "wind_speed": 4,
+                "snow": { "1h": 0.7 }, "weather": [{ "id": 601 }]
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[openweather]\napikey = ow-secret-key\nbase_url = {}/data/3.0\n",
+        server.uri()
+    ));
+
+    let output = stdout(&app.run(&["get", "-p", "openweather", LOCATION]).await);
+    assert!(output.contains("Weather: snow"), "{output}");
+    assert!(output.contains("Temperature: 8.5°C"), "{output}");
+    assert!(output.contains("Wind speed: 4 m/s"), "{output}");
+    assert!(output.contains("Precipitation: 0.7 mm"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "openweather")]
+async fn openweather_error_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/data/3.0/onecall"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "cod": 401, "message": "Invalid API key."
+        })))
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[openweather]\napikey = ow-secret-key\nbase_url = {}/data/3.0\n",
+        server.uri()
+    ));
+
+    let output = stderr(&app.run(&["get", "-p", "openweather", LOCATION]).await);
+    assert!(
+        output.contains("API error 401: Invalid API key."),
+        "{output}"
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "accuweather")]
+async fn accuweather_resolves_location_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/locations/v1/cities/geoposition/search"))
+        .and(query_param("apikey", "aw-secret-key"))
+        .and(query_param("q", "51.5000,-0.1000"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "Key": "328328" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/currentconditions/v1/328328"))
+        .and(query_param("apikey", "aw-secret-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "Temperature": { "Metric": { "Value": 14 } },
+            "RelativeHumidity": 70,
+            "Wind": { "Speed": { "Metric": { "Value": 7.2 } } },
+            "CloudCover": 90,
+            "PrecipitationType": null,
+            "PrecipitationSummary": { "PastHour": { "Metric": { "Value": 0 } } },
+            "WeatherIcon": 7
+        }])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[accuweather]\napikey = aw-secret-key\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stdout(&app.run(&["get", "-p", "accuweather", LOCATION]).await);
+    assert!(output.contains("Weather: clouds"), "{output}");
+    assert!(output.contains("Temperature: 14°C"), "{output}");
+    assert!(output.contains("Wind speed: 2 m/s"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "brightsky")]
+async fn brightsky_summarizes_day() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/weather"))
+        .and(query_param("date", "2024-07-01"))
+        .and(query_param("lat", "51.5000"))
+        .and(query_param("tz", "Europe/London"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "weather": [
+                {
+                    "timestamp": "2024-07-01T06:00:00+01:00", "source_id": 7,
+                    "temperature": 12.0, "relative_humidity": 90, "wind_speed": 7.2,
+                    "precipitation": 0.0, "condition": "dry", "icon": "cloudy"
+                },
+                {
+                    "timestamp": "2024-07-01T12:00:00+01:00", "source_id": 7,
+                    "temperature": 18.0, "relative_humidity": 70, "wind_speed": 18.0,
+                    "precipitation": 2.0, "condition": "thunderstorm", "icon": "thunderstorm"
+                },
+                {
+                    "timestamp": "2024-07-02T00:00:00+01:00", "source_id": 7,
+                    "temperature": 30.0, "relative_humidity": 10, "wind_speed": 50.0,
+                    "precipitation": 9.0, "condition": "hail", "icon": "hail"
+                }
+            ],
+            "sources": [
+                { "id": 7, "station_name": "Berlin-Tempelhof", "observation_type": "historical" }
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new("");
+    let base_url = format!("base_url={}", server.uri());
+    stdout(
+        &app.run(&["configure", "brightsky", &base_url, "--no-test"])
+            .await,
+    );
+    assert!(app.config().contains("[brightsky]"), "{}", app.config());
+
+    let output = stdout(
+        &app.run(&["get", LOCATION, "--date", "2024-07-01", "--detailed"])
+            .await,
+    );
+    // Equally frequent weathers are resolved towards more severe one
+    assert!(output.contains("Weather: thunderstorm"), "{output}");
+    assert!(output.contains("Temperature: 15°C (12…18°C)"), "{output}");
+    assert!(output.contains("Wind speed: 5 m/s"), "{output}");
+    assert!(output.contains("Precipitation: 2 mm"), "{output}");
+    assert!(output.contains("Data: observation"), "{output}");
+    assert!(
+        output.contains("Source: station Berlin-Tempelhof"),
+        "{output}"
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "brightsky")]
+async fn brightsky_current_weather() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/current_weather"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "weather": {
+                "timestamp": "2024-07-01T12:30:00+00:00", "source_id": 3,
+                "temperature": 21.5, "relative_humidity": 55, "wind_speed_10": 18.0,
+                "precipitation_60": 0.0, "condition": "dry", "icon": "partly-cloudy-day"
+            },
+            "sources": [{ "id": 3, "station_name": "London", "observation_type": "synop" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!("[brightsky]\nbase_url = {}\n", server.uri()));
+
+    let output = stdout(&app.run(&["get", "-p", "brightsky", LOCATION]).await);
+    assert!(output.contains("Weather: clouds"), "{output}");
+    assert!(output.contains("Temperature: 21.5°C"), "{output}");
+    assert!(output.contains("Wind speed: 5 m/s"), "{output}");
+    assert!(output.contains("Humidity: 55%"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "brightsky")]
+async fn brightsky_trend_is_single_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/current_weather"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "weather": {
+                "timestamp": "2024-07-01T12:30:00+00:00", "source_id": 3,
+                "temperature": 21.5, "relative_humidity": 55, "wind_speed_10": 18.0,
+                "precipitation_60": 0.0, "condition": "dry", "icon": "partly-cloudy-day"
+            },
+            "sources": [{ "id": 3, "station_name": "London", "observation_type": "synop" }]
+        })))
+        .mount(&server)
+        .await;
+    // Records cover following day and a half, warming hour by hour
+    let now = chrono::Utc::now().timestamp() / 3600 * 3600;
+    let records: Vec<_> = (0..36)
+        .map(|hour| {
+            let time = chrono::DateTime::from_timestamp(now + hour * 3600, 0).unwrap();
+            json!({
+                "timestamp": time.to_rfc3339(), "source_id": 3, "temperature": hour,
+                "relative_humidity": 55, "wind_speed": 7.2, "precipitation": 0.0,
+                "condition": "dry", "icon": "clear-day"
+            })
+        })
+        .collect();
+    Mock::given(method("GET"))
+        .and(path("/weather"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "weather": records,
+            "sources": [{ "id": 3, "station_name": "London", "observation_type": "forecast" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!("[brightsky]\nbase_url = {}\n", server.uri()));
+
+    let output = stdout(
+        &app.run(&["get", "-p", "brightsky", "-o", "oneline", LOCATION])
+            .await,
+    );
+    assert!(output.contains('▁') && output.contains('█'), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "brightsky")]
+async fn hourly_forecast_is_charted() {
+    let server = MockServer::start().await;
+    let records: Vec<_> = (0..24)
+        .map(|hour| {
+            json!({
+                "timestamp": format!("2024-07-01T{hour:02}:00:00+01:00"), "source_id": 3,
+                "temperature": hour, "relative_humidity": 55, "wind_speed": 7.2,
+                "precipitation": 0.5, "condition": "dry", "icon": "clear-day"
+            })
+        })
+        .collect();
+    Mock::given(method("GET"))
+        .and(path("/weather"))
+        .and(query_param("date", "2024-07-01"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "weather": records,
+            "sources": [{ "id": 3, "station_name": "London", "observation_type": "historical" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!("[brightsky]\nbase_url = {}\n", server.uri()));
+
+    let output = stdout(
+        &app.run(&[
+            "get",
+            "-p",
+            "brightsky",
+            "-d",
+            "2024-07-01 10:00",
+            "--hours",
+            "3",
+            LOCATION,
+        ])
+        .await,
+    );
+    assert!(output.contains("Temperature ▁▅█\n"), "{output}");
+    assert!(output.contains("12:00 │"), "{output}");
+    assert!(output.contains("\nPrecipitation\n"), "{output}");
+    assert!(!output.contains("13:00"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "brightsky")]
+async fn check_threshold_counts_hours() {
+    let server = MockServer::start().await;
+    // Temperature equals hour of day, so half of day is at least 12°C
+    let records: Vec<_> = (0..24)
+        .map(|hour| {
+            json!({
+                "timestamp": format!("2024-07-01T{hour:02}:00:00+01:00"), "source_id": 3,
+                "temperature": hour, "relative_humidity": 55, "wind_speed": 7.2,
+                "precipitation": 0.0, "condition": "dry", "icon": "clear-day"
+            })
+        })
+        .collect();
+    Mock::given(method("GET"))
+        .and(path("/weather"))
+        .and(query_param("date", "2024-07-01"))
+        .and(query_param("last_date", "2024-07-02"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "weather": records,
+            "sources": [{ "id": 3, "station_name": "London", "observation_type": "historical" }]
+        })))
+        .mount(&server)
+        .await;
+    let app = App::new(&format!("[brightsky]\nbase_url = {}\n", server.uri()));
+    let check = |threshold: &'static str| {
+        let app = &app;
+        async move {
+            app.run(&[
+                "check",
+                "-p",
+                "brightsky",
+                "-d",
+                "2024-07-01",
+                "-C",
+                "temp >= 12",
+                "--threshold",
+                threshold,
+                LOCATION,
+            ])
+            .await
+        }
+    };
+
+    assert_eq!(check("50").await.status.code(), Some(0));
+    assert_eq!(check("51").await.status.code(), Some(1));
+    // Failure to check condition is told from unmet condition
+    let output = app
+        .run(&["check", "-p", "brightsky", "-C", "temp >>= 12", LOCATION])
+        .await;
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[tokio::test]
+#[cfg(feature = "envcanada")]
+async fn envcanada_uses_nearest_site() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/citypage_weather/docs/site_list_en.csv"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "Site Names,,,,\n\
+             Codes,English Names,Province Codes,Latitude,Longitude\n\
+             s0000458,Toronto,ON,43.74N,79.37W\n\
+             s0000630,\"Halifax, Nova Scotia\",NS,44.65N,63.57W\n",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/citypage_weather/xml/NS/s0000630_e.xml"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<siteData>
+  <currentConditions>
+    <station code="yhz">Halifax Stanfield Int'l Airport</station>
+    <dateTime name="observation" zone="UTC"><timeStamp>20240701120000</timeStamp></dateTime>
+    <dateTime name="observation" zone="ADT"><timeStamp>20240701090000</timeStamp></dateTime>
+    <condition>Light Rain</condition>
+    <iconCode format="gif">11</iconCode>
+    <temperature unitType="metric" units="C">14.2</temperature>
+    <relativeHumidity units="%">93</relativeHumidity>
+    <wind><speed unitType="metric" units="km/h">18</speed></wind>
+  </currentConditions>
+</siteData>"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!("[envcanada]\nbase_url = {}\n", server.uri()));
+
+    let output = stdout(
+        &app.run(&["get", "-p", "envcanada", LOCATION, "--detailed"])
+            .await,
+    );
+    assert!(output.contains("Weather: rain"), "{output}");
+    assert!(output.contains("Temperature: 14.2°C"), "{output}");
+    assert!(output.contains("Wind speed: 5 m/s"), "{output}");
+    assert!(output.contains("Humidity: 93%"), "{output}");
+    assert!(
+        output.contains("Source: station Halifax Stanfield Int'l Airport"),
+        "{output}"
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "envcanada")]
+async fn envcanada_combines_forecast_periods() {
+    let server = MockServer::start().await;
+    // Forecast issued in the evening starts with night
+    Mock::given(method("GET"))
+        .and(path("/citypage_weather/xml/ON/s0000458_e.xml"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<siteData>
+  <forecastGroup>
+    <dateTime name="forecastIssue" zone="UTC"><timeStamp>20990701010000</timeStamp></dateTime>
+    <dateTime name="forecastIssue" zone="EDT"><timeStamp>20990630210000</timeStamp></dateTime>
+    <forecast>
+      <period textForecastName="Tonight">Tuesday night</period>
+      <abbreviatedForecast><iconCode format="gif">30</iconCode><textSummary>Clear</textSummary></abbreviatedForecast>
+      <temperatures><temperature unitType="metric" units="C" class="low">11</temperature></temperatures>
+      <winds><wind><speed unitType="metric" units="km/h">calm</speed></wind></winds>
+    </forecast>
+    <forecast>
+      <period textForecastName="Wednesday">Wednesday</period>
+      <abbreviatedForecast><iconCode format="gif">06</iconCode><textSummary>Showers</textSummary></abbreviatedForecast>
+      <temperatures><temperature unitType="metric" units="C" class="high">22</temperature></temperatures>
+      <winds>
+        <wind><speed unitType="metric" units="km/h">10</speed></wind>
+        <wind><speed unitType="metric" units="km/h">18</speed></wind>
+      </winds>
+      <precipitation><accumulation><name>rain</name><amount unitType="metric" units="mm">5</amount></accumulation></precipitation>
+      <relativeHumidity units="%">75</relativeHumidity>
+    </forecast>
+    <forecast>
+      <period textForecastName="Wednesday night">Wednesday night</period>
+      <abbreviatedForecast><iconCode format="gif">36</iconCode><textSummary>Showers</textSummary></abbreviatedForecast>
+      <temperatures><temperature unitType="metric" units="C" class="low">14</temperature></temperatures>
+      <precipitation><accumulation><name>snow</name><amount unitType="metric" units="cm">0.5</amount></accumulation></precipitation>
+      <relativeHumidity units="%"/>
+    </forecast>
+  </forecastGroup>
+</siteData>"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[envcanada]\nsite = ON/s0000458\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stdout(
+        &app.run(&["get", "-p", "envcanada", LOCATION, "--date", "2099-07-01"])
+            .await,
+    );
+    assert!(output.contains("Weather: rain"), "{output}");
+    assert!(output.contains("Temperature: 18°C (14…22°C)"), "{output}");
+    assert!(output.contains("Wind speed: 5 m/s"), "{output}");
+    assert!(output.contains("Humidity: 75%"), "{output}");
+    assert!(output.contains("Precipitation: 10 mm"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "meteomatics")]
+async fn meteomatics_sends_credentials_in_header() {
+    let server = MockServer::start().await;
+    let value = |parameter: &str, value: f32| json!({ "parameter": parameter, "coordinates": [{ "dates": [{ "value": value }] }] });
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/now/[^/]+/51\.5000,-0\.1000/json$"))
+        // "user:pass" in Base64
+        .and(header("Authorization", "Basic dXNlcjpwYXNz"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [
+                value("t_2m:C", -2.5),
+                value("wind_speed_10m:ms", 6.0),
+                value("relative_humidity_2m:p", 95.0),
+                value("precip_1h:mm", 0.4),
+                value("weather_symbol_1h:idx", 106.0),
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[meteomatics]\nusername = user\npassword = pass\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stdout(&app.run(&["get", "-p", "meteomatics", LOCATION]).await);
+    assert!(output.contains("Weather: sleet"), "{output}");
+    assert!(output.contains("Temperature: -2.5°C"), "{output}");
+    assert!(output.contains("Humidity: 95%"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "pirateweather")]
+async fn pirateweather_current_conditions() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/forecast/pw-secret-key/51.5000,-0.1000"))
+        .and(query_param("units", "si"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "offset": 1.0,
+            "currently": {
+                "time": 1719850000,
+                "icon": "rain",
+                "summary": "Drizzle",
+                "temperature": 14.5,
+                "humidity": 0.81,
+                "windSpeed": 4.2,
+                "precipIntensity": 0.3
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[pirateweather]\napikey = pw-secret-key\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stdout(&app.run(&["get", "-p", "pirateweather", LOCATION]).await);
+    assert!(output.contains("Weather: drizzle"), "{output}");
+    assert!(output.contains("Temperature: 14.5°C"), "{output}");
+    assert!(output.contains("Humidity: 81%"), "{output}");
+    assert!(output.contains("Precipitation: 0.3 mm"), "{output}");
+
+    let output = stdout(
+        &app.run(&["get", "-p", "pirateweather", LOCATION, "--dry-run"])
+            .await,
+    );
+    assert!(!output.contains("pw-secret-key"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "pirateweather")]
+async fn pirateweather_error_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({ "message": "Forbidden" })))
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[pirateweather]\napikey = pw-secret-key\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stderr(&app.run(&["get", "-p", "pirateweather", LOCATION]).await);
+    assert!(output.contains("API error: Forbidden"), "{output}");
+    assert!(!output.contains("pw-secret-key"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "meteostat")]
+async fn meteostat_summarizes_station_observations() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/stations/meta"))
+        .and(query_param("id", "03772"))
+        .and(header("X-RapidAPI-Key", "ms-secret-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "id": "03772",
+                "name": { "en": "London Heathrow" },
+                "timezone": "Europe/London"
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/stations/hourly"))
+        .and(query_param("station", "03772"))
+        .and(query_param("start", "2024-07-01"))
+        .and(query_param("tz", "Europe/London"))
+        .and(query_param("model", "0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [
+                { "time": "2024-07-01 06:00:00", "temp": 12.0, "rhum": 90, "prcp": 0.5, "wspd": 7.2, "coco": 8 },
+                { "time": "2024-07-01 12:00:00", "temp": 18.0, "rhum": 70, "prcp": 1.0, "wspd": 18.0, "coco": 8 },
+                { "time": "2024-07-01 18:00:00", "temp": 15.0, "rhum": 80, "prcp": null, "wspd": 10.8, "coco": 3 },
+                { "time": "2024-07-01 23:00:00", "temp": null, "rhum": null, "prcp": null, "wspd": null, "coco": null }
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[meteostat]\napikey = ms-secret-key\nstation = 03772\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stdout(
+        &app.run(&[
+            "get",
+            "-p",
+            "meteostat",
+            LOCATION,
+            "--date",
+            "2024-07-01",
+            "--detailed",
+        ])
+        .await,
+    );
+    assert!(output.contains("Weather: raining"), "{output}");
+    assert!(output.contains("Temperature: 15°C (12…18°C)"), "{output}");
+    assert!(output.contains("Humidity: 80%"), "{output}");
+    assert!(
+        output.contains("Data time: 2024-07-01 05:00 +00:00"),
+        "{output}"
+    );
+    assert!(output.contains("Wind speed: 5 m/s"), "{output}");
+    assert!(output.contains("Precipitation: 1.5 mm"), "{output}");
+    assert!(
+        output.contains("Source: station 03772 (London Heathrow)"),
+        "{output}"
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "meteostat")]
+async fn meteostat_uses_nearest_station() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/stations/nearby"))
+        .and(query_param("lat", "51.5000"))
+        .and(query_param("lon", "-0.1000"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [{ "id": "03779", "name": { "en": "London Weather Centre" } }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/stations/hourly"))
+        .and(query_param("station", "03779"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [
+                { "time": "2024-07-01 06:00:00", "temp": 12.0, "rhum": 90, "prcp": 0.0, "wspd": 3.6, "coco": 1 },
+                { "time": "2024-07-01 07:00:00", "temp": 13.5, "rhum": 85, "prcp": 0.0, "wspd": 7.2, "coco": 2 }
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[meteostat]\napikey = ms-secret-key\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stdout(&app.run(&["get", "-p", "meteostat", LOCATION]).await);
+    assert!(output.contains("Weather: clear"), "{output}");
+    assert!(output.contains("Temperature: 13.5°C"), "{output}");
+    assert!(output.contains("Humidity: 85%"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "xweather")]
+async fn xweather_current_conditions() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/observations/{LOCATION}")))
+        .and(query_param("client_id", "xw-client"))
+        .and(query_param("client_secret", "xw-secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "error": null,
+            "response": { "ob": {
+                "tempC": 18, "humidity": 40, "windSpeedKPH": 0,
+                "precipMM": 0, "weatherPrimaryCoded": "::CL"
+            }}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[xweather]\nclient_id = xw-client\nclient_secret = xw-secret\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stdout(&app.run(&["get", "-p", "xweather", LOCATION]).await);
+    assert!(output.contains("Weather: clear"), "{output}");
+    assert!(output.contains("Temperature: 18°C"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "xweather")]
+async fn xweather_error_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": false,
+            "error": { "code": "invalid_client", "description": "The client provided is invalid." },
+            "response": []
+        })))
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[xweather]\nclient_id = xw-client\nclient_secret = xw-secret\nbase_url = {}\n",
+        server.uri()
+    ));
+
+    let output = stderr(&app.run(&["get", "-p", "xweather", LOCATION]).await);
+    assert!(output.contains("invalid_client"), "{output}");
+    assert!(!output.contains("xw-secret"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn configure_checks_provider_before_saving() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .and(query_param("key", "good-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "current": {
+                "temp_c": 10, "humidity": 50, "wind_kph": 0, "precip_mm": 0,
+                "condition": { "code": 1000 }
+            }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .and(query_param("key", "bad-key"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "error": { "code": 2008, "message": "API key has been disabled." }
+        })))
+        .mount(&server)
+        .await;
+    let app = App::new("# Personal settings\n");
+    let base_url = format!("base_url={}/v1", server.uri());
+
+    let output = stderr(
+        &app.run(&[
+            "configure",
+            "weatherapi",
+            "apikey=bad-key",
+            &base_url,
+            "--test-location",
+            LOCATION,
+        ])
+        .await,
+    );
+    assert!(output.contains("API error 2008"), "{output}");
+    assert_eq!(app.config(), "# Personal settings\n");
+
+    stdout(
+        &app.run(&[
+            "configure",
+            "weatherapi",
+            "apikey=good-key",
+            &base_url,
+            "--test-location",
+            LOCATION,
+        ])
+        .await,
+    );
+    let config = app.config();
+    assert!(config.starts_with("# Personal settings\n"), "{config}");
+    assert!(config.contains("current = weatherapi"), "{config}");
+    assert!(config.contains("apikey = good-key"), "{config}");
+}
+
+#[tokio::test]
+#[cfg(feature = "weatherapi")]
+async fn doctor_reports_rejected_key_with_fix() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/current.json"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": { "code": 2006, "message": "API key is invalid." }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = App::new(&format!(
+        "[weatherapi]\napikey = wa-secret-key\nbase_url = {}/v1\n",
+        server.uri()
+    ));
+
+    let output = app.run(&["doctor", "--location", LOCATION]).await;
+    assert!(!output.status.success());
+    let output = String::from_utf8_lossy(&output.stdout);
+    assert!(output.contains("[FAIL] Provider 'weatherapi'"), "{output}");
+    assert!(output.contains("weather configure weatherapi"), "{output}");
+    assert!(output.contains("[ OK ] Cache directory"), "{output}");
+    assert!(!output.contains("wa-secret-key"), "{output}");
+}
+
+#[tokio::test]
+#[cfg(all(feature = "weatherapi", feature = "openweather"))]
+async fn manual_documents_provider_parameters() {
+    let app = App::new("");
+
+    let page = stdout(&app.run(&["gen-man"]).await);
+    assert!(page.contains(".SH PROVIDERS"), "{page}");
+    assert!(page.contains(".SS weatherapi"), "{page}");
+    assert!(page.contains("WEATHER_CLI_WEATHERAPI_APIKEY"), "{page}");
+
+    let help = stdout(&app.run(&["gen-man", "--markdown"]).await);
+    assert!(help.contains("## `weather get`"), "{help}");
+    assert!(help.contains("### openweather"), "{help}");
+    assert!(
+        help.contains("| `apikey` | secret |"),
+        "Parameters should be listed: {help}"
+    );
+}